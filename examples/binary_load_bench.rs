@@ -0,0 +1,111 @@
+//! Compares loading a GPT-2-sized tokenizer from its binary form, from `merges.txt`
+//! and, with the `json` feature, from `tokenizer.json`. With the `mmap` feature it
+//! also opens the tokenizer as a memory mapping, the only path that builds no hash
+//! tables, and times the first encode after opening it.
+//!
+//! Run with `cargo run --release --features bincode,json,mmap --example binary_load_bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bpe_tokenizer_rs::{BpeTokenizer, bytes_to_unicode};
+
+/// Builds 50,000 valid merge rules: every pair of base tokens, then three-symbol
+/// tokens on top of them, like the 50,000 merges of GPT-2.
+fn gpt2_sized_merges() -> Vec<(String, String)> {
+    let symbols: Vec<String> = bytes_to_unicode()
+        .into_values()
+        .filter(|ch| ch.is_ascii_alphanumeric())
+        .map(String::from)
+        .collect();
+    let pairs: Vec<(String, String)> = symbols
+        .iter()
+        .flat_map(|a| symbols.iter().map(move |b| (a.clone(), b.clone())))
+        .collect();
+    let triples = pairs.iter().flat_map(|(a, b)| {
+        symbols
+            .iter()
+            .map(move |c| (format!("{}{}", a, b), c.clone()))
+    });
+
+    pairs.iter().cloned().chain(triples).take(50_000).collect()
+}
+
+fn time<T>(rounds: u32, mut load: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        black_box(load());
+    }
+    start.elapsed() / rounds
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    let binary = dir.path().join("tokenizer.bin");
+    let merges = dir.path().join("merges.txt");
+
+    let merge_rules = gpt2_sized_merges();
+    let tokenizer = BpeTokenizer::new(merge_rules.clone(), vec!["<|endoftext|>".to_string()]);
+    tokenizer.save_binary(&binary).unwrap();
+    tokenizer.save_merges(&merges).unwrap();
+    println!(
+        "{} tokens; binary {} KiB, merges.txt {} KiB",
+        tokenizer.vocabulary().len(),
+        std::fs::metadata(&binary).unwrap().len() / 1024,
+        std::fs::metadata(&merges).unwrap().len() / 1024
+    );
+
+    let rounds = 20;
+    let binary_time = time(rounds, || BpeTokenizer::load_binary(&binary).unwrap());
+    let text_time = time(rounds, || {
+        BpeTokenizer::from_merges_file(&merges, vec!["<|endoftext|>".to_string()]).unwrap()
+    });
+
+    let construct_time = time(rounds, || {
+        BpeTokenizer::new(merge_rules.clone(), vec!["<|endoftext|>".to_string()])
+    });
+    println!(
+        "in-memory construction (lower bound) {:>8.2?}",
+        construct_time
+    );
+
+    let report = |name: &str, other: Duration| {
+        println!(
+            "load_binary {:>8.2?}  {:<16} {:>8.2?}  speedup {:.2}x",
+            binary_time,
+            name,
+            other,
+            other.as_secs_f64() / binary_time.as_secs_f64()
+        )
+    };
+    report("from_merges_file", text_time);
+
+    #[cfg(feature = "json")]
+    {
+        let json = dir.path().join("tokenizer.json");
+        tokenizer.save(&json).unwrap();
+        report(
+            "from_file (json)",
+            time(rounds, || BpeTokenizer::from_file(&json).unwrap()),
+        );
+    }
+
+    #[cfg(feature = "mmap")]
+    {
+        let mapped = dir.path().join("tokenizer.map");
+        tokenizer.save_mapped(&mapped).unwrap();
+        let open_time = time(rounds, || BpeTokenizer::open_mapped(&mapped).unwrap());
+        println!(
+            "open_mapped {:>8.2?}  mapped file {} KiB",
+            open_time,
+            std::fs::metadata(&mapped).unwrap().len() / 1024
+        );
+
+        // The encoder's byte-pair filter is built on first use.
+        let text = "The quick brown fox jumps over the lazy dog.";
+        let first_encode = time(rounds, || {
+            BpeTokenizer::open_mapped(&mapped).unwrap().encode(text)
+        });
+        println!("open_mapped + first encode {:>8.2?}", first_encode);
+    }
+}
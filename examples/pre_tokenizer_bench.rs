@@ -0,0 +1,44 @@
+//! Compares the hand-written GPT-2 scanner against the regex formulation.
+//!
+//! Run with `cargo run --release --example pre_tokenizer_bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bpe_tokenizer_rs::PreTokenizer;
+
+fn time(pre_tokenizer: &PreTokenizer, texts: &[&str], rounds: usize) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for text in texts {
+            black_box(pre_tokenizer.pre_tokenize_with_offsets(black_box(text)));
+        }
+    }
+    start.elapsed()
+}
+
+fn main() {
+    let short = ["Hi!", "OK", "it's fine", "42", "What's up?", "日本語"];
+    let long = "The quick brown fox jumps over the lazy dog. It's 2024, and we're \
+                measuring how fast 12345 words, numbers and punctuation!!! get split. "
+        .repeat(50);
+    let workloads: [(&str, Vec<&str>, usize); 2] = [
+        ("short strings", short.to_vec(), 200_000),
+        ("long document", vec![long.as_str()], 2_000),
+    ];
+
+    let scanner = PreTokenizer::new();
+    let regex = PreTokenizer::gpt2_regex();
+
+    for (name, texts, rounds) in &workloads {
+        let scanner_time = time(&scanner, texts, *rounds);
+        let regex_time = time(&regex, texts, *rounds);
+
+        println!(
+            "{name:>14}: scanner {:>8.2?}  regex {:>8.2?}  speedup {:.2}x",
+            scanner_time,
+            regex_time,
+            regex_time.as_secs_f64() / scanner_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,109 @@
+//! Times training under the system allocator, mimalloc, jemalloc or the dhat heap
+//! profiler.
+//!
+//! Training allocates heavily: every merge rewrites the words containing the merged
+//! pair and rebuilds their pair counts. The global allocator is picked by feature:
+//!
+//! ```text
+//! cargo run --release --example train_allocator [corpus] [merges]
+//! cargo run --release --features mimalloc --example train_allocator [corpus] [merges]
+//! cargo run --release --features jemalloc --example train_allocator [corpus] [merges]
+//! cargo run --release --features dhat-heap --example train_allocator [corpus] [merges]
+//! ```
+//!
+//! If several allocator features are enabled, `dhat-heap` wins over `jemalloc`,
+//! which wins over `mimalloc`. Without a corpus path a synthetic corpus is
+//! generated. The `dhat-heap` run prints allocation totals and writes
+//! `dhat-heap.json` for the viewer at
+//! <https://nnethercote.github.io/dh_view/dh_view.html>; it is much slower, so use it
+//! to find where allocations come from, not for timing.
+
+use std::env;
+use std::time::Instant;
+
+use bpe_tokenizer_rs::{CorpusSource, Segmentation, Trainer};
+
+#[cfg(feature = "dhat-heap")]
+#[global_allocator]
+static ALLOCATOR: dhat::Alloc = dhat::Alloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "dhat-heap")))]
+#[global_allocator]
+static ALLOCATOR: jemallocator::Jemalloc = jemallocator::Jemalloc;
+
+#[cfg(all(
+    feature = "mimalloc",
+    not(feature = "jemalloc"),
+    not(feature = "dhat-heap")
+))]
+#[global_allocator]
+static ALLOCATOR: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+const ALLOCATOR_NAME: &str = if cfg!(feature = "dhat-heap") {
+    "dhat (profiling)"
+} else if cfg!(feature = "jemalloc") {
+    "jemalloc"
+} else if cfg!(feature = "mimalloc") {
+    "mimalloc"
+} else {
+    "system"
+};
+
+/// Generates `lines` lines of text from a fixed pool of pseudo-random words with a
+/// skewed frequency distribution, like natural text.
+fn synthetic_corpus(lines: usize) -> String {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = move |bound: u64| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state % bound
+    };
+    let words: Vec<String> = (0..20_000)
+        .map(|_| {
+            let length = 2 + next(9);
+            (0..length)
+                .map(|_| (b'a' + next(26) as u8) as char)
+                .collect()
+        })
+        .collect();
+
+    let mut corpus = String::new();
+    for _ in 0..lines {
+        for _ in 0..12 {
+            // Squaring a uniform draw favours low indexes: a few words are very common.
+            let pick = next(words.len() as u64);
+            corpus.push_str(&words[(pick * pick / words.len() as u64) as usize]);
+            corpus.push(' ');
+        }
+        corpus.push('\n');
+    }
+    corpus
+}
+
+fn main() {
+    #[cfg(feature = "dhat-heap")]
+    let _profiler = dhat::Profiler::new_heap();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    let corpus = match args.first() {
+        Some(path) => CorpusSource::parse(path)
+            .read()
+            .expect("corpus is readable"),
+        None => synthetic_corpus(10_000),
+    };
+    let merges: usize = args
+        .get(1)
+        .map_or(500, |n| n.parse().expect("merges is a number"));
+    let texts = Segmentation::Lines.segments(&corpus);
+
+    let start = Instant::now();
+    let learned = Trainer::new(merges).train(&texts);
+    println!(
+        "{}: {} merges on {} texts in {:.2?}",
+        ALLOCATOR_NAME,
+        learned.len(),
+        texts.len(),
+        start.elapsed()
+    );
+}
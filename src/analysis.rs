@@ -0,0 +1,600 @@
+use std::io::{self, Write};
+
+/// Per-rule usage statistics collected by encoding a corpus.
+///
+/// Each merge rule is identified by its rank, i.e. its index in the merge list.
+/// The count for a rank is the number of times that rule merged a pair of symbols
+/// while encoding the corpus. Rules with a count of zero never fired and are
+/// candidates for pruning.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::BpeTokenizer;
+///
+/// let merges = vec![
+///     ("a".to_string(), "b".to_string()),
+///     ("x".to_string(), "y".to_string()),
+/// ];
+/// let tokenizer = BpeTokenizer::new(merges, vec![]);
+///
+/// let usage = tokenizer.merge_usage(&["ab ab"]);
+/// assert_eq!(usage.count(0), Some(2));
+/// assert_eq!(usage.dead_rules(), vec![1]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeUsage {
+    counts: Vec<usize>,
+}
+
+impl MergeUsage {
+    pub(crate) fn new(counts: Vec<usize>) -> Self {
+        MergeUsage { counts }
+    }
+
+    /// Returns how many times the merge rule at `rank` fired, or `None` if the
+    /// rank is out of range.
+    pub fn count(&self, rank: usize) -> Option<usize> {
+        self.counts.get(rank).copied()
+    }
+
+    /// Returns the usage counts for all merge rules, indexed by rank.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Returns the ranks of merge rules that never fired, in ascending order.
+    pub fn dead_rules(&self) -> Vec<usize> {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count == 0)
+            .map(|(rank, _)| rank)
+            .collect()
+    }
+
+    /// Returns the total number of merge operations performed across the corpus.
+    pub fn total_merges(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// Token density of one language sample set, produced by
+/// [`BpeTokenizer::language_coverage`](crate::BpeTokenizer::language_coverage).
+///
+/// Characters are Unicode scalar values, so scripts with multi-byte encodings are
+/// compared fairly against ASCII text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageCoverage {
+    /// Label supplied by the caller, e.g. `"en"` or `"Japanese"`.
+    pub language: String,
+    /// Number of sample texts.
+    pub texts: usize,
+    /// Total number of characters across the samples.
+    pub characters: usize,
+    /// Total number of tokens produced for the samples.
+    pub tokens: usize,
+}
+
+impl LanguageCoverage {
+    /// Returns the average number of tokens per character, or `0.0` for empty samples.
+    ///
+    /// Lower is better: a well-covered language needs fewer tokens per character.
+    pub fn tokens_per_char(&self) -> f64 {
+        if self.characters == 0 {
+            0.0
+        } else {
+            self.tokens as f64 / self.characters as f64
+        }
+    }
+}
+
+/// Tokens per word of one dated corpus slice, produced by
+/// [`BpeTokenizer::token_drift`](crate::BpeTokenizer::token_drift).
+///
+/// Words are the pre-tokenized chunks of the normalized texts that contain
+/// something other than whitespace; special tokens are not counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SliceDrift {
+    /// Label supplied by the caller, e.g. `"2024-Q1"`.
+    pub slice: String,
+    /// Number of texts in the slice.
+    pub texts: usize,
+    /// Number of words in the slice.
+    pub words: usize,
+    /// Total number of tokens produced for the words.
+    pub tokens: usize,
+    /// Number of words that needed more than one token.
+    pub split_words: usize,
+}
+
+impl SliceDrift {
+    /// Returns the average number of tokens per word, or `0.0` for an empty slice.
+    pub fn tokens_per_word(&self) -> f64 {
+        if self.words == 0 {
+            0.0
+        } else {
+            self.tokens as f64 / self.words as f64
+        }
+    }
+
+    /// Returns the share of words split into several tokens, or `0.0` for an empty
+    /// slice.
+    pub fn split_word_share(&self) -> f64 {
+        if self.words == 0 {
+            0.0
+        } else {
+            self.split_words as f64 / self.words as f64
+        }
+    }
+
+    /// Returns the relative change of [`tokens_per_word`](SliceDrift::tokens_per_word)
+    /// from `baseline`, e.g. `0.05` for 5% more tokens per word, or `0.0` if the
+    /// baseline is empty.
+    pub fn drift_from(&self, baseline: &SliceDrift) -> f64 {
+        let baseline = baseline.tokens_per_word();
+        if baseline == 0.0 {
+            0.0
+        } else {
+            self.tokens_per_word() / baseline - 1.0
+        }
+    }
+}
+
+/// Token counts of a text with and without candidate added tokens, produced by
+/// [`BpeTokenizer::simulate_with_extra_tokens`](crate::BpeTokenizer::simulate_with_extra_tokens).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtraTokenSimulation {
+    /// Tokens the text encodes to today.
+    pub tokens_before: usize,
+    /// Tokens the text would encode to with every candidate as a single token.
+    pub tokens_after: usize,
+    /// Impact of each candidate, in input order.
+    pub candidates: Vec<CandidateImpact>,
+}
+
+impl ExtraTokenSimulation {
+    /// Returns the share of tokens all candidates together would save, or `0.0` for
+    /// an empty text.
+    pub fn savings_ratio(&self) -> f64 {
+        if self.tokens_before == 0 {
+            0.0
+        } else {
+            1.0 - self.tokens_after as f64 / self.tokens_before as f64
+        }
+    }
+}
+
+/// What turning one string into a single token would change, reported by
+/// [`BpeTokenizer::simulate_with_extra_tokens`](crate::BpeTokenizer::simulate_with_extra_tokens)
+/// and [`BpeTokenizer::mine_candidates`](crate::BpeTokenizer::mine_candidates).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidateImpact {
+    /// The candidate string, matched in normalized text.
+    pub token: String,
+    /// Times the candidate occurs. In a simulation with several candidates, the
+    /// longer one wins where two overlap.
+    pub occurrences: usize,
+    /// Tokens saved by adding this candidate alone.
+    pub tokens_saved: usize,
+}
+
+/// Compression statistics of a tokenizer on a corpus, produced by
+/// [`BpeTokenizer::stats`](crate::BpeTokenizer::stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenizerStats {
+    /// Number of tokens in the vocabulary, including special and byte-level tokens.
+    pub vocab_size: usize,
+    /// Number of texts in the corpus.
+    pub texts: usize,
+    /// Total number of characters in the corpus.
+    pub characters: usize,
+    /// Total number of UTF-8 bytes in the corpus.
+    pub bytes: usize,
+    /// Total number of tokens produced for the corpus.
+    pub tokens: usize,
+}
+
+impl TokenizerStats {
+    /// Returns the average number of UTF-8 bytes covered by one token, or `0.0`
+    /// if no tokens were produced. Higher means better compression.
+    pub fn bytes_per_token(&self) -> f64 {
+        if self.tokens == 0 {
+            0.0
+        } else {
+            self.bytes as f64 / self.tokens as f64
+        }
+    }
+
+    /// Returns the average number of tokens per character, or `0.0` for an empty corpus.
+    pub fn tokens_per_char(&self) -> f64 {
+        if self.characters == 0 {
+            0.0
+        } else {
+            self.tokens as f64 / self.characters as f64
+        }
+    }
+}
+
+/// Number of documents whose token count falls into `min..=max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LengthBucket {
+    pub min: usize,
+    pub max: usize,
+    pub documents: usize,
+}
+
+/// Per-document token counts collected during bulk encoding.
+///
+/// Recording counts while a corpus is encoded makes it possible to drop documents
+/// that are too short or too long without tokenizing the corpus a second time.
+/// The counts and a summary histogram can be written to sidecar files next to the
+/// encoded output.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::DocumentStats;
+///
+/// let mut stats = DocumentStats::new();
+/// stats.record(3);
+/// stats.record(10);
+///
+/// assert_eq!(stats.total_tokens(), 13);
+/// assert_eq!((stats.min(), stats.max()), (Some(3), Some(10)));
+///
+/// let mut out = Vec::new();
+/// stats.write_counts(&mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "document\ttokens\n0\t3\n1\t10\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    counts: Vec<usize>,
+}
+
+impl DocumentStats {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the token count of the next document.
+    pub fn record(&mut self, tokens: usize) {
+        self.counts.push(tokens);
+    }
+
+    /// Returns the token count of every recorded document, in recording order.
+    pub fn counts(&self) -> &[usize] {
+        &self.counts
+    }
+
+    /// Returns the number of recorded documents.
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// Returns `true` if no documents have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the total number of tokens across all documents.
+    pub fn total_tokens(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns the smallest token count, or `None` if nothing was recorded.
+    pub fn min(&self) -> Option<usize> {
+        self.counts.iter().copied().min()
+    }
+
+    /// Returns the largest token count, or `None` if nothing was recorded.
+    pub fn max(&self) -> Option<usize> {
+        self.counts.iter().copied().max()
+    }
+
+    /// Returns the average token count, or `0.0` if nothing was recorded.
+    pub fn mean(&self) -> f64 {
+        if self.counts.is_empty() {
+            0.0
+        } else {
+            self.total_tokens() as f64 / self.counts.len() as f64
+        }
+    }
+
+    /// Groups documents into power-of-two length buckets.
+    ///
+    /// The first bucket holds empty documents and bucket `k` holds lengths in
+    /// `2^(k-1)..=2^k - 1`. Buckets up to the longest document are returned, including
+    /// empty ones, so the output can be plotted directly.
+    pub fn histogram(&self) -> Vec<LengthBucket> {
+        let Some(max) = self.max() else {
+            return Vec::new();
+        };
+
+        let bucket_count = (usize::BITS - max.leading_zeros()) as usize + 1;
+        let mut buckets: Vec<LengthBucket> = (0..bucket_count)
+            .map(|k| match k {
+                0 => LengthBucket {
+                    min: 0,
+                    max: 0,
+                    documents: 0,
+                },
+                _ => LengthBucket {
+                    min: 1 << (k - 1),
+                    max: (1 << (k - 1)) * 2 - 1,
+                    documents: 0,
+                },
+            })
+            .collect();
+
+        for &count in &self.counts {
+            buckets[(usize::BITS - count.leading_zeros()) as usize].documents += 1;
+        }
+
+        buckets
+    }
+
+    /// Writes one `document\ttokens` row per recorded document, after a header.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by `writer`.
+    pub fn write_counts(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "document\ttokens")?;
+
+        for (index, count) in self.counts.iter().enumerate() {
+            writeln!(writer, "{index}\t{count}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the [`histogram`](DocumentStats::histogram) as tab-separated
+    /// `min\tmax\tdocuments` rows, after a header.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by `writer`.
+    pub fn write_histogram(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "min\tmax\tdocuments")?;
+
+        for bucket in self.histogram() {
+            writeln!(
+                writer,
+                "{}\t{}\t{}",
+                bucket.min, bucket.max, bucket.documents
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BpeTokenizer, DocumentStats, Trainer};
+
+    #[test]
+    fn language_coverage_counts_chars_and_tokens() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+
+        let report = tokenizer.language_coverage(&[("en", &["abc", "de"]), ("ja", &["日本"])]);
+
+        assert_eq!(report[0].language, "en");
+        assert_eq!(
+            (report[0].texts, report[0].characters, report[0].tokens),
+            (2, 5, 5)
+        );
+        assert_eq!(report[1].tokens_per_char(), 3.0);
+    }
+
+    #[test]
+    fn language_coverage_reflects_trained_merges() {
+        let trainer = Trainer::new(10);
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &["hello hello hello"], vec![]);
+
+        let report = tokenizer.language_coverage(&[("trained", &["hello"]), ("other", &["qwxyz"])]);
+
+        assert!(report[0].tokens_per_char() < report[1].tokens_per_char());
+    }
+
+    #[test]
+    fn language_coverage_empty_samples() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+
+        let report = tokenizer.language_coverage(&[("none", &[])]);
+
+        assert_eq!(report[0].tokens_per_char(), 0.0);
+    }
+
+    #[test]
+    fn stats_measure_compression() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+
+        let stats = tokenizer.stats(&["abab", "é"]);
+
+        assert_eq!(stats.vocab_size, 257);
+        assert_eq!((stats.texts, stats.characters, stats.bytes), (2, 5, 6));
+        assert_eq!(stats.tokens, 4);
+        assert_eq!(stats.bytes_per_token(), 1.5);
+    }
+
+    #[test]
+    fn merge_usage_counts_each_firing() {
+        let merges = vec![
+            ("a".to_string(), "a".to_string()),
+            ("aa".to_string(), "aa".to_string()),
+        ];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+
+        let usage = tokenizer.merge_usage(&["aaaa", "aa"]);
+
+        assert_eq!(usage.counts(), &[3, 1]);
+        assert_eq!(usage.total_merges(), 4);
+    }
+
+    #[test]
+    fn merge_usage_reports_dead_rules() {
+        let merges = vec![
+            ("h".to_string(), "e".to_string()),
+            ("q".to_string(), "z".to_string()),
+            ("l".to_string(), "l".to_string()),
+        ];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+
+        let usage = tokenizer.merge_usage(&["hello"]);
+
+        assert_eq!(usage.dead_rules(), vec![1]);
+    }
+
+    #[test]
+    fn merge_usage_empty_corpus_marks_all_rules_dead() {
+        let trainer = Trainer::new(3);
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &["aa bb cc"], vec![]);
+
+        let usage = tokenizer.merge_usage(&[]);
+
+        assert_eq!(usage.dead_rules(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn merge_usage_ignores_special_tokens() {
+        let merges = vec![("e".to_string(), "n".to_string())];
+        let special_tokens = vec!["<|endoftext|>".to_string()];
+        let tokenizer = BpeTokenizer::new(merges, special_tokens);
+
+        let usage = tokenizer.merge_usage(&["<|endoftext|>"]);
+
+        assert_eq!(usage.count(0), Some(0));
+    }
+
+    #[test]
+    fn merge_usage_out_of_range_rank() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+
+        let usage = tokenizer.merge_usage(&["hello"]);
+
+        assert_eq!(usage.count(0), None);
+    }
+
+    #[test]
+    fn document_stats_histogram_uses_power_of_two_buckets() {
+        let mut stats = DocumentStats::new();
+        for count in [0, 1, 2, 3, 5] {
+            stats.record(count);
+        }
+
+        let documents: Vec<_> = stats.histogram().iter().map(|b| b.documents).collect();
+
+        assert_eq!(documents, vec![1, 1, 2, 1]);
+        assert_eq!((stats.histogram()[3].min, stats.histogram()[3].max), (4, 7));
+    }
+
+    #[test]
+    fn document_stats_empty() {
+        let stats = DocumentStats::new();
+
+        assert!(stats.histogram().is_empty());
+        assert_eq!(stats.mean(), 0.0);
+        assert_eq!(stats.min(), None);
+    }
+
+    #[test]
+    fn document_stats_write_histogram() {
+        let mut stats = DocumentStats::new();
+        stats.record(2);
+
+        let mut out = Vec::new();
+        stats.write_histogram(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "min\tmax\tdocuments\n0\t0\t0\n1\t1\t0\n2\t3\t1\n"
+        );
+    }
+
+    #[test]
+    fn extra_token_simulation_counts_occurrences_and_savings() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+        let text = "use tokio::spawn; tokio::spawn(x)<|endoftext|>";
+
+        let simulation =
+            tokenizer.simulate_with_extra_tokens(text, &["tokio::spawn", "tokio", "", "zzz"]);
+
+        assert_eq!(simulation.tokens_before, tokenizer.encode(text).len());
+        assert_eq!(
+            simulation.tokens_before,
+            text.len() - "<|endoftext|>".len() + 1
+        );
+        assert_eq!(simulation.tokens_after, simulation.tokens_before - 2 * 11);
+        let impact = |index: usize| {
+            let candidate = &simulation.candidates[index];
+            (candidate.occurrences, candidate.tokens_saved)
+        };
+        assert_eq!(impact(0), (2, 22));
+        assert_eq!(impact(1), (0, 8));
+        assert_eq!(impact(2), (0, 0));
+        assert_eq!(impact(3), (0, 0));
+        assert!(simulation.savings_ratio() > 0.5);
+    }
+
+    #[test]
+    fn mined_candidates_are_frequent_multi_token_spans() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+        let corpus = [
+            "use tokio::spawn;<|endoftext|> tokio::spawn",
+            "a b a b",
+            "once",
+        ];
+
+        let candidates = tokenizer.mine_candidates(&corpus, 100);
+
+        let find = |token: &str| candidates.iter().find(|candidate| candidate.token == token);
+        let spawn = find(" tokio::spawn").unwrap();
+        assert_eq!((spawn.occurrences, spawn.tokens_saved), (2, 24));
+        assert_eq!(candidates[0].token, " tokio::spawn");
+        assert_eq!(find(" b").unwrap().tokens_saved, 2);
+        assert!(find("a").is_none(), "single tokens save nothing");
+        assert!(find("once").is_none(), "spans seen once are not proposed");
+        assert!(
+            find(";<|endoftext|>").is_none(),
+            "spans stop at special tokens"
+        );
+        assert!(
+            candidates
+                .windows(2)
+                .all(|pair| pair[0].tokens_saved >= pair[1].tokens_saved)
+        );
+        assert_eq!(tokenizer.mine_candidates(&corpus, 1).len(), 1);
+    }
+
+    #[test]
+    fn token_drift_counts_words_and_split_words() {
+        let tokenizer = BpeTokenizer::from_trainer(
+            &Trainer::new(30),
+            &["the cat sat on the mat", "the cat sat"],
+            vec!["<|endoftext|>".to_string()],
+        );
+
+        let report = tokenizer.token_drift(&[
+            ("2023", &["the cat sat", "on the mat"]),
+            ("2024", &["the cat  vibecoded<|endoftext|>"]),
+            ("empty", &[]),
+        ]);
+
+        assert_eq!(report[0].slice, "2023");
+        assert_eq!((report[0].texts, report[0].words), (2, 6));
+        assert_eq!(report[0].tokens, 6);
+        assert_eq!(report[0].split_words, 0);
+        assert_eq!(
+            report[1].words, 3,
+            "whitespace and special tokens are not words"
+        );
+        assert_eq!(report[1].split_words, 1);
+        assert!(report[1].drift_from(&report[0]) > 0.5);
+        assert_eq!(report[2].tokens_per_word(), 0.0);
+        assert_eq!(report[0].drift_from(&report[2]), 0.0);
+    }
+}
@@ -0,0 +1,226 @@
+//! Command-line tools for working with tokenizers and tokenized datasets.
+//!
+//! Run `bpe help` for the list of commands.
+
+use std::env;
+use std::process::ExitCode;
+
+use bpe_tokenizer_rs::{
+    BpeTokenizer, CorpusSource, Segmentation, ShardReader, Shuffler, verify_roundtrip,
+};
+
+const USAGE: &str = "\
+usage: bpe <command> [options]
+
+commands:
+  sample <merges.txt> <dataset>   Decode random documents or sequences of a token dataset
+      --count N                   Number of samples (default 5)
+      --seed S                    Sampling seed (default 0)
+      --sequence-length L         Read a packed dataset of L-token sequences instead
+                                  of a shard with an .idx index
+      --special TOKEN             Special token of the tokenizer (repeatable, in ID order)
+  verify <merges.txt> <corpus>    Check that every text of a corpus survives encode -> decode;
+                                  exits with an error if any does not
+      --segmentation MODE         lines (default), paragraphs or whole
+      --special TOKEN             As for sample
+  test-vectors <merges.txt>       Write JSON test vectors (text -> ids) for checking other
+                                  implementations against this one (feature `json`)
+      --corpus PATH               Encode the texts of a corpus instead of the built-in set
+      --segmentation MODE         As for verify, applied to --corpus
+      --output PATH               Write to a file instead of standard output
+      --special TOKEN             As for sample
+  help                            Show this message";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("sample") => Options::parse(&args[1..]).and_then(|options| sample(&options)),
+        Some("verify") => Options::parse(&args[1..]).and_then(|options| verify(&options)),
+        Some("test-vectors") => {
+            Options::parse(&args[1..]).and_then(|options| test_vectors(&options))
+        }
+        Some("help" | "--help" | "-h") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(command) => Err(format!("unknown command '{}'\n\n{}", command, USAGE)),
+        None => Err(USAGE.to_string()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Positional arguments and `--name value` options of a command.
+struct Options {
+    positional: Vec<String>,
+    named: Vec<(String, String)>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut options = Options {
+            positional: Vec::new(),
+            named: Vec::new(),
+        };
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("option --{} needs a value", name))?;
+                    options.named.push((name.to_string(), value.clone()));
+                }
+                None => options.positional.push(arg.clone()),
+            }
+        }
+
+        Ok(options)
+    }
+
+    fn positional(&self, index: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing argument <{}>\n\n{}", name, USAGE))
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.named
+            .iter()
+            .rev()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn all(&self, name: &str) -> Vec<String> {
+        self.named
+            .iter()
+            .filter(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("option --{} expects a number, got '{}'", name, value)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn load_tokenizer(options: &Options) -> Result<BpeTokenizer, String> {
+    let path = options.positional(0, "merges.txt")?;
+
+    BpeTokenizer::from_merges_file(path, options.all("special"))
+        .map_err(|e| format!("cannot load {}: {}", path, e))
+}
+
+fn sample(options: &Options) -> Result<(), String> {
+    let tokenizer = load_tokenizer(options)?;
+    let dataset = options.positional(1, "dataset")?;
+    let count = options.number("count")?.unwrap_or(5);
+    let seed = options.number("seed")?.unwrap_or(0);
+
+    let mut reader = match options.number("sequence-length")? {
+        Some(0) => return Err("option --sequence-length must be positive".to_string()),
+        Some(length) => ShardReader::open_packed(dataset, length),
+        None => ShardReader::open(dataset),
+    }
+    .map_err(|e| format!("cannot open {}: {}", dataset, e))?;
+
+    let picks = Shuffler::new(seed).shuffle(std::slice::from_ref(reader.index()));
+    for pick in picks.into_iter().take(count) {
+        let ids = reader
+            .document(pick.document)
+            .map_err(|e| format!("cannot read {}: {}", dataset, e))?;
+
+        println!("=== #{} ({} tokens) ===", pick.document, ids.len());
+        println!("{}", tokenizer.decode_lenient(&ids));
+    }
+
+    Ok(())
+}
+
+fn segmentation(options: &Options) -> Result<Segmentation, String> {
+    match options.value("segmentation").unwrap_or("lines") {
+        "lines" => Ok(Segmentation::Lines),
+        "paragraphs" => Ok(Segmentation::Paragraphs),
+        "whole" => Ok(Segmentation::Whole),
+        other => Err(format!("unknown segmentation '{}'", other)),
+    }
+}
+
+fn read_corpus(corpus: &str) -> Result<String, String> {
+    CorpusSource::parse(corpus)
+        .read()
+        .map_err(|e| format!("cannot read {}: {}", corpus, e))
+}
+
+fn verify(options: &Options) -> Result<(), String> {
+    let tokenizer = load_tokenizer(options)?;
+    let corpus = options.positional(1, "corpus")?;
+    let segmentation = segmentation(options)?;
+
+    let contents = read_corpus(corpus)?;
+    let texts = segmentation.segments(&contents);
+    let mismatches = verify_roundtrip(&tokenizer, &texts);
+
+    for mismatch in &mismatches {
+        println!("{}", mismatch);
+    }
+    if mismatches.is_empty() {
+        println!("all {} texts round-trip", texts.len());
+        Ok(())
+    } else {
+        Err(format!(
+            "{} of {} texts do not round-trip",
+            mismatches.len(),
+            texts.len()
+        ))
+    }
+}
+
+#[cfg(feature = "json")]
+fn test_vectors(options: &Options) -> Result<(), String> {
+    let tokenizer = load_tokenizer(options)?;
+    let texts = match options.value("corpus") {
+        Some(corpus) => segmentation(options)?
+            .segments(&read_corpus(corpus)?)
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        None => tokenizer.canonical_test_texts(),
+    };
+    let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+
+    let result = match options.value("output") {
+        Some(path) => std::fs::File::create(path)
+            .map_err(|e| format!("cannot create {}: {}", path, e))
+            .and_then(|file| {
+                tokenizer
+                    .write_test_vectors(&texts, std::io::BufWriter::new(file))
+                    .map_err(|e| e.to_string())
+            }),
+        None => tokenizer
+            .write_test_vectors(&texts, std::io::stdout().lock())
+            .map_err(|e| e.to_string()),
+    };
+    result.map_err(|e| format!("cannot write test vectors: {}", e))
+}
+
+#[cfg(not(feature = "json"))]
+fn test_vectors(_: &Options) -> Result<(), String> {
+    Err("test-vectors needs the `json` feature".to_string())
+}
@@ -0,0 +1,98 @@
+//! Differential fuzzing against HuggingFace `tokenizers`.
+//!
+//! Trains both tokenizers on random corpora (or on a corpus you pass in) and exits
+//! with an error describing the first divergence.
+
+use std::env;
+use std::process::ExitCode;
+
+use bpe_tokenizer_rs::{CompatFuzzer, CorpusSource, Segmentation};
+
+const USAGE: &str = "\
+usage: compat-fuzz [options] [corpus]
+
+Trains this crate's tokenizer and HuggingFace's on the same data and reports the
+first difference in vocabulary or encoding. Without a corpus, random corpora are
+generated from the seed.
+
+options:
+  --seed S         Random seed (default: current time, printed for reproduction)
+  --cases N        Number of random corpora (default 20)
+  --merges N       Number of merges to train (default 30)
+  --texts N        Texts per random corpus (default 20)
+  --max-chars N    Maximum characters per random text (default 40)";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match run(&args) {
+        Ok(message) => {
+            println!("{}", message);
+            ExitCode::SUCCESS
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &[String]) -> Result<String, String> {
+    let mut corpus = None;
+    let mut seed = None;
+    let mut fuzzer_options = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            corpus = Some(arg.clone());
+            continue;
+        };
+        if matches!(name, "help" | "h") {
+            return Ok(USAGE.to_string());
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("option --{} needs a value", name))?;
+        let number: u64 = value
+            .parse()
+            .map_err(|_| format!("option --{} expects a number, got '{}'", name, value))?;
+        match name {
+            "seed" => seed = Some(number),
+            "cases" | "merges" | "texts" | "max-chars" => fuzzer_options.push((name, number)),
+            other => return Err(format!("unknown option --{}\n\n{}", other, USAGE)),
+        }
+    }
+
+    let seed = seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    let mut fuzzer = CompatFuzzer::new(seed);
+    for (name, number) in fuzzer_options {
+        let number = number as usize;
+        fuzzer = match name {
+            "cases" => fuzzer.with_cases(number),
+            "merges" => fuzzer.with_num_merges(number),
+            "texts" => fuzzer.with_corpus_texts(number),
+            _ => fuzzer.with_max_chars(number),
+        };
+    }
+
+    let result = match corpus {
+        Some(path) => {
+            let contents = CorpusSource::parse(&path)
+                .read()
+                .map_err(|e| format!("cannot read {}: {}", path, e))?;
+            fuzzer
+                .check_corpus(&Segmentation::Lines.segments(&contents))
+                .map(|()| format!("no divergence on {} (seed {})", path, seed))
+        }
+        None => fuzzer
+            .run()
+            .map(|cases| format!("no divergence in {} cases (seed {})", cases, seed)),
+    };
+
+    result.map_err(|divergence| format!("divergence with seed {}:\n{}", seed, divergence))
+}
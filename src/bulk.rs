@@ -0,0 +1,536 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::{
+    BpeTokenizer, CorpusHasher, CorpusSource, Segmentation, ShardIndex, ShardWriter,
+    shard_index_path,
+};
+
+/// File name of the manifest written into the output directory.
+pub const MANIFEST_FILE: &str = "manifest.tsv";
+
+const MANIFEST_HEADER: &str = "#bulk-manifest 1";
+
+/// Progress of one input shard in a [`Manifest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardStatus {
+    /// Not tokenized yet, or interrupted before its output was complete.
+    Pending,
+    /// Tokenized; the output file has the recorded size and checksum.
+    Done {
+        /// Number of documents in the shard.
+        documents: usize,
+        /// Number of tokens written, including end-of-document ids.
+        tokens: usize,
+        /// XXH3 hash of the output file.
+        checksum: u64,
+    },
+}
+
+/// One input of a bulk tokenization job and the file its tokens go to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardEntry {
+    /// The input, as displayed by [`CorpusSource`].
+    pub input: String,
+    /// File name of the output, relative to the output directory.
+    pub output: String,
+    pub status: ShardStatus,
+}
+
+/// Per-shard progress of a [`BulkTokenizer`] job.
+///
+/// The manifest is saved as `manifest.tsv` in the output directory after every
+/// shard, so an interrupted job can pick up where it stopped. It is a text file
+/// with a `#bulk-manifest 1` header, a `tokenizer` line holding the fingerprint, and
+/// one tab-separated line per shard: `shard`, the output file, `done` or `pending`,
+/// for done shards the document count, token count and hex checksum, and finally
+/// the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    /// Fingerprint of the tokenizer and job settings that produced the shards.
+    pub tokenizer: u64,
+    pub shards: Vec<ShardEntry>,
+}
+
+impl Manifest {
+    /// Returns `true` if every shard is done.
+    pub fn is_complete(&self) -> bool {
+        self.shards
+            .iter()
+            .all(|shard| matches!(shard.status, ShardStatus::Done { .. }))
+    }
+
+    /// Returns the total number of tokens in finished shards.
+    pub fn tokens(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| match shard.status {
+                ShardStatus::Done { tokens, .. } => tokens,
+                ShardStatus::Pending => 0,
+            })
+            .sum()
+    }
+
+    /// Parses a manifest written by [`format`](Manifest::format).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::InvalidData`] naming the offending line if the
+    /// manifest is malformed.
+    pub fn parse(contents: &str) -> io::Result<Manifest> {
+        let mut lines = contents.lines().enumerate();
+
+        if lines.next().map(|(_, line)| line) != Some(MANIFEST_HEADER) {
+            return Err(invalid_manifest(1, "missing manifest header"));
+        }
+        let tokenizer = match lines.next() {
+            Some((_, line)) if line.starts_with("tokenizer\t") => {
+                parse_hex(&line["tokenizer\t".len()..])
+                    .ok_or_else(|| invalid_manifest(2, "invalid tokenizer fingerprint"))?
+            }
+            _ => return Err(invalid_manifest(2, "missing tokenizer fingerprint")),
+        };
+
+        let mut shards = Vec::new();
+        for (index, line) in lines {
+            let entry = parse_shard(line).ok_or_else(|| invalid_manifest(index + 1, line))?;
+            shards.push(entry);
+        }
+
+        Ok(Manifest { tokenizer, shards })
+    }
+
+    /// Formats the manifest in the text form read by [`parse`](Manifest::parse).
+    pub fn format(&self) -> String {
+        let mut out = format!("{}\ntokenizer\t{:016x}\n", MANIFEST_HEADER, self.tokenizer);
+
+        for shard in &self.shards {
+            match shard.status {
+                ShardStatus::Pending => {
+                    writeln!(out, "shard\t{}\tpending\t{}", shard.output, shard.input)
+                }
+                ShardStatus::Done {
+                    documents,
+                    tokens,
+                    checksum,
+                } => writeln!(
+                    out,
+                    "shard\t{}\tdone\t{}\t{}\t{:016x}\t{}",
+                    shard.output, documents, tokens, checksum, shard.input
+                ),
+            }
+            .expect("writing to a String cannot fail");
+        }
+
+        out
+    }
+
+    /// Reads a manifest file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Manifest> {
+        Manifest::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the manifest atomically, through a temporary file that replaces `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from writing or renaming the file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_atomically(path.as_ref(), self.format().as_bytes())
+    }
+}
+
+fn parse_shard(line: &str) -> Option<ShardEntry> {
+    let mut fields = line.splitn(4, '\t');
+    if fields.next()? != "shard" {
+        return None;
+    }
+    let output = fields.next()?.to_string();
+    let status = fields.next()?;
+    let rest = fields.next()?;
+
+    let (status, input) = match status {
+        "pending" => (ShardStatus::Pending, rest),
+        "done" => {
+            let mut fields = rest.splitn(4, '\t');
+            let status = ShardStatus::Done {
+                documents: fields.next()?.parse().ok()?,
+                tokens: fields.next()?.parse().ok()?,
+                checksum: parse_hex(fields.next()?)?,
+            };
+            (status, fields.next()?)
+        }
+        _ => return None,
+    };
+
+    Some(ShardEntry {
+        input: input.to_string(),
+        output,
+        status,
+    })
+}
+
+fn parse_hex(value: &str) -> Option<u64> {
+    u64::from_str_radix(value, 16).ok()
+}
+
+fn invalid_manifest(line: usize, detail: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid manifest at line {}: {}", line, detail),
+    )
+}
+
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    let mut file = fs::File::create(&temporary)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+/// Tokenizes a list of corpus shards into token files, resuming interrupted runs.
+///
+/// Each input becomes one token shard of little-endian `u32` ids, with the
+/// end-of-document id (if set) after every document, and a [`ShardIndex`] next to it
+/// so [`ShardReader`](crate::ShardReader) can fetch any document directly. Progress is recorded in a
+/// [`Manifest`] in the output directory after each shard. Running the same job again
+/// skips every shard whose output still matches its recorded checksum and redoes the
+/// rest, so a multi-hour job survives interruption without losing finished work.
+///
+/// Outputs are written to a temporary file and renamed into place, so a crash never
+/// leaves a truncated shard that looks complete.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, BulkTokenizer, CorpusSource};
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let input = dir.path().join("part-0.txt");
+/// std::fs::write(&input, "hello\nworld\n").unwrap();
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// let job = BulkTokenizer::new(&tokenizer, dir.path().join("out"));
+///
+/// let manifest = job.run(&[CorpusSource::from(input.as_path())]).unwrap();
+/// assert!(manifest.is_complete());
+/// assert_eq!(manifest.tokens(), 10);
+/// ```
+pub struct BulkTokenizer<'a> {
+    tokenizer: &'a BpeTokenizer,
+    output_dir: PathBuf,
+    segmentation: Segmentation,
+    eos_id: Option<u32>,
+}
+
+impl<'a> BulkTokenizer<'a> {
+    /// Creates a job writing to `output_dir`, one document per line and no
+    /// end-of-document id.
+    pub fn new(tokenizer: &'a BpeTokenizer, output_dir: impl Into<PathBuf>) -> Self {
+        BulkTokenizer {
+            tokenizer,
+            output_dir: output_dir.into(),
+            segmentation: Segmentation::default(),
+            eos_id: None,
+        }
+    }
+
+    /// Sets how each input is cut into documents.
+    pub fn with_segmentation(mut self, segmentation: Segmentation) -> Self {
+        self.segmentation = segmentation;
+        self
+    }
+
+    /// Appends `eos_id` after every document.
+    pub fn with_eos_id(mut self, eos_id: u32) -> Self {
+        self.eos_id = Some(eos_id);
+        self
+    }
+
+    /// Returns the path of the manifest in the output directory.
+    pub fn manifest_path(&self) -> PathBuf {
+        self.output_dir.join(MANIFEST_FILE)
+    }
+
+    /// Tokenizes every input not already finished and returns the final manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an input cannot be read, an output cannot be written, or an
+    /// existing manifest is malformed, lists different inputs, or was produced by a
+    /// different tokenizer ([`io::ErrorKind::InvalidInput`]). Shards finished before
+    /// the error stay recorded as done.
+    pub fn run(&self, inputs: &[CorpusSource]) -> io::Result<Manifest> {
+        fs::create_dir_all(&self.output_dir)?;
+
+        let mut manifest = self.open_manifest(inputs)?;
+        manifest.save(self.manifest_path())?;
+
+        for (index, source) in inputs.iter().enumerate() {
+            let output = self.output_dir.join(&manifest.shards[index].output);
+            if let ShardStatus::Done { checksum, .. } = manifest.shards[index].status
+                && fs::read(&output).is_ok_and(|bytes| xxh3_64(&bytes) == checksum)
+                && ShardIndex::load(shard_index_path(&output)).is_ok()
+            {
+                continue;
+            }
+
+            manifest.shards[index].status = self.tokenize_shard(source, &output)?;
+            // Saving after every shard bounds the work lost to an interruption.
+            manifest.save(self.manifest_path())?;
+        }
+
+        Ok(manifest)
+    }
+
+    fn open_manifest(&self, inputs: &[CorpusSource]) -> io::Result<Manifest> {
+        let fresh = Manifest {
+            tokenizer: self.fingerprint(),
+            shards: inputs
+                .iter()
+                .enumerate()
+                .map(|(index, source)| ShardEntry {
+                    input: source.to_string(),
+                    output: format!("shard-{:05}.bin", index),
+                    status: ShardStatus::Pending,
+                })
+                .collect(),
+        };
+
+        let path = self.manifest_path();
+        if !path.exists() {
+            return Ok(fresh);
+        }
+
+        let existing = Manifest::load(&path)?;
+        if existing.tokenizer != fresh.tokenizer {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} was written by a different tokenizer", path.display()),
+            ));
+        }
+        let same_inputs = existing.shards.len() == fresh.shards.len()
+            && existing
+                .shards
+                .iter()
+                .zip(&fresh.shards)
+                .all(|(old, new)| old.input == new.input && old.output == new.output);
+        if !same_inputs {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} lists different inputs", path.display()),
+            ));
+        }
+
+        Ok(existing)
+    }
+
+    fn tokenize_shard(&self, source: &CorpusSource, output: &Path) -> io::Result<ShardStatus> {
+        let contents = source.read()?;
+        let documents = self.segmentation.segments(&contents);
+
+        let mut writer = ShardWriter::new(Vec::new());
+        for mut ids in self.tokenizer.encode_batch(&documents) {
+            ids.extend(self.eos_id);
+            writer.write_document(&ids)?;
+        }
+        let (bytes, index) = writer.finish()?;
+        // The index goes first: a shard file in place always has a matching index.
+        write_atomically(&shard_index_path(output), &index.to_bytes())?;
+        write_atomically(output, &bytes)?;
+
+        Ok(ShardStatus::Done {
+            documents: index.len(),
+            tokens: index.tokens() as usize,
+            checksum: xxh3_64(&bytes),
+        })
+    }
+
+    /// Fingerprints everything that decides the shard contents, so a resumed job
+    /// cannot mix shards from two tokenizers or two job setups: the vocabulary, the
+    /// merges in rank order, every encoding setting, and this job's segmentation and
+    /// end-of-document ID.
+    fn fingerprint(&self) -> u64 {
+        let config = self.tokenizer.config();
+        let vocabulary = self.tokenizer.vocabulary();
+        let mut hasher = CorpusHasher::new();
+
+        // Each list is preceded by its length so items cannot shift between lists.
+        hasher.update(&vocabulary.len().to_string());
+        for (_, token) in vocabulary.iter() {
+            hasher.update(token);
+        }
+        hasher.update(&config.merges.len().to_string());
+        for (first, second) in &config.merges {
+            hasher.update(first);
+            hasher.update(second);
+        }
+        hasher.update(&config.special_tokens.len().to_string());
+        for token in &config.special_tokens {
+            hasher.update(token);
+        }
+        for setting in [
+            config.normalizer.describe(),
+            config.pre_tokenizer.describe(),
+            format!("{:?}", config.encode_strategy),
+            format!("{:?}", self.tokenizer.unknown_token()),
+            format!("{:?}", self.segmentation),
+            format!("{:?}", self.eos_id),
+        ] {
+            hasher.update(&setting);
+        }
+
+        hasher.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PreTokenizer;
+    use crate::test_support::merges;
+
+    fn write_input(dir: &Path, name: &str, contents: &str) -> CorpusSource {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        CorpusSource::from(path)
+    }
+
+    #[test]
+    fn manifest_roundtrips_through_text() {
+        let manifest = Manifest {
+            tokenizer: 0xabc,
+            shards: vec![
+                ShardEntry {
+                    input: "s3://bucket/a b.txt".to_string(),
+                    output: "shard-00000.bin".to_string(),
+                    status: ShardStatus::Done {
+                        documents: 2,
+                        tokens: 7,
+                        checksum: 42,
+                    },
+                },
+                ShardEntry {
+                    input: "b.txt".to_string(),
+                    output: "shard-00001.bin".to_string(),
+                    status: ShardStatus::Pending,
+                },
+            ],
+        };
+
+        assert_eq!(Manifest::parse(&manifest.format()).unwrap(), manifest);
+    }
+
+    #[test]
+    fn malformed_manifest_names_line() {
+        let err =
+            Manifest::parse("#bulk-manifest 1\ntokenizer\t0\nshard\tx\tfinished\ta\n").unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 3"));
+    }
+
+    #[test]
+    fn run_writes_tokens_with_eos() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(dir.path(), "a.txt", "A\nBC\n");
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+
+        let manifest = BulkTokenizer::new(&tokenizer, dir.path().join("out"))
+            .with_eos_id(0)
+            .run(&[input])
+            .unwrap();
+
+        let bytes = fs::read(dir.path().join("out/shard-00000.bin")).unwrap();
+        let ids: Vec<u32> = bytes
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(ids, vec![32, 0, 33, 34, 0]);
+        let mut reader = crate::ShardReader::open(dir.path().join("out/shard-00000.bin")).unwrap();
+        assert_eq!(reader.document(1).unwrap(), vec![33, 34, 0]);
+        assert!(matches!(
+            manifest.shards[0].status,
+            ShardStatus::Done {
+                documents: 2,
+                tokens: 5,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn rerun_skips_finished_shards_and_redoes_damaged_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = write_input(dir.path(), "a.txt", "A\n");
+        let second = write_input(dir.path(), "b.txt", "B\n");
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let job = BulkTokenizer::new(&tokenizer, dir.path().join("out"));
+        job.run(&[first.clone(), second.clone()]).unwrap();
+
+        // Changed inputs are not re-read for finished shards...
+        write_input(dir.path(), "a.txt", "AAAA\n");
+        write_input(dir.path(), "b.txt", "BBBB\n");
+        // ...unless their output no longer matches the manifest.
+        fs::write(dir.path().join("out/shard-00001.bin"), b"junk").unwrap();
+        let manifest = job.run(&[first, second]).unwrap();
+
+        assert_eq!(manifest.tokens(), 1 + 4);
+        assert_eq!(Manifest::load(job.manifest_path()).unwrap(), manifest);
+    }
+
+    #[test]
+    fn resume_rejects_different_tokenizer() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = write_input(dir.path(), "a.txt", "A\n");
+        let out = dir.path().join("out");
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        BulkTokenizer::new(&tokenizer, &out)
+            .run(std::slice::from_ref(&input))
+            .unwrap();
+
+        let other = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+        let err = BulkTokenizer::new(&other, &out).run(&[input]).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn fingerprint_covers_merges_settings_and_job_options() {
+        let base = BpeTokenizer::new(merges(&[("a", "b"), ("c", "d")]), vec![]);
+        // Same vocabulary, merges in the other rank order.
+        let reordered = BpeTokenizer::new(merges(&[("c", "d"), ("a", "b")]), vec![])
+            .with_vocabulary(base.vocabulary().clone())
+            .unwrap();
+        let lookahead = BpeTokenizer::new(merges(&[("a", "b"), ("c", "d")]), vec![])
+            .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true));
+        let fingerprint = |job: BulkTokenizer| job.fingerprint();
+
+        let original = fingerprint(BulkTokenizer::new(&base, "out"));
+
+        assert_eq!(fingerprint(BulkTokenizer::new(&base, "out")), original);
+        assert_ne!(fingerprint(BulkTokenizer::new(&reordered, "out")), original);
+        assert_ne!(fingerprint(BulkTokenizer::new(&lookahead, "out")), original);
+        assert_ne!(
+            fingerprint(BulkTokenizer::new(&base, "out").with_eos_id(0)),
+            original
+        );
+        assert_ne!(
+            fingerprint(
+                BulkTokenizer::new(&base, "out").with_segmentation(Segmentation::Paragraphs)
+            ),
+            original
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// Creates a mapping from bytes (0-255) to Unicode characters.
 ///
@@ -12,7 +12,8 @@ use std::collections::HashMap;
 ///
 /// # Returns
 ///
-/// A HashMap mapping each byte value (0-255) to its corresponding Unicode character.
+/// A map from each byte value (0-255) to its corresponding Unicode character. Iteration
+/// is in ascending byte order, so the result can be snapshotted directly.
 ///
 /// # Examples
 ///
@@ -23,8 +24,8 @@ use std::collections::HashMap;
 /// assert_eq!(mapping[&65], 'A');  // ASCII 'A' maps to itself
 /// assert_eq!(mapping[&0], 'Ā');   // Byte 0 maps to Unicode 256
 /// ```
-pub fn bytes_to_unicode() -> HashMap<u8, char> {
-    let mut byte_to_char = HashMap::new();
+pub fn bytes_to_unicode() -> BTreeMap<u8, char> {
+    let mut byte_to_char = BTreeMap::new();
     let mut n = 0u32;
 
     for b in 0u8..=255u8 {
@@ -46,7 +47,8 @@ pub fn bytes_to_unicode() -> HashMap<u8, char> {
 ///
 /// # Returns
 ///
-/// A HashMap mapping each Unicode character to its corresponding byte value (0-255).
+/// A map from each Unicode character to its corresponding byte value (0-255). Iteration
+/// is in ascending character order.
 ///
 /// # Examples
 ///
@@ -57,8 +59,8 @@ pub fn bytes_to_unicode() -> HashMap<u8, char> {
 /// assert_eq!(mapping[&'A'], 65);  // 'A' maps back to ASCII 65
 /// assert_eq!(mapping[&'Ā'], 0);   // Unicode 256 maps back to byte 0
 /// ```
-pub fn unicode_to_bytes() -> HashMap<char, u8> {
-    let mut byte_to_char = HashMap::new();
+pub fn unicode_to_bytes() -> BTreeMap<char, u8> {
+    let mut byte_to_char = BTreeMap::new();
     let mut n = 0u32;
 
     for b in 0u8..=255u8 {
@@ -96,4 +98,13 @@ mod tests {
         assert_eq!(mapping.get(&'Ċ'), Some(&10));
         assert_eq!(mapping.get(&'ÿ'), Some(&255));
     }
+
+    #[test]
+    fn mappings_iterate_in_key_order() {
+        let bytes: Vec<u8> = bytes_to_unicode().keys().copied().collect();
+        let chars: Vec<char> = unicode_to_bytes().keys().copied().collect();
+
+        assert_eq!(bytes, (0..=255).collect::<Vec<u8>>());
+        assert!(chars.windows(2).all(|pair| pair[0] < pair[1]));
+    }
 }
@@ -0,0 +1,359 @@
+use std::fmt;
+
+use tokenizers::models::bpe::{BPE, BpeTrainerBuilder};
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::{Tokenizer, TokenizerBuilder};
+
+use crate::shuffle::SplitMix64;
+use crate::{BpeTokenizer, PreTokenizer, Trainer, minimize_failure};
+
+/// Code point ranges random texts are drawn from, with relative weights. Spaces,
+/// ASCII and newlines dominate so that pre-tokenization and merges get exercised;
+/// the rest covers multi-byte UTF-8, combining marks and astral-plane characters.
+const ALPHABET: &[(u32, u32, u64)] = &[
+    (0x61, 0x7A, 30),      // a-z
+    (0x41, 0x5A, 6),       // A-Z
+    (0x30, 0x39, 6),       // 0-9
+    (0x20, 0x20, 20),      // space
+    (0x0A, 0x0A, 3),       // newline
+    (0x21, 0x2F, 5),       // punctuation
+    (0x27, 0x27, 3),       // apostrophe, for contractions
+    (0xE0, 0xFF, 4),       // Latin-1 letters
+    (0x300, 0x36F, 2),     // combining marks
+    (0x3B1, 0x3C9, 3),     // Greek
+    (0x4E00, 0x4E5F, 3),   // CJK
+    (0x1F600, 0x1F64F, 2), // emoji
+];
+
+/// The first difference found between this crate and HuggingFace `tokenizers`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// Training on the same corpus gave a different token at `id`.
+    Vocabulary {
+        /// Texts both tokenizers were trained on.
+        corpus: Vec<String>,
+        /// First ID whose token differs.
+        id: u32,
+        /// This crate's token at `id`, or `None` past the end of its vocabulary.
+        ours: Option<String>,
+        /// HuggingFace's token at `id`, or `None` past the end of its vocabulary.
+        theirs: Option<String>,
+    },
+    /// Both tokenizers have the same vocabulary but encode `text` differently.
+    Encoding {
+        /// Texts both tokenizers were trained on.
+        corpus: Vec<String>,
+        /// Corpus text or probe string whose encodings differ.
+        text: String,
+        /// Shortest substring of `text` that still encodes differently.
+        minimized: String,
+        /// This crate's encoding of `minimized`.
+        ours: Vec<u32>,
+        /// HuggingFace's encoding of `minimized`.
+        theirs: Vec<u32>,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Vocabulary {
+                corpus,
+                id,
+                ours,
+                theirs,
+            } => {
+                writeln!(
+                    f,
+                    "vocabulary differs at id {}: ours {:?}, HuggingFace {:?}",
+                    id, ours, theirs
+                )?;
+                write!(f, "training corpus: {:?}", corpus)
+            }
+            Divergence::Encoding {
+                corpus,
+                text,
+                minimized,
+                ours,
+                theirs,
+            } => {
+                writeln!(f, "encoding of {:?} differs", minimized)?;
+                writeln!(f, "  ours:        {:?}", ours)?;
+                writeln!(f, "  HuggingFace: {:?}", theirs)?;
+                writeln!(f, "found in text: {:?}", text)?;
+                write!(f, "training corpus: {:?}", corpus)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// Differential tester comparing this crate with HuggingFace `tokenizers` (feature
+/// `compat-fuzz`).
+///
+/// Both sides are configured like `tests/huggingface_compatibility.rs`: byte-level
+/// pre-tokenizer without prefix space (ours with
+/// [whitespace lookahead](PreTokenizer::with_whitespace_lookahead), as HuggingFace's
+/// regex has), full byte alphabet, no normalizer, and the same number of merges. Training results are compared token by token, then encodings of
+/// the corpus texts and of extra random strings are compared, and an encoding
+/// difference is shrunk with [`minimize_failure`] before it is reported.
+///
+/// Runs are reproducible from the seed.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::CompatFuzzer;
+///
+/// let fuzzer = CompatFuzzer::new(1).with_num_merges(0).with_cases(2);
+/// assert!(fuzzer.run().is_ok());
+///
+/// // Check parity on your own data.
+/// assert!(fuzzer.check_corpus(&["hello world", "hello there"]).is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatFuzzer {
+    seed: u64,
+    cases: usize,
+    num_merges: usize,
+    corpus_texts: usize,
+    probes: usize,
+    max_chars: usize,
+}
+
+impl CompatFuzzer {
+    /// Creates a fuzzer with the given seed and defaults of 20 cases, 30 merges,
+    /// corpora of 20 texts, 50 extra probe strings per case and texts of up to 40
+    /// characters.
+    pub fn new(seed: u64) -> Self {
+        CompatFuzzer {
+            seed,
+            cases: 20,
+            num_merges: 30,
+            corpus_texts: 20,
+            probes: 50,
+            max_chars: 40,
+        }
+    }
+
+    /// Sets the number of random corpora tried by [`run`](CompatFuzzer::run).
+    pub fn with_cases(mut self, cases: usize) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Sets the number of merges both trainers learn.
+    pub fn with_num_merges(mut self, num_merges: usize) -> Self {
+        self.num_merges = num_merges;
+        self
+    }
+
+    /// Sets the number of texts in each random corpus.
+    pub fn with_corpus_texts(mut self, corpus_texts: usize) -> Self {
+        self.corpus_texts = corpus_texts;
+        self
+    }
+
+    /// Sets the number of random strings encoded in addition to the corpus texts.
+    pub fn with_probes(mut self, probes: usize) -> Self {
+        self.probes = probes;
+        self
+    }
+
+    /// Sets the maximum length of random texts, in characters.
+    pub fn with_max_chars(mut self, max_chars: usize) -> Self {
+        self.max_chars = max_chars;
+        self
+    }
+
+    /// Trains both tokenizers on random corpora and compares them.
+    ///
+    /// Returns the number of cases checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`Divergence`] found.
+    pub fn run(&self) -> Result<usize, Divergence> {
+        let mut rng = SplitMix64::new(self.seed);
+
+        for _ in 0..self.cases {
+            let corpus = self.random_texts(&mut rng, self.corpus_texts);
+            let corpus: Vec<&str> = corpus.iter().map(String::as_str).collect();
+            let probes = self.random_texts(&mut rng, self.probes);
+            let probes: Vec<&str> = probes.iter().map(String::as_str).collect();
+
+            self.compare(&corpus, &probes)?;
+        }
+
+        Ok(self.cases)
+    }
+
+    /// Trains both tokenizers on `corpus` and compares their vocabularies and their
+    /// encodings of every corpus text and of random probe strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first [`Divergence`] found.
+    pub fn check_corpus(&self, corpus: &[&str]) -> Result<(), Divergence> {
+        let probes = self.random_texts(&mut SplitMix64::new(self.seed), self.probes);
+        let probes: Vec<&str> = probes.iter().map(String::as_str).collect();
+
+        self.compare(corpus, &probes)
+    }
+
+    fn compare(&self, corpus: &[&str], probes: &[&str]) -> Result<(), Divergence> {
+        let trainer = Trainer::new(self.num_merges)
+            .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true));
+        let ours = BpeTokenizer::from_trainer(&trainer, corpus, vec![]);
+        let theirs = train_huggingface(corpus, self.num_merges);
+        let owned_corpus = || corpus.iter().map(|text| text.to_string()).collect();
+
+        let our_vocab = ours.get_vocab();
+        let mut their_vocab: Vec<(String, u32)> = theirs.get_vocab(false).into_iter().collect();
+        their_vocab.sort_by_key(|(_, id)| *id);
+        let length = our_vocab.len().max(their_vocab.len());
+        if let Some(id) = (0..length).find(|&id| our_vocab.get(id) != their_vocab.get(id)) {
+            return Err(Divergence::Vocabulary {
+                corpus: owned_corpus(),
+                id: id as u32,
+                ours: our_vocab.get(id).map(|(token, _)| token.clone()),
+                theirs: their_vocab.get(id).map(|(token, _)| token.clone()),
+            });
+        }
+
+        let differs = |text: &str| ours.encode(text) != encode_huggingface(&theirs, text);
+        for text in corpus.iter().chain(probes) {
+            if let Some(minimized) = minimize_failure(text, differs) {
+                return Err(Divergence::Encoding {
+                    corpus: owned_corpus(),
+                    text: text.to_string(),
+                    minimized: minimized.to_string(),
+                    ours: ours.encode(minimized),
+                    theirs: encode_huggingface(&theirs, minimized),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn random_texts(&self, rng: &mut SplitMix64, count: usize) -> Vec<String> {
+        // Draw from a small pool of words so pairs repeat and merges get learned.
+        let words: Vec<String> = (0..8)
+            .map(|_| {
+                let length = 1 + rng.below(6) as usize;
+                (0..length).map(|_| random_char(rng)).collect()
+            })
+            .collect();
+
+        (0..count)
+            .map(|_| {
+                let mut text = String::new();
+                let length = rng.below(self.max_chars as u64 + 1) as usize;
+                while text.chars().count() < length {
+                    if rng.below(3) == 0 {
+                        text.push(random_char(rng));
+                    } else {
+                        text.push_str(&words[rng.below(words.len() as u64) as usize]);
+                    }
+                }
+                text.chars().take(length).collect()
+            })
+            .collect()
+    }
+}
+
+fn random_char(rng: &mut SplitMix64) -> char {
+    let total: u64 = ALPHABET.iter().map(|(_, _, weight)| weight).sum();
+    let mut pick = rng.below(total);
+
+    for &(start, end, weight) in ALPHABET {
+        if pick < weight {
+            let code = start + rng.below(u64::from(end - start + 1)) as u32;
+            return char::from_u32(code).expect("alphabet ranges hold valid scalar values");
+        }
+        pick -= weight;
+    }
+
+    unreachable!("pick is below the total weight")
+}
+
+fn train_huggingface(corpus: &[&str], num_merges: usize) -> Tokenizer {
+    let mut trainer = BpeTrainerBuilder::new()
+        .vocab_size(256 + num_merges)
+        .min_frequency(0)
+        .show_progress(false)
+        .initial_alphabet(ByteLevel::alphabet().into_iter().collect())
+        .build();
+
+    let mut tokenizer = TokenizerBuilder::new()
+        .with_model(BPE::default())
+        .with_pre_tokenizer(Some(ByteLevel::default().add_prefix_space(false)))
+        .with_decoder(Some(tokenizers::decoders::byte_level::ByteLevel::default()))
+        .with_normalizer(None::<tokenizers::normalizers::Sequence>)
+        .with_post_processor(None::<tokenizers::processors::sequence::Sequence>)
+        .build()
+        .expect("static HuggingFace configuration is valid");
+    tokenizer
+        .train(&mut trainer, corpus.iter())
+        .expect("HuggingFace training on in-memory texts cannot fail");
+
+    tokenizer.into()
+}
+
+fn encode_huggingface(tokenizer: &Tokenizer, text: &str) -> Vec<u32> {
+    tokenizer
+        .encode(text, false)
+        .expect("byte-level encoding cannot fail")
+        .get_ids()
+        .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_texts_are_reproducible_and_bounded() {
+        let fuzzer = CompatFuzzer::new(9).with_max_chars(12);
+
+        let first = fuzzer.random_texts(&mut SplitMix64::new(9), 30);
+        let second = fuzzer.random_texts(&mut SplitMix64::new(9), 30);
+
+        assert_eq!(first, second);
+        assert!(first.iter().all(|text| text.chars().count() <= 12));
+        assert!(first.iter().any(|text| !text.is_ascii()));
+    }
+
+    #[test]
+    fn byte_level_encoding_matches_without_merges() {
+        let fuzzer = CompatFuzzer::new(3).with_num_merges(0).with_cases(3);
+
+        assert_eq!(fuzzer.run(), Ok(3));
+    }
+
+    #[test]
+    fn runs_of_spaces_match() {
+        let corpus = ["a  dog  a  dog", "x   y   x   y", "  the\n\n  cat  "];
+
+        assert_eq!(
+            CompatFuzzer::new(1)
+                .with_num_merges(10)
+                .check_corpus(&corpus),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn merges_match_on_untied_corpus() {
+        let corpus = ["aaab aaab aaab", "aab aab", "b"];
+
+        assert_eq!(
+            CompatFuzzer::new(0)
+                .with_num_merges(3)
+                .check_corpus(&corpus),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+use crate::{EncodeStrategy, Metadata, Normalizer, PreTokenizer, UnknownIdMode, Vocabulary};
+
+/// The complete state of a [`BpeTokenizer`](crate::BpeTokenizer) as plain data.
+///
+/// Returned by [`BpeTokenizer::config`](crate::BpeTokenizer::config) and turned back
+/// into a tokenizer by [`BpeTokenizer::from_config`](crate::BpeTokenizer::from_config).
+/// `vocabulary` is only needed when IDs differ from the layout [`Vocabulary::new`]
+/// derives from the special tokens and merges, for example after
+/// [`rank_ids_by_frequency`](crate::BpeTokenizer::rank_ids_by_frequency) or when
+/// loading GPT-2's files.
+///
+/// With the `serde` feature enabled, a `TokenizerConfig` serializes to an object with
+/// one entry per field, so applications can embed a tokenizer in their own
+/// configuration files and wire formats. Every field except `merges` may be left out
+/// and takes its default.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, EncodeStrategy, TokenizerConfig};
+///
+/// let config = TokenizerConfig {
+///     merges: vec![("h".to_string(), "i".to_string())],
+///     special_tokens: vec!["<|endoftext|>".to_string()],
+///     encode_strategy: EncodeStrategy::LongestMatch,
+///     ..TokenizerConfig::default()
+/// };
+/// let tokenizer = BpeTokenizer::from_config(config).unwrap();
+///
+/// assert_eq!(tokenizer.encode("hi<|endoftext|>"), vec![257, 0]);
+/// assert_eq!(tokenizer.config().encode_strategy, EncodeStrategy::LongestMatch);
+/// ```
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TokenizerConfig {
+    /// Tokens by ID, or `None` for the layout of [`Vocabulary::new`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub vocabulary: Option<Vocabulary>,
+    /// Merge rules in rank order.
+    pub merges: Vec<(String, String)>,
+    /// Special tokens, matched in text before pre-tokenization.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub special_tokens: Vec<String>,
+    /// Normalization applied before encoding.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub normalizer: Normalizer,
+    /// Rules for splitting text before merging.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub pre_tokenizer: PreTokenizer,
+    /// How pre-tokenized words are segmented.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub encode_strategy: EncodeStrategy,
+    /// How lenient decoding renders unknown IDs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unknown_id_mode: UnknownIdMode,
+    /// Descriptive metadata.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metadata: Metadata,
+}
+
+/// Error returned by [`BpeTokenizer::from_config`](crate::BpeTokenizer::from_config)
+/// when the vocabulary does not give one ID to each token the merges and special
+/// tokens define.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError(pub(crate) String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "inconsistent tokenizer config: {}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BpeTokenizer;
+    use crate::test_support;
+
+    fn trained() -> BpeTokenizer {
+        test_support::trained(&["the cat sat on the mat", "the end"], 10)
+    }
+
+    #[test]
+    fn default_layout_leaves_out_the_vocabulary() {
+        let tokenizer = trained();
+        assert!(tokenizer.config().vocabulary.is_none());
+
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["the the the"]);
+        let config = ranked.config();
+        assert!(config.vocabulary.is_some());
+
+        let rebuilt = BpeTokenizer::from_config(config).unwrap();
+        assert_eq!(rebuilt.encode("the cat"), ranked.encode("the cat"));
+    }
+
+    #[test]
+    fn rejects_a_vocabulary_that_does_not_match_the_merges() {
+        let config = TokenizerConfig {
+            vocabulary: Some(BpeTokenizer::new(vec![], vec![]).vocabulary().clone()),
+            ..trained().config()
+        };
+
+        let err = BpeTokenizer::from_config(config).err().unwrap();
+        assert!(err.to_string().contains("tokens"), "{}", err);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_every_setting() {
+        use crate::{ControlChars, NewlineMode};
+
+        let normalizer = Normalizer::new()
+            .with_nfc(true)
+            .with_newlines(NewlineMode::Unix)
+            .with_control_chars(ControlChars::Replace(' '));
+        #[cfg(feature = "regex")]
+        let pre_tokenizer = PreTokenizer::with_regex(regex::Regex::new(r"\w+|\s").unwrap());
+        #[cfg(not(feature = "regex"))]
+        let pre_tokenizer = PreTokenizer::new();
+        let (tokenizer, _) = trained()
+            .with_normalizer(normalizer)
+            .with_pre_tokenizer(pre_tokenizer.with_whitespace_runs(&[4]))
+            .with_unknown_id_mode(UnknownIdMode::Skip)
+            .with_metadata(Metadata::new().with_name("cats"))
+            .rank_ids_by_frequency(&["mat mat"]);
+
+        let json = serde_json::to_string(&tokenizer.config()).unwrap();
+        let config: TokenizerConfig = serde_json::from_str(&json).unwrap();
+        let rebuilt = BpeTokenizer::from_config(config).unwrap();
+
+        let text = "the\r\ncat  \u{7}      sat";
+        assert_eq!(rebuilt.encode(text), tokenizer.encode(text));
+        assert_eq!(
+            rebuilt.decode_lenient(&[99_999, 0]),
+            tokenizer.decode_lenient(&[99_999, 0])
+        );
+        assert_eq!(rebuilt.metadata(), tokenizer.metadata());
+        assert_eq!(serde_json::to_string(&rebuilt.config()).unwrap(), json);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_fills_in_defaults() {
+        let json = r#"{"merges": [["h", "i"]], "special_tokens": ["<s>"]}"#;
+        let config: TokenizerConfig = serde_json::from_str(json).unwrap();
+
+        let tokenizer = BpeTokenizer::from_config(config).unwrap();
+        assert_eq!(tokenizer.encode("<s>hi"), vec![0, 257]);
+
+        let vocabulary: Vocabulary = serde_json::from_str(r#"["a", "b"]"#).unwrap();
+        assert_eq!(vocabulary.token_to_id("b"), Some(1));
+        assert_eq!(serde_json::to_string(&vocabulary).unwrap(), r#"["a","b"]"#);
+    }
+}
@@ -0,0 +1,177 @@
+use std::io::{self, BufRead};
+
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::Normalizer;
+
+/// Streaming fingerprint of a training corpus.
+///
+/// Each text is normalized, then fed to 64-bit XXH3 with its byte length in front,
+/// so `["ab", "c"]` and `["a", "bc"]` hash differently. The hash does not depend on
+/// how the corpus is chunked into calls, which lets a corpus be hashed from a file
+/// line by line and still match the hash of the same lines held in memory.
+///
+/// [`Trainer`](crate::Trainer) records this hash, computed with its own normalizer, in
+/// [`TrainConfig::corpus_hash`](crate::TrainConfig::corpus_hash).
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::CorpusHasher;
+///
+/// let mut hasher = CorpusHasher::new();
+/// hasher.update_lines("hello world\nhello rust\n".as_bytes()).unwrap();
+///
+/// assert_eq!(hasher.texts(), 2);
+/// assert_eq!(hasher.finish(), CorpusHasher::hash(&["hello world", "hello rust"]));
+/// ```
+#[derive(Clone)]
+pub struct CorpusHasher {
+    normalizer: Normalizer,
+    state: Xxh3,
+    texts: usize,
+}
+
+impl CorpusHasher {
+    /// Creates a hasher that hashes texts as given.
+    pub fn new() -> Self {
+        Self::with_normalizer(Normalizer::new())
+    }
+
+    /// Creates a hasher that normalizes each text before hashing it.
+    ///
+    /// Corpora that differ only in ways the normalizer erases hash the same, just as
+    /// they train the same merges.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{CorpusHasher, NewlineMode, Normalizer};
+    ///
+    /// let unix = Normalizer::new().with_newlines(NewlineMode::Unix);
+    ///
+    /// assert_eq!(
+    ///     CorpusHasher::hash_with(&unix, &["a\r\nb"]),
+    ///     CorpusHasher::hash_with(&unix, &["a\nb"]),
+    /// );
+    /// ```
+    pub fn with_normalizer(normalizer: Normalizer) -> Self {
+        Self {
+            normalizer,
+            state: Xxh3::new(),
+            texts: 0,
+        }
+    }
+
+    /// Adds one text to the hash.
+    pub fn update(&mut self, text: &str) {
+        let normalized = self.normalizer.normalize(text);
+        self.state.update(&(normalized.len() as u64).to_le_bytes());
+        self.state.update(normalized.as_bytes());
+        self.texts += 1;
+    }
+
+    /// Adds every line of `reader` to the hash as a separate text.
+    ///
+    /// Line terminators (`\n` or `\r\n`) are not part of the hashed text.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from `reader`, or [`io::ErrorKind::InvalidData`] if a
+    /// line is not valid UTF-8. Lines read before the error remain in the hash.
+    pub fn update_lines<R: BufRead>(&mut self, reader: R) -> io::Result<()> {
+        for line in reader.lines() {
+            self.update(&line?);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of texts hashed so far.
+    pub fn texts(&self) -> usize {
+        self.texts
+    }
+
+    /// Returns the hash of the texts added so far.
+    pub fn finish(&self) -> u64 {
+        self.state.digest()
+    }
+
+    /// Hashes `texts` without normalization.
+    pub fn hash(texts: &[&str]) -> u64 {
+        Self::hash_with(&Normalizer::new(), texts)
+    }
+
+    /// Hashes `texts` after normalizing them with a copy of `normalizer`.
+    ///
+    /// The copy has its own counters, so hashing does not show up in
+    /// [`Normalizer::stats`].
+    pub fn hash_with(normalizer: &Normalizer, texts: &[&str]) -> u64 {
+        let mut hasher = Self::with_normalizer(normalizer.clone());
+        for text in texts {
+            hasher.update(text);
+        }
+
+        hasher.finish()
+    }
+}
+
+impl Default for CorpusHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_depends_on_text_boundaries() {
+        assert_eq!(
+            CorpusHasher::hash(&["ab", "c"]),
+            CorpusHasher::hash(&["ab", "c"])
+        );
+        assert_ne!(
+            CorpusHasher::hash(&["ab", "c"]),
+            CorpusHasher::hash(&["a", "bc"])
+        );
+        assert_ne!(CorpusHasher::hash(&[]), CorpusHasher::hash(&[""]));
+    }
+
+    #[test]
+    fn update_lines_strips_line_terminators() {
+        let mut hasher = CorpusHasher::new();
+        hasher.update_lines("one\r\ntwo\nthree".as_bytes()).unwrap();
+
+        assert_eq!(hasher.texts(), 3);
+        assert_eq!(
+            hasher.finish(),
+            CorpusHasher::hash(&["one", "two", "three"])
+        );
+    }
+
+    #[test]
+    fn normalizer_erases_differences() {
+        let nfc = Normalizer::new().with_nfc(true).with_stats(true);
+
+        assert_eq!(
+            CorpusHasher::hash_with(&nfc, &["cafe\u{301}"]),
+            CorpusHasher::hash_with(&nfc, &["caf\u{e9}"])
+        );
+        assert_ne!(
+            CorpusHasher::hash(&["cafe\u{301}"]),
+            CorpusHasher::hash(&["caf\u{e9}"])
+        );
+        assert_eq!(nfc.stats().inputs, 0);
+    }
+
+    #[test]
+    fn update_lines_rejects_invalid_utf8() {
+        let mut hasher = CorpusHasher::new();
+        let err = hasher.update_lines(&b"ok\n\xff\n"[..]).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(hasher.texts(), 1);
+    }
+}
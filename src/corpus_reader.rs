@@ -0,0 +1,363 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Compression formats recognized when reading a corpus, chosen by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn from_name(name: &str) -> Self {
+        match Path::new(name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Where a training corpus is read from.
+///
+/// Local paths are read from disk. `s3://` and `gs://` URIs are fetched from the object
+/// store directly, which needs the `object-store` feature; credentials and region come
+/// from the usual environment variables (`AWS_ACCESS_KEY_ID`, `AWS_REGION`,
+/// `GOOGLE_SERVICE_ACCOUNT`, ...). Either kind is decompressed according to its
+/// extension, as described in [`read_corpus`].
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::CorpusSource;
+///
+/// assert!(matches!(
+///     CorpusSource::parse("s3://corpora/web/part-0000.txt.zst"),
+///     CorpusSource::Object(_)
+/// ));
+/// assert!(matches!(
+///     CorpusSource::parse("data/part-0000.txt"),
+///     CorpusSource::Path(_)
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorpusSource {
+    /// A file on the local file system.
+    Path(PathBuf),
+    /// An object-store URI such as `s3://bucket/key` or `gs://bucket/key`.
+    Object(String),
+}
+
+impl CorpusSource {
+    /// URI schemes treated as object-store locations by [`parse`](CorpusSource::parse).
+    pub const OBJECT_SCHEMES: [&'static str; 2] = ["s3://", "gs://"];
+
+    /// Interprets `location` as an object-store URI if it starts with one of
+    /// [`OBJECT_SCHEMES`](CorpusSource::OBJECT_SCHEMES), and as a local path otherwise.
+    pub fn parse(location: &str) -> Self {
+        if Self::OBJECT_SCHEMES
+            .iter()
+            .any(|scheme| location.starts_with(scheme))
+        {
+            CorpusSource::Object(location.to_string())
+        } else {
+            CorpusSource::Path(PathBuf::from(location))
+        }
+    }
+
+    /// Reads the whole corpus as UTF-8 text, decompressing it if needed.
+    ///
+    /// Objects are read chunk by chunk as the store sends them and decompressed on
+    /// the fly, so only the decompressed text is held in memory and nothing is
+    /// written to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source cannot be read or decompressed, is not valid
+    /// UTF-8, or needs a feature that is disabled ([`io::ErrorKind::Unsupported`]).
+    pub fn read(&self) -> io::Result<String> {
+        let reader: Box<dyn Read> = match self {
+            CorpusSource::Path(path) => Box::new(BufReader::new(File::open(path)?)),
+            CorpusSource::Object(uri) => open_object(uri)?,
+        };
+
+        let mut contents = String::new();
+        decompress(reader, self)?.read_to_string(&mut contents)?;
+
+        Ok(contents)
+    }
+}
+
+impl From<PathBuf> for CorpusSource {
+    fn from(path: PathBuf) -> Self {
+        CorpusSource::Path(path)
+    }
+}
+
+impl From<&Path> for CorpusSource {
+    fn from(path: &Path) -> Self {
+        CorpusSource::Path(path.to_path_buf())
+    }
+}
+
+impl fmt::Display for CorpusSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorpusSource::Path(path) => write!(f, "{}", path.display()),
+            CorpusSource::Object(uri) => write!(f, "{}", uri),
+        }
+    }
+}
+
+/// Reads a UTF-8 corpus file, decompressing `.gz` and `.zst` files on the fly.
+///
+/// Compressed files are decoded while reading, so only the decompressed text is held
+/// in memory and nothing is written to disk. Gzip support needs the `gzip` feature and
+/// zstd support the `zstd` feature; any other extension is read as plain text.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not valid compressed data, is not
+/// valid UTF-8 once decompressed, or needs a compression feature that is disabled
+/// ([`io::ErrorKind::Unsupported`]).
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::read_corpus;
+///
+/// let dir = tempfile::tempdir().unwrap();
+/// let path = dir.path().join("corpus.txt");
+/// std::fs::write(&path, "hello world\n").unwrap();
+///
+/// assert_eq!(read_corpus(&path).unwrap(), "hello world\n");
+/// ```
+pub fn read_corpus(path: impl AsRef<Path>) -> io::Result<String> {
+    CorpusSource::from(path.as_ref()).read()
+}
+
+fn decompress(reader: Box<dyn Read>, source: &CorpusSource) -> io::Result<Box<dyn Read>> {
+    match Compression::from_name(&source.to_string()) {
+        Compression::None => Ok(reader),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+        #[cfg(not(feature = "gzip"))]
+        Compression::Gzip => Err(missing_feature(source, "gzip")),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::new(reader)?)),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(missing_feature(source, "zstd")),
+    }
+}
+
+#[cfg(feature = "object-store")]
+fn open_object(uri: &str) -> io::Result<Box<dyn Read>> {
+    let url = url::Url::parse(uri).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid object URI {}: {}", uri, e),
+        )
+    })?;
+    let options = std::env::vars().map(|(key, value)| (key.to_ascii_lowercase(), value));
+    let (store, path) = object_store::parse_url_opts(&url, options)?;
+
+    Ok(Box::new(ObjectReader::open(store.as_ref(), &path)?))
+}
+
+#[cfg(not(feature = "object-store"))]
+fn open_object(uri: &str) -> io::Result<Box<dyn Read>> {
+    Err(missing_feature(
+        &CorpusSource::Object(uri.to_string()),
+        "object-store",
+    ))
+}
+
+/// Reads an object in the chunks the store sends, fetching the next chunk only once
+/// the previous one has been consumed.
+#[cfg(feature = "object-store")]
+struct ObjectReader {
+    runtime: tokio::runtime::Runtime,
+    stream: futures::stream::BoxStream<'static, object_store::Result<bytes::Bytes>>,
+    chunk: bytes::Bytes,
+}
+
+#[cfg(feature = "object-store")]
+impl ObjectReader {
+    fn open(
+        store: &dyn object_store::ObjectStore,
+        path: &object_store::path::Path,
+    ) -> io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let stream = runtime.block_on(store.get(path))?.into_stream();
+
+        Ok(ObjectReader {
+            runtime,
+            stream,
+            chunk: bytes::Bytes::new(),
+        })
+    }
+}
+
+#[cfg(feature = "object-store")]
+impl Read for ObjectReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        use futures::StreamExt;
+
+        while self.chunk.is_empty() {
+            match self.runtime.block_on(self.stream.next()) {
+                Some(chunk) => self.chunk = chunk?,
+                None => return Ok(0),
+            }
+        }
+
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
+}
+
+#[cfg(not(all(feature = "gzip", feature = "zstd", feature = "object-store")))]
+fn missing_feature(source: &CorpusSource, feature: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("reading {} requires the `{}` feature", source, feature),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn compression_follows_extension() {
+        assert_eq!(Compression::from_name("a.txt.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_name("a.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_name("a.txt"), Compression::None);
+        assert_eq!(Compression::from_name("gz"), Compression::None);
+    }
+
+    #[test]
+    fn plain_files_must_be_utf8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt");
+        fs::write(&path, b"\xff").unwrap();
+
+        let err = read_corpus(&path).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn reads_concatenated_gzip_members() {
+        use flate2::{Compression as Level, write::GzEncoder};
+        use std::io::Write;
+
+        let mut compressed = Vec::new();
+        for part in ["hello ", "world\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            compressed.extend(encoder.finish().unwrap());
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt.gz");
+        fs::write(&path, compressed).unwrap();
+
+        assert_eq!(read_corpus(&path).unwrap(), "hello world\n");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn reads_zstd() {
+        let compressed = zstd::encode_all(&b"hello world\n"[..], 0).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt.zst");
+        fs::write(&path, compressed).unwrap();
+
+        assert_eq!(read_corpus(&path).unwrap(), "hello world\n");
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn gzip_without_feature_is_unsupported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("corpus.txt.gz");
+        fs::write(&path, b"").unwrap();
+
+        let err = read_corpus(&path).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("`gzip` feature"));
+    }
+
+    #[test]
+    fn parse_recognizes_object_schemes() {
+        assert_eq!(
+            CorpusSource::parse("gs://bucket/a.txt"),
+            CorpusSource::Object("gs://bucket/a.txt".to_string())
+        );
+        assert_eq!(
+            CorpusSource::parse("s3.txt"),
+            CorpusSource::Path(PathBuf::from("s3.txt"))
+        );
+    }
+
+    #[cfg(not(feature = "object-store"))]
+    #[test]
+    fn objects_without_feature_are_unsupported() {
+        let err = CorpusSource::parse("s3://bucket/a.txt").read().unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("`object-store` feature"));
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn object_reader_reads_every_byte_in_small_reads() {
+        use object_store::ObjectStore;
+        use object_store::memory::InMemory;
+        use object_store::path::Path as ObjectPath;
+
+        let store = InMemory::new();
+        let path = ObjectPath::from("corpus.txt");
+        let contents = "hello world\n".repeat(1000);
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime
+            .block_on(store.put(&path, contents.clone().into_bytes().into()))
+            .unwrap();
+
+        let mut reader = ObjectReader::open(&store, &path).unwrap();
+        let mut read = Vec::new();
+        let mut buf = [0; 7];
+        loop {
+            let len = reader.read(&mut buf).unwrap();
+            if len == 0 {
+                break;
+            }
+            read.extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(read, contents.as_bytes());
+    }
+
+    #[cfg(feature = "object-store")]
+    #[test]
+    fn malformed_object_uri_is_invalid_input() {
+        let err = CorpusSource::Object("s3://".to_string())
+            .read()
+            .unwrap_err();
+
+        assert_ne!(err.kind(), io::ErrorKind::Unsupported);
+    }
+}
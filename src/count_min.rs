@@ -0,0 +1,297 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::f64::consts::E;
+
+use xxhash_rust::xxh3::{xxh3_64, xxh3_64_with_seed};
+
+/// Approximate pair counting for [`Trainer::with_sketch_counting`](crate::Trainer::with_sketch_counting).
+///
+/// Exact training keeps a table with every distinct adjacent pair, which on very
+/// large or noisy corpora can dwarf the corpus itself. In sketch mode each merge
+/// step instead streams every pair into a count-min sketch of `depth` rows of
+/// `width` counters, keeps the `candidates` pairs with the highest estimates, and
+/// recounts those candidates exactly to pick the merge. Memory for pair counts is
+/// then fixed at `width * depth` counters plus the candidates, whatever the corpus.
+///
+/// A count-min sketch only overestimates. With probability at least
+/// `1 - e^-depth`, no estimate exceeds the true count by more than
+/// `e / width` times the total number of pair occurrences. The chosen merge is
+/// recounted exactly, so the only possible error is missing the most frequent pair
+/// because noise pushed it out of the candidates, and [`SketchAccuracy`] reports
+/// after every step whether that could have happened. With the default 1024
+/// candidates, merges almost always match exact training; ties between equally
+/// frequent pairs are the most common difference. The sketch only saves memory
+/// when the corpus has far more distinct pairs than `width * depth`.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{SketchCounting, Trainer};
+///
+/// let corpus = ["the theme of the thesis", "then there were three"];
+/// let sketched = Trainer::new(10)
+///     .with_sketch_counting(SketchCounting::new(1 << 12, 4))
+///     .train(&corpus);
+///
+/// assert_eq!(sketched, Trainer::new(10).train(&corpus));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SketchCounting {
+    width: usize,
+    depth: usize,
+    candidates: usize,
+}
+
+impl SketchCounting {
+    /// Creates a sketch of `depth` rows of `width` counters that recounts the 1024
+    /// pairs with the highest estimates.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` or `depth` is zero.
+    pub fn new(width: usize, depth: usize) -> Self {
+        assert!(
+            width > 0 && depth > 0,
+            "sketch width and depth must be positive"
+        );
+
+        Self {
+            width,
+            depth,
+            candidates: 1024,
+        }
+    }
+
+    /// Sets how many pairs with the highest estimates are recounted exactly.
+    ///
+    /// More candidates make missing the most frequent pair less likely at the cost
+    /// of a second, larger pass per merge.
+    pub fn with_candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates.max(1);
+        self
+    }
+
+    /// Returns the number of counters per row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Returns how many candidates are recounted exactly.
+    pub fn candidates(&self) -> usize {
+        self.candidates
+    }
+
+    /// Returns the bytes held by the sketch counters.
+    pub fn sketch_bytes(&self) -> usize {
+        self.width * self.depth * size_of::<usize>()
+    }
+
+    /// Returns the overestimate that no count exceeds with probability at least
+    /// [`confidence`](SketchCounting::confidence), for `total` pair occurrences.
+    pub fn error_bound(&self, total: usize) -> usize {
+        (E / self.width as f64 * total as f64).ceil() as usize
+    }
+
+    /// Returns the probability that every estimate is within
+    /// [`error_bound`](SketchCounting::error_bound), `1 - e^-depth`.
+    pub fn confidence(&self) -> f64 {
+        1.0 - (-(self.depth as f64)).exp()
+    }
+
+    /// Returns the stable name recorded in [`TrainConfig::pair_counting`](crate::TrainConfig::pair_counting).
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "count-min width={} depth={} candidates={}",
+            self.width, self.depth, self.candidates
+        )
+    }
+}
+
+impl Default for SketchCounting {
+    /// A sketch of 4 rows of 2^20 counters, 32 MiB on 64-bit targets.
+    fn default() -> Self {
+        Self::new(1 << 20, 4)
+    }
+}
+
+/// How close the sketched counts of one merge step came to exact counting,
+/// reported in [`TrainProgress::sketch`](crate::TrainProgress::sketch).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SketchAccuracy {
+    /// Overestimate that, with the sketch's
+    /// [`confidence`](SketchCounting::confidence), no estimate exceeds.
+    pub error_bound: usize,
+    /// Highest estimate among pairs that were not recounted, or zero if every pair
+    /// was. Estimates never undercount, so no left-out pair is more frequent.
+    pub excluded_estimate: usize,
+    /// Exact count of the pair chosen to merge next.
+    pub chosen_count: usize,
+}
+
+impl SketchAccuracy {
+    /// Returns whether the chosen pair is certainly the most frequent one, which
+    /// holds when every left-out pair's estimate is below its exact count. Ties with
+    /// left-out pairs may still be broken differently than in exact training.
+    pub fn is_certain(&self) -> bool {
+        self.excluded_estimate < self.chosen_count
+    }
+}
+
+/// Count-min sketch over symbol pairs.
+pub(crate) struct CountMinSketch {
+    width: usize,
+    depth: usize,
+    counters: Vec<usize>,
+}
+
+impl CountMinSketch {
+    pub(crate) fn new(counting: &SketchCounting) -> Self {
+        Self {
+            width: counting.width,
+            depth: counting.depth,
+            counters: vec![0; counting.width * counting.depth],
+        }
+    }
+
+    pub(crate) fn add(&mut self, first: &str, second: &str, count: usize) {
+        for index in self.cells(first, second) {
+            self.counters[index] += count;
+        }
+    }
+
+    pub(crate) fn estimate(&self, first: &str, second: &str) -> usize {
+        self.cells(first, second)
+            .map(|index| self.counters[index])
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Counter index of the pair in each row, from two hashes combined as in
+    /// Kirsch and Mitzenmacher's double hashing.
+    fn cells(&self, first: &str, second: &str) -> impl Iterator<Item = usize> + use<> {
+        let hash = xxh3_64_with_seed(second.as_bytes(), xxh3_64(first.as_bytes()));
+        let step = hash.rotate_left(32) | 1;
+        let width = self.width as u64;
+
+        (0..self.depth as u64).map(move |row| {
+            (row * width + hash.wrapping_add(row.wrapping_mul(step)) % width) as usize
+        })
+    }
+}
+
+/// The `limit` pairs with the highest estimates, ties going to the greater pair,
+/// kept while streaming estimates.
+pub(crate) struct TopCandidates<'a> {
+    limit: usize,
+    members: HashSet<(&'a str, &'a str)>,
+    /// Min-heap of the members by estimate, so the weakest is evicted first.
+    heap: BinaryHeap<Reverse<(usize, (&'a str, &'a str))>>,
+    /// Highest estimate of a pair turned away or evicted.
+    excluded: usize,
+}
+
+impl<'a> TopCandidates<'a> {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            members: HashSet::with_capacity(limit),
+            heap: BinaryHeap::with_capacity(limit + 1),
+            excluded: 0,
+        }
+    }
+
+    pub(crate) fn offer(&mut self, pair: (&'a str, &'a str), estimate: usize) {
+        if self.members.contains(&pair) {
+            return;
+        }
+        if self.members.len() == self.limit {
+            // Ties are broken by the pair itself, so the candidates do not depend
+            // on the order pairs are offered in.
+            let Reverse(floor) = *self.heap.peek().expect("a full set has members");
+            if (estimate, pair) <= floor {
+                self.excluded = self.excluded.max(estimate);
+                return;
+            }
+            let Reverse((evicted_estimate, evicted)) = self.heap.pop().expect("non-empty");
+            self.members.remove(&evicted);
+            self.excluded = self.excluded.max(evicted_estimate);
+        }
+
+        self.members.insert(pair);
+        self.heap.push(Reverse((estimate, pair)));
+    }
+
+    /// Returns the candidate pairs and the highest estimate left out.
+    pub(crate) fn finish(self) -> (Vec<(&'a str, &'a str)>, usize) {
+        (self.members.into_iter().collect(), self.excluded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_never_undercount() {
+        let counting = SketchCounting::new(8, 3);
+        let mut sketch = CountMinSketch::new(&counting);
+        let pairs: Vec<(String, String)> = (0..50)
+            .map(|n| (format!("a{}", n), format!("b{}", n % 7)))
+            .collect();
+
+        for (count, (first, second)) in (1..).zip(&pairs) {
+            sketch.add(first, second, count);
+        }
+
+        for (count, (first, second)) in (1..).zip(&pairs) {
+            assert!(sketch.estimate(first, second) >= count);
+        }
+        assert_eq!(counting.sketch_bytes(), 8 * 3 * size_of::<usize>());
+    }
+
+    #[test]
+    fn wide_sketch_counts_few_pairs_exactly() {
+        let mut sketch = CountMinSketch::new(&SketchCounting::new(1 << 16, 4));
+
+        sketch.add("a", "b", 3);
+        sketch.add("a", "b", 2);
+        sketch.add("b", "a", 7);
+
+        assert_eq!(sketch.estimate("a", "b"), 5);
+        assert_eq!(sketch.estimate("b", "a"), 7);
+        assert_eq!(sketch.estimate("ab", ""), 0);
+    }
+
+    #[test]
+    fn top_candidates_keep_highest_estimates() {
+        let mut top = TopCandidates::new(2);
+
+        top.offer(("a", "b"), 5);
+        top.offer(("c", "d"), 1);
+        top.offer(("a", "b"), 5);
+        top.offer(("e", "f"), 9);
+        top.offer(("g", "h"), 3);
+
+        let (mut pairs, excluded) = top.finish();
+        pairs.sort();
+        assert_eq!(pairs, vec![("a", "b"), ("e", "f")]);
+        assert_eq!(excluded, 3);
+    }
+
+    #[test]
+    fn error_bound_scales_with_total_and_width() {
+        let counting = SketchCounting::new(1000, 5);
+
+        assert_eq!(counting.error_bound(0), 0);
+        assert_eq!(counting.error_bound(1_000_000), 2719);
+        assert!(counting.confidence() > 0.99);
+        assert!(
+            SketchCounting::new(2000, 5).error_bound(1_000_000) < counting.error_bound(1_000_000)
+        );
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+/// Error returned when a benchmark dataset cannot be fetched or verified.
+#[derive(Debug)]
+pub enum DatasetError {
+    /// Reading or writing the cache failed.
+    Io(io::Error),
+    /// The download failed.
+    Http(String),
+    /// The downloaded file does not match the pinned checksum.
+    ChecksumMismatch { expected: String, actual: String },
+    /// The dataset name is not a plain file name, so it could point outside the
+    /// cache directory.
+    InvalidName(String),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(err) => write!(f, "dataset cache error: {}", err),
+            DatasetError::Http(err) => write!(f, "dataset download failed: {}", err),
+            DatasetError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "dataset checksum mismatch: expected sha256 {}, got {}",
+                expected, actual
+            ),
+            DatasetError::InvalidName(name) => {
+                write!(f, "dataset name {:?} is not a plain file name", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DatasetError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DatasetError {
+    fn from(err: io::Error) -> Self {
+        DatasetError::Io(err)
+    }
+}
+
+/// A benchmark text file pinned by URL and SHA-256 checksum.
+///
+/// Pinning the checksum makes comparisons between tokenizers reproducible: every
+/// user evaluates on byte-identical text, and a changed upstream file is reported
+/// instead of silently skewing results.
+///
+/// # Examples
+///
+/// ```no_run
+/// use bpe_tokenizer_rs::Dataset;
+///
+/// for dataset in Dataset::pinned() {
+///     let text = dataset.load(Dataset::default_cache_dir()).unwrap();
+///     println!("{}: {} bytes", dataset.name(), text.len());
+/// }
+///
+/// let dataset = Dataset::new(
+///     "sample",
+///     "https://example.com/sample.txt",
+///     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+/// );
+///
+/// let text = dataset.load(Dataset::default_cache_dir()).unwrap();
+/// let lines: Vec<&str> = text.lines().collect();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dataset {
+    name: String,
+    url: String,
+    sha256: String,
+}
+
+impl Dataset {
+    /// Describes a dataset stored as `name` in the cache, downloaded from `url` and
+    /// expected to have the given hex-encoded SHA-256 checksum.
+    pub fn new(name: impl Into<String>, url: impl Into<String>, sha256: impl Into<String>) -> Self {
+        Dataset {
+            name: name.into(),
+            url: url.into(),
+            sha256: sha256.into().to_ascii_lowercase(),
+        }
+    }
+
+    /// Returns the benchmark texts this crate pins, for comparisons that should not
+    /// depend on a corpus only one user has:
+    ///
+    /// - `wikipedia-france.wikitext`: the Wikipedia article on France in wiki markup
+    ///   (339 KB), as shipped with Pygments 2.20.0;
+    /// - `apache-2.0.txt`: the Apache License 2.0 (11 KB);
+    /// - `gpl-3.0.txt`: the GNU General Public License 3.0 (35 KB).
+    pub fn pinned() -> Vec<Dataset> {
+        vec![
+            Dataset::new(
+                "wikipedia-france.wikitext",
+                "https://raw.githubusercontent.com/pygments/pygments/2.20.0/tests/examplefiles/wikitext/article_france.wikitext",
+                "bd901b41ea64d8482675a02cefc0b7063b1d71e8e4dc59a54e7c154dd240f053",
+            ),
+            Dataset::new(
+                "apache-2.0.txt",
+                "https://www.apache.org/licenses/LICENSE-2.0.txt",
+                "cfc7749b96f63bd31c3c42b5c471bf756814053e847c10f3eb003417bc523d30",
+            ),
+            Dataset::new(
+                "gpl-3.0.txt",
+                "https://www.gnu.org/licenses/gpl-3.0.txt",
+                "3972dc9744f6499f0f9b2dbf76696f2ae7ad8af9b23dde66d6af86c9dfb36986",
+            ),
+        ]
+    }
+
+    /// Returns the cache file name of the dataset.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the download URL of the dataset.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns the pinned hex-encoded SHA-256 checksum.
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+
+    /// Returns the default cache directory: `$BPE_TOKENIZER_CACHE` if set, otherwise
+    /// `bpe-tokenizer-rs` under `$XDG_CACHE_HOME` or `$HOME/.cache`.
+    pub fn default_cache_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("BPE_TOKENIZER_CACHE") {
+            return PathBuf::from(dir);
+        }
+
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .unwrap_or_else(std::env::temp_dir);
+
+        base.join("bpe-tokenizer-rs")
+    }
+
+    /// Returns the path of the verified dataset file in `cache_dir`, downloading it
+    /// first if it is missing or its checksum does not match.
+    ///
+    /// The download is written to a temporary file and only moved into place after
+    /// its checksum has been verified, so an interrupted or corrupted download never
+    /// ends up in the cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DatasetError::InvalidName`] if the name is not a plain file name,
+    /// such as `..` or a name containing a path separator. Otherwise returns a
+    /// [`DatasetError`] if the download fails, the cache cannot be written, or the
+    /// downloaded file does not match the pinned checksum.
+    pub fn fetch(&self, cache_dir: impl AsRef<Path>) -> Result<PathBuf, DatasetError> {
+        if !is_file_name(&self.name) {
+            return Err(DatasetError::InvalidName(self.name.clone()));
+        }
+        let cache_dir = cache_dir.as_ref();
+        let path = cache_dir.join(&self.name);
+
+        if let Ok(bytes) = fs::read(&path)
+            && sha256_hex(&bytes) == self.sha256
+        {
+            return Ok(path);
+        }
+
+        let bytes = self.download()?;
+        let actual = sha256_hex(&bytes);
+        if actual != self.sha256 {
+            return Err(DatasetError::ChecksumMismatch {
+                expected: self.sha256.clone(),
+                actual,
+            });
+        }
+
+        fs::create_dir_all(cache_dir)?;
+        let partial = cache_dir.join(format!("{}.part", self.name));
+        fs::write(&partial, &bytes)?;
+        fs::rename(&partial, &path)?;
+
+        Ok(path)
+    }
+
+    /// Fetches the dataset like [`fetch`](Dataset::fetch) and returns its contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DatasetError`] if fetching fails or the file is not valid UTF-8.
+    pub fn load(&self, cache_dir: impl AsRef<Path>) -> Result<String, DatasetError> {
+        let path = self.fetch(cache_dir)?;
+        Ok(fs::read_to_string(path)?)
+    }
+
+    fn download(&self) -> Result<Vec<u8>, DatasetError> {
+        let response = ureq::get(&self.url)
+            .call()
+            .map_err(|err| DatasetError::Http(err.to_string()))?;
+
+        let mut bytes = Vec::new();
+        io::copy(&mut response.into_reader(), &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Returns true if `name` joined to a directory names a file directly inside it.
+fn is_file_name(name: &str) -> bool {
+    !name.contains(['/', '\\'])
+        && matches!(
+            Path::new(name).components().collect::<Vec<_>>().as_slice(),
+            [Component::Normal(_)]
+        )
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nothing listens on the discard port, so any download attempt fails fast.
+    const UNREACHABLE: &str = "http://127.0.0.1:9/dataset.txt";
+
+    #[test]
+    fn sha256_hex_known_vector() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn fetch_uses_verified_cache_without_network() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("abc.txt"), "abc").unwrap();
+        let dataset = Dataset::new(
+            "abc.txt",
+            UNREACHABLE,
+            "BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD",
+        );
+
+        assert_eq!(dataset.load(dir.path()).unwrap(), "abc");
+    }
+
+    #[test]
+    fn fetch_redownloads_corrupted_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("abc.txt"), "tampered").unwrap();
+        let dataset = Dataset::new("abc.txt", UNREACHABLE, sha256_hex(b"abc"));
+
+        let err = dataset.fetch(dir.path()).unwrap_err();
+
+        assert!(matches!(err, DatasetError::Http(_)));
+    }
+
+    #[test]
+    fn fetch_rejects_names_outside_the_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache");
+        fs::write(dir.path().join("abc.txt"), "abc").unwrap();
+
+        for name in [
+            "../abc.txt",
+            "..",
+            ".",
+            "",
+            "sub/abc.txt",
+            "sub\\abc.txt",
+            "/abc.txt",
+        ] {
+            let dataset = Dataset::new(name, UNREACHABLE, sha256_hex(b"abc"));
+            let err = dataset.fetch(&cache).unwrap_err();
+            assert!(matches!(err, DatasetError::InvalidName(_)), "{:?}", name);
+        }
+        assert!(is_file_name("abc.txt"));
+        assert!(is_file_name("..abc"));
+    }
+
+    #[test]
+    fn pinned_datasets_have_plain_names_and_checksums() {
+        let datasets = Dataset::pinned();
+
+        assert_eq!(datasets.len(), 3);
+        for dataset in &datasets {
+            assert!(is_file_name(dataset.name()), "{}", dataset.name());
+            assert!(dataset.url().starts_with("https://"));
+            assert_eq!(dataset.sha256().len(), 64);
+            assert!(dataset.sha256().bytes().all(|b| b.is_ascii_hexdigit()));
+        }
+    }
+}
@@ -1,5 +1,91 @@
-use crate::{Vocabulary, unicode_to_bytes};
-use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{DecodeError, Strictness, Vocabulary, strictness, unicode_to_bytes};
+
+/// What lenient decoding writes for an ID that is not in the vocabulary.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{Decoder, UnknownIdMode, Vocabulary};
+///
+/// let vocab = Vocabulary::new(vec![], vec![]);
+/// let skip = Decoder::new(vocab.clone()).with_unknown_id_mode(UnknownIdMode::Skip);
+/// let mark = Decoder::new(vocab).with_unknown_id_mode(UnknownIdMode::Replace("<?>".into()));
+///
+/// assert_eq!(skip.decode_lenient(&[32, 9999, 33]), "AB");
+/// assert_eq!(mark.decode_lenient(&[32, 9999, 33]), "A<?>B");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnknownIdMode {
+    /// Write this string in place of the ID. The default is U+FFFD.
+    Replace(String),
+    /// Drop the ID from the output.
+    Skip,
+}
+
+impl Default for UnknownIdMode {
+    fn default() -> Self {
+        UnknownIdMode::Replace(char::REPLACEMENT_CHARACTER.to_string())
+    }
+}
+
+impl UnknownIdMode {
+    fn replacement(&self) -> &str {
+        match self {
+            UnknownIdMode::Replace(replacement) => replacement,
+            UnknownIdMode::Skip => "",
+        }
+    }
+}
+
+/// Per-call decoding settings, used with [`Decoder::decode_with_options`] and
+/// [`BpeTokenizer::decode_with_options`](crate::BpeTokenizer::decode_with_options).
+///
+/// By default IDs outside the vocabulary are an error. Setting an [`UnknownIdMode`]
+/// skips or replaces them instead, so output from a model that occasionally emits
+/// IDs past the vocabulary still renders as best-effort text. Bytes that do not
+/// form valid UTF-8 and characters with no byte-level form are always replaced
+/// with U+FFFD.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{DecodeError, DecodeOptions, Decoder, UnknownIdMode, Vocabulary};
+///
+/// let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+/// let ids = [32, 9999, 33];
+///
+/// let strict = DecodeOptions::new();
+/// assert_eq!(decoder.decode_with_options(&ids, &strict), Err(DecodeError::UnknownId(9999)));
+///
+/// let skip = DecodeOptions::new().with_unknown_id_mode(UnknownIdMode::Skip);
+/// assert_eq!(decoder.decode_with_options(&ids, &skip).unwrap(), "AB");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DecodeOptions {
+    on_unknown_id: Option<UnknownIdMode>,
+}
+
+impl DecodeOptions {
+    /// Creates options that report IDs outside the vocabulary as errors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skips or replaces IDs outside the vocabulary instead of failing.
+    pub fn with_unknown_id_mode(mut self, mode: UnknownIdMode) -> Self {
+        self.on_unknown_id = Some(mode);
+        self
+    }
+
+    /// Returns what happens to IDs outside the vocabulary, or `None` if they are
+    /// an error.
+    pub fn unknown_id_mode(&self) -> Option<&UnknownIdMode> {
+        self.on_unknown_id.as_ref()
+    }
+}
 
 /// Decodes token IDs back into text using the vocabulary.
 ///
@@ -10,8 +96,10 @@ use std::collections::HashMap;
 ///
 /// # Performance
 ///
-/// The decoder caches the unicode-to-byte mapping to avoid reconstructing it
-/// on every decode operation, improving performance for repeated decodings.
+/// The decoder caches the unicode-to-byte mapping as a flat lookup table to avoid
+/// reconstructing it on every decode operation. Decoding first sums the token lengths
+/// and then writes every byte straight into one buffer of that size, so even very long
+/// tokens cost no intermediate allocations.
 ///
 /// # Examples
 ///
@@ -26,7 +114,12 @@ use std::collections::HashMap;
 /// ```
 pub struct Decoder {
     vocabulary: Vocabulary,
-    unicode_to_byte: HashMap<char, u8>,
+    unicode_to_byte: Box<[Option<u8>]>,
+    /// Built on first use, so opening a mapped tokenizer does not scan every token.
+    byte_lens: OnceLock<Box<[usize]>>,
+    /// Sorted IDs of the special tokens, which decode to their own text.
+    special_ids: Box<[u32]>,
+    unknown_id_mode: UnknownIdMode,
 }
 
 impl Decoder {
@@ -45,13 +138,65 @@ impl Decoder {
     /// let decoder = Decoder::new(vocab);
     /// ```
     pub fn new(vocabulary: Vocabulary) -> Self {
-        let unicode_to_byte = unicode_to_bytes();
+        let mapping = unicode_to_bytes();
+        let table_len = mapping.keys().last().map_or(0, |&ch| ch as usize + 1);
+        let mut unicode_to_byte = vec![None; table_len].into_boxed_slice();
+        for (ch, byte) in mapping {
+            unicode_to_byte[ch as usize] = Some(byte);
+        }
         Decoder {
             vocabulary,
             unicode_to_byte,
+            byte_lens: OnceLock::new(),
+            special_ids: Box::new([]),
+            unknown_id_mode: UnknownIdMode::default(),
         }
     }
 
+    /// Marks `special_tokens` as special, so they decode to their own text instead
+    /// of going through the byte-level mapping.
+    ///
+    /// Special tokens are stored as written, so one containing characters outside
+    /// the byte-level alphabet, such as a space, only decodes correctly once marked.
+    /// Tokens missing from the vocabulary are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Decoder, Vocabulary};
+    ///
+    /// let special = vec!["<my token>".to_string()];
+    /// let decoder = Decoder::new(Vocabulary::new(special.clone(), vec![]))
+    ///     .with_special_tokens(&special);
+    ///
+    /// assert_eq!(decoder.decode(&[0, 33]), "<my token>A");
+    /// ```
+    pub fn with_special_tokens(mut self, special_tokens: &[String]) -> Self {
+        let mut special_ids: Vec<u32> = special_tokens
+            .iter()
+            .filter_map(|token| self.vocabulary.token_to_id(token))
+            .collect();
+        special_ids.sort_unstable();
+        special_ids.dedup();
+        self.special_ids = special_ids.into_boxed_slice();
+        self.byte_lens = OnceLock::new();
+        self
+    }
+
+    /// Sets what lenient decoding writes for IDs outside the vocabulary.
+    ///
+    /// Useful when decoding IDs produced by an older tokenizer version with a
+    /// slightly larger vocabulary. Strict decoding is unaffected and still fails.
+    pub fn with_unknown_id_mode(mut self, mode: UnknownIdMode) -> Self {
+        self.unknown_id_mode = mode;
+        self
+    }
+
+    /// Returns what lenient decoding writes for IDs outside the vocabulary.
+    pub fn unknown_id_mode(&self) -> &UnknownIdMode {
+        &self.unknown_id_mode
+    }
+
     /// Decodes a sequence of token IDs back into text.
     ///
     /// # Arguments
@@ -64,10 +209,13 @@ impl Decoder {
     ///
     /// # Panics
     ///
-    /// Panics if:
+    /// Under [`Strictness::Strict`], panics if:
     /// - A token ID is not found in the vocabulary
+    /// - A token holds a character outside the byte-level alphabet
     /// - The resulting bytes cannot be decoded as valid UTF-8
     ///
+    /// Under [`Strictness::Lenient`] this behaves like [`decode_lenient`](Decoder::decode_lenient).
+    ///
     /// # Examples
     ///
     /// ```
@@ -80,32 +228,302 @@ impl Decoder {
     /// assert_eq!(text, "Hello");
     /// ```
     pub fn decode(&self, token_ids: &[u32]) -> String {
-        let bytes: Vec<u8> = token_ids
+        if strictness() == Strictness::Lenient {
+            return self.decode_lenient(token_ids);
+        }
+
+        match self.try_decode(token_ids) {
+            Ok(text) => text,
+            Err(DecodeError::UnknownId(token_id)) => panic!(
+                "Token ID '{}' not in vocabulary. This indicates vocabulary and merge rules are out of sync!",
+                token_id
+            ),
+            Err(err) => panic!("{}. This indicates a bug in the encoder or decoder!", err),
+        }
+    }
+
+    /// Decodes a sequence of token IDs, returning an error instead of panicking.
+    ///
+    /// Unlike [`decode`](Decoder::decode), this ignores the global [`Strictness`] and
+    /// never panics.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for the first ID outside the vocabulary,
+    /// [`DecodeError::NotByteLevel`] for a token that has no byte-level form and
+    /// [`DecodeError::InvalidUtf8`] if the IDs do not form valid UTF-8, e.g. when a
+    /// multi-byte character is split across sequences.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{DecodeError, Decoder, Vocabulary};
+    ///
+    /// let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+    ///
+    /// assert_eq!(decoder.try_decode(&[32]).unwrap(), "A");
+    /// assert_eq!(decoder.try_decode(&[9999]), Err(DecodeError::UnknownId(9999)));
+    /// ```
+    pub fn try_decode(&self, token_ids: &[u32]) -> Result<String, DecodeError> {
+        Ok(String::from_utf8(self.decode_bytes(token_ids, false)?)?)
+    }
+
+    /// Decodes token IDs, replacing bytes that do not form valid UTF-8 with U+FFFD
+    /// as [`String::from_utf8_lossy`] does. Characters with no byte-level form are
+    /// replaced with U+FFFD too.
+    ///
+    /// Use this for sequences that may end or start inside a multi-byte character,
+    /// such as generated text truncated to a token budget. Unlike
+    /// [`decode_lenient`](Decoder::decode_lenient), IDs outside the vocabulary are
+    /// still an error, since they point at a mismatched tokenizer rather than a cut.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for the first ID outside the vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{DecodeError, Decoder, Vocabulary};
+    ///
+    /// let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+    /// // "é" is the two bytes C3 A9, one byte-level token each.
+    /// let e_acute = [127, 102];
+    ///
+    /// assert_eq!(decoder.decode_lossy(&[32, e_acute[0]]).unwrap(), "A\u{FFFD}");
+    /// assert_eq!(decoder.decode_lossy(&e_acute).unwrap(), "é");
+    /// assert_eq!(decoder.decode_lossy(&[9999]), Err(DecodeError::UnknownId(9999)));
+    /// ```
+    pub fn decode_lossy(&self, token_ids: &[u32]) -> Result<String, DecodeError> {
+        let bytes = self.decode_bytes(token_ids, true)?;
+
+        Ok(String::from_utf8(bytes)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned()))
+    }
+
+    /// Concatenates the bytes of every token, failing on the first unknown ID.
+    /// Characters without a byte-level form become U+FFFD if `lossy` is set and are
+    /// an error otherwise.
+    fn decode_bytes(&self, token_ids: &[u32], lossy: bool) -> Result<Vec<u8>, DecodeError> {
+        if let Some(&token_id) = token_ids
+            .iter()
+            .find(|&&id| self.vocabulary.id_to_token(id).is_none())
+        {
+            return Err(DecodeError::UnknownId(token_id));
+        }
+
+        let mut bytes = Vec::with_capacity(self.decoded_len(token_ids));
+        for &token_id in token_ids {
+            if let Some(token) = self.vocabulary.id_to_token(token_id) {
+                self.write_token_bytes(token_id, token, &mut bytes, lossy)?;
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    /// Returns the byte length of the text `token_ids` decode to, without decoding it.
+    ///
+    /// This only sums precomputed per-token lengths, so it is cheap enough to run on
+    /// untrusted input before decoding, e.g. to preallocate or to reject sequences whose
+    /// output would exceed a size limit. The result is exact whenever
+    /// [`try_decode`](Decoder::try_decode) succeeds. Unknown IDs count as whatever the
+    /// [`UnknownIdMode`] makes lenient decoding write for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Decoder, Vocabulary};
+    ///
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// let decoder = Decoder::new(Vocabulary::new(vec![], merges));
+    ///
+    /// assert_eq!(decoder.decoded_len(&[256, 0]), 3);
+    /// assert_eq!(decoder.decoded_len(&[256, 0]), decoder.decode(&[256, 0]).len());
+    /// ```
+    pub fn decoded_len(&self, token_ids: &[u32]) -> usize {
+        let byte_lens = self.byte_lens.get_or_init(|| {
+            let mut lens = self.vocabulary.char_lens();
+            for &token_id in self.special_ids.iter() {
+                if let Some(token) = self.vocabulary.id_to_token(token_id) {
+                    lens[token_id as usize] = token.len();
+                }
+            }
+            lens
+        });
+        token_ids
             .iter()
-            .flat_map(|&token_id| {
-                let token = self.vocabulary.id_to_token(token_id).unwrap_or_else(|| {
-                    panic!(
-                        "Token ID '{}' not in vocabulary. This indicates vocabulary and merge rules are out of sync!",
-                        token_id
-                    )
-                });
-                token.chars().map(|ch| self.unicode_to_byte[&ch]).collect::<Vec<u8>>()
+            .map(|&token_id| {
+                byte_lens
+                    .get(token_id as usize)
+                    .copied()
+                    .unwrap_or(self.unknown_id_mode.replacement().len())
             })
-            .collect();
+            .sum()
+    }
+
+    /// Decodes token IDs without failing, regardless of the global [`Strictness`].
+    ///
+    /// IDs outside the vocabulary are handled according to the [`UnknownIdMode`]
+    /// (U+FFFD by default). Invalid UTF-8 and characters with no byte-level form,
+    /// such as the space in an unmarked special token, are replaced with U+FFFD.
+    pub fn decode_lenient(&self, token_ids: &[u32]) -> String {
+        self.decode_replacing(token_ids, &self.unknown_id_mode)
+    }
+
+    /// Decodes token IDs with per-call settings, see [`DecodeOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for the first ID outside the vocabulary,
+    /// unless the options set an [`UnknownIdMode`].
+    pub fn decode_with_options(
+        &self,
+        token_ids: &[u32],
+        options: &DecodeOptions,
+    ) -> Result<String, DecodeError> {
+        match options.unknown_id_mode() {
+            Some(mode) => Ok(self.decode_replacing(token_ids, mode)),
+            None => self.decode_lossy(token_ids),
+        }
+    }
+
+    /// Decodes token IDs, handling unknown IDs according to `mode` and replacing
+    /// invalid UTF-8 with U+FFFD.
+    fn decode_replacing(&self, token_ids: &[u32], mode: &UnknownIdMode) -> String {
+        let replacement = mode.replacement().as_bytes();
+        let mut bytes = Vec::with_capacity(self.decoded_len(token_ids));
+        for &token_id in token_ids {
+            match self.vocabulary.id_to_token(token_id) {
+                Some(token) => self.write_lossy(token_id, token, &mut bytes),
+                None => bytes.extend_from_slice(replacement),
+            }
+        }
+
+        String::from_utf8(bytes)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+
+    /// Decodes each ID into the text it contributes, for
+    /// [`BpeTokenizer::split_decoded_by_tokens`](crate::BpeTokenizer::split_decoded_by_tokens).
+    ///
+    /// A character split across tokens belongs to the token completing it; bytes
+    /// left incomplete before a special token, an unknown ID or the end become
+    /// U+FFFD in the fragment of the last token holding them.
+    pub(crate) fn split_decoded(&self, token_ids: &[u32]) -> Vec<(String, u32)> {
+        let mut fragments: Vec<(String, u32)> = Vec::with_capacity(token_ids.len());
+        let mut pending = Vec::new();
+
+        for &token_id in token_ids {
+            let token = self.vocabulary.id_to_token(token_id);
+            let text = match token {
+                Some(token) if !self.is_special(token_id) => {
+                    self.write_lossy(token_id, token, &mut pending);
+                    drain_complete_chars(&mut pending)
+                }
+                _ => {
+                    flush_incomplete(&mut pending, &mut fragments);
+                    token
+                        .unwrap_or(self.unknown_id_mode.replacement())
+                        .to_string()
+                }
+            };
+            fragments.push((text, token_id));
+        }
+        flush_incomplete(&mut pending, &mut fragments);
+
+        fragments
+    }
+
+    /// Returns whether `token_id` is a special token, written out verbatim.
+    fn is_special(&self, token_id: u32) -> bool {
+        !self.special_ids.is_empty() && self.special_ids.binary_search(&token_id).is_ok()
+    }
+
+    /// Appends the bytes represented by a token to `bytes`: the text of a special
+    /// token, or the bytes behind each symbol of a byte-level token.
+    ///
+    /// A character outside the byte-level alphabet is written as U+FFFD if `lossy`
+    /// is set and is reported as [`DecodeError::NotByteLevel`] otherwise.
+    fn write_token_bytes(
+        &self,
+        token_id: u32,
+        token: &str,
+        bytes: &mut Vec<u8>,
+        lossy: bool,
+    ) -> Result<(), DecodeError> {
+        if self.is_special(token_id) {
+            bytes.extend_from_slice(token.as_bytes());
+            return Ok(());
+        }
+        for ch in token.chars() {
+            match self.unicode_to_byte.get(ch as usize).copied().flatten() {
+                Some(byte) => bytes.push(byte),
+                None if lossy => {
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(
+                        char::REPLACEMENT_CHARACTER.encode_utf8(&mut buf).as_bytes(),
+                    );
+                }
+                None => return Err(DecodeError::NotByteLevel { id: token_id, ch }),
+            }
+        }
 
-        String::from_utf8(bytes).unwrap_or_else(|e| {
-            panic!(
-                "Failed to decode bytes to UTF-8: {}. This indicates a bug in the encoder or decoder!",
-                e
-            )
-        })
+        Ok(())
+    }
+
+    /// Appends the bytes represented by a token, writing U+FFFD for characters
+    /// outside the byte-level alphabet.
+    fn write_lossy(&self, token_id: u32, token: &str, bytes: &mut Vec<u8>) {
+        // Lossy writing replaces every character it cannot map, so it cannot fail.
+        let _ = self.write_token_bytes(token_id, token, bytes, true);
+    }
+}
+
+/// Removes and returns the longest decodable prefix of `pending`, replacing invalid
+/// sequences with U+FFFD but keeping a character that may still be completed.
+fn drain_complete_chars(pending: &mut Vec<u8>) -> String {
+    let mut text = String::new();
+    loop {
+        match std::str::from_utf8(pending) {
+            Ok(valid) => {
+                text.push_str(valid);
+                pending.clear();
+                return text;
+            }
+            Err(err) => {
+                let valid = err.valid_up_to();
+                text.push_str(&String::from_utf8_lossy(&pending[..valid]));
+                match err.error_len() {
+                    Some(len) => {
+                        text.push(char::REPLACEMENT_CHARACTER);
+                        pending.drain(..valid + len);
+                    }
+                    None => {
+                        pending.drain(..valid);
+                        return text;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Appends bytes that can no longer form a character to the last fragment.
+fn flush_incomplete(pending: &mut Vec<u8>, fragments: &mut [(String, u32)]) {
+    if pending.is_empty() {
+        return;
+    }
+    if let Some((text, _)) = fragments.last_mut() {
+        text.push_str(&String::from_utf8_lossy(pending));
     }
+    pending.clear();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{Encoder, PreTokenizer, Trainer};
+    use crate::{Encoder, PreTokenizer, TokenizerError, Trainer};
 
     #[test]
     fn decode_empty_sequence() {
@@ -439,4 +857,248 @@ mod tests {
 
         assert_eq!(decoded, original);
     }
+
+    #[test]
+    fn decode_lenient_replaces_unknown_ids_and_invalid_utf8() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+        let e_acute = Encoder::new(
+            vec![],
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            vec![],
+        )
+        .encode("é");
+
+        assert_eq!(decoder.decode_lenient(&[32, 9999]), "A\u{FFFD}");
+        assert_eq!(decoder.decode_lenient(&e_acute[..1]), "\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_lenient_handles_special_tokens_with_spaces() {
+        let special = vec!["<my token>".to_string()];
+        let vocab = Vocabulary::new(special.clone(), vec![]);
+        let unmarked = Decoder::new(vocab.clone());
+        let marked = Decoder::new(vocab).with_special_tokens(&special);
+
+        assert_eq!(marked.decode_lenient(&[33, 0]), "A<my token>");
+        assert_eq!(unmarked.decode_lenient(&[33, 0]), "A<my\u{FFFD}token>");
+        assert_eq!(unmarked.split_decoded(&[0])[0].0, "<my\u{FFFD}token>");
+    }
+
+    #[test]
+    fn decode_very_long_token() {
+        let mut merges = vec![("a".to_string(), "a".to_string())];
+        for _ in 0..11 {
+            let token = format!("{}{}", merges.last().unwrap().0, merges.last().unwrap().1);
+            merges.push((token.clone(), token));
+        }
+        let vocab = Vocabulary::new(vec![], merges);
+        let long_id = vocab.len() as u32 - 1;
+        let decoder = Decoder::new(vocab);
+
+        let text = decoder.decode(&[32, long_id, 33]);
+
+        assert_eq!(text.len(), 4096 + 2);
+        assert_eq!(text, format!("A{}B", "a".repeat(4096)));
+    }
+
+    #[test]
+    fn try_decode_reports_unknown_id_after_valid_tokens() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+
+        assert_eq!(
+            decoder.try_decode(&[32, 33, 9999]),
+            Err(DecodeError::UnknownId(9999))
+        );
+    }
+
+    #[test]
+    fn try_decode_reports_tokens_without_a_byte_level_form() {
+        let special = vec!["<my token>".to_string()];
+        let vocab = Vocabulary::new(special.clone(), vec![]);
+        let unmarked = Decoder::new(vocab.clone());
+        let marked = Decoder::new(vocab).with_special_tokens(&special);
+
+        let err = unmarked.try_decode(&[33, 0]).unwrap_err();
+
+        assert_eq!(err, DecodeError::NotByteLevel { id: 0, ch: ' ' });
+        assert_eq!(
+            TokenizerError::from(err),
+            TokenizerError::NotByteLevel { id: 0, ch: ' ' }
+        );
+        assert_eq!(marked.try_decode(&[33, 0]).unwrap(), "A<my token>");
+    }
+
+    #[test]
+    fn try_decode_reports_split_multibyte_character() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+        let e_acute = Encoder::new(
+            vec![],
+            PreTokenizer::new(),
+            decoder.vocabulary.clone(),
+            vec![],
+        )
+        .encode("é");
+
+        let err = decoder.try_decode(&e_acute[..1]).unwrap_err();
+
+        assert!(matches!(err, DecodeError::InvalidUtf8(_)));
+        assert!(std::error::Error::source(&err).is_some());
+        assert!(matches!(
+            TokenizerError::from(err),
+            TokenizerError::InvalidUtf8(_)
+        ));
+    }
+
+    #[test]
+    fn decode_lossy_replaces_cut_characters_at_both_ends() {
+        let text = "日本";
+        let vocab = Vocabulary::new(vec![], vec![]);
+        let ids = Encoder::new(vec![], PreTokenizer::new(), vocab.clone(), vec![]).encode(text);
+        let decoder = Decoder::new(vocab);
+
+        assert_eq!(ids.len(), 6);
+        assert_eq!(decoder.decode_lossy(&ids).unwrap(), text);
+        assert_eq!(decoder.decode_lossy(&ids[..4]).unwrap(), "日\u{FFFD}");
+        assert_eq!(
+            decoder.decode_lossy(&ids[1..]).unwrap(),
+            "\u{FFFD}\u{FFFD}本"
+        );
+        assert!(decoder.try_decode(&ids[..4]).is_err());
+    }
+
+    #[test]
+    fn decode_lossy_replaces_characters_without_a_byte_level_form() {
+        let special = vec!["<my token>".to_string()];
+        let vocab = Vocabulary::new(special.clone(), vec![]);
+        let unmarked = Decoder::new(vocab.clone());
+        let marked = Decoder::new(vocab).with_special_tokens(&special);
+
+        assert_eq!(marked.decode_lossy(&[0, 33]).unwrap(), "<my token>A");
+        assert_eq!(
+            unmarked.decode_lossy(&[0, 33]).unwrap(),
+            "<my\u{FFFD}token>A"
+        );
+        assert_eq!(
+            unmarked.decode_lossy(&[0, 9999]),
+            Err(DecodeError::UnknownId(9999))
+        );
+    }
+
+    #[test]
+    fn decoded_len_matches_decode_for_multibyte_text() {
+        let text = "Привет, 世界! 🦀";
+        let merges = Trainer::new(10).train(&[text]);
+        let vocab = Vocabulary::new(vec![], merges.clone());
+        let encoder = Encoder::new(merges, PreTokenizer::new(), vocab.clone(), vec![]);
+        let decoder = Decoder::new(vocab);
+
+        let ids = encoder.encode(text);
+
+        assert_eq!(decoder.decoded_len(&ids), text.len());
+    }
+
+    #[test]
+    fn decoded_len_counts_unknown_ids_as_replacement_character() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+
+        assert_eq!(decoder.decoded_len(&[32, 9999]), 4);
+    }
+
+    #[test]
+    fn decoded_len_follows_unknown_id_mode() {
+        let vocab = Vocabulary::new(vec![], vec![]);
+        let skip = Decoder::new(vocab.clone()).with_unknown_id_mode(UnknownIdMode::Skip);
+        let mark = Decoder::new(vocab).with_unknown_id_mode(UnknownIdMode::Replace("[?]".into()));
+
+        assert_eq!(skip.decoded_len(&[32, 9999]), 1);
+        assert_eq!(mark.decoded_len(&[32, 9999]), 4);
+        assert_eq!(mark.decode_lenient(&[9999, 9999]), "[?][?]");
+    }
+
+    #[test]
+    fn strict_decode_ignores_unknown_id_mode() {
+        let decoder =
+            Decoder::new(Vocabulary::new(vec![], vec![])).with_unknown_id_mode(UnknownIdMode::Skip);
+
+        assert_eq!(
+            decoder.try_decode(&[9999]),
+            Err(DecodeError::UnknownId(9999))
+        );
+    }
+
+    #[test]
+    fn split_decoded_flushes_cut_characters_into_the_last_fragment() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+        // "é" is C3 A9; A9 alone is a stray continuation byte.
+        let fragments = decoder.split_decoded(&[32, 127, 9999, 102, 127]);
+
+        let texts: Vec<&str> = fragments.iter().map(|(text, _)| text.as_str()).collect();
+        assert_eq!(texts, ["A", "\u{FFFD}", "\u{FFFD}", "\u{FFFD}", "\u{FFFD}"]);
+        assert_eq!(
+            texts.concat(),
+            decoder.decode_lenient(&[32, 127, 9999, 102, 127])
+        );
+    }
+
+    #[test]
+    fn special_tokens_decode_to_their_own_text() {
+        let special = vec!["<my token>".to_string(), "[PAD]".to_string()];
+        let decoder =
+            Decoder::new(Vocabulary::new(special.clone(), vec![])).with_special_tokens(&special);
+        let ids = [0, 1, 34, 1];
+
+        assert_eq!(decoder.decode(&ids), "<my token>[PAD]A[PAD]");
+        assert_eq!(decoder.decoded_len(&ids), "<my token>[PAD]A[PAD]".len());
+        let fragments = decoder.split_decoded(&ids);
+        assert_eq!(fragments[0], ("<my token>".to_string(), 0));
+    }
+
+    #[test]
+    fn decode_with_options_overrides_the_unknown_id_mode_per_call() {
+        let decoder = Decoder::new(Vocabulary::new(vec![], vec![]));
+        // A cut "é" followed by an ID past the vocabulary.
+        let ids = [32, 127, 300];
+
+        let replace = DecodeOptions::new().with_unknown_id_mode(UnknownIdMode::Replace("?".into()));
+        assert_eq!(
+            decoder.decode_with_options(&ids, &replace).unwrap(),
+            "A\u{FFFD}?"
+        );
+        assert_eq!(
+            decoder.decode_with_options(&ids, &DecodeOptions::new()),
+            Err(DecodeError::UnknownId(300))
+        );
+        assert_eq!(
+            decoder
+                .decode_with_options(&ids[..2], &DecodeOptions::new())
+                .unwrap(),
+            "A\u{FFFD}"
+        );
+        assert_eq!(decoder.decode_lenient(&ids), "A\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn decode_with_options_handles_special_tokens_with_spaces() {
+        let special = vec!["<my token>".to_string()];
+        let vocab = Vocabulary::new(special.clone(), vec![]);
+        let unmarked = Decoder::new(vocab.clone());
+        let marked = Decoder::new(vocab).with_special_tokens(&special);
+        let ids = [0, 9999, 33];
+
+        let skip = DecodeOptions::new().with_unknown_id_mode(UnknownIdMode::Skip);
+        let replace = DecodeOptions::new().with_unknown_id_mode(UnknownIdMode::Replace("?".into()));
+        assert_eq!(
+            marked.decode_with_options(&ids, &skip).unwrap(),
+            "<my token>A"
+        );
+        assert_eq!(
+            marked.decode_with_options(&ids, &replace).unwrap(),
+            "<my token>?A"
+        );
+        assert_eq!(
+            unmarked.decode_with_options(&ids, &skip).unwrap(),
+            "<my\u{FFFD}token>A"
+        );
+    }
 }
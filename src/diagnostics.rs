@@ -0,0 +1,201 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use crate::{Normalizer, Vocabulary, bytes_to_unicode};
+
+/// A suspicious but valid configuration, reported by the `warnings` methods of
+/// [`Trainer`](crate::Trainer), [`BpeTokenizer`](crate::BpeTokenizer) and
+/// [`PreTokenizer`](crate::PreTokenizer).
+///
+/// Warnings never change behavior. They point out settings that are accepted but
+/// most likely not what was intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The trainer was asked for zero merges and will only produce byte tokens.
+    ZeroMerges,
+    /// A script boost with factor zero removes matching words from training entirely.
+    ZeroScriptBoost { range: RangeInclusive<char> },
+    /// An empty special token matches between every pair of characters.
+    EmptySpecialToken,
+    /// A special token contains whitespace, so matches depend on exact spacing and
+    /// can be broken by stray spaces or newline normalization.
+    SpecialTokenWhitespace { token: String },
+    /// The same special token is listed more than once.
+    DuplicateSpecialToken { token: String },
+    /// A special token contains an earlier special token and can therefore never match.
+    ShadowedSpecialToken { token: String, shadowed_by: String },
+    /// The pre-tokenizer pattern can match the empty string and produce empty chunks.
+    EmptyPreTokenizerMatch,
+    /// A reproduced training run was recorded by another release of this crate.
+    /// The merges still matched.
+    CrateVersionChanged { recorded: String, current: String },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ZeroMerges => {
+                write!(f, "zero merges requested; only byte tokens will be learned")
+            }
+            Warning::ZeroScriptBoost { range } => write!(
+                f,
+                "script boost for {:?}..={:?} has factor 0 and drops matching words from training",
+                range.start(),
+                range.end()
+            ),
+            Warning::EmptySpecialToken => write!(f, "special token is empty"),
+            Warning::SpecialTokenWhitespace { token } => {
+                write!(f, "special token '{}' contains whitespace", token)
+            }
+            Warning::DuplicateSpecialToken { token } => {
+                write!(f, "special token '{}' is listed more than once", token)
+            }
+            Warning::ShadowedSpecialToken { token, shadowed_by } => write!(
+                f,
+                "special token '{}' contains earlier special token '{}' and can never match",
+                token, shadowed_by
+            ),
+            Warning::EmptyPreTokenizerMatch => {
+                write!(f, "pre-tokenizer pattern can match the empty string")
+            }
+            Warning::CrateVersionChanged { recorded, current } => write!(
+                f,
+                "training run was recorded by version {} but reproduced by version {}",
+                recorded, current
+            ),
+        }
+    }
+}
+
+/// Collects warnings about a list of special tokens, in the order they are matched.
+pub(crate) fn special_token_warnings(special_tokens: &[String]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for (index, token) in special_tokens.iter().enumerate() {
+        let earlier = &special_tokens[..index];
+
+        if token.is_empty() {
+            warnings.push(Warning::EmptySpecialToken);
+            continue;
+        }
+        if token.chars().any(char::is_whitespace) {
+            warnings.push(Warning::SpecialTokenWhitespace {
+                token: token.clone(),
+            });
+        }
+        if earlier.contains(token) {
+            warnings.push(Warning::DuplicateSpecialToken {
+                token: token.clone(),
+            });
+        } else if let Some(shadowed_by) = earlier
+            .iter()
+            .find(|other| !other.is_empty() && token.contains(other.as_str()))
+        {
+            warnings.push(Warning::ShadowedSpecialToken {
+                token: token.clone(),
+                shadowed_by: shadowed_by.clone(),
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Byte-level base tokens a tokenizer cannot produce, as reported by
+/// [`BpeTokenizer::validate_byte_coverage`](crate::BpeTokenizer::validate_byte_coverage).
+///
+/// A byte-level BPE tokenizer encodes any input only if all 256 base tokens are in
+/// its vocabulary. Vocabularies loaded from external files may not be.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ByteCoverage {
+    /// Bytes whose base token has no ID, so text containing them cannot be encoded.
+    pub missing: Vec<u8>,
+    /// ASCII bytes whose base token has an ID but is never emitted, because the
+    /// normalizer removes or rewrites the character, such as `\r` under
+    /// [`NewlineMode::Unix`](crate::NewlineMode::Unix).
+    pub unreachable: Vec<u8>,
+}
+
+impl ByteCoverage {
+    /// Returns whether every base token is present and reachable.
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.unreachable.is_empty()
+    }
+}
+
+/// Checks which base tokens of `vocabulary` are missing or normalized away.
+pub(crate) fn byte_coverage(vocabulary: &Vocabulary, normalizer: &Normalizer) -> ByteCoverage {
+    let mut coverage = ByteCoverage::default();
+
+    for (byte, ch) in bytes_to_unicode() {
+        if vocabulary.token_to_id(&ch.to_string()).is_none() {
+            coverage.missing.push(byte);
+        } else if byte.is_ascii() {
+            let text = char::from(byte).to_string();
+            if !normalizer.normalize(&text).contains(text.as_str()) {
+                coverage.unreachable.push(byte);
+            }
+        }
+    }
+
+    coverage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(tokens: &[&str]) -> Vec<String> {
+        tokens.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn clean_special_tokens_have_no_warnings() {
+        assert!(special_token_warnings(&tokens(&["<|endoftext|>", "[PAD]"])).is_empty());
+    }
+
+    #[test]
+    fn reports_each_special_token_problem() {
+        let warnings = special_token_warnings(&tokens(&["", "<s>", "<s> x", "<s>"]));
+
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::EmptySpecialToken,
+                Warning::SpecialTokenWhitespace {
+                    token: "<s> x".to_string()
+                },
+                Warning::ShadowedSpecialToken {
+                    token: "<s> x".to_string(),
+                    shadowed_by: "<s>".to_string()
+                },
+                Warning::DuplicateSpecialToken {
+                    token: "<s>".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn longer_token_listed_first_is_not_shadowed() {
+        assert!(special_token_warnings(&tokens(&["<s>x", "<s>"])).is_empty());
+    }
+
+    #[test]
+    fn byte_coverage_reports_missing_and_normalized_bytes() {
+        let full = Vocabulary::new(vec![], vec![]);
+        let tokens: Vec<String> = full
+            .iter()
+            .map(|(_, token)| token.to_string())
+            .filter(|token| token != "A")
+            .collect();
+        let partial = Vocabulary::from_tokens(tokens);
+        let unix = Normalizer::new().with_newlines(crate::NewlineMode::Unix);
+
+        assert!(byte_coverage(&full, &Normalizer::new()).is_complete());
+        let coverage = byte_coverage(&partial, &unix);
+        assert_eq!(coverage.missing, vec![b'A']);
+        assert_eq!(coverage.unreachable, vec![b'\r']);
+        assert!(!coverage.is_complete());
+    }
+}
@@ -0,0 +1,122 @@
+use crate::MergeGraph;
+
+/// Chooses which merge rules to keep when shrinking a vocabulary to `merge_budget` merges.
+///
+/// Rules are considered from most to least used (ties broken by rank). A rule is kept
+/// only together with every rule that produces one of its operands, so the selected
+/// set is closed under dependencies and every merge product stays encodable.
+/// Rules that never fired are never kept.
+///
+/// Returns the selected ranks in ascending order.
+pub(crate) fn select_merges(
+    merges: &[(String, String)],
+    usage: &[usize],
+    merge_budget: usize,
+) -> Vec<usize> {
+    let graph = MergeGraph::new(merges);
+
+    let mut candidates: Vec<usize> = (0..merges.len()).filter(|&rank| usage[rank] > 0).collect();
+    candidates.sort_by(|&a, &b| usage[b].cmp(&usage[a]).then(a.cmp(&b)));
+
+    let mut kept = vec![false; merges.len()];
+    let mut kept_count = 0;
+
+    for rank in candidates {
+        if kept[rank] {
+            continue;
+        }
+
+        let required: Vec<usize> = std::iter::once(rank)
+            .chain(graph.ancestors(rank))
+            .filter(|&dependency| !kept[dependency])
+            .collect();
+
+        if kept_count + required.len() <= merge_budget {
+            for &dependency in &required {
+                kept[dependency] = true;
+            }
+            kept_count += required.len();
+        }
+    }
+
+    (0..merges.len()).filter(|&rank| kept[rank]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+    use crate::{BpeTokenizer, Trainer};
+
+    #[test]
+    fn select_merges_keeps_most_used_within_budget() {
+        let merges = vec![pair("a", "b"), pair("c", "d"), pair("e", "f")];
+
+        let selected = select_merges(&merges, &[1, 5, 3], 2);
+
+        assert_eq!(selected, vec![1, 2]);
+    }
+
+    #[test]
+    fn select_merges_pulls_in_dependencies() {
+        let merges = vec![pair("a", "b"), pair("x", "y"), pair("ab", "c")];
+
+        let selected = select_merges(&merges, &[0, 4, 9], 2);
+
+        assert_eq!(selected, vec![0, 2]);
+    }
+
+    #[test]
+    fn select_merges_skips_rule_whose_dependencies_do_not_fit() {
+        let merges = vec![pair("a", "b"), pair("ab", "c"), pair("x", "y")];
+
+        let selected = select_merges(&merges, &[1, 9, 2], 1);
+
+        assert_eq!(selected, vec![2]);
+    }
+
+    #[test]
+    fn select_merges_drops_unused_rules() {
+        let merges = vec![pair("a", "b"), pair("c", "d")];
+
+        let selected = select_merges(&merges, &[0, 3], 10);
+
+        assert_eq!(selected, vec![1]);
+    }
+
+    #[test]
+    fn distill_respects_target_size() {
+        let trainer = Trainer::new(20);
+        let corpus = ["the cat sat on the mat", "the dog sat on the log"];
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &corpus, vec![]);
+
+        let distilled = tokenizer.distill(&corpus, 256 + 5);
+
+        assert!(distilled.merges().len() <= 5);
+        assert!(distilled.vocabulary().len() <= 256 + 5);
+    }
+
+    #[test]
+    fn distill_preserves_roundtrip() {
+        let trainer = Trainer::new(30);
+        let corpus = ["hello world", "hello there", "world peace"];
+        let special_tokens = vec!["<|endoftext|>".to_string()];
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &corpus, special_tokens);
+
+        let distilled = tokenizer.distill(&corpus, 1 + 256 + 8);
+
+        let text = "<|endoftext|>hello world, hello peace";
+        assert_eq!(distilled.decode(&distilled.encode(text)), text);
+        assert_eq!(distilled.special_tokens(), tokenizer.special_tokens());
+    }
+
+    #[test]
+    fn distill_below_base_size_keeps_no_merges() {
+        let trainer = Trainer::new(5);
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &["aa bb cc"], vec![]);
+
+        let distilled = tokenizer.distill(&["aa bb cc"], 100);
+
+        assert!(distilled.merges().is_empty());
+    }
+}
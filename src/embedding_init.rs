@@ -0,0 +1,273 @@
+use crate::BpeTokenizer;
+
+/// How to initialize an embedding row for a token the old tokenizer does not have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitStrategy {
+    /// Average the old rows of the pieces the old tokenizer splits the token into.
+    #[default]
+    OldMerges,
+    /// Average the old rows of the token's byte-level base tokens.
+    Bytes,
+}
+
+/// Where the embedding row of one new token comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowInit {
+    /// The same token exists in the old vocabulary; copy its row.
+    Copy(u32),
+    /// Average the rows of these old IDs, in token order.
+    Mean(Vec<u32>),
+    /// No source in the old vocabulary, e.g. a newly added special token.
+    Unmapped,
+}
+
+/// A plan for initializing a new embedding matrix from an old one after a tokenizer change.
+///
+/// The plan has one [`RowInit`] per ID of the new vocabulary. Tokens present in both
+/// vocabularies copy their old row. Other merged tokens are decomposed according to an
+/// [`InitStrategy`] and initialized to the mean of the resulting old rows. Special tokens
+/// are matched by name only; new special tokens are left [`RowInit::Unmapped`].
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, EmbeddingPlan, InitStrategy, RowInit};
+///
+/// let old = BpeTokenizer::new(vec![("h".to_string(), "e".to_string())], vec![]);
+/// let new = BpeTokenizer::new(
+///     vec![
+///         ("h".to_string(), "e".to_string()),
+///         ("he".to_string(), "y".to_string()),
+///     ],
+///     vec![],
+/// );
+///
+/// let plan = EmbeddingPlan::new(&old, &new, InitStrategy::OldMerges);
+/// let hey = new.vocabulary().token_to_id("hey").unwrap();
+/// let he = old.vocabulary().token_to_id("he").unwrap();
+/// let y = old.vocabulary().token_to_id("y").unwrap();
+///
+/// assert_eq!(plan.row(hey), Some(&RowInit::Mean(vec![he, y])));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmbeddingPlan {
+    rows: Vec<RowInit>,
+    old_vocab_size: usize,
+}
+
+impl EmbeddingPlan {
+    /// Builds the plan for moving embeddings from `old` to `new`.
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - The tokenizer the existing embeddings were trained with
+    /// * `new` - The tokenizer whose embedding matrix should be initialized
+    /// * `strategy` - How to decompose tokens missing from `old`
+    pub fn new(old: &BpeTokenizer, new: &BpeTokenizer, strategy: InitStrategy) -> Self {
+        let old_specials = old.special_tokens();
+        let new_specials = new.special_tokens().len();
+        let new_vocabulary = new.vocabulary();
+
+        let rows = (0..new_vocabulary.len() as u32)
+            .map(|new_id| {
+                let token = new_vocabulary
+                    .id_to_token(new_id)
+                    .expect("IDs below the vocabulary size are assigned");
+
+                if (new_id as usize) < new_specials {
+                    return old_specials
+                        .iter()
+                        .position(|special| special == token)
+                        .map_or(RowInit::Unmapped, |old_id| RowInit::Copy(old_id as u32));
+                }
+
+                match old.vocabulary().token_to_id(token) {
+                    Some(old_id) if old_id as usize >= old_specials.len() => RowInit::Copy(old_id),
+                    _ => RowInit::Mean(decompose(old, token, strategy)),
+                }
+            })
+            .collect();
+
+        EmbeddingPlan {
+            rows,
+            old_vocab_size: old.vocabulary().len(),
+        }
+    }
+
+    /// Returns the initialization of every new row, indexed by new token ID.
+    pub fn rows(&self) -> &[RowInit] {
+        &self.rows
+    }
+
+    /// Returns the initialization of the row for `new_id`, or `None` if it is out of range.
+    pub fn row(&self, new_id: u32) -> Option<&RowInit> {
+        self.rows.get(new_id as usize)
+    }
+
+    /// Returns the number of rows in the new embedding matrix.
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns true if the new vocabulary is empty.
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// Returns the new IDs whose rows have no source in the old embeddings.
+    pub fn unmapped(&self) -> Vec<u32> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| **row == RowInit::Unmapped)
+            .map(|(new_id, _)| new_id as u32)
+            .collect()
+    }
+
+    /// Builds the new embedding matrix from the old one.
+    ///
+    /// Both matrices are row-major with `dim` columns. Rows of [`RowInit::Unmapped`]
+    /// tokens are filled with zeros; callers usually overwrite them with a random
+    /// initialization of their choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `old_embeddings` does not hold exactly one row of `dim` values per
+    /// token of the old vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, EmbeddingPlan, InitStrategy};
+    ///
+    /// let old = BpeTokenizer::new(vec![], vec![]);
+    /// let new = BpeTokenizer::new(vec![("a".to_string(), "b".to_string())], vec![]);
+    /// let plan = EmbeddingPlan::new(&old, &new, InitStrategy::Bytes);
+    ///
+    /// let old_embeddings: Vec<f32> = (0..256).map(|id| id as f32).collect();
+    /// let new_embeddings = plan.apply(&old_embeddings, 1);
+    ///
+    /// assert_eq!(new_embeddings.len(), 257);
+    /// assert_eq!(new_embeddings[256], (64.0 + 65.0) / 2.0);
+    /// ```
+    pub fn apply(&self, old_embeddings: &[f32], dim: usize) -> Vec<f32> {
+        assert_eq!(
+            old_embeddings.len(),
+            self.old_vocab_size * dim,
+            "expected {} old rows of dimension {}",
+            self.old_vocab_size,
+            dim
+        );
+
+        let old_row = |id: u32| &old_embeddings[id as usize * dim..(id as usize + 1) * dim];
+        let mut new_embeddings = vec![0.0; self.rows.len() * dim];
+
+        for (row, target) in self.rows.iter().zip(new_embeddings.chunks_exact_mut(dim)) {
+            match row {
+                RowInit::Copy(id) => target.copy_from_slice(old_row(*id)),
+                RowInit::Mean(ids) => {
+                    for &id in ids {
+                        for (value, old) in target.iter_mut().zip(old_row(id)) {
+                            *value += old;
+                        }
+                    }
+                    for value in target.iter_mut() {
+                        *value /= ids.len() as f32;
+                    }
+                }
+                RowInit::Unmapped => {}
+            }
+        }
+
+        new_embeddings
+    }
+}
+
+fn decompose(old: &BpeTokenizer, token: &str, strategy: InitStrategy) -> Vec<u32> {
+    match strategy {
+        InitStrategy::OldMerges => old.encoder().encode_byte_level(token),
+        InitStrategy::Bytes => token
+            .chars()
+            .map(|ch| {
+                old.vocabulary()
+                    .token_to_id(&ch.to_string())
+                    .expect("byte-level symbols are always in the vocabulary")
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    #[test]
+    fn identical_tokenizers_copy_every_row() {
+        let tokenizer = BpeTokenizer::new(vec![pair("a", "b")], vec!["<s>".to_string()]);
+        let plan = EmbeddingPlan::new(&tokenizer, &tokenizer, InitStrategy::OldMerges);
+
+        assert_eq!(plan.len(), tokenizer.vocabulary().len());
+        for (new_id, row) in plan.rows().iter().enumerate() {
+            assert_eq!(*row, RowInit::Copy(new_id as u32));
+        }
+    }
+
+    #[test]
+    fn base_tokens_are_remapped_across_special_token_shift() {
+        let old = BpeTokenizer::new(vec![], vec![]);
+        let new = BpeTokenizer::new(vec![], vec!["<s>".to_string(), "</s>".to_string()]);
+        let plan = EmbeddingPlan::new(&old, &new, InitStrategy::OldMerges);
+
+        assert_eq!(plan.unmapped(), vec![0, 1]);
+        assert_eq!(plan.row(2 + 32), Some(&RowInit::Copy(32)));
+    }
+
+    #[test]
+    fn special_tokens_are_matched_by_name() {
+        let old = BpeTokenizer::new(vec![], vec!["<pad>".to_string(), "<s>".to_string()]);
+        let new = BpeTokenizer::new(vec![], vec!["<s>".to_string()]);
+        let plan = EmbeddingPlan::new(&old, &new, InitStrategy::OldMerges);
+
+        assert_eq!(plan.row(0), Some(&RowInit::Copy(1)));
+        assert!(plan.unmapped().is_empty());
+    }
+
+    #[test]
+    fn strategies_decompose_new_merges_differently() {
+        let old = BpeTokenizer::new(vec![pair("a", "b")], vec![]);
+        let new = BpeTokenizer::new(vec![pair("a", "b"), pair("ab", "c")], vec![]);
+        let abc = new.vocabulary().token_to_id("abc").unwrap();
+        let ab = old.vocabulary().token_to_id("ab").unwrap();
+
+        let merges = EmbeddingPlan::new(&old, &new, InitStrategy::OldMerges);
+        let bytes = EmbeddingPlan::new(&old, &new, InitStrategy::Bytes);
+
+        assert_eq!(merges.row(abc), Some(&RowInit::Mean(vec![ab, 66])));
+        assert_eq!(bytes.row(abc), Some(&RowInit::Mean(vec![64, 65, 66])));
+    }
+
+    #[test]
+    fn apply_copies_and_averages_rows() {
+        let old = BpeTokenizer::new(vec![], vec![]);
+        let new = BpeTokenizer::new(vec![pair("a", "b")], vec!["<s>".to_string()]);
+        let plan = EmbeddingPlan::new(&old, &new, InitStrategy::OldMerges);
+
+        let old_embeddings: Vec<f32> = (0..256).flat_map(|id| [id as f32, 1.0]).collect();
+        let new_embeddings = plan.apply(&old_embeddings, 2);
+
+        assert_eq!(new_embeddings.len(), 258 * 2);
+        assert_eq!(&new_embeddings[0..2], &[0.0, 0.0]);
+        assert_eq!(&new_embeddings[2..4], &[0.0, 1.0]);
+        assert_eq!(&new_embeddings[257 * 2..], &[64.5, 1.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 256 old rows of dimension 4")]
+    fn apply_rejects_wrong_matrix_size() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let plan = EmbeddingPlan::new(&tokenizer, &tokenizer, InitStrategy::Bytes);
+
+        plan.apply(&[0.0; 10], 4);
+    }
+}
@@ -1,6 +1,155 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
+use crate::instrumentation;
+use crate::longest_match::TokenTrie;
+use crate::low_level::MergeTable;
+use crate::word_cache::WordCache;
+use crate::{
+    ConfigError, EncodeError, EncodeStrategy, Encoding, Field, FieldSpan, MissingToken, Normalizer,
+    PreTokenizer, Strictness, TokenizerError, Vocabulary, VocabularyMismatch, WordCacheStats,
+    bytes_to_unicode, strictness, unicode_to_bytes,
+};
+
+/// A single token produced while walking the encoding pipeline.
+pub(crate) struct TokenSpan<'a> {
+    pub(crate) id: u32,
+    pub(crate) token: &'a str,
+    pub(crate) offsets: (usize, usize),
+    pub(crate) is_special: bool,
+}
+
+/// Set of byte pairs that some merge rule joins directly.
+///
+/// Every merge chain starts with a rule joining two single bytes, so a word without
+/// any such adjacent pair cannot trigger a merge. Checking the word's bytes against
+/// this 64 Kbit set lets the encoder skip the merge loop for those words.
+struct BytePairSet {
+    bits: Box<[u64; 1024]>,
+}
 
-use crate::{PreTokenizer, Vocabulary, bytes_to_unicode};
+impl BytePairSet {
+    fn new(merge_table: &MergeTable) -> Self {
+        let mut unicode_to_byte = [None; 512];
+        for (ch, byte) in unicode_to_bytes() {
+            unicode_to_byte[ch as usize] = Some(byte);
+        }
+        // Byte-level characters take at most two bytes in UTF-8. Rules are visited
+        // as bytes, so the character is decoded by hand.
+        let single_byte = |token: &[u8]| {
+            let ch = match *token {
+                [ascii] => ascii as usize,
+                [lead, continuation] if lead >= 0xC0 => {
+                    ((lead & 0x1F) as usize) << 6 | (continuation & 0x3F) as usize
+                }
+                _ => return None,
+            };
+            unicode_to_byte.get(ch).copied().flatten()
+        };
+        let mut bits = Box::new([0u64; 1024]);
+
+        merge_table.for_each_short_rule(2, |first, second| {
+            if let (Some(a), Some(b)) = (single_byte(first), single_byte(second)) {
+                let index = (a as usize) << 8 | b as usize;
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        });
+
+        BytePairSet { bits }
+    }
+
+    fn contains(&self, a: u8, b: u8) -> bool {
+        let index = (a as usize) << 8 | b as usize;
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Returns `false` if no merge rule can fire on `word`.
+    fn may_merge(&self, word: &[u8]) -> bool {
+        word.windows(2).any(|pair| self.contains(pair[0], pair[1]))
+    }
+}
+
+/// Per-call settings used with [`Encoder::encode_with_options`] and
+/// [`BpeTokenizer::encode_with_options`](crate::BpeTokenizer::encode_with_options):
+/// which special tokens are recognized, what happens to tokens missing from the
+/// vocabulary and how long the input may be.
+///
+/// A service that mixes trusted prompts with untrusted user text can encode the user
+/// part with special tokens disabled, so typing `<|endoftext|>` produces ordinary
+/// text tokens instead of the control token, and with a length limit, so one huge
+/// request cannot tie up an encoding thread.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    disabled: Vec<String>,
+    disable_all: bool,
+    extra: Vec<String>,
+    strictness: Strictness,
+    max_input_len: Option<usize>,
+}
+
+impl EncodeOptions {
+    /// Creates options that keep the tokenizer's special tokens unchanged.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treats `token` as ordinary text for this call.
+    pub fn disable(mut self, token: impl Into<String>) -> Self {
+        self.disabled.push(token.into());
+        self
+    }
+
+    /// Treats all of the tokenizer's special tokens as ordinary text for this call.
+    /// Tokens added with [`enable`](EncodeOptions::enable) are still recognized.
+    pub fn disable_all(mut self) -> Self {
+        self.disable_all = true;
+        self
+    }
+
+    /// Recognizes `token` as a special token for this call. It must be in the
+    /// vocabulary under its literal text.
+    pub fn enable(mut self, token: impl Into<String>) -> Self {
+        self.extra.push(token.into());
+        self
+    }
+
+    /// Sets what happens to a produced token missing from the vocabulary when no
+    /// unknown token is set: [`Strictness::Strict`], the default, returns an error
+    /// and [`Strictness::Lenient`] encodes the token as its byte-level tokens.
+    ///
+    /// Only this call is affected, whatever the process-wide [`Strictness`].
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Rejects texts longer than `max` bytes before normalizing or encoding them.
+    pub fn with_max_input_len(mut self, max: usize) -> Self {
+        self.max_input_len = Some(max);
+        self
+    }
+
+    /// Returns the strictness for tokens missing from the vocabulary.
+    pub fn strictness(&self) -> Strictness {
+        self.strictness
+    }
+
+    /// Returns the input length limit in bytes, if any.
+    pub fn max_input_len(&self) -> Option<usize> {
+        self.max_input_len
+    }
+}
+
+/// Callback told about every token replaced by the unknown token.
+type FallbackCallback = Arc<dyn Fn(&EncodeError) + Send + Sync>;
+
+/// Token emitted in place of merged tokens missing from the vocabulary, set with
+/// [`Encoder::with_unknown_token`].
+struct UnknownToken {
+    token: String,
+    id: u32,
+    on_fallback: Option<FallbackCallback>,
+}
 
 /// Encodes text into token IDs using Byte Pair Encoding (BPE).
 ///
@@ -31,11 +180,17 @@ use crate::{PreTokenizer, Vocabulary, bytes_to_unicode};
 /// assert_eq!(ids, vec![39, 68, 75, 75, 78]);
 /// ```
 pub struct Encoder {
-    merge_rules: Vec<(String, String)>,
+    merge_table: MergeTable,
     pre_tokenizer: PreTokenizer,
     vocabulary: Vocabulary,
     special_tokens: Vec<String>,
     byte_encoder: HashMap<u8, char>,
+    normalizer: Normalizer,
+    /// Built on first use, so opening a mapped tokenizer does not scan every rule.
+    base_pairs: OnceLock<BytePairSet>,
+    longest_match: Option<TokenTrie>,
+    unknown: Option<UnknownToken>,
+    word_cache: Option<WordCache>,
 }
 
 impl Encoder {
@@ -63,20 +218,269 @@ impl Encoder {
         vocabulary: Vocabulary,
         special_tokens: Vec<String>,
     ) -> Self {
-        let byte_encoder = bytes_to_unicode();
+        Self::from_merge_table(
+            MergeTable::new(merge_rules),
+            pre_tokenizer,
+            vocabulary,
+            special_tokens,
+        )
+    }
+
+    /// Creates an encoder like [`Encoder::new`] from an already indexed table.
+    pub(crate) fn from_merge_table(
+        merge_table: MergeTable,
+        pre_tokenizer: PreTokenizer,
+        vocabulary: Vocabulary,
+        special_tokens: Vec<String>,
+    ) -> Self {
+        let byte_encoder = bytes_to_unicode().into_iter().collect();
         Encoder {
-            merge_rules,
+            merge_table,
             pre_tokenizer,
             vocabulary,
             special_tokens,
             byte_encoder,
+            normalizer: Normalizer::new(),
+            base_pairs: OnceLock::new(),
+            longest_match: None,
+            unknown: None,
+            word_cache: None,
+        }
+    }
+
+    /// Creates an encoder like [`Encoder::new`], but first checks that the vocabulary
+    /// has every token encoding can produce.
+    ///
+    /// [`Encoder::new`] accepts merges whose products are missing from the
+    /// vocabulary, and encoding then panics on the first text that needs them. This
+    /// checks every base token, special token and merge product up front, as well as
+    /// that each merge only combines tokens an earlier merge or the byte alphabet
+    /// provides.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`VocabularyMismatch`] listing every problem found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Encoder, MissingToken, PreTokenizer, Vocabulary};
+    ///
+    /// let merges = vec![("a".to_string(), "b".to_string())];
+    /// let vocab = Vocabulary::new(vec![], vec![]);
+    ///
+    /// let err = Encoder::try_new(merges, PreTokenizer::new(), vocab, vec![]).err().unwrap();
+    /// assert_eq!(
+    ///     err.missing,
+    ///     vec![MissingToken::MergeProduct { rank: 0, token: "ab".to_string() }]
+    /// );
+    /// ```
+    pub fn try_new(
+        merge_rules: Vec<(String, String)>,
+        pre_tokenizer: PreTokenizer,
+        vocabulary: Vocabulary,
+        special_tokens: Vec<String>,
+    ) -> Result<Self, VocabularyMismatch> {
+        let missing = missing_tokens(&merge_rules, &vocabulary, &special_tokens);
+        if !missing.is_empty() {
+            return Err(VocabularyMismatch { missing });
+        }
+
+        Ok(Self::new(
+            merge_rules,
+            pre_tokenizer,
+            vocabulary,
+            special_tokens,
+        ))
+    }
+
+    /// Sets the normalizer applied to text before it is split and encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Encoder, NewlineMode, Normalizer, PreTokenizer, Vocabulary};
+    ///
+    /// let vocab = Vocabulary::new(vec![], vec![]);
+    /// let normalizer = Normalizer::new().with_newlines(NewlineMode::Unix);
+    /// let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, vec![])
+    ///     .with_normalizer(normalizer);
+    ///
+    /// assert_eq!(encoder.encode("\r\n"), encoder.encode("\n"));
+    /// ```
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Sets the pre-tokenizer used to split text before merging.
+    pub fn with_pre_tokenizer(mut self, pre_tokenizer: PreTokenizer) -> Self {
+        self.pre_tokenizer = pre_tokenizer;
+        self
+    }
+
+    /// Sets how each pre-tokenized word is split into tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{EncodeStrategy, Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// // "bc" has the better rank, but "ab" is the longest token at position 0.
+    /// let merges = vec![
+    ///     ("b".to_string(), "c".to_string()),
+    ///     ("a".to_string(), "b".to_string()),
+    /// ];
+    /// let vocab = Vocabulary::new(vec![], merges.clone());
+    /// let encoder = Encoder::new(merges, PreTokenizer::new(), vocab, vec![]);
+    /// assert_eq!(encoder.encode("abc"), vec![64, 256]);
+    ///
+    /// let encoder = encoder.with_strategy(EncodeStrategy::LongestMatch);
+    /// assert_eq!(encoder.encode("abc"), vec![257, 66]);
+    /// ```
+    pub fn with_strategy(mut self, strategy: EncodeStrategy) -> Self {
+        self.longest_match = match strategy {
+            EncodeStrategy::MergeRank => None,
+            EncodeStrategy::LongestMatch => {
+                Some(TokenTrie::new(&self.vocabulary, self.special_tokens.len()))
+            }
+        };
+        if let Some(cache) = &self.word_cache {
+            cache.clear();
+        }
+        self
+    }
+
+    /// Returns how each pre-tokenized word is split into tokens.
+    pub fn strategy(&self) -> EncodeStrategy {
+        if self.longest_match.is_some() {
+            EncodeStrategy::LongestMatch
+        } else {
+            EncodeStrategy::MergeRank
         }
     }
 
+    /// Emits the ID of `token` for merged tokens missing from the vocabulary,
+    /// instead of panicking or returning an [`EncodeError`].
+    ///
+    /// The fallback applies under every [`Strictness`], and also to special tokens
+    /// without an ID. Returns an error if `token` itself is not in the vocabulary,
+    /// typically because it was not registered as a special token.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// // The vocabulary lacks the merged token "ab".
+    /// let merges = vec![("a".to_string(), "b".to_string())];
+    /// let vocab = Vocabulary::new(vec!["[UNK]".to_string()], vec![]);
+    /// let encoder = Encoder::new(merges, PreTokenizer::new(), vocab, vec!["[UNK]".to_string()])
+    ///     .with_unknown_token("[UNK]")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(encoder.encode("abc"), vec![0, 67]);
+    /// ```
+    pub fn with_unknown_token(mut self, token: impl Into<String>) -> Result<Self, ConfigError> {
+        let token = token.into();
+        let id = self.vocabulary.token_to_id(&token).ok_or_else(|| {
+            ConfigError(format!(
+                "unknown token {:?} is not in the vocabulary",
+                token
+            ))
+        })?;
+        let on_fallback = self.unknown.take().and_then(|unknown| unknown.on_fallback);
+
+        self.unknown = Some(UnknownToken {
+            token,
+            id,
+            on_fallback,
+        });
+        Ok(self)
+    }
+
+    /// Sets a callback run with the missing token and its word every time the
+    /// unknown token stands in for it, for example to log a warning.
+    ///
+    /// Has no effect until an unknown token is set with
+    /// [`Encoder::with_unknown_token`].
+    pub fn with_unknown_token_callback(
+        mut self,
+        callback: impl Fn(&EncodeError) + Send + Sync + 'static,
+    ) -> Self {
+        if let Some(unknown) = &mut self.unknown {
+            unknown.on_fallback = Some(Arc::new(callback));
+        }
+        self
+    }
+
+    /// Returns the token emitted for merged tokens missing from the vocabulary, if
+    /// any.
+    pub fn unknown_token(&self) -> Option<&str> {
+        self.unknown.as_ref().map(|unknown| unknown.token.as_str())
+    }
+
+    /// Carries the unknown token and callback of `other` over to this encoder, if
+    /// its vocabulary has the token.
+    pub(crate) fn with_unknown_token_of(mut self, other: &Encoder) -> Self {
+        if let Some(unknown) = &other.unknown
+            && let Some(id) = self.vocabulary.token_to_id(&unknown.token)
+        {
+            self.unknown = Some(UnknownToken {
+                token: unknown.token.clone(),
+                id,
+                on_fallback: unknown.on_fallback.clone(),
+            });
+        }
+        self
+    }
+
+    /// Caches the tokens of up to `capacity` pre-tokenized words, so a word seen
+    /// before is not merged again. A `capacity` of 0 removes the cache.
+    ///
+    /// Like Hugging Face's BPE model, this pays off on large corpora where most
+    /// words repeat. Once full, the least recently used word is evicted. The cache
+    /// is shared by every thread using the encoder and starts out empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// let vocab = Vocabulary::new(vec![], vec![]);
+    /// let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, vec![]).with_word_cache(1024);
+    ///
+    /// // Words keep their leading space, so only " be" repeats.
+    /// encoder.encode("to be or not to be");
+    /// let stats = encoder.word_cache_stats().unwrap();
+    /// assert_eq!((stats.hits, stats.misses), (1, 5));
+    /// ```
+    pub fn with_word_cache(mut self, capacity: usize) -> Self {
+        self.word_cache = (capacity > 0).then(|| WordCache::new(capacity));
+        self
+    }
+
+    /// Returns the size and hit counts of the word cache, if one is set.
+    pub fn word_cache_stats(&self) -> Option<WordCacheStats> {
+        self.word_cache.as_ref().map(WordCache::stats)
+    }
+
+    /// Empties the word cache and resets its hit counts.
+    pub fn clear_word_cache(&self) {
+        if let Some(cache) = &self.word_cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns the normalizer applied before encoding.
+    pub fn normalizer(&self) -> &Normalizer {
+        &self.normalizer
+    }
+
     /// Encodes text into a sequence of token IDs.
     ///
     /// The encoding process:
-    /// 1. Splits text on special tokens
+    /// 1. Normalizes the text, then splits it on special tokens
     /// 2. For regular text: pre-tokenizes, converts to bytes, applies merges
     /// 3. For special tokens: directly maps to their IDs
     /// 4. Returns the concatenated sequence of IDs
@@ -91,8 +495,9 @@ impl Encoder {
     ///
     /// # Panics
     ///
-    /// Panics if a token is not found in the vocabulary, indicating a mismatch
-    /// between the vocabulary and merge rules.
+    /// Under [`Strictness::Strict`], panics if a token is not found in the vocabulary,
+    /// indicating a mismatch between the vocabulary and merge rules. Under
+    /// [`Strictness::Lenient`] such a token is encoded as its byte-level tokens.
     ///
     /// # Examples
     ///
@@ -109,48 +514,183 @@ impl Encoder {
     /// assert_eq!(ids, vec![32, 33]);
     /// ```
     pub fn encode(&self, text: &str) -> Vec<u32> {
-        let chunks = self.split_on_special_tokens(text);
+        self.encode_with_strictness(text, strictness())
+            .unwrap_or_else(|err| out_of_sync(err))
+    }
 
-        chunks
-            .into_iter()
-            .flat_map(|(chunk_text, is_special)| {
-                if is_special {
-                    vec![self.token_to_id(&chunk_text)]
-                } else {
-                    self.encode_regular_text(&chunk_text)
-                }
-            })
-            .collect()
+    /// Encodes text into a sequence of token IDs, returning an error instead of
+    /// panicking when a token is missing from the vocabulary.
+    ///
+    /// Unlike [`encode`](Encoder::encode), this never falls back to byte-level
+    /// tokens, whatever the process-wide [`Strictness`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] naming the missing token and the chunk it came from
+    /// if the vocabulary does not contain a special token or a token produced by the
+    /// merge rules.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{EncodeError, Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// let merges = vec![("a".to_string(), "b".to_string())];
+    /// let vocab = Vocabulary::new(vec![], vec![]);
+    /// let encoder = Encoder::new(merges, PreTokenizer::new(), vocab, vec![]);
+    ///
+    /// assert_eq!(encoder.try_encode("ba").unwrap(), vec![65, 64]);
+    /// assert_eq!(
+    ///     encoder.try_encode("x abc"),
+    ///     Err(EncodeError { token: "ab".to_string(), chunk: " abc".to_string() })
+    /// );
+    /// ```
+    pub fn try_encode(&self, text: &str) -> Result<Vec<u32>, EncodeError> {
+        self.encode_with_strictness(text, Strictness::Strict)
+    }
+
+    fn encode_with_strictness(
+        &self,
+        text: &str,
+        strictness: Strictness,
+    ) -> Result<Vec<u32>, EncodeError> {
+        self.encode_with_special_tokens(text, &self.special_tokens, strictness)
     }
 
-    fn encode_regular_text(&self, text: &str) -> Vec<u32> {
-        self.pre_tokenizer
-            .pre_tokenize(text)
+    /// Encodes text for a single call with the settings in `options`.
+    ///
+    /// Disabled special tokens are encoded as ordinary text. Extra tokens are matched
+    /// like special tokens and must already be in the vocabulary under their literal
+    /// text. The encoder itself is not changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::InputTooLong`] if the text exceeds the length limit,
+    /// [`TokenizerError::UnknownToken`] if an extra token is not in the vocabulary,
+    /// and [`TokenizerError::Encode`] if a produced token is missing from it under
+    /// [`Strictness::Strict`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{EncodeOptions, Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// let special_tokens = vec!["<|endoftext|>".to_string()];
+    /// let vocab = Vocabulary::new(special_tokens.clone(), vec![]);
+    /// let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, special_tokens);
+    ///
+    /// let options = EncodeOptions::new().disable("<|endoftext|>");
+    /// let ids = encoder.encode_with_options("<|endoftext|>", &options).unwrap();
+    /// assert_eq!(ids.len(), "<|endoftext|>".len());
+    /// ```
+    pub fn encode_with_options(
+        &self,
+        text: &str,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u32>, TokenizerError> {
+        if let Some(max) = options.max_input_len
+            && text.len() > max
+        {
+            return Err(TokenizerError::InputTooLong {
+                len: text.len(),
+                max,
+            });
+        }
+        if let Some(missing) = options
+            .extra
             .iter()
-            .flat_map(|word| {
-                let unicode_symbols: Vec<String> = word
-                    .as_bytes()
-                    .iter()
-                    .map(|&byte| self.byte_encoder[&byte].to_string())
-                    .collect();
+            .find(|token| self.vocabulary.token_to_id(token).is_none())
+        {
+            return Err(TokenizerError::UnknownToken(missing.clone()));
+        }
 
-                let merged_tokens = self.apply_merge_rules(unicode_symbols);
+        let mut active: Vec<String> = self
+            .special_tokens
+            .iter()
+            .filter(|token| !options.disable_all && !options.disabled.contains(token))
+            .cloned()
+            .collect();
+        for token in &options.extra {
+            if !active.contains(token) {
+                active.push(token.clone());
+            }
+        }
 
-                merged_tokens
-                    .into_iter()
-                    .map(|token| self.token_to_id(&token))
-            })
-            .collect()
+        Ok(self.encode_with_special_tokens(text, &active, options.strictness)?)
+    }
+
+    fn encode_with_special_tokens(
+        &self,
+        text: &str,
+        special_tokens: &[String],
+        strictness: Strictness,
+    ) -> Result<Vec<u32>, EncodeError> {
+        let text = self.normalizer.normalize(text);
+        let mut ids = Vec::new();
+
+        for (chunk_text, is_special) in self.split_on_tokens(&text, special_tokens) {
+            if is_special {
+                let symbols = self.word_to_symbols(&chunk_text);
+                self.push_token_id(&chunk_text, &symbols, &chunk_text, strictness, &mut ids)?;
+                continue;
+            }
+
+            for word in self.pre_tokenizer.pre_tokenize(&chunk_text) {
+                for token in self.merge_word(&word) {
+                    let symbols: Vec<String> = token.chars().map(String::from).collect();
+                    self.push_token_id(&token, &symbols, &word, strictness, &mut ids)?;
+                }
+            }
+        }
+
+        instrumentation::record_encode(ids.len());
+        Ok(ids)
+    }
+
+    /// Pushes the ID of `token`, produced from `chunk`, or under
+    /// [`Strictness::Lenient`] the IDs of its byte-level `symbols` if the token
+    /// itself is missing from the vocabulary.
+    fn push_token_id(
+        &self,
+        token: &str,
+        symbols: &[String],
+        chunk: &str,
+        strictness: Strictness,
+        ids: &mut Vec<u32>,
+    ) -> Result<(), EncodeError> {
+        let err = match self.token_id(token, chunk) {
+            Ok(id) => {
+                ids.push(id);
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        match strictness {
+            Strictness::Strict => Err(err),
+            Strictness::Lenient => {
+                ids.extend(
+                    symbols
+                        .iter()
+                        .filter_map(|s| self.vocabulary.token_to_id(s)),
+                );
+                Ok(())
+            }
+        }
     }
 
     fn split_on_special_tokens(&self, text: &str) -> Vec<(String, bool)> {
-        if self.special_tokens.is_empty() {
+        self.split_on_tokens(text, &self.special_tokens)
+    }
+
+    fn split_on_tokens(&self, text: &str, special_tokens: &[String]) -> Vec<(String, bool)> {
+        if special_tokens.is_empty() {
             return vec![(text.to_string(), false)];
         }
 
         let mut chunks = vec![(text.to_string(), false)];
 
-        for special_token in &self.special_tokens {
+        for special_token in special_tokens {
             chunks = chunks
                 .into_iter()
                 .flat_map(|(chunk_text, is_special)| {
@@ -190,58 +730,444 @@ impl Encoder {
         &self.vocabulary
     }
 
-    fn apply_merge_rules(&self, mut symbols: Vec<String>) -> Vec<String> {
-        while let Some((rule_idx, positions)) = self.find_best_pair(&symbols) {
-            let (first, second) = &self.merge_rules[rule_idx];
-            let merged = format!("{}{}", first, second);
-            let mut new_symbols = Vec::with_capacity(symbols.len() - positions.len());
-            let mut i = 0;
-
-            while i < symbols.len() {
-                if positions.contains(&i) {
-                    new_symbols.push(merged.clone());
-                    i += 2;
-                } else {
-                    new_symbols.push(std::mem::take(&mut symbols[i]));
-                    i += 1;
-                }
+    /// Returns the merge rules used by this encoder, in rank order.
+    pub fn merge_rules(&self) -> &[(String, String)] {
+        self.merge_table.rules()
+    }
+
+    /// Returns the pre-tokenizer used to split text before merging.
+    pub fn pre_tokenizer(&self) -> &PreTokenizer {
+        &self.pre_tokenizer
+    }
+
+    /// Returns the special tokens recognized by this encoder.
+    pub fn special_tokens(&self) -> &[String] {
+        &self.special_tokens
+    }
+
+    /// Encodes text and returns the IDs together with token strings and byte offsets.
+    ///
+    /// Offsets are `(start, end)` byte ranges into the normalized text. With the default
+    /// normalizer that is the input itself. A token that covers only part of a multi-byte
+    /// character gets the byte range of that part; use
+    /// [`Encoding::grapheme_offsets`] to widen ranges to whole display units.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Encoder, PreTokenizer, Vocabulary};
+    ///
+    /// let vocab = Vocabulary::new(vec![], vec![]);
+    /// let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, vec![]);
+    ///
+    /// let encoding = encoder.encode_with_offsets("Hi!");
+    /// assert_eq!(encoding.offsets(), &[(0, 1), (1, 2), (2, 3)]);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a produced token is missing from the vocabulary. Use
+    /// [`try_encode_with_offsets`](Encoder::try_encode_with_offsets) to get an error
+    /// instead.
+    pub fn encode_with_offsets(&self, text: &str) -> Encoding {
+        self.try_encode_with_offsets(text)
+            .unwrap_or_else(|err| out_of_sync(err))
+    }
+
+    /// Encodes text with offsets like [`encode_with_offsets`](Encoder::encode_with_offsets),
+    /// returning an error instead of panicking when a token is missing from the
+    /// vocabulary.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] naming the missing token and the chunk it came from.
+    pub fn try_encode_with_offsets(&self, text: &str) -> Result<Encoding, EncodeError> {
+        let mut encoding = Encoding::default();
+
+        self.encode_observing(text, None, &mut |span| encoding.push(span))?;
+
+        instrumentation::record_encode(encoding.len());
+        Ok(encoding)
+    }
+
+    /// Encodes a structured record as one sequence, each field preceded by its separator.
+    ///
+    /// Every separator must be one of the encoder's special tokens, so that field text
+    /// can never merge across a field boundary. Field text is encoded with special
+    /// tokens disabled, so a field containing a separator cannot start a new field.
+    /// Offsets refer to the concatenation of
+    /// each separator followed by its normalized field text, and
+    /// [`Encoding::fields`] records where every field landed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::UnknownToken`] if a separator is not a special token
+    /// and [`TokenizerError::Encode`] if a token produced from field text is missing
+    /// from the vocabulary.
+    pub fn encode_fields(&self, fields: &[Field<'_>]) -> Result<Encoding, TokenizerError> {
+        let mut encoding = Encoding::default();
+        let mut offset = 0;
+
+        for field in fields {
+            let separator_id = self
+                .special_tokens
+                .iter()
+                .any(|token| token == field.separator)
+                .then(|| self.vocabulary.token_to_id(field.separator))
+                .flatten()
+                .ok_or_else(|| TokenizerError::UnknownToken(field.separator.to_string()))?;
+            let separator_end = offset + field.separator.len();
+            encoding.push(TokenSpan {
+                id: separator_id,
+                token: field.separator,
+                offsets: (offset, separator_end),
+                is_special: true,
+            });
+
+            let text = self.normalizer.normalize(field.text);
+            let first_token = encoding.len();
+            self.encode_normalized(&text, separator_end, &[], None, &mut |span| {
+                encoding.push(span)
+            })?;
+            offset = separator_end + text.len();
+
+            encoding.push_field(FieldSpan {
+                label: field.label.to_string(),
+                bytes: separator_end..offset,
+                tokens: first_token..encoding.len(),
+            });
+        }
+
+        instrumentation::record_encode(encoding.len());
+        Ok(encoding)
+    }
+
+    /// Encodes text and calls `on_merge(rule_idx, count)` every time a merge rule fires.
+    ///
+    /// `count` is the number of positions the rule merged in a single pre-tokenized chunk.
+    /// Special tokens never trigger merges.
+    pub(crate) fn encode_observing_merges(
+        &self,
+        text: &str,
+        on_merge: &mut impl FnMut(usize, usize),
+    ) -> Vec<u32> {
+        let mut ids = Vec::new();
+        self.encode_observing(text, Some(on_merge), &mut |span| ids.push(span.id))
+            .unwrap_or_else(|err| out_of_sync(err));
+        ids
+    }
+
+    /// Calls `on_word(word, tokens)` for every pre-tokenized word of the normalized
+    /// `text` with the number of tokens it encodes to. Special tokens are skipped.
+    pub(crate) fn for_each_word(&self, text: &str, on_word: &mut impl FnMut(&str, usize)) {
+        let text = self.normalizer.normalize(text);
+
+        for (chunk_text, is_special) in self.split_on_special_tokens(&text) {
+            if is_special {
+                continue;
+            }
+            for word in self.pre_tokenizer.pre_tokenize(&chunk_text) {
+                on_word(&word, self.merge_word(&word).len());
             }
+        }
+    }
+
+    /// Returns the pre-tokenized words of the normalized `text` with the number of
+    /// tokens each encodes to, in runs broken by special tokens.
+    pub(crate) fn word_runs(&self, text: &str) -> Vec<Vec<(String, usize)>> {
+        let text = self.normalizer.normalize(text);
 
-            symbols = new_symbols;
+        self.split_on_special_tokens(&text)
+            .into_iter()
+            .filter(|(_, is_special)| !is_special)
+            .map(|(chunk_text, _)| {
+                self.pre_tokenizer
+                    .pre_tokenize(&chunk_text)
+                    .into_iter()
+                    .map(|word| {
+                        let tokens = self.merge_word(&word).len();
+                        (word, tokens)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Counts the tokens `text` would encode to if every string in `added` were
+    /// matched like a special token and given a single ID, longer strings first.
+    /// Returns the count and how often each added string matched.
+    pub(crate) fn count_with_added_tokens(
+        &self,
+        text: &str,
+        added: &[&str],
+    ) -> (usize, Vec<usize>) {
+        let text = self.normalizer.normalize(text);
+        let mut by_length: Vec<&str> = added
+            .iter()
+            .copied()
+            .filter(|token| !token.is_empty())
+            .collect();
+        by_length.sort_by_key(|token| std::cmp::Reverse(token.len()));
+        let mut matched_tokens = self.special_tokens.clone();
+        matched_tokens.extend(by_length.iter().map(|token| token.to_string()));
+
+        let mut tokens = 0;
+        let mut occurrences = vec![0; added.len()];
+        for (chunk_text, is_special) in self.split_on_tokens(&text, &matched_tokens) {
+            if is_special {
+                tokens += 1;
+                if let Some(index) = added.iter().position(|&token| token == chunk_text) {
+                    occurrences[index] += 1;
+                }
+                continue;
+            }
+            for word in self.pre_tokenizer.pre_tokenize(&chunk_text) {
+                tokens += self.merge_word(&word).len();
+            }
         }
 
-        symbols
+        (tokens, occurrences)
+    }
+
+    /// Applies the merge rules to a token given in byte-level form and returns its IDs.
+    ///
+    /// Unlike [`Encoder::encode`], this skips normalization, pre-tokenization and special
+    /// token matching, so it can re-segment vocabulary entries of another tokenizer.
+    pub(crate) fn encode_byte_level(&self, token: &str) -> Vec<u32> {
+        let symbols = token.chars().map(|ch| ch.to_string()).collect();
+
+        self.merge_table
+            .merge_symbols(symbols)
+            .iter()
+            .map(|symbol| {
+                self.token_id(symbol, token)
+                    .unwrap_or_else(|err| out_of_sync(err))
+            })
+            .collect()
     }
 
-    fn find_best_pair(&self, symbols: &[String]) -> Option<(usize, Vec<usize>)> {
-        for (rule_idx, (first, second)) in self.merge_rules.iter().enumerate() {
-            let mut positions = Vec::new();
-            let mut i = 0;
+    /// Walks the full encoding pipeline, reporting merges and every produced token.
+    ///
+    /// Offsets passed to `on_token` are byte ranges in the normalized text. Words
+    /// bypass the word cache when `on_merge` is set, so that every merge is reported.
+    fn encode_observing(
+        &self,
+        text: &str,
+        on_merge: Option<&mut dyn FnMut(usize, usize)>,
+        on_token: &mut impl FnMut(TokenSpan<'_>),
+    ) -> Result<(), EncodeError> {
+        let text = self.normalizer.normalize(text);
+        self.encode_normalized(&text, 0, &self.special_tokens, on_merge, on_token)
+    }
 
-            while i < symbols.len().saturating_sub(1) {
-                if symbols[i] == *first && symbols[i + 1] == *second {
-                    positions.push(i);
-                    i += 2;
-                } else {
-                    i += 1;
+    /// Encodes already normalized text, matching only `special_tokens` and shifting
+    /// all offsets by `base_offset`.
+    fn encode_normalized(
+        &self,
+        text: &str,
+        base_offset: usize,
+        special_tokens: &[String],
+        mut on_merge: Option<&mut dyn FnMut(usize, usize)>,
+        on_token: &mut impl FnMut(TokenSpan<'_>),
+    ) -> Result<(), EncodeError> {
+        let mut chunk_start = base_offset;
+
+        for (chunk_text, is_special) in self.split_on_tokens(text, special_tokens) {
+            let chunk_end = chunk_start + chunk_text.len();
+
+            if is_special {
+                on_token(TokenSpan {
+                    id: self.token_id(&chunk_text, &chunk_text)?,
+                    token: &chunk_text,
+                    offsets: (chunk_start, chunk_end),
+                    is_special: true,
+                });
+                chunk_start = chunk_end;
+                continue;
+            }
+
+            for (word_start, word) in self.pre_tokenizer.pre_tokenize_with_offsets(&chunk_text) {
+                let merged_tokens = match on_merge.as_deref_mut() {
+                    Some(mut on_merge) => self.merge_word_observed(word, &mut on_merge),
+                    None => self.merge_word(word),
+                };
+                let mut token_start = chunk_start + word_start;
+
+                for token in &merged_tokens {
+                    // Every byte-level character stands for exactly one input byte.
+                    let token_end = token_start + token.chars().count();
+                    on_token(TokenSpan {
+                        id: self.token_id(token, word)?,
+                        token,
+                        offsets: (token_start, token_end),
+                        is_special: false,
+                    });
+                    token_start = token_end;
                 }
             }
 
-            if !positions.is_empty() {
-                return Some((rule_idx, positions));
+            chunk_start = chunk_end;
+        }
+
+        Ok(())
+    }
+
+    fn word_to_symbols(&self, word: &str) -> Vec<String> {
+        word.as_bytes()
+            .iter()
+            .map(|&byte| self.byte_encoder[&byte].to_string())
+            .collect()
+    }
+
+    /// Splits a pre-tokenized word into tokens, through the word cache if one is set.
+    fn merge_word(&self, word: &str) -> Vec<String> {
+        let merge = || self.merge_word_observed(word, &mut |_, _| {});
+        match &self.word_cache {
+            Some(cache) => cache.get_or_merge(word, merge),
+            None => merge(),
+        }
+    }
+
+    /// Converts a pre-tokenized word to byte-level symbols and applies the merge rules,
+    /// skipping the merge loop when no rule can fire.
+    ///
+    /// Under [`EncodeStrategy::LongestMatch`] the word is segmented greedily instead and
+    /// `on_merge` is never called.
+    fn merge_word_observed(
+        &self,
+        word: &str,
+        on_merge: &mut impl FnMut(usize, usize),
+    ) -> Vec<String> {
+        if let Some(trie) = &self.longest_match {
+            let symbols: String = word.bytes().map(|byte| self.byte_encoder[&byte]).collect();
+            return trie.segment(&symbols);
+        }
+
+        let symbols = self.word_to_symbols(word);
+
+        let base_pairs = self
+            .base_pairs
+            .get_or_init(|| BytePairSet::new(&self.merge_table));
+        if !base_pairs.may_merge(word.as_bytes()) {
+            return symbols;
+        }
+
+        self.merge_table.merge_symbols_observed(symbols, on_merge)
+    }
+
+    /// Splits normalized text into the pieces encoded independently of each other,
+    /// special tokens and pre-tokenized words, as `(byte offset, text, is_special)`.
+    pub(crate) fn chunks(&self, text: &str) -> Vec<(usize, String, bool)> {
+        let mut chunks = Vec::new();
+        let mut chunk_start = 0;
+
+        for (chunk_text, is_special) in self.split_on_special_tokens(text) {
+            if is_special {
+                chunks.push((chunk_start, chunk_text.clone(), true));
+            } else {
+                for (word_start, word) in self.pre_tokenizer.pre_tokenize_with_offsets(&chunk_text)
+                {
+                    chunks.push((chunk_start + word_start, word.to_string(), false));
+                }
             }
+            chunk_start += chunk_text.len();
+        }
+
+        chunks
+    }
+
+    /// Encodes one piece returned by [`Encoder::chunks`].
+    pub(crate) fn chunk_ids(&self, chunk: &str, is_special: bool) -> Result<Vec<u32>, EncodeError> {
+        if is_special {
+            return Ok(vec![self.token_id(chunk, chunk)?]);
         }
 
-        None
+        self.merge_word(chunk)
+            .iter()
+            .map(|token| self.token_id(token, chunk))
+            .collect()
     }
 
-    fn token_to_id(&self, token: &str) -> u32 {
-        self.vocabulary
-            .token_to_id(token)
-            .unwrap_or_else(|| panic!("Token '{}' not in vocabulary. This indicates vocabulary and merge rules are out of sync!", token))
+    /// Looks up `token`, produced from `chunk`, falling back to the unknown token
+    /// if one is set.
+    fn token_id(&self, token: &str, chunk: &str) -> Result<u32, EncodeError> {
+        if let Some(id) = self.vocabulary.token_to_id(token) {
+            return Ok(id);
+        }
+
+        let err = EncodeError {
+            token: token.to_string(),
+            chunk: chunk.to_string(),
+        };
+        match &self.unknown {
+            Some(unknown) => {
+                if let Some(on_fallback) = &unknown.on_fallback {
+                    on_fallback(&err);
+                }
+                Ok(unknown.id)
+            }
+            None => Err(err),
+        }
     }
 }
 
+/// Lists the tokens encoding with these merges and special tokens may need that
+/// `vocabulary` lacks, for [`Encoder::try_new`].
+fn missing_tokens(
+    merge_rules: &[(String, String)],
+    vocabulary: &Vocabulary,
+    special_tokens: &[String],
+) -> Vec<MissingToken> {
+    let mut missing = Vec::new();
+    let mut known: HashSet<String> = HashSet::new();
+
+    for (byte, ch) in bytes_to_unicode() {
+        let token = ch.to_string();
+        if vocabulary.token_to_id(&token).is_none() {
+            missing.push(MissingToken::ByteToken {
+                byte,
+                token: token.clone(),
+            });
+        }
+        known.insert(token);
+    }
+    for token in special_tokens {
+        if vocabulary.token_to_id(token).is_none() {
+            missing.push(MissingToken::SpecialToken {
+                token: token.clone(),
+            });
+        }
+    }
+    for (rank, (first, second)) in merge_rules.iter().enumerate() {
+        for operand in [first, second] {
+            if !known.contains(operand) {
+                missing.push(MissingToken::MergeOperand {
+                    rank,
+                    token: operand.clone(),
+                });
+            }
+        }
+        let product = format!("{}{}", first, second);
+        if vocabulary.token_to_id(&product).is_none() {
+            missing.push(MissingToken::MergeProduct {
+                rank,
+                token: product.clone(),
+            });
+        }
+        known.insert(product);
+    }
+
+    missing
+}
+
+/// Panics with `err` from an infallible encoding method.
+fn out_of_sync(err: EncodeError) -> ! {
+    panic!(
+        "{}. This indicates vocabulary and merge rules are out of sync!",
+        err
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -257,7 +1183,7 @@ mod tests {
 
         let ids = encoder.encode("");
 
-        assert_eq!(ids, vec![]);
+        assert_eq!(ids, Vec::<u32>::new());
     }
 
     #[test]
@@ -526,4 +1452,306 @@ mod tests {
 
         assert_eq!(ids, vec![72, 69, 76, 76, 79, 221, 87, 79, 82, 76, 68]);
     }
+
+    #[test]
+    fn lenient_encoding_falls_back_to_byte_tokens() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let special_tokens = vec!["<s>".to_string()];
+        let encoder = Encoder::new(
+            merges,
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            special_tokens,
+        );
+
+        let ids = encoder
+            .encode_with_strictness("ab<s>", Strictness::Lenient)
+            .unwrap();
+
+        assert_eq!(ids, vec![64, 65, 27, 82, 29]);
+        assert!(encoder.try_encode("ab").is_err());
+    }
+
+    #[test]
+    fn fallible_offsets_and_fields_report_missing_tokens() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let special_tokens = vec!["<s>".to_string()];
+        let encoder = Encoder::new(
+            merges,
+            PreTokenizer::new(),
+            Vocabulary::new(special_tokens.clone(), vec![]),
+            special_tokens,
+        );
+
+        let err = encoder.try_encode_with_offsets("x cab").unwrap_err();
+        assert_eq!((err.token.as_str(), err.chunk.as_str()), ("ab", " cab"));
+        assert_eq!(
+            encoder
+                .encode_fields(&[Field::new("body", "<s>", "cab")])
+                .unwrap_err(),
+            TokenizerError::Encode(EncodeError {
+                token: "ab".to_string(),
+                chunk: "cab".to_string(),
+            })
+        );
+        assert_eq!(encoder.try_encode_with_offsets("ba<s>").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn try_encode_reports_missing_token_and_chunk() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let encoder = Encoder::new(
+            merges,
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            vec!["<s>".to_string()],
+        );
+
+        let err = encoder.try_encode("x <s> cab!").unwrap_err();
+        assert_eq!(err.token, "<s>");
+        assert_eq!(err.chunk, "<s>");
+
+        let err = encoder.try_encode("x cab!").unwrap_err();
+        assert_eq!((err.token.as_str(), err.chunk.as_str()), ("ab", " cab"));
+        assert_eq!(
+            err.to_string(),
+            "token 'ab' not in vocabulary (encoding chunk \" cab\")"
+        );
+        assert_eq!(
+            TokenizerError::from(err.clone()),
+            TokenizerError::Encode(err)
+        );
+    }
+
+    #[test]
+    fn fast_path_matches_full_merge_loop() {
+        let merges = Trainer::new(40).train(&["the cat sat on the mat", "naïve café 123"]);
+        let vocab = Vocabulary::new(vec![], merges.clone());
+        let encoder = Encoder::new(merges, PreTokenizer::new(), vocab, vec![]);
+
+        for word in ["the", " cat", "xyz", "q", "", " naïve", "123", "日本"] {
+            let symbols = encoder.word_to_symbols(word);
+
+            assert_eq!(
+                encoder.merge_word(word),
+                encoder.merge_table.merge_symbols(symbols),
+                "word: {:?}",
+                word
+            );
+        }
+    }
+
+    #[test]
+    fn byte_pair_set_only_admits_words_with_base_merges() {
+        let merges = vec![
+            ("h".to_string(), "i".to_string()),
+            ("Ã".to_string(), "©".to_string()),
+            ("hi".to_string(), "!".to_string()),
+            ("ab".to_string(), "c".to_string()),
+        ];
+        let pairs = BytePairSet::new(&MergeTable::new(merges));
+
+        assert!(pairs.may_merge(b"chin"));
+        assert!(pairs.may_merge("é".as_bytes()));
+        assert!(!pairs.may_merge(b"ih!"));
+        assert!(!pairs.may_merge(b"bc"));
+        assert!(!pairs.may_merge(b"h"));
+    }
+
+    #[test]
+    fn special_tokens_with_regex_metacharacters_match_literally() {
+        let special_tokens: Vec<String> = ["[PAD]", "<|endoftext|>", ".*", "a+", r"\d"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let vocab = Vocabulary::new(special_tokens.clone(), vec![]);
+        let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, special_tokens);
+
+        assert_eq!(encoder.encode("[PAD]"), vec![0]);
+        assert_eq!(encoder.encode("x<|endoftext|>"), vec![92, 1]);
+        assert_eq!(encoder.encode(".*"), vec![2]);
+        assert_eq!(encoder.encode("aa+"), vec![69, 3]);
+        assert_eq!(encoder.encode(r"7\d"), vec![27, 4]);
+        assert_eq!(encoder.encode("PAD|"), vec![52, 37, 40, 96]);
+    }
+
+    #[test]
+    fn encode_options_disable_and_enable_special_tokens() {
+        let special_tokens = vec!["<a>".to_string(), "<b>".to_string()];
+        let vocab = Vocabulary::new(special_tokens.clone(), vec![]);
+        let encoder = Encoder::new(vec![], PreTokenizer::new(), vocab, vec!["<a>".to_string()]);
+
+        assert_eq!(encoder.encode("<a><b>").len(), 4);
+
+        let swapped = EncodeOptions::new().disable("<a>").enable("<b>");
+        let ids = encoder.encode_with_options("<a><b>", &swapped).unwrap();
+        assert_eq!(ids.len(), 4);
+        assert_eq!(ids[3], 1);
+
+        let none = EncodeOptions::new().disable_all();
+        assert_eq!(encoder.encode_with_options("<a>", &none).unwrap().len(), 3);
+        assert_eq!(
+            encoder
+                .encode_with_options("<a>", &EncodeOptions::new())
+                .unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn encode_options_set_strictness_and_input_limit() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let encoder = Encoder::new(
+            merges,
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            vec![],
+        );
+
+        assert_eq!(
+            encoder.encode_with_options("ab", &EncodeOptions::new()),
+            Err(TokenizerError::Encode(EncodeError {
+                token: "ab".to_string(),
+                chunk: "ab".to_string(),
+            }))
+        );
+        let lenient = EncodeOptions::new().with_strictness(Strictness::Lenient);
+        assert_eq!(
+            encoder.encode_with_options("ab", &lenient).unwrap(),
+            vec![64, 65]
+        );
+
+        let limited = lenient.with_max_input_len(2);
+        assert_eq!(
+            encoder.encode_with_options("ab", &limited).unwrap().len(),
+            2
+        );
+        assert_eq!(
+            encoder.encode_with_options("abc", &limited),
+            Err(TokenizerError::InputTooLong { len: 3, max: 2 })
+        );
+    }
+
+    #[test]
+    fn encode_options_reject_unknown_extra_tokens() {
+        let encoder = Encoder::new(
+            vec![],
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            vec![],
+        );
+
+        let options = EncodeOptions::new().enable("<new>");
+
+        assert_eq!(
+            encoder.encode_with_options("x", &options),
+            Err(TokenizerError::UnknownToken("<new>".to_string()))
+        );
+    }
+
+    #[test]
+    fn word_cache_returns_the_same_tokens_as_merging() {
+        let merges = vec![
+            ("b".to_string(), "c".to_string()),
+            ("a".to_string(), "b".to_string()),
+        ];
+        let vocab = Vocabulary::new(vec![], merges.clone());
+        let plain = Encoder::new(merges.clone(), PreTokenizer::new(), vocab.clone(), vec![]);
+        let cached = Encoder::new(merges, PreTokenizer::new(), vocab, vec![]).with_word_cache(2);
+        let text = "abc abc bc abc x abc";
+
+        assert_eq!(cached.encode(text), plain.encode(text));
+        assert_eq!(
+            cached.encode_with_offsets(text),
+            plain.encode_with_offsets(text)
+        );
+        let stats = cached.word_cache_stats().unwrap();
+        assert_eq!(stats.len, 2);
+        assert_eq!(stats.hits + stats.misses, 12);
+
+        let mut merges_fired = 0;
+        cached.encode_observing_merges("abc", &mut |_, count| merges_fired += count);
+        assert_eq!(merges_fired, 1);
+
+        let cached = cached.with_strategy(EncodeStrategy::LongestMatch);
+        assert_eq!(cached.word_cache_stats().unwrap().len, 0);
+        assert_eq!(cached.encode("abc"), vec![257, 66]);
+        assert!(cached.with_word_cache(0).word_cache_stats().is_none());
+    }
+
+    #[test]
+    fn unknown_token_replaces_missing_merged_tokens() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let unk = vec!["[UNK]".to_string()];
+        let vocab = Vocabulary::new(unk.clone(), vec![]);
+        let missing = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = std::sync::Arc::clone(&missing);
+        let encoder = Encoder::new(merges, PreTokenizer::new(), vocab, unk)
+            .with_unknown_token("[UNK]")
+            .unwrap()
+            .with_unknown_token_callback(move |err| {
+                recorded.lock().unwrap().push(err.token.clone())
+            });
+
+        assert_eq!(encoder.unknown_token(), Some("[UNK]"));
+        assert_eq!(encoder.try_encode("abc ab").unwrap(), vec![0, 67, 221, 0]);
+        let encoding = encoder.try_encode_with_offsets("abc").unwrap();
+        assert_eq!(encoding.ids(), &[0, 67]);
+        assert_eq!(encoding.offsets()[0], (0, 2));
+        assert_eq!(*missing.lock().unwrap(), vec!["ab", "ab", "ab"]);
+    }
+
+    #[test]
+    fn unknown_token_must_be_in_the_vocabulary() {
+        let encoder = Encoder::new(
+            vec![],
+            PreTokenizer::new(),
+            Vocabulary::new(vec![], vec![]),
+            vec![],
+        );
+
+        let err = encoder.with_unknown_token("[UNK]").err().unwrap();
+        assert!(err.to_string().contains("[UNK]"));
+    }
+
+    #[test]
+    fn try_new_reports_every_token_missing_from_the_vocabulary() {
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("x".to_string(), "yz".to_string()),
+            ("ab".to_string(), "c".to_string()),
+        ];
+        let vocab = Vocabulary::new(vec![], merges[..1].to_vec());
+        let build = |specials: Vec<String>| {
+            Encoder::try_new(merges.clone(), PreTokenizer::new(), vocab.clone(), specials)
+        };
+
+        let err = build(vec!["<s>".to_string()]).err().unwrap();
+        assert_eq!(
+            err.missing,
+            vec![
+                MissingToken::SpecialToken {
+                    token: "<s>".to_string()
+                },
+                MissingToken::MergeOperand {
+                    rank: 1,
+                    token: "yz".to_string()
+                },
+                MissingToken::MergeProduct {
+                    rank: 1,
+                    token: "xyz".to_string()
+                },
+                MissingToken::MergeProduct {
+                    rank: 2,
+                    token: "abc".to_string()
+                },
+            ]
+        );
+        assert!(err.to_string().starts_with("4 token(s) missing"));
+
+        let complete = Vocabulary::new(vec![], merges[..1].to_vec());
+        assert!(
+            Encoder::try_new(merges[..1].to_vec(), PreTokenizer::new(), complete, vec![]).is_ok()
+        );
+    }
 }
@@ -0,0 +1,642 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::encoder::TokenSpan;
+
+/// The result of encoding text with offset tracking.
+///
+/// Holds, for every produced token, its ID, its vocabulary string (byte-level
+/// characters, e.g. `Ġworld`) and its `(start, end)` byte range in the encoded text.
+///
+/// Byte offsets are exact but can land inside a multi-byte character or inside an
+/// emoji ZWJ sequence when BPE splits one across several tokens. UIs that highlight
+/// tokens should use [`grapheme_offsets`](Encoding::grapheme_offsets) or
+/// [`display_units`](Encoding::display_units), which never split a user-perceived
+/// character.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::BpeTokenizer;
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// let encoding = tokenizer.encode_with_offsets("a é");
+///
+/// assert_eq!(encoding.ids(), tokenizer.encode("a é").as_slice());
+/// assert_eq!(encoding.tokens(), &["a", "Ġ", "Ã", "©"]);
+/// assert_eq!(encoding.offsets(), &[(0, 1), (1, 2), (2, 3), (3, 4)]);
+/// ```
+///
+/// # Serialization
+///
+/// With the `serde` feature enabled, `Encoding` serializes to an object with five
+/// arrays of equal length, one entry per token, followed by the field spans of
+/// [`fields`](Encoding::fields):
+///
+/// ```json
+/// {
+///   "ids": [40, 69, 0],
+///   "tokens": ["H", "e", "<|endoftext|>"],
+///   "offsets": [[0, 1], [1, 2], [2, 15]],
+///   "special_tokens_mask": [0, 0, 1],
+///   "attention_mask": [1, 1, 1],
+///   "fields": []
+/// }
+/// ```
+///
+/// Field names and order are stable. Offsets are `[start, end)` byte ranges in the
+/// normalized input. A missing `fields` key deserializes as empty. Deserialization
+/// rejects objects whose arrays differ in length, whose masks hold values other
+/// than 0 and 1, or whose field spans point past the last token.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "EncodingData"))]
+pub struct Encoding {
+    ids: Vec<u32>,
+    tokens: Vec<String>,
+    offsets: Vec<(usize, usize)>,
+    special_tokens_mask: Vec<u32>,
+    attention_mask: Vec<u32>,
+    fields: Vec<FieldSpan>,
+}
+
+/// Unvalidated mirror of [`Encoding`] used as the deserialization target.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct EncodingData {
+    ids: Vec<u32>,
+    tokens: Vec<String>,
+    offsets: Vec<(usize, usize)>,
+    special_tokens_mask: Vec<u32>,
+    attention_mask: Vec<u32>,
+    #[serde(default)]
+    fields: Vec<FieldSpan>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<EncodingData> for Encoding {
+    type Error = String;
+
+    fn try_from(data: EncodingData) -> Result<Self, Self::Error> {
+        let len = data.ids.len();
+        let lengths = [
+            data.tokens.len(),
+            data.offsets.len(),
+            data.special_tokens_mask.len(),
+            data.attention_mask.len(),
+        ];
+
+        if lengths.iter().any(|&other| other != len) {
+            return Err(format!(
+                "encoding arrays differ in length: ids has {}, others have {:?}",
+                len, lengths
+            ));
+        }
+        for (name, mask) in [
+            ("special_tokens_mask", &data.special_tokens_mask),
+            ("attention_mask", &data.attention_mask),
+        ] {
+            if let Some(value) = mask.iter().find(|&&value| value > 1) {
+                return Err(format!("{} holds {}, expected 0 or 1", name, value));
+            }
+        }
+        if let Some(field) = data.fields.iter().find(|field| field.tokens.end > len) {
+            return Err(format!(
+                "field '{}' ends at token {} but the encoding has {} tokens",
+                field.label, field.tokens.end, len
+            ));
+        }
+
+        Ok(Encoding {
+            ids: data.ids,
+            tokens: data.tokens,
+            offsets: data.offsets,
+            special_tokens_mask: data.special_tokens_mask,
+            attention_mask: data.attention_mask,
+            fields: data.fields,
+        })
+    }
+}
+
+/// A run of tokens that together cover whole grapheme clusters.
+///
+/// Produced by [`Encoding::display_units`]. `bytes` is the byte range in the encoded
+/// text and `tokens` the range of token indices that fall inside it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayUnit {
+    pub bytes: Range<usize>,
+    pub tokens: Range<usize>,
+}
+
+/// A special token found while encoding, reported by
+/// [`Encoding::special_token_matches`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpecialTokenMatch {
+    /// The matched special token.
+    pub token: String,
+    /// Index of the token in the encoding.
+    pub index: usize,
+    /// `(start, end)` byte range of the match in the normalized input.
+    pub offsets: (usize, usize),
+}
+
+/// One field of a structured record passed to
+/// [`BpeTokenizer::encode_fields`](crate::BpeTokenizer::encode_fields).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    /// Name reported back in the field's [`FieldSpan`], e.g. `"title"`.
+    pub label: &'a str,
+    /// Special token inserted before the field text.
+    pub separator: &'a str,
+    /// The field text.
+    pub text: &'a str,
+}
+
+impl<'a> Field<'a> {
+    /// Creates a field with the given label, separator special token and text.
+    pub fn new(label: &'a str, separator: &'a str, text: &'a str) -> Self {
+        Field {
+            label,
+            separator,
+            text,
+        }
+    }
+}
+
+/// Location of one field of a structured record within an [`Encoding`].
+///
+/// `bytes` is the byte range of the field text and `tokens` the range of token
+/// indices it was encoded to. Both exclude the field's separator token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldSpan {
+    pub label: String,
+    pub bytes: Range<usize>,
+    pub tokens: Range<usize>,
+}
+
+impl Encoding {
+    pub(crate) fn push(&mut self, span: TokenSpan<'_>) {
+        self.ids.push(span.id);
+        self.tokens.push(span.token.to_string());
+        self.offsets.push(span.offsets);
+        self.special_tokens_mask.push(span.is_special as u32);
+        self.attention_mask.push(1);
+    }
+
+    /// Returns the token IDs.
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// Returns the token strings as stored in the vocabulary.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Returns the `(start, end)` byte range of every token.
+    pub fn offsets(&self) -> &[(usize, usize)] {
+        &self.offsets
+    }
+
+    /// Returns `1` for every special token and `0` for every regular token.
+    pub fn special_tokens_mask(&self) -> &[u32] {
+        &self.special_tokens_mask
+    }
+
+    /// Returns `1` for every token that should be attended to.
+    ///
+    /// Every token produced by encoding is attended to, so this is all ones.
+    pub fn attention_mask(&self) -> &[u32] {
+        &self.attention_mask
+    }
+
+    /// Returns every special token matched in the input, in order.
+    ///
+    /// Security-sensitive callers can log or reject inputs that smuggle control
+    /// tokens such as `<|endoftext|>` into user text. To encode such text without
+    /// matching special tokens at all, see
+    /// [`EncodeOptions::disable_all`](crate::EncodeOptions::disable_all).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+    /// let encoding = tokenizer.encode_with_offsets("hi<|endoftext|>");
+    ///
+    /// let matches = encoding.special_token_matches();
+    /// assert_eq!(matches.len(), 1);
+    /// assert_eq!(matches[0].token, "<|endoftext|>");
+    /// assert_eq!(matches[0].offsets, (2, 15));
+    /// ```
+    pub fn special_token_matches(&self) -> Vec<SpecialTokenMatch> {
+        self.special_tokens_mask
+            .iter()
+            .enumerate()
+            .filter(|(_, special)| **special == 1)
+            .map(|(index, _)| SpecialTokenMatch {
+                token: self.tokens[index].clone(),
+                index,
+                offsets: self.offsets[index],
+            })
+            .collect()
+    }
+
+    /// Returns a mask selecting the tokens to average over for mean pooling.
+    ///
+    /// The mask is `1` for tokens that are attended to and not special, and `0`
+    /// for special tokens such as separators and for padding. Embedding services can
+    /// multiply hidden states by it instead of re-deriving it from the IDs and the
+    /// special-token list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["<s>".to_string()]);
+    /// let encoding = tokenizer.encode_with_offsets("<s>Hi<s>");
+    ///
+    /// assert_eq!(encoding.pooling_mask(), vec![0, 1, 1, 0]);
+    /// ```
+    pub fn pooling_mask(&self) -> Vec<u32> {
+        self.attention_mask
+            .iter()
+            .zip(&self.special_tokens_mask)
+            .map(|(&attended, &special)| (attended == 1 && special == 0) as u32)
+            .collect()
+    }
+
+    /// Returns the field spans of a structured record, or an empty slice for plain text.
+    pub fn fields(&self) -> &[FieldSpan] {
+        &self.fields
+    }
+
+    /// Returns the span of the first field with the given label.
+    pub fn field(&self, label: &str) -> Option<&FieldSpan> {
+        self.fields.iter().find(|field| field.label == label)
+    }
+
+    pub(crate) fn push_field(&mut self, field: FieldSpan) {
+        self.fields.push(field);
+    }
+
+    /// Returns the number of tokens.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if the encoding contains no tokens.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns token offsets widened to grapheme cluster boundaries.
+    ///
+    /// A token that starts or ends inside a grapheme cluster (a multi-byte character,
+    /// a flag, a ZWJ emoji family, a base letter plus combining marks) is extended to
+    /// cover the whole cluster. Neighbouring tokens inside the same cluster therefore
+    /// get the same range, so slicing `text` with any returned range is always valid
+    /// and renders a complete glyph.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text that was encoded (after normalization)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let text = "é";
+    /// let encoding = tokenizer.encode_with_offsets(text);
+    ///
+    /// assert_eq!(encoding.offsets(), &[(0, 1), (1, 2)]);
+    /// assert_eq!(encoding.grapheme_offsets(text), vec![(0, 2), (0, 2)]);
+    /// ```
+    pub fn grapheme_offsets(&self, text: &str) -> Vec<(usize, usize)> {
+        let boundaries = grapheme_boundaries(text);
+
+        self.offsets
+            .iter()
+            .map(|&(start, end)| {
+                let snapped_start = snap_down(&boundaries, start);
+                let snapped_end = if end == start {
+                    snapped_start
+                } else {
+                    snap_up(&boundaries, end)
+                };
+                (snapped_start, snapped_end)
+            })
+            .collect()
+    }
+
+    /// Groups tokens into display units that each cover whole grapheme clusters.
+    ///
+    /// Every token belongs to exactly one unit and units are returned in text order.
+    /// A unit contains several tokens when BPE split a cluster (for example the bytes
+    /// of a ZWJ emoji sequence) or when several small clusters were merged into one
+    /// token. This is the natural granularity for coloring tokens in a UI.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text that was encoded (after normalization)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let text = "a🦀";
+    /// let units = tokenizer.encode_with_offsets(text).display_units(text);
+    ///
+    /// assert_eq!(units.len(), 2);
+    /// assert_eq!(units[1].bytes, 1..5);
+    /// assert_eq!(units[1].tokens, 1..5);
+    /// ```
+    pub fn display_units(&self, text: &str) -> Vec<DisplayUnit> {
+        let snapped = self.grapheme_offsets(text);
+        let mut units: Vec<DisplayUnit> = Vec::new();
+
+        for (index, &(start, end)) in snapped.iter().enumerate() {
+            match units.last_mut() {
+                Some(unit) if start < unit.bytes.end => {
+                    unit.bytes.end = unit.bytes.end.max(end);
+                    unit.tokens.end = index + 1;
+                }
+                _ => units.push(DisplayUnit {
+                    bytes: start..end,
+                    tokens: index..index + 1,
+                }),
+            }
+        }
+
+        units
+    }
+}
+
+fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    text.grapheme_indices(true)
+        .map(|(start, _)| start)
+        .chain(std::iter::once(text.len()))
+        .collect()
+}
+
+fn snap_down(boundaries: &[usize], offset: usize) -> usize {
+    match boundaries.binary_search(&offset) {
+        Ok(index) => boundaries[index],
+        Err(index) => boundaries[index.saturating_sub(1)],
+    }
+}
+
+fn snap_up(boundaries: &[usize], offset: usize) -> usize {
+    match boundaries.binary_search(&offset) {
+        Ok(index) => boundaries[index],
+        Err(index) => boundaries.get(index).copied().unwrap_or(offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "serde")]
+    use crate::Encoding;
+    use crate::{BpeTokenizer, Field, TokenizerError};
+
+    const FAMILY: &str = "👨\u{200d}👩\u{200d}👧";
+    const FLAG: &str = "🇯🇵";
+
+    #[test]
+    fn offsets_cover_input_bytes() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let text = "Hi 世界";
+
+        let encoding = tokenizer.encode_with_offsets(text);
+
+        assert_eq!(encoding.len(), text.len());
+        assert_eq!(encoding.offsets().first(), Some(&(0, 1)));
+        assert_eq!(encoding.offsets().last(), Some(&(8, 9)));
+    }
+
+    #[test]
+    fn offsets_follow_merged_tokens() {
+        let merges = vec![
+            ("h".to_string(), "e".to_string()),
+            ("Ġ".to_string(), "w".to_string()),
+        ];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+
+        let encoding = tokenizer.encode_with_offsets("he w");
+
+        assert_eq!(encoding.tokens(), &["he", "Ġw"]);
+        assert_eq!(encoding.offsets(), &[(0, 2), (2, 4)]);
+    }
+
+    #[test]
+    fn offsets_include_special_tokens() {
+        let special_tokens = vec!["<|endoftext|>".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+
+        let encoding = tokenizer.encode_with_offsets("a<|endoftext|>b");
+
+        assert_eq!(encoding.ids(), &[65, 0, 66]);
+        assert_eq!(encoding.offsets(), &[(0, 1), (1, 14), (14, 15)]);
+    }
+
+    #[test]
+    fn masks_mark_special_tokens() {
+        let special_tokens = vec!["[PAD]".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+
+        let encoding = tokenizer.encode_with_offsets("a[PAD]");
+
+        assert_eq!(encoding.special_tokens_mask(), &[0, 1]);
+        assert_eq!(encoding.attention_mask(), &[1, 1]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_json_schema_is_stable() {
+        let special_tokens = vec!["<|endoftext|>".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+
+        let encoding = tokenizer.encode_with_offsets("Hi<|endoftext|>");
+        let json = serde_json::to_string(&encoding).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"ids":[40,73,0],"tokens":["H","i","<|endoftext|>"],"offsets":[[0,1],[1,2],[2,15]],"special_tokens_mask":[0,0,1],"attention_mask":[1,1,1],"fields":[]}"#
+        );
+        assert_eq!(serde_json::from_str::<Encoding>(&json).unwrap(), encoding);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_accepts_missing_fields_and_rejects_out_of_range_spans() {
+        let plain = r#"{"ids":[1],"tokens":["a"],"offsets":[[0,1]],"special_tokens_mask":[0],"attention_mask":[1]}"#;
+        let out_of_range = r#"{"ids":[1],"tokens":["a"],"offsets":[[0,1]],"special_tokens_mask":[0],"attention_mask":[1],"fields":[{"label":"x","bytes":{"start":0,"end":1},"tokens":{"start":0,"end":2}}]}"#;
+
+        assert!(
+            serde_json::from_str::<Encoding>(plain)
+                .unwrap()
+                .fields()
+                .is_empty()
+        );
+        assert!(serde_json::from_str::<Encoding>(out_of_range).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_mismatched_lengths() {
+        let json = r#"{"ids":[1,2],"tokens":["a"],"offsets":[[0,1]],"special_tokens_mask":[0],"attention_mask":[1]}"#;
+
+        assert!(serde_json::from_str::<Encoding>(json).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_masks_other_than_zero_and_one() {
+        let special = r#"{"ids":[1],"tokens":["a"],"offsets":[[0,1]],"special_tokens_mask":[2],"attention_mask":[1]}"#;
+        let attention = r#"{"ids":[1],"tokens":["a"],"offsets":[[0,1]],"special_tokens_mask":[0],"attention_mask":[3]}"#;
+
+        let err = serde_json::from_str::<Encoding>(special).unwrap_err();
+        assert!(err.to_string().contains("special_tokens_mask holds 2"));
+        assert!(serde_json::from_str::<Encoding>(attention).is_err());
+    }
+
+    #[test]
+    fn grapheme_offsets_keep_zwj_family_whole() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let text = format!("a{}b", FAMILY);
+
+        let encoding = tokenizer.encode_with_offsets(&text);
+        let snapped = encoding.grapheme_offsets(&text);
+
+        let family_range = (1, 1 + FAMILY.len());
+        assert_eq!(snapped.first(), Some(&(0, 1)));
+        assert!(
+            snapped[1..snapped.len() - 1]
+                .iter()
+                .all(|&r| r == family_range)
+        );
+        assert_eq!(&text[family_range.0..family_range.1], FAMILY);
+    }
+
+    #[test]
+    fn display_units_group_flag_and_family() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let text = format!("{} {}", FLAG, FAMILY);
+
+        let encoding = tokenizer.encode_with_offsets(&text);
+        let units = encoding.display_units(&text);
+
+        let rendered: Vec<&str> = units.iter().map(|u| &text[u.bytes.clone()]).collect();
+        assert_eq!(rendered, vec![FLAG, " ", FAMILY]);
+        assert_eq!(units.last().unwrap().tokens.end, encoding.len());
+    }
+
+    #[test]
+    fn display_units_merge_tokens_spanning_several_graphemes() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+        let text = "abc";
+
+        let units = tokenizer.encode_with_offsets(text).display_units(text);
+
+        assert_eq!(units[0].bytes, 0..2);
+        assert_eq!(units[0].tokens, 0..1);
+        assert_eq!(units[1].bytes, 2..3);
+    }
+
+    #[test]
+    fn fields_record_token_and_byte_ranges() {
+        let special_tokens = vec!["<title>".to_string(), "<body>".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+
+        let encoding = tokenizer
+            .encode_fields(&[
+                Field::new("title", "<title>", "Hi"),
+                Field::new("body", "<body>", "ok!"),
+            ])
+            .unwrap();
+
+        assert_eq!(encoding.ids()[0], 0);
+        assert_eq!(encoding.ids()[3], 1);
+        assert_eq!(encoding.special_tokens_mask(), &[1, 0, 0, 1, 0, 0, 0]);
+
+        let body = encoding.field("body").unwrap();
+        assert_eq!(body.tokens, 4..7);
+        assert_eq!(body.bytes, 15..18);
+        assert_eq!(encoding.offsets()[4], (15, 16));
+        assert_eq!(encoding.field("title").unwrap().tokens, 1..3);
+    }
+
+    #[test]
+    fn separators_in_field_text_are_plain_text() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<a>".to_string(), "<b>".to_string()]);
+
+        let encoding = tokenizer
+            .encode_fields(&[Field::new("a", "<a>", "x<b>y"), Field::new("b", "<b>", "z")])
+            .unwrap();
+
+        assert_eq!(encoding.ids().iter().filter(|&&id| id == 1).count(), 1);
+        assert_eq!(encoding.field("a").unwrap().tokens, 1..6);
+        assert_eq!(encoding.special_tokens_mask(), &[1, 0, 0, 0, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn fields_require_special_token_separators() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<sep>".to_string()]);
+
+        let result = tokenizer.encode_fields(&[Field::new("title", "<title>", "Hi")]);
+
+        assert_eq!(
+            result,
+            Err(TokenizerError::UnknownToken("<title>".to_string()))
+        );
+    }
+
+    #[test]
+    fn pooling_mask_keeps_only_field_text() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<a>".to_string(), "<b>".to_string()]);
+
+        let encoding = tokenizer
+            .encode_fields(&[Field::new("a", "<a>", "x"), Field::new("b", "<b>", "")])
+            .unwrap();
+
+        assert_eq!(encoding.pooling_mask(), vec![0, 1, 0]);
+        assert!(crate::Encoding::default().pooling_mask().is_empty());
+    }
+
+    #[test]
+    fn special_token_matches_report_each_occurrence() {
+        let special_tokens = vec!["<a>".to_string(), "<b>".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+
+        let encoding = tokenizer.encode_with_offsets("<b>x<a><b>");
+        let matches: Vec<_> = encoding
+            .special_token_matches()
+            .into_iter()
+            .map(|m| (m.token, m.index, m.offsets))
+            .collect();
+
+        assert_eq!(
+            matches,
+            vec![
+                ("<b>".to_string(), 0, (0, 3)),
+                ("<a>".to_string(), 2, (4, 7)),
+                ("<b>".to_string(), 3, (7, 10)),
+            ]
+        );
+        assert!(
+            tokenizer
+                .encode_with_offsets("plain")
+                .special_token_matches()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,642 @@
+use std::fmt;
+use std::string::FromUtf8Error;
+use std::sync::Arc;
+
+use crate::low_level::SymbolError;
+use crate::{ConfigError, GgufError, MergesError, ReproducibilityError, TiktokenError};
+
+/// Crate-wide error covering encoding, decoding, vocabulary construction and
+/// training.
+///
+/// Methods return the narrowest error that describes their failures, such as
+/// [`EncodeError`], [`DecodeError`], [`ConfigError`] or [`ReproducibilityError`]. Each
+/// of them converts into `TokenizerError` with `?`, so a function that builds,
+/// checks and runs a tokenizer can return this one type. I/O errors and the errors
+/// of the file loaders and exporters convert too; an I/O error cannot be cloned, so
+/// they are shared behind an [`Arc`] and compare equal only to their clones. More
+/// variants may be added without a major release.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, TokenizerError};
+///
+/// fn roundtrip(tokenizer: BpeTokenizer, text: &str) -> Result<String, TokenizerError> {
+///     let tokenizer = BpeTokenizer::from_config(tokenizer.config())?;
+///     let ids = tokenizer.try_encode(text)?;
+///     Ok(tokenizer.try_decode(&ids)?)
+/// }
+///
+/// assert_eq!(roundtrip(BpeTokenizer::new(vec![], vec![]), "hi").unwrap(), "hi");
+/// ```
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum TokenizerError {
+    /// A token produced during encoding has no ID, meaning the vocabulary and merge
+    /// rules are out of sync, with the chunk of input it came from.
+    Encode(EncodeError),
+    /// A token the caller named, such as an extra token or a field separator, has
+    /// no ID.
+    UnknownToken(String),
+    /// An ID passed to the decoder is not in the vocabulary.
+    UnknownTokenId(u32),
+    /// The decoded bytes are not valid UTF-8, e.g. because the IDs split a
+    /// multi-byte character.
+    InvalidUtf8(FromUtf8Error),
+    /// A token that is not marked special holds a character outside the byte-level
+    /// alphabet, so it has no bytes to decode to.
+    NotByteLevel { id: u32, ch: char },
+    /// A vocabulary or configuration does not give one ID to each token the merges
+    /// and special tokens define.
+    InvalidConfig(ConfigError),
+    /// Training could not reproduce a recorded run.
+    Training(ReproducibilityError),
+    /// Special tokens or merges would give one token several IDs.
+    InvalidVocabulary(VocabularyError),
+    /// Merges or special tokens need tokens the vocabulary lacks.
+    VocabularyMismatch(VocabularyMismatch),
+    /// The text is longer than the limit set with
+    /// [`EncodeOptions::with_max_input_len`](crate::EncodeOptions::with_max_input_len).
+    InputTooLong { len: usize, max: usize },
+    /// A file or stream could not be read or written.
+    Io(Arc<std::io::Error>),
+    /// A merges file could not be read or parsed.
+    Merges(Arc<MergesError>),
+    /// A tiktoken rank file could not be read or parsed.
+    Tiktoken(Arc<TiktokenError>),
+    /// A `tokenizer.json` or GPT-2 file could not be saved or loaded.
+    #[cfg(feature = "json")]
+    TokenizerJson(Arc<crate::TokenizerJsonError>),
+    /// A binary tokenizer or batch could not be saved or loaded.
+    #[cfg(feature = "bincode")]
+    Wire(Arc<crate::WireError>),
+    /// A pinned dataset could not be downloaded or verified.
+    #[cfg(feature = "datasets")]
+    Dataset(Arc<crate::DatasetError>),
+    /// A tokenizer could not be exported as GGUF.
+    Gguf(Arc<GgufError>),
+    /// A word could not be cut into end-of-word symbols.
+    Symbol(SymbolError),
+}
+
+impl PartialEq for TokenizerError {
+    fn eq(&self, other: &Self) -> bool {
+        use TokenizerError::*;
+        match (self, other) {
+            (Encode(a), Encode(b)) => a == b,
+            (UnknownToken(a), UnknownToken(b)) => a == b,
+            (UnknownTokenId(a), UnknownTokenId(b)) => a == b,
+            (InvalidUtf8(a), InvalidUtf8(b)) => a == b,
+            (NotByteLevel { id: a, ch: c }, NotByteLevel { id: b, ch: d }) => a == b && c == d,
+            (InvalidConfig(a), InvalidConfig(b)) => a == b,
+            (Training(a), Training(b)) => a == b,
+            (InvalidVocabulary(a), InvalidVocabulary(b)) => a == b,
+            (VocabularyMismatch(a), VocabularyMismatch(b)) => a == b,
+            (InputTooLong { len: a, max: m }, InputTooLong { len: b, max: n }) => a == b && m == n,
+            (Io(a), Io(b)) => Arc::ptr_eq(a, b),
+            (Merges(a), Merges(b)) => Arc::ptr_eq(a, b),
+            (Tiktoken(a), Tiktoken(b)) => Arc::ptr_eq(a, b),
+            #[cfg(feature = "json")]
+            (TokenizerJson(a), TokenizerJson(b)) => Arc::ptr_eq(a, b),
+            #[cfg(feature = "bincode")]
+            (Wire(a), Wire(b)) => Arc::ptr_eq(a, b),
+            #[cfg(feature = "datasets")]
+            (Dataset(a), Dataset(b)) => Arc::ptr_eq(a, b),
+            (Gguf(a), Gguf(b)) => Arc::ptr_eq(a, b),
+            (Symbol(a), Symbol(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for TokenizerError {}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizerError::Encode(err) => err.fmt(f),
+            TokenizerError::UnknownToken(token) => {
+                write!(f, "token '{}' not in vocabulary", token)
+            }
+            TokenizerError::UnknownTokenId(id) => write!(f, "token ID {} not in vocabulary", id),
+            TokenizerError::InvalidUtf8(err) => {
+                write!(f, "failed to decode bytes to UTF-8: {}", err)
+            }
+            TokenizerError::NotByteLevel { id, ch } => {
+                write!(
+                    f,
+                    "token ID {} holds {:?}, which is not a byte-level symbol",
+                    id, ch
+                )
+            }
+            TokenizerError::InvalidConfig(err) => err.fmt(f),
+            TokenizerError::Training(err) => err.fmt(f),
+            TokenizerError::InvalidVocabulary(err) => err.fmt(f),
+            TokenizerError::VocabularyMismatch(err) => err.fmt(f),
+            TokenizerError::InputTooLong { len, max } => {
+                write!(
+                    f,
+                    "input of {} bytes exceeds the limit of {} bytes",
+                    len, max
+                )
+            }
+            TokenizerError::Io(err) => err.fmt(f),
+            TokenizerError::Merges(err) => err.fmt(f),
+            TokenizerError::Tiktoken(err) => err.fmt(f),
+            #[cfg(feature = "json")]
+            TokenizerError::TokenizerJson(err) => err.fmt(f),
+            #[cfg(feature = "bincode")]
+            TokenizerError::Wire(err) => err.fmt(f),
+            #[cfg(feature = "datasets")]
+            TokenizerError::Dataset(err) => err.fmt(f),
+            TokenizerError::Gguf(err) => err.fmt(f),
+            TokenizerError::Symbol(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TokenizerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TokenizerError::Encode(err) => Some(err),
+            TokenizerError::InvalidUtf8(err) => Some(err),
+            TokenizerError::InvalidConfig(err) => Some(err),
+            TokenizerError::Training(err) => Some(err),
+            TokenizerError::InvalidVocabulary(err) => Some(err),
+            TokenizerError::VocabularyMismatch(err) => Some(err),
+            TokenizerError::Io(err) => Some(&**err),
+            TokenizerError::Merges(err) => Some(&**err),
+            TokenizerError::Tiktoken(err) => Some(&**err),
+            #[cfg(feature = "json")]
+            TokenizerError::TokenizerJson(err) => Some(&**err),
+            #[cfg(feature = "bincode")]
+            TokenizerError::Wire(err) => Some(&**err),
+            #[cfg(feature = "datasets")]
+            TokenizerError::Dataset(err) => Some(&**err),
+            TokenizerError::Gguf(err) => Some(&**err),
+            TokenizerError::Symbol(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for TokenizerError {
+    fn from(err: FromUtf8Error) -> Self {
+        TokenizerError::InvalidUtf8(err)
+    }
+}
+
+impl From<EncodeError> for TokenizerError {
+    fn from(err: EncodeError) -> Self {
+        TokenizerError::Encode(err)
+    }
+}
+
+impl From<std::io::Error> for TokenizerError {
+    fn from(err: std::io::Error) -> Self {
+        TokenizerError::Io(Arc::new(err))
+    }
+}
+
+impl From<ConfigError> for TokenizerError {
+    fn from(err: ConfigError) -> Self {
+        TokenizerError::InvalidConfig(err)
+    }
+}
+
+impl From<ReproducibilityError> for TokenizerError {
+    fn from(err: ReproducibilityError) -> Self {
+        TokenizerError::Training(err)
+    }
+}
+
+impl From<VocabularyError> for TokenizerError {
+    fn from(err: VocabularyError) -> Self {
+        TokenizerError::InvalidVocabulary(err)
+    }
+}
+
+impl From<VocabularyMismatch> for TokenizerError {
+    fn from(err: VocabularyMismatch) -> Self {
+        TokenizerError::VocabularyMismatch(err)
+    }
+}
+
+impl From<MergesError> for TokenizerError {
+    fn from(err: MergesError) -> Self {
+        TokenizerError::Merges(Arc::new(err))
+    }
+}
+
+impl From<TiktokenError> for TokenizerError {
+    fn from(err: TiktokenError) -> Self {
+        TokenizerError::Tiktoken(Arc::new(err))
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<crate::TokenizerJsonError> for TokenizerError {
+    fn from(err: crate::TokenizerJsonError) -> Self {
+        TokenizerError::TokenizerJson(Arc::new(err))
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl From<crate::WireError> for TokenizerError {
+    fn from(err: crate::WireError) -> Self {
+        TokenizerError::Wire(Arc::new(err))
+    }
+}
+
+#[cfg(feature = "datasets")]
+impl From<crate::DatasetError> for TokenizerError {
+    fn from(err: crate::DatasetError) -> Self {
+        TokenizerError::Dataset(Arc::new(err))
+    }
+}
+
+impl From<GgufError> for TokenizerError {
+    fn from(err: GgufError) -> Self {
+        TokenizerError::Gguf(Arc::new(err))
+    }
+}
+
+impl From<SymbolError> for TokenizerError {
+    fn from(err: SymbolError) -> Self {
+        TokenizerError::Symbol(err)
+    }
+}
+
+impl From<DecodeError> for TokenizerError {
+    fn from(err: DecodeError) -> Self {
+        match err {
+            DecodeError::UnknownId(id) => TokenizerError::UnknownTokenId(id),
+            DecodeError::InvalidUtf8(err) => TokenizerError::InvalidUtf8(err),
+            DecodeError::NotByteLevel { id, ch } => TokenizerError::NotByteLevel { id, ch },
+        }
+    }
+}
+
+/// Error returned by [`BpeTokenizer::try_encode`](crate::BpeTokenizer::try_encode) and
+/// [`Encoder::try_encode`](crate::Encoder::try_encode) when a token produced while
+/// encoding has no ID, meaning the vocabulary and merge rules are out of sync.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeError {
+    /// The token missing from the vocabulary, in byte-level form.
+    pub token: String,
+    /// The pre-tokenized chunk of normalized input the token was produced from, or
+    /// the special token itself.
+    pub chunk: String,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "token '{}' not in vocabulary (encoding chunk {:?})",
+            self.token, self.chunk
+        )
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Error returned by [`BpeTokenizer::try_decode`](crate::BpeTokenizer::try_decode) and
+/// [`Decoder::try_decode`](crate::Decoder::try_decode).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// An ID is not in the vocabulary, e.g. one produced by a different tokenizer.
+    UnknownId(u32),
+    /// The decoded bytes are not valid UTF-8, e.g. because the IDs split a
+    /// multi-byte character.
+    InvalidUtf8(FromUtf8Error),
+    /// A token that is not marked special holds a character outside the byte-level
+    /// alphabet, e.g. a special token containing a space decoded by a [`Decoder`]
+    /// built without [`with_special_tokens`](crate::Decoder::with_special_tokens).
+    ///
+    /// [`Decoder`]: crate::Decoder
+    NotByteLevel { id: u32, ch: char },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnknownId(id) => write!(f, "token ID {} not in vocabulary", id),
+            DecodeError::InvalidUtf8(err) => {
+                write!(f, "failed to decode bytes to UTF-8: {}", err)
+            }
+            DecodeError::NotByteLevel { id, ch } => {
+                write!(
+                    f,
+                    "token ID {} holds {:?}, which is not a byte-level symbol",
+                    id, ch
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::InvalidUtf8(err) => Some(err),
+            DecodeError::UnknownId(_) | DecodeError::NotByteLevel { .. } => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for DecodeError {
+    fn from(err: FromUtf8Error) -> Self {
+        DecodeError::InvalidUtf8(err)
+    }
+}
+
+/// Error returned by [`BpeTokenizer::validate_ids`](crate::BpeTokenizer::validate_ids)
+/// when some IDs are outside the vocabulary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidIds {
+    /// Size of the vocabulary the IDs were checked against; valid IDs are below it.
+    pub vocab_size: usize,
+    /// Every out-of-range ID as `(position, id)`, in input order.
+    pub invalid: Vec<(usize, u32)>,
+}
+
+impl fmt::Display for InvalidIds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} token ID(s) out of range for vocabulary of size {}:",
+            self.invalid.len(),
+            self.vocab_size
+        )?;
+        for (position, id) in &self.invalid {
+            write!(f, " {} at position {};", id, position)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidIds {}
+
+/// One way special tokens or merges would give a token several IDs, reported in
+/// [`VocabularyError::conflicts`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VocabularyConflict {
+    /// The same special token is listed more than once.
+    DuplicateSpecialToken { token: String },
+    /// A special token is also a byte-level base token or a merge product.
+    SpecialTokenCollision { token: String },
+    /// The merge at `rank` produces the same token as the earlier one at
+    /// `first_rank`, e.g. `a + bc` after `ab + c`.
+    DuplicateMergeTarget {
+        token: String,
+        first_rank: usize,
+        rank: usize,
+    },
+}
+
+impl fmt::Display for VocabularyConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VocabularyConflict::DuplicateSpecialToken { token } => {
+                write!(f, "special token '{}' is listed more than once", token)
+            }
+            VocabularyConflict::SpecialTokenCollision { token } => {
+                write!(f, "special token '{}' is also a regular token", token)
+            }
+            VocabularyConflict::DuplicateMergeTarget {
+                token,
+                first_rank,
+                rank,
+            } => write!(
+                f,
+                "merge {} produces '{}', already produced by merge {}",
+                rank, token, first_rank
+            ),
+        }
+    }
+}
+
+/// Error returned by [`Vocabulary::try_new`](crate::Vocabulary::try_new) when the
+/// special tokens and merges do not give every token exactly one ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VocabularyError {
+    /// Every conflict found: duplicate special tokens first, then special tokens
+    /// that collide with regular tokens, then merges in rank order.
+    pub conflicts: Vec<VocabularyConflict>,
+}
+
+impl fmt::Display for VocabularyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} conflicting token(s) in vocabulary:",
+            self.conflicts.len()
+        )?;
+        for conflict in &self.conflicts {
+            write!(f, " {};", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VocabularyError {}
+
+/// A token that merges or special tokens need but a vocabulary lacks, reported in
+/// [`VocabularyMismatch::missing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingToken {
+    /// The byte-level base token for `byte` has no ID.
+    ByteToken { byte: u8, token: String },
+    /// A special token has no ID.
+    SpecialToken { token: String },
+    /// An operand of the merge at `rank` is neither a base token nor produced by an
+    /// earlier merge, so the merge can never apply.
+    MergeOperand { rank: usize, token: String },
+    /// The token produced by the merge at `rank` has no ID.
+    MergeProduct { rank: usize, token: String },
+}
+
+impl fmt::Display for MissingToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingToken::ByteToken { byte, token } => {
+                write!(
+                    f,
+                    "base token '{}' for byte 0x{:02x} has no ID",
+                    token, byte
+                )
+            }
+            MissingToken::SpecialToken { token } => {
+                write!(f, "special token '{}' has no ID", token)
+            }
+            MissingToken::MergeOperand { rank, token } => write!(
+                f,
+                "merge {} uses '{}', which no earlier merge produces",
+                rank, token
+            ),
+            MissingToken::MergeProduct { rank, token } => {
+                write!(f, "merge {} produces '{}', which has no ID", rank, token)
+            }
+        }
+    }
+}
+
+/// Error returned by [`Encoder::try_new`](crate::Encoder::try_new) when the merges
+/// or special tokens need tokens the vocabulary does not have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VocabularyMismatch {
+    /// Every missing token: base tokens first, then special tokens, then merges in
+    /// rank order.
+    pub missing: Vec<MissingToken>,
+}
+
+impl fmt::Display for VocabularyMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} token(s) missing from vocabulary:",
+            self.missing.len()
+        )?;
+        for missing in &self.missing {
+            write!(f, " {};", missing)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for VocabularyMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use std::io;
+
+    use super::*;
+    use crate::low_level::{MergeTable, SymbolError, SymbolMode};
+    use crate::{BpeTokenizer, MergesError, TiktokenError, Trainer, parse_merges};
+
+    /// Every public error type must work with `?` into `Box<dyn Error + Send + Sync>`,
+    /// which is what `anyhow` and `thiserror`'s `#[from]` need.
+    fn assert_composable<E: Error + Send + Sync + 'static>() {}
+
+    #[test]
+    fn public_errors_are_composable() {
+        assert_composable::<TokenizerError>();
+        assert_composable::<EncodeError>();
+        assert_composable::<DecodeError>();
+        assert_composable::<InvalidIds>();
+        assert_composable::<VocabularyError>();
+        assert_composable::<VocabularyMismatch>();
+        assert_composable::<MergesError>();
+        assert_composable::<ReproducibilityError>();
+        assert_composable::<SymbolError>();
+        assert_composable::<TiktokenError>();
+        #[cfg(feature = "json")]
+        assert_composable::<crate::TokenizerJsonError>();
+        #[cfg(feature = "bincode")]
+        assert_composable::<crate::WireError>();
+        #[cfg(feature = "datasets")]
+        assert_composable::<crate::DatasetError>();
+        #[cfg(feature = "compat-fuzz")]
+        assert_composable::<crate::Divergence>();
+    }
+
+    #[test]
+    fn stage_errors_convert_into_tokenizer_error() {
+        let corpus = ["the cat sat"];
+        let report = Trainer::new(3).train_with_report(&corpus);
+        let err: TokenizerError = Trainer::new(4)
+            .reproduce(&corpus, &report)
+            .unwrap_err()
+            .into();
+        assert!(matches!(err, TokenizerError::Training(_)));
+        assert!(err.source().is_some());
+
+        let tokenizer = BpeTokenizer::new(vec![("a".into(), "b".into())], vec![]);
+        let config = crate::TokenizerConfig {
+            vocabulary: Some(BpeTokenizer::new(vec![], vec![]).vocabulary().clone()),
+            ..tokenizer.config()
+        };
+        let err: TokenizerError = BpeTokenizer::from_config(config).err().unwrap().into();
+        assert!(err.to_string().starts_with("inconsistent tokenizer config"));
+
+        let err: TokenizerError = tokenizer.try_decode(&[9999]).unwrap_err().into();
+        assert_eq!(err, TokenizerError::UnknownTokenId(9999));
+    }
+
+    #[test]
+    fn loader_errors_keep_their_cause() {
+        fn load() -> Result<BpeTokenizer, Box<dyn Error + Send + Sync>> {
+            let merges = parse_merges("a b c")?;
+            Ok(BpeTokenizer::new(merges, vec![]))
+        }
+        let err = load().err().unwrap();
+        assert!(err.downcast_ref::<MergesError>().is_some());
+
+        let err = BpeTokenizer::from_tiktoken_file("/nonexistent/ranks.tiktoken", &[])
+            .err()
+            .unwrap();
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::NotFound
+        );
+    }
+
+    #[test]
+    fn loader_errors_convert_into_tokenizer_error() {
+        fn load(merges: &str, word: &str) -> Result<Vec<String>, TokenizerError> {
+            let table = MergeTable::new(parse_merges(merges)?);
+            let symbols = SymbolMode::EndOfWord.symbols(word)?;
+            Ok(table.merge_symbols(symbols))
+        }
+        let err = load("a b c", "ab").unwrap_err();
+        assert!(matches!(err, TokenizerError::Merges(_)));
+        assert!(
+            err.source()
+                .unwrap()
+                .downcast_ref::<MergesError>()
+                .is_some()
+        );
+        assert_eq!(err, err.clone());
+        assert_ne!(err, load("a b c", "ab").unwrap_err());
+
+        assert_eq!(
+            load("a b", "a b").unwrap_err(),
+            TokenizerError::Symbol(SymbolError::Whitespace("a b".into()))
+        );
+
+        let err: TokenizerError =
+            BpeTokenizer::from_tiktoken_file("/nonexistent/ranks.tiktoken", &[])
+                .err()
+                .unwrap()
+                .into();
+        assert!(matches!(err, TokenizerError::Tiktoken(_)));
+        assert!(err.to_string().starts_with("failed to read tiktoken file"));
+    }
+
+    #[test]
+    fn encode_and_io_errors_keep_their_details() {
+        let err = EncodeError {
+            token: "ab".to_string(),
+            chunk: " cab".to_string(),
+        };
+        let converted = TokenizerError::from(err.clone());
+        assert_eq!(converted.to_string(), err.to_string());
+        assert_eq!(
+            converted.source().unwrap().downcast_ref::<EncodeError>(),
+            Some(&err)
+        );
+
+        fn read(path: &str) -> Result<String, TokenizerError> {
+            Ok(std::fs::read_to_string(path)?)
+        }
+        let err = read("/nonexistent/corpus.txt").unwrap_err();
+        let TokenizerError::Io(io_err) = &err else {
+            panic!("expected an I/O error, got {:?}", err);
+        };
+        assert_eq!(io_err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(err, err.clone());
+    }
+}
@@ -0,0 +1,351 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::{BpeTokenizer, EncodeStrategy, Normalizer};
+
+/// GGUF version written by [`BpeTokenizer::export_gguf`].
+const GGUF_VERSION: u32 = 3;
+
+/// Alignment of the (empty) tensor data section, GGUF's default.
+const ALIGNMENT: usize = 32;
+
+/// GGUF value type tags.
+const TYPE_UINT32: u32 = 4;
+const TYPE_INT32: u32 = 5;
+const TYPE_BOOL: u32 = 7;
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+
+/// llama.cpp token types (`llama_token_type`).
+const TOKEN_NORMAL: i32 = 1;
+const TOKEN_CONTROL: i32 = 3;
+
+/// Error returned when a tokenizer cannot be exported as GGUF.
+#[derive(Debug)]
+pub enum GgufError {
+    /// The file could not be written.
+    Io(io::Error),
+    /// A setting has no equivalent in llama.cpp's tokenizer, such as a normalizer or
+    /// a custom pre-tokenizer regex.
+    Unsupported(String),
+}
+
+impl fmt::Display for GgufError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GgufError::Io(err) => write!(f, "failed to write GGUF file: {}", err),
+            GgufError::Unsupported(setting) => {
+                write!(f, "unsupported in GGUF tokenizer metadata: {}", setting)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GgufError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GgufError::Io(err) => Some(err),
+            GgufError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for GgufError {
+    fn from(err: io::Error) -> Self {
+        GgufError::Io(err)
+    }
+}
+
+/// A metadata value of one of the types the tokenizer keys use.
+enum Value {
+    U32(u32),
+    Bool(bool),
+    String(String),
+    Strings(Vec<String>),
+    I32s(Vec<i32>),
+}
+
+/// Writes `tokenizer` as a GGUF file with tokenizer metadata and no tensors.
+pub(crate) fn write(tokenizer: &BpeTokenizer, mut writer: impl Write) -> Result<(), GgufError> {
+    let metadata = metadata(tokenizer)?;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"GGUF");
+    bytes.extend_from_slice(&GGUF_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    bytes.extend_from_slice(&(metadata.len() as u64).to_le_bytes());
+    for (key, value) in &metadata {
+        put_string(&mut bytes, key);
+        put_value(&mut bytes, value);
+    }
+    bytes.resize(bytes.len().next_multiple_of(ALIGNMENT), 0);
+
+    writer.write_all(&bytes)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// The key/value pairs llama.cpp reads to rebuild a GPT-2 style BPE tokenizer.
+fn metadata(tokenizer: &BpeTokenizer) -> Result<Vec<(&'static str, Value)>, GgufError> {
+    if tokenizer.encode_strategy() != EncodeStrategy::MergeRank {
+        return Err(GgufError::Unsupported(format!(
+            "encode strategy {:?}",
+            tokenizer.encode_strategy()
+        )));
+    }
+    if tokenizer.normalizer().describe() != Normalizer::new().describe() {
+        return Err(GgufError::Unsupported(format!(
+            "normalizer {}",
+            tokenizer.normalizer().describe()
+        )));
+    }
+    if !tokenizer.pre_tokenizer().is_gpt2() {
+        return Err(GgufError::Unsupported(format!(
+            "pre-tokenizer {}",
+            tokenizer.pre_tokenizer().describe()
+        )));
+    }
+
+    let vocabulary = tokenizer.vocabulary();
+    let special_ids: Vec<u32> = tokenizer
+        .special_tokens()
+        .iter()
+        .filter_map(|token| vocabulary.token_to_id(token))
+        .collect();
+    let token_types = vocabulary
+        .iter()
+        .map(|(id, _)| {
+            if special_ids.contains(&id) {
+                TOKEN_CONTROL
+            } else {
+                TOKEN_NORMAL
+            }
+        })
+        .collect();
+
+    let mut metadata = vec![("general.architecture", Value::String("gpt2".to_string()))];
+    if let Some(name) = &tokenizer.metadata().name {
+        metadata.push(("general.name", Value::String(name.clone())));
+    }
+    metadata.extend([
+        ("tokenizer.ggml.model", Value::String("gpt2".to_string())),
+        ("tokenizer.ggml.pre", Value::String("gpt-2".to_string())),
+        (
+            "tokenizer.ggml.tokens",
+            Value::Strings(
+                vocabulary
+                    .iter()
+                    .map(|(_, token)| token.to_string())
+                    .collect(),
+            ),
+        ),
+        ("tokenizer.ggml.token_type", Value::I32s(token_types)),
+        (
+            "tokenizer.ggml.merges",
+            Value::Strings(
+                tokenizer
+                    .merges()
+                    .iter()
+                    .map(|(first, second)| format!("{} {}", first, second))
+                    .collect(),
+            ),
+        ),
+        ("tokenizer.ggml.add_bos_token", Value::Bool(false)),
+    ]);
+    if let Some(id) = vocabulary.token_to_id("<|endoftext|>") {
+        metadata.push(("tokenizer.ggml.bos_token_id", Value::U32(id)));
+        metadata.push(("tokenizer.ggml.eos_token_id", Value::U32(id)));
+    }
+
+    Ok(metadata)
+}
+
+fn put_string(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(&(text.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(text.as_bytes());
+}
+
+fn put_value(bytes: &mut Vec<u8>, value: &Value) {
+    match value {
+        Value::U32(number) => {
+            bytes.extend_from_slice(&TYPE_UINT32.to_le_bytes());
+            bytes.extend_from_slice(&number.to_le_bytes());
+        }
+        Value::Bool(flag) => {
+            bytes.extend_from_slice(&TYPE_BOOL.to_le_bytes());
+            bytes.push(u8::from(*flag));
+        }
+        Value::String(text) => {
+            bytes.extend_from_slice(&TYPE_STRING.to_le_bytes());
+            put_string(bytes, text);
+        }
+        Value::Strings(texts) => {
+            put_array_header(bytes, TYPE_STRING, texts.len());
+            for text in texts {
+                put_string(bytes, text);
+            }
+        }
+        Value::I32s(numbers) => {
+            put_array_header(bytes, TYPE_INT32, numbers.len());
+            for number in numbers {
+                bytes.extend_from_slice(&number.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn put_array_header(bytes: &mut Vec<u8>, item_type: u32, len: usize) {
+    bytes.extend_from_slice(&TYPE_ARRAY.to_le_bytes());
+    bytes.extend_from_slice(&item_type.to_le_bytes());
+    bytes.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{Metadata, PreTokenizer, Trainer};
+
+    /// A decoded metadata value, for checking files in tests.
+    #[derive(Debug, PartialEq)]
+    enum Parsed {
+        U32(u32),
+        Bool(bool),
+        String(String),
+        Strings(Vec<String>),
+        I32s(Vec<i32>),
+    }
+
+    /// Minimal GGUF reader for the value types the exporter writes.
+    struct Reader<'a> {
+        bytes: &'a [u8],
+        position: usize,
+    }
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, len: usize) -> &'a [u8] {
+            let slice = &self.bytes[self.position..self.position + len];
+            self.position += len;
+            slice
+        }
+
+        fn u32(&mut self) -> u32 {
+            u32::from_le_bytes(self.take(4).try_into().unwrap())
+        }
+
+        fn len(&mut self) -> usize {
+            u64::from_le_bytes(self.take(8).try_into().unwrap()) as usize
+        }
+
+        fn string(&mut self) -> String {
+            let len = self.len();
+            String::from_utf8(self.take(len).to_vec()).unwrap()
+        }
+
+        fn value(&mut self) -> Parsed {
+            match self.u32() {
+                TYPE_UINT32 => Parsed::U32(self.u32()),
+                TYPE_BOOL => Parsed::Bool(self.take(1)[0] != 0),
+                TYPE_STRING => Parsed::String(self.string()),
+                TYPE_ARRAY => {
+                    let item_type = self.u32();
+                    let len = self.len();
+                    match item_type {
+                        TYPE_STRING => Parsed::Strings((0..len).map(|_| self.string()).collect()),
+                        TYPE_INT32 => Parsed::I32s((0..len).map(|_| self.u32() as i32).collect()),
+                        other => panic!("unexpected array type {}", other),
+                    }
+                }
+                other => panic!("unexpected value type {}", other),
+            }
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> HashMap<String, Parsed> {
+        let mut reader = Reader { bytes, position: 0 };
+
+        assert_eq!(reader.take(4), b"GGUF");
+        assert_eq!(reader.u32(), GGUF_VERSION);
+        assert_eq!(reader.len(), 0, "no tensors");
+        let count = reader.len();
+        let metadata = (0..count)
+            .map(|_| (reader.string(), reader.value()))
+            .collect();
+
+        assert!(bytes.len().is_multiple_of(ALIGNMENT));
+        assert!(bytes[reader.position..].iter().all(|&byte| byte == 0));
+        metadata
+    }
+
+    #[test]
+    fn writes_tokens_merges_and_special_tokens() {
+        let tokenizer = BpeTokenizer::from_trainer(
+            &Trainer::new(5),
+            &["hello world", "hello there"],
+            vec!["<|endoftext|>".to_string()],
+        )
+        .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true))
+        .with_metadata(Metadata::new().with_name("tiny"));
+        let mut bytes = Vec::new();
+
+        write(&tokenizer, &mut bytes).unwrap();
+        let metadata = parse(&bytes);
+
+        let Parsed::Strings(tokens) = &metadata["tokenizer.ggml.tokens"] else {
+            panic!("tokens are strings");
+        };
+        assert_eq!(tokens.len(), tokenizer.vocabulary().len());
+        assert_eq!(tokens[0], "<|endoftext|>");
+        assert_eq!(tokens[33], "A");
+        let Parsed::I32s(types) = &metadata["tokenizer.ggml.token_type"] else {
+            panic!("token types are integers");
+        };
+        assert_eq!(types[0], TOKEN_CONTROL);
+        assert!(types[1..].iter().all(|&kind| kind == TOKEN_NORMAL));
+        let Parsed::Strings(merges) = &metadata["tokenizer.ggml.merges"] else {
+            panic!("merges are strings");
+        };
+        let (first, second) = &tokenizer.merges()[0];
+        assert_eq!(merges[0], format!("{} {}", first, second));
+        assert_eq!(merges.len(), 5);
+
+        assert_eq!(
+            metadata["tokenizer.ggml.model"],
+            Parsed::String("gpt2".to_string())
+        );
+        assert_eq!(metadata["general.name"], Parsed::String("tiny".to_string()));
+        assert_eq!(metadata["tokenizer.ggml.eos_token_id"], Parsed::U32(0));
+        assert_eq!(
+            metadata["tokenizer.ggml.add_bos_token"],
+            Parsed::Bool(false)
+        );
+    }
+
+    #[test]
+    fn rejects_settings_llama_cpp_cannot_reproduce() {
+        let base = || {
+            BpeTokenizer::new(vec![], vec![])
+                .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true))
+        };
+        let export = |tokenizer: &BpeTokenizer| write(tokenizer, io::sink()).err();
+
+        assert!(export(&base()).is_none());
+        // llama.cpp's "gpt-2" pattern has `\s+(?!\S)`, so "a  dog" splits differently.
+        let no_lookahead = base().with_pre_tokenizer(PreTokenizer::new());
+        assert!(matches!(
+            export(&no_lookahead),
+            Some(GgufError::Unsupported(_))
+        ));
+        assert!(
+            export(&base().with_normalizer(Normalizer::new().with_nfc(true)))
+                .unwrap()
+                .to_string()
+                .contains("normalizer")
+        );
+        let runs = base().with_pre_tokenizer(PreTokenizer::new().with_whitespace_runs(&[4]));
+        assert!(matches!(export(&runs), Some(GgufError::Unsupported(_))));
+        #[cfg(feature = "regex")]
+        assert!(export(&base().with_pre_tokenizer(PreTokenizer::gpt2_regex())).is_some());
+    }
+}
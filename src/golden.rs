@@ -0,0 +1,236 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+
+use crate::BpeTokenizer;
+
+/// Entries listed per section by the [`Display`](fmt::Display) output of
+/// [`TokenizerDiff`]; one changed special token can shift every ID after it.
+const MAX_LISTED: usize = 20;
+
+/// A token whose ID differs between the golden and the current tokenizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdChange {
+    /// The token, in byte-level form.
+    pub token: String,
+    /// Its ID in the golden tokenizer.
+    pub golden: u32,
+    /// Its ID in the current tokenizer.
+    pub current: u32,
+}
+
+/// Differences between a tokenizer and a stored golden copy of it, as returned by
+/// [`BpeTokenizer::diff_against_golden`].
+///
+/// Merges are compared as pairs, so a merge that only moved to another rank shows
+/// up in [`changed_ids`](TokenizerDiff::changed_ids) rather than as removed and
+/// added. The [`Display`](fmt::Display) output lists the differences one per line,
+/// `-` for the golden side and `+` for the current one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenizerDiff {
+    /// Merges of the current tokenizer missing from the golden one, with their rank.
+    pub added_merges: Vec<(usize, (String, String))>,
+    /// Merges of the golden tokenizer missing from the current one, with their rank.
+    pub removed_merges: Vec<(usize, (String, String))>,
+    /// Special tokens only the current tokenizer has.
+    pub added_special_tokens: Vec<String>,
+    /// Special tokens only the golden tokenizer has.
+    pub removed_special_tokens: Vec<String>,
+    /// Tokens in both vocabularies under different IDs, in golden ID order.
+    pub changed_ids: Vec<IdChange>,
+    /// Each differing setting as `(setting, golden, current)`.
+    pub settings: Vec<(&'static str, String, String)>,
+}
+
+impl TokenizerDiff {
+    /// Compares `current` with `golden`.
+    pub(crate) fn new(golden: &BpeTokenizer, current: &BpeTokenizer) -> Self {
+        let removed_merges = missing_from(golden.merges(), current.merges());
+        let added_merges = missing_from(current.merges(), golden.merges());
+        let special_tokens = |from: &BpeTokenizer, other: &BpeTokenizer| {
+            missing_from(from.special_tokens(), other.special_tokens())
+                .into_iter()
+                .map(|(_, token)| token)
+                .collect()
+        };
+
+        let current_ids: HashMap<&str, u32> = current
+            .vocabulary()
+            .iter()
+            .map(|(id, token)| (token, id))
+            .collect();
+        let changed_ids = golden
+            .vocabulary()
+            .iter()
+            .filter_map(|(id, token)| {
+                let current = *current_ids.get(token)?;
+                (current != id).then(|| IdChange {
+                    token: token.to_string(),
+                    golden: id,
+                    current,
+                })
+            })
+            .collect();
+
+        let settings = [
+            (
+                "vocab_size",
+                golden.vocabulary().len().to_string(),
+                current.vocabulary().len().to_string(),
+            ),
+            (
+                "normalizer",
+                golden.normalizer().describe(),
+                current.normalizer().describe(),
+            ),
+            (
+                "pre_tokenizer",
+                golden.pre_tokenizer().describe(),
+                current.pre_tokenizer().describe(),
+            ),
+            (
+                "encode_strategy",
+                format!("{:?}", golden.encode_strategy()),
+                format!("{:?}", current.encode_strategy()),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, golden, current)| golden != current)
+        .collect();
+
+        TokenizerDiff {
+            added_merges,
+            removed_merges,
+            added_special_tokens: special_tokens(current, golden),
+            removed_special_tokens: special_tokens(golden, current),
+            changed_ids,
+            settings,
+        }
+    }
+
+    /// Returns true if the tokenizers match.
+    pub fn is_empty(&self) -> bool {
+        self.added_merges.is_empty()
+            && self.removed_merges.is_empty()
+            && self.added_special_tokens.is_empty()
+            && self.removed_special_tokens.is_empty()
+            && self.changed_ids.is_empty()
+            && self.settings.is_empty()
+    }
+}
+
+/// Items of `items` missing from `other`, with their index.
+fn missing_from<T: Clone + Eq + Hash>(items: &[T], other: &[T]) -> Vec<(usize, T)> {
+    let other: HashSet<&T> = other.iter().collect();
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| !other.contains(item))
+        .map(|(index, item)| (index, item.clone()))
+        .collect()
+}
+
+/// Writes at most [`MAX_LISTED`] lines, then a count of the rest.
+fn write_lines<T>(
+    f: &mut fmt::Formatter<'_>,
+    items: &[T],
+    line: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+) -> fmt::Result {
+    for item in items.iter().take(MAX_LISTED) {
+        line(f, item)?;
+        writeln!(f)?;
+    }
+    if items.len() > MAX_LISTED {
+        writeln!(f, "  ... and {} more", items.len() - MAX_LISTED)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for TokenizerDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "tokenizer matches the golden copy");
+        }
+
+        for (setting, golden, current) in &self.settings {
+            writeln!(f, "- {}: {}", setting, golden)?;
+            writeln!(f, "+ {}: {}", setting, current)?;
+        }
+        write_lines(f, &self.removed_special_tokens, |f, token| {
+            write!(f, "- special token {:?}", token)
+        })?;
+        write_lines(f, &self.added_special_tokens, |f, token| {
+            write!(f, "+ special token {:?}", token)
+        })?;
+        write_lines(f, &self.removed_merges, |f, (rank, (first, second))| {
+            write!(f, "- merge {}: {:?} {:?}", rank, first, second)
+        })?;
+        write_lines(f, &self.added_merges, |f, (rank, (first, second))| {
+            write!(f, "+ merge {}: {:?} {:?}", rank, first, second)
+        })?;
+        write_lines(f, &self.changed_ids, |f, change| {
+            write!(
+                f,
+                "~ id of {:?}: {} -> {}",
+                change.token, change.golden, change.current
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Normalizer;
+    use crate::test_support::merges;
+
+    #[test]
+    fn identical_tokenizers_have_no_differences() {
+        let tokenizer = BpeTokenizer::new(merges(&[("h", "e")]), vec!["<s>".into()]);
+        let diff = TokenizerDiff::new(&tokenizer, &tokenizer);
+
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "tokenizer matches the golden copy\n");
+    }
+
+    #[test]
+    fn reports_merges_special_tokens_ids_and_settings() {
+        let golden = BpeTokenizer::new(merges(&[("h", "e"), ("l", "l")]), vec!["<a>".into()]);
+        let current = BpeTokenizer::new(merges(&[("l", "l"), ("t", "h")]), vec!["<b>".into()])
+            .with_normalizer(Normalizer::new().with_nfc(true));
+
+        let diff = TokenizerDiff::new(&golden, &current);
+
+        assert_eq!(diff.removed_merges, vec![(0, ("h".into(), "e".into()))]);
+        assert_eq!(diff.added_merges, vec![(1, ("t".into(), "h".into()))]);
+        assert_eq!(diff.removed_special_tokens, vec!["<a>".to_string()]);
+        assert_eq!(diff.added_special_tokens, vec!["<b>".to_string()]);
+        assert_eq!(
+            diff.changed_ids,
+            vec![IdChange {
+                token: "ll".into(),
+                golden: 258,
+                current: 257,
+            }]
+        );
+        assert_eq!(diff.settings.len(), 1);
+        assert_eq!(diff.settings[0].0, "normalizer");
+
+        let text = diff.to_string();
+        assert!(text.contains("- merge 0: \"h\" \"e\"\n"));
+        assert!(text.contains("+ special token \"<b>\"\n"));
+        assert!(text.contains("~ id of \"ll\": 258 -> 257\n"));
+    }
+
+    #[test]
+    fn long_sections_are_cut_short() {
+        let golden = BpeTokenizer::new(vec![], vec!["<a>".into()]);
+        let current = BpeTokenizer::new(vec![], vec!["<a>".into(), "<b>".into()]);
+
+        let diff = TokenizerDiff::new(&golden, &current);
+
+        assert_eq!(diff.changed_ids.len(), 256);
+        let text = diff.to_string();
+        assert!(text.contains(&format!("  ... and {} more\n", 256 - MAX_LISTED)));
+    }
+}
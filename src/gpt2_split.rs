@@ -0,0 +1,205 @@
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+
+const CONTRACTIONS: [&str; 7] = ["s", "t", "re", "ve", "m", "ll", "d"];
+
+/// Splits text with the GPT-2 pre-tokenization rules without using regex.
+///
+/// Produces exactly the matches of
+/// `'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+` under
+/// leftmost-first semantics, as `(start, chunk)` pairs. Every character belongs to
+/// exactly one of letters, numbers, whitespace or "other", so after the
+/// contractions the class of the first non-space character decides the match.
+///
+/// With `whitespace_lookahead`, the whitespace alternative is instead
+/// `\s+(?!\S)|\s+` as in OpenAI's original pattern: a whitespace run followed by
+/// other text leaves its last character to start the next match.
+pub(crate) struct Gpt2Splits<'a> {
+    text: &'a str,
+    position: usize,
+    whitespace_lookahead: bool,
+}
+
+impl<'a> Gpt2Splits<'a> {
+    pub(crate) fn new(text: &'a str, whitespace_lookahead: bool) -> Self {
+        Gpt2Splits {
+            text,
+            position: 0,
+            whitespace_lookahead,
+        }
+    }
+}
+
+impl<'a> Iterator for Gpt2Splits<'a> {
+    type Item = (usize, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.text[self.position..];
+        if rest.is_empty() {
+            return None;
+        }
+
+        let len = match_len(rest, self.whitespace_lookahead);
+        let start = self.position;
+        self.position += len;
+
+        Some((start, &rest[..len]))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Letter,
+    Number,
+    Whitespace,
+    Other,
+}
+
+fn class(ch: char) -> Class {
+    if ch.is_ascii() {
+        return ASCII_CLASSES[ch as usize];
+    }
+    if ch.is_whitespace() {
+        return Class::Whitespace;
+    }
+
+    match ch.general_category_group() {
+        GeneralCategoryGroup::Letter => Class::Letter,
+        GeneralCategoryGroup::Number => Class::Number,
+        _ => Class::Other,
+    }
+}
+
+/// Classes of the ASCII characters, which make up most input and would otherwise
+/// go through a binary search of the Unicode category table.
+const ASCII_CLASSES: [Class; 128] = {
+    let mut classes = [Class::Other; 128];
+    let mut byte = 0;
+    while byte < 128 {
+        classes[byte] = match byte as u8 {
+            b'a'..=b'z' | b'A'..=b'Z' => Class::Letter,
+            b'0'..=b'9' => Class::Number,
+            b'\t' | b'\n' | 0x0B | 0x0C | b'\r' | b' ' => Class::Whitespace,
+            _ => Class::Other,
+        };
+        byte += 1;
+    }
+    classes
+};
+
+/// Returns the byte length of the run of `class` characters at the start of `text`.
+fn run_len(text: &str, class_of_run: Class) -> usize {
+    let bytes = text.as_bytes();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        if byte.is_ascii() {
+            if ASCII_CLASSES[byte as usize] != class_of_run {
+                return index;
+            }
+            index += 1;
+        } else {
+            let ch = text[index..].chars().next().unwrap();
+            if class(ch) != class_of_run {
+                return index;
+            }
+            index += ch.len_utf8();
+        }
+    }
+
+    index
+}
+
+/// Returns the byte length of the match starting at the beginning of `rest`,
+/// which must not be empty.
+fn match_len(rest: &str, whitespace_lookahead: bool) -> usize {
+    if let Some(after_quote) = rest.strip_prefix('\'')
+        && let Some(suffix) = CONTRACTIONS.iter().find(|c| after_quote.starts_with(*c))
+    {
+        return 1 + suffix.len();
+    }
+
+    let (space_len, word) = match rest.strip_prefix(' ') {
+        Some(word) => (1, word),
+        None => (0, rest),
+    };
+
+    match word.chars().next().map(class) {
+        Some(word_class @ (Class::Letter | Class::Number | Class::Other)) => {
+            space_len + run_len(word, word_class)
+        }
+        _ => {
+            let len = run_len(rest, Class::Whitespace);
+            match rest[..len].char_indices().next_back() {
+                // `\s+(?!\S)` backs off one character when text follows the run,
+                // and only `\s+` matches a single character.
+                Some((last, _)) if whitespace_lookahead && last > 0 && len < rest.len() => last,
+                _ => len,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(text: &str) -> Vec<&str> {
+        Gpt2Splits::new(text, false)
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+
+    fn split_lookahead(text: &str) -> Vec<&str> {
+        Gpt2Splits::new(text, true)
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+
+    #[test]
+    fn splits_words_numbers_and_punctuation() {
+        assert_eq!(
+            split("Hello, world! 123 ok?!"),
+            vec!["Hello", ",", " world", "!", " 123", " ok", "?!"]
+        );
+    }
+
+    #[test]
+    fn splits_contractions_in_pattern_order() {
+        assert_eq!(
+            split("it's we're I'd 'x"),
+            vec!["it", "'s", " we", "'re", " I", "'d", " '", "x"]
+        );
+    }
+
+    #[test]
+    fn whitespace_runs_are_greedy() {
+        assert_eq!(split("a  \n b"), vec!["a", "  \n ", "b"]);
+    }
+
+    #[test]
+    fn whitespace_lookahead_leaves_a_space_for_the_next_word() {
+        assert_eq!(split_lookahead("a  dog"), vec!["a", " ", " dog"]);
+        assert_eq!(split_lookahead("x   y"), vec!["x", "  ", " y"]);
+        assert_eq!(split_lookahead("a \n\nb"), vec!["a", " \n", "\n", "b"]);
+        assert_eq!(
+            split_lookahead("a \u{3000}b"),
+            vec!["a", " ", "\u{3000}", "b"]
+        );
+        assert_eq!(split_lookahead("end  "), vec!["end", "  "]);
+    }
+
+    #[test]
+    fn uses_unicode_categories() {
+        assert_eq!(split("日本語 ١٢ café"), vec!["日本語", " ١٢", " café"]);
+    }
+
+    #[test]
+    fn reports_byte_offsets() {
+        let starts: Vec<usize> = Gpt2Splits::new("é x", false)
+            .map(|(start, _)| start)
+            .collect();
+
+        assert_eq!(starts, vec![0, 2]);
+    }
+}
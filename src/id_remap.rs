@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+
+use crate::{TokenizerError, Vocabulary};
+
+/// A deterministic translation between two token ID spaces.
+///
+/// Every old ID maps to at most one new ID and vice versa. IDs without a counterpart,
+/// e.g. tokens dropped by pruning or added by an import, map to `None`. Remaps are
+/// produced by [`BpeTokenizer::rank_ids_by_frequency`](crate::BpeTokenizer::rank_ids_by_frequency)
+/// and, for any pair of vocabularies, by [`IdRemap::between`], and can be inverted and
+/// chained with [`compose`](IdRemap::compose) to translate stored token dumps across
+/// several tokenizer revisions.
+///
+/// With the `serde` feature enabled, an `IdRemap` serializes to an object with the
+/// `old_to_new` and `new_to_old` arrays, using `null` for unmapped IDs.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::BpeTokenizer;
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// let (ranked, remap) = tokenizer.rank_ids_by_frequency(&["zzz"]);
+///
+/// let old_ids = tokenizer.encode("zz");
+/// assert_eq!(remap.apply(&old_ids), ranked.encode("zz"));
+/// assert_eq!(remap.new_id(old_ids[0]), Some(0));
+/// assert_eq!(remap.inverse().apply(&ranked.encode("zz")), old_ids);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "IdRemapData"))]
+pub struct IdRemap {
+    old_to_new: Vec<Option<u32>>,
+    new_to_old: Vec<Option<u32>>,
+}
+
+/// Unvalidated mirror of [`IdRemap`] used as the deserialization target.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct IdRemapData {
+    old_to_new: Vec<Option<u32>>,
+    new_to_old: Vec<Option<u32>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<IdRemapData> for IdRemap {
+    type Error = String;
+
+    fn try_from(data: IdRemapData) -> Result<Self, Self::Error> {
+        let check = |ids: &[Option<u32>], bound: usize, name: &str| match ids
+            .iter()
+            .flatten()
+            .find(|&&id| id as usize >= bound)
+        {
+            Some(id) => Err(format!(
+                "{} maps to ID {} but the target has {} IDs",
+                name, id, bound
+            )),
+            None => Ok(()),
+        };
+        check(&data.old_to_new, data.new_to_old.len(), "old_to_new")?;
+        check(&data.new_to_old, data.old_to_new.len(), "new_to_old")?;
+
+        Ok(IdRemap {
+            old_to_new: data.old_to_new,
+            new_to_old: data.new_to_old,
+        })
+    }
+}
+
+/// Orders IDs by descending count, keeping the first `fixed` IDs in place.
+///
+/// Ties keep their original relative order, so the ranking is deterministic. Returns
+/// the old ID for every new ID.
+pub(crate) fn frequency_order(counts: &[usize], fixed: usize) -> Vec<u32> {
+    let mut new_to_old: Vec<u32> = (0..counts.len() as u32).collect();
+    new_to_old[fixed..].sort_by(|&a, &b| counts[b as usize].cmp(&counts[a as usize]));
+    new_to_old
+}
+
+/// Matches IDs listed in a tokenizer file against the IDs `vocabulary` assigns,
+/// returning the old ID for every new ID, or `None` if the IDs already agree.
+///
+/// Fails with a description of the problem unless `ids` gives every token of
+/// `vocabulary` a distinct ID below its length.
+pub(crate) fn file_order(
+    vocabulary: &Vocabulary,
+    ids: &HashMap<&str, u32>,
+) -> Result<Option<Vec<u32>>, String> {
+    if ids.len() != vocabulary.len() {
+        return Err(format!(
+            "the file lists {} tokens, but the merges and special tokens define {}",
+            ids.len(),
+            vocabulary.len()
+        ));
+    }
+
+    let mut new_to_old = vec![None; vocabulary.len()];
+    for (old_id, token) in vocabulary.iter() {
+        let new_id = *ids
+            .get(token)
+            .ok_or_else(|| format!("token {:?} has no ID", token))?;
+        let slot = new_to_old
+            .get_mut(new_id as usize)
+            .ok_or_else(|| format!("ID {} of token {:?} is out of range", new_id, token))?;
+        if slot.replace(old_id).is_some() {
+            return Err(format!("ID {} is assigned to more than one token", new_id));
+        }
+    }
+
+    let new_to_old: Vec<u32> = new_to_old.into_iter().map(Option::unwrap).collect();
+    let identity = new_to_old
+        .iter()
+        .enumerate()
+        .all(|(new_id, &old_id)| new_id as u32 == old_id);
+
+    Ok((!identity).then_some(new_to_old))
+}
+
+impl IdRemap {
+    /// Builds the remap for a permutation given as the old ID of every new ID.
+    pub(crate) fn from_permutation(new_to_old: &[u32]) -> Self {
+        let mut old_to_new = vec![None; new_to_old.len()];
+        for (new_id, &old_id) in new_to_old.iter().enumerate() {
+            old_to_new[old_id as usize] = Some(new_id as u32);
+        }
+
+        IdRemap {
+            old_to_new,
+            new_to_old: new_to_old.iter().copied().map(Some).collect(),
+        }
+    }
+
+    /// Builds the remap between two vocabularies by matching token strings.
+    ///
+    /// Works for any pair of revisions, e.g. a tokenizer and its
+    /// [`distill`](crate::BpeTokenizer::distill)ed or re-imported version. Tokens only
+    /// present on one side map to `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{IdRemap, Vocabulary};
+    ///
+    /// let old = Vocabulary::new(vec![], vec![("a".to_string(), "b".to_string())]);
+    /// let new = Vocabulary::new(vec!["<s>".to_string()], vec![]);
+    /// let remap = IdRemap::between(&old, &new);
+    ///
+    /// assert_eq!(remap.new_id(32), Some(33));
+    /// assert_eq!(remap.new_id(256), None);
+    /// assert_eq!(remap.old_id(0), None);
+    /// ```
+    pub fn between(old: &Vocabulary, new: &Vocabulary) -> Self {
+        let translate = |from: &Vocabulary, to: &Vocabulary| {
+            from.iter()
+                .map(|(_, token)| to.token_to_id(token))
+                .collect()
+        };
+
+        IdRemap {
+            old_to_new: translate(old, new),
+            new_to_old: translate(new, old),
+        }
+    }
+
+    /// Returns the new ID of `old_id`, or `None` if it is out of range or unmapped.
+    pub fn new_id(&self, old_id: u32) -> Option<u32> {
+        self.old_to_new.get(old_id as usize).copied().flatten()
+    }
+
+    /// Returns the old ID that became `new_id`, or `None` if it is out of range or unmapped.
+    pub fn old_id(&self, new_id: u32) -> Option<u32> {
+        self.new_to_old.get(new_id as usize).copied().flatten()
+    }
+
+    /// Returns the new ID of every old ID, indexed by old ID.
+    pub fn old_to_new(&self) -> &[Option<u32>] {
+        &self.old_to_new
+    }
+
+    /// Returns the old ID of every new ID, indexed by new ID.
+    ///
+    /// This is the row order to gather an old embedding matrix into the new one.
+    pub fn new_to_old(&self) -> &[Option<u32>] {
+        &self.new_to_old
+    }
+
+    /// Returns the size of the old ID space.
+    pub fn old_len(&self) -> usize {
+        self.old_to_new.len()
+    }
+
+    /// Returns the size of the new ID space.
+    pub fn new_len(&self) -> usize {
+        self.new_to_old.len()
+    }
+
+    /// Translates a sequence of old IDs to the new ID space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an ID is out of range or has no new counterpart. Use
+    /// [`try_apply`](IdRemap::try_apply) to handle such IDs.
+    pub fn apply(&self, old_ids: &[u32]) -> Vec<u32> {
+        self.try_apply(old_ids).unwrap_or_else(|err| {
+            panic!(
+                "{}. The IDs do not belong to this remap's old vocabulary!",
+                err
+            )
+        })
+    }
+
+    /// Translates a sequence of old IDs, failing on the first ID without a new counterpart.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::UnknownTokenId`] with the offending old ID.
+    pub fn try_apply(&self, old_ids: &[u32]) -> Result<Vec<u32>, TokenizerError> {
+        old_ids
+            .iter()
+            .map(|&id| self.new_id(id).ok_or(TokenizerError::UnknownTokenId(id)))
+            .collect()
+    }
+
+    /// Returns the remap in the opposite direction.
+    pub fn inverse(&self) -> IdRemap {
+        IdRemap {
+            old_to_new: self.new_to_old.clone(),
+            new_to_old: self.old_to_new.clone(),
+        }
+    }
+
+    /// Chains this remap with `then`, which must start from this remap's new ID space.
+    ///
+    /// An ID is mapped only if it survives both steps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `then` does not start from an ID space of this remap's new size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{IdRemap, Vocabulary};
+    ///
+    /// let v1 = Vocabulary::new(vec![], vec![]);
+    /// let v2 = Vocabulary::new(vec!["<s>".to_string()], vec![]);
+    /// let v3 = Vocabulary::new(vec!["<s>".to_string(), "</s>".to_string()], vec![]);
+    ///
+    /// let chained = IdRemap::between(&v1, &v2).compose(&IdRemap::between(&v2, &v3));
+    /// assert_eq!(chained, IdRemap::between(&v1, &v3));
+    /// ```
+    pub fn compose(&self, then: &IdRemap) -> IdRemap {
+        assert_eq!(
+            self.new_len(),
+            then.old_len(),
+            "cannot compose a remap into {} IDs with a remap from {} IDs",
+            self.new_len(),
+            then.old_len()
+        );
+
+        IdRemap {
+            old_to_new: self
+                .old_to_new
+                .iter()
+                .map(|id| id.and_then(|id| then.new_id(id)))
+                .collect(),
+            new_to_old: then
+                .new_to_old
+                .iter()
+                .map(|id| id.and_then(|id| self.old_id(id)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    #[test]
+    fn frequency_order_sorts_descending_and_keeps_fixed_prefix() {
+        assert_eq!(frequency_order(&[0, 1, 5, 9, 5], 1), vec![0, 3, 2, 4, 1]);
+        assert_eq!(frequency_order(&[2, 2, 2], 0), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn from_permutation_inverts_order() {
+        let remap = IdRemap::from_permutation(&[0, 3, 2, 4, 1]);
+
+        assert_eq!(
+            remap.old_to_new(),
+            &[Some(0), Some(4), Some(2), Some(1), Some(3)]
+        );
+        for old_id in 0..5 {
+            let new_id = remap.new_id(old_id).unwrap();
+            assert_eq!(remap.old_id(new_id), Some(old_id));
+        }
+        assert_eq!(remap.new_id(5), None);
+    }
+
+    #[test]
+    fn between_maps_pruned_and_added_tokens_to_none() {
+        let old = Vocabulary::new(vec![], vec![pair("a", "b"), pair("c", "d")]);
+        let new = Vocabulary::new(vec!["<s>".to_string()], vec![pair("c", "d")]);
+        let remap = IdRemap::between(&old, &new);
+
+        assert_eq!(remap.old_len(), 258);
+        assert_eq!(remap.new_len(), 258);
+        assert_eq!(remap.new_id(256), None);
+        assert_eq!(remap.new_id(257), Some(257));
+        assert_eq!(remap.old_id(0), None);
+        assert_eq!(
+            remap.try_apply(&[32, 256]),
+            Err(TokenizerError::UnknownTokenId(256))
+        );
+    }
+
+    #[test]
+    fn inverse_of_inverse_is_identity() {
+        let old = Vocabulary::new(vec![], vec![pair("a", "b")]);
+        let new = Vocabulary::new(vec!["<s>".to_string()], vec![]);
+        let remap = IdRemap::between(&old, &new);
+
+        assert_eq!(remap.inverse().inverse(), remap);
+        assert_eq!(remap.inverse().apply(&[33]), vec![32]);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot compose a remap into 257 IDs with a remap from 256 IDs")]
+    fn compose_rejects_mismatched_id_spaces() {
+        let v1 = Vocabulary::new(vec![], vec![]);
+        let v2 = Vocabulary::new(vec!["<s>".to_string()], vec![]);
+
+        IdRemap::between(&v1, &v2).compose(&IdRemap::between(&v1, &v2));
+    }
+
+    #[test]
+    #[should_panic(expected = "token ID 7 not in vocabulary")]
+    fn apply_panics_on_unmapped_id() {
+        IdRemap::from_permutation(&[1, 0]).apply(&[7]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_and_validates_targets() {
+        let old = Vocabulary::new(vec![], vec![pair("a", "b")]);
+        let new = Vocabulary::new(vec!["<s>".to_string()], vec![]);
+        let remap = IdRemap::between(&old, &new);
+
+        let json = serde_json::to_string(&remap).unwrap();
+        assert_eq!(serde_json::from_str::<IdRemap>(&json).unwrap(), remap);
+
+        let bad = r#"{"old_to_new":[5],"new_to_old":[null]}"#;
+        assert!(serde_json::from_str::<IdRemap>(bad).is_err());
+    }
+}
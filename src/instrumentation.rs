@@ -0,0 +1,125 @@
+//! Counters reported through the [`metrics`](https://docs.rs/metrics) facade.
+//!
+//! With the `metrics` feature enabled, the tokenizer records the following metrics
+//! into whatever recorder the application installed (for example a Prometheus
+//! exporter). Without the feature every function here compiles to nothing.
+//!
+//! | Name | Kind | Meaning |
+//! |------|------|---------|
+//! | `bpe_tokenizer_encode_calls_total` | counter | Successful encode calls |
+//! | `bpe_tokenizer_tokens_total` | counter | Tokens produced by those calls |
+//! | `bpe_tokenizer_batch_size` | histogram | Number of texts per batch call |
+//! | `bpe_tokenizer_word_cache_hits_total` | counter | Words found in an encoder's word cache |
+//! | `bpe_tokenizer_word_cache_misses_total` | counter | Words merged because they were not cached |
+
+#[cfg(feature = "metrics")]
+pub(crate) const ENCODE_CALLS: &str = "bpe_tokenizer_encode_calls_total";
+#[cfg(feature = "metrics")]
+pub(crate) const TOKENS: &str = "bpe_tokenizer_tokens_total";
+#[cfg(feature = "metrics")]
+pub(crate) const BATCH_SIZE: &str = "bpe_tokenizer_batch_size";
+#[cfg(feature = "metrics")]
+pub(crate) const WORD_CACHE_HITS: &str = "bpe_tokenizer_word_cache_hits_total";
+#[cfg(feature = "metrics")]
+pub(crate) const WORD_CACHE_MISSES: &str = "bpe_tokenizer_word_cache_misses_total";
+
+/// Records one successful encode call that produced `tokens` tokens.
+#[cfg(feature = "metrics")]
+pub(crate) fn record_encode(tokens: usize) {
+    ::metrics::counter!(ENCODE_CALLS).increment(1);
+    ::metrics::counter!(TOKENS).increment(tokens as u64);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn record_encode(_tokens: usize) {}
+
+/// Records the number of texts or sequences passed to one batch call.
+#[cfg(feature = "metrics")]
+pub(crate) fn record_batch(size: usize) {
+    ::metrics::histogram!(BATCH_SIZE).record(size as f64);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn record_batch(_size: usize) {}
+
+/// Records one word cache lookup.
+#[cfg(feature = "metrics")]
+pub(crate) fn record_word_cache(hit: bool) {
+    let name = if hit {
+        WORD_CACHE_HITS
+    } else {
+        WORD_CACHE_MISSES
+    };
+    ::metrics::counter!(name).increment(1);
+}
+
+#[cfg(not(feature = "metrics"))]
+#[inline(always)]
+pub(crate) fn record_word_cache(_hit: bool) {}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::BpeTokenizer;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    fn snapshot(run: impl FnOnce()) -> Vec<(String, DebugValue)> {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        ::metrics::with_local_recorder(&recorder, run);
+
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| (key.key().name().to_string(), value))
+            .collect()
+    }
+
+    fn counter(values: &[(String, DebugValue)], name: &str) -> Option<u64> {
+        values.iter().find_map(|(key, value)| match value {
+            DebugValue::Counter(count) if key == name => Some(*count),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn encode_calls_and_tokens_are_counted() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let values = snapshot(|| {
+            tokenizer.encode("abc");
+            tokenizer.encode_with_offsets("de");
+        });
+
+        assert_eq!(counter(&values, ENCODE_CALLS), Some(2));
+        assert_eq!(counter(&values, TOKENS), Some(5));
+    }
+
+    #[test]
+    fn batch_sizes_are_recorded() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let values = snapshot(|| {
+            tokenizer.encode_batch(&["a", "b", "c"]);
+        });
+
+        let sizes = values.iter().find_map(|(key, value)| match value {
+            DebugValue::Histogram(sizes) if key == BATCH_SIZE => Some(sizes.clone()),
+            _ => None,
+        });
+        assert_eq!(sizes.map(|sizes| sizes.len()), Some(1));
+        assert_eq!(counter(&values, ENCODE_CALLS), Some(3));
+    }
+
+    #[test]
+    fn word_cache_lookups_are_counted() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]).with_word_cache(16);
+        let values = snapshot(|| {
+            tokenizer.encode("the the the");
+        });
+
+        assert_eq!(counter(&values, WORD_CACHE_MISSES), Some(2));
+        assert_eq!(counter(&values, WORD_CACHE_HITS), Some(1));
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::Trainer;
+
+/// Word tables per label and the merges each label has dominated so far, for
+/// [`Trainer::train_labeled`](crate::Trainer::train_labeled).
+///
+/// A merge is dominated by the label whose texts contribute most of its pair's
+/// count, ties going to the label that sorts first.
+pub(crate) struct LabelBudget {
+    max_share: f64,
+    word_freqs: Vec<HashMap<Vec<String>, usize>>,
+    /// Merges dominated by each label.
+    dominated: Vec<usize>,
+    /// Dominant label of every merge so far.
+    dominated_by: Vec<usize>,
+}
+
+impl LabelBudget {
+    /// Creates a budget over one word table per label, in label order.
+    pub(crate) fn new(max_share: f64, word_freqs: Vec<HashMap<Vec<String>, usize>>) -> Self {
+        Self {
+            max_share,
+            dominated: vec![0; word_freqs.len()],
+            dominated_by: Vec::new(),
+            word_freqs,
+        }
+    }
+
+    /// Keeps the pairs whose dominant label may take merge number
+    /// `merges_learned + 1` without exceeding its share.
+    pub(crate) fn eligible(
+        &self,
+        pair_freqs: HashMap<(String, String), usize>,
+        merges_learned: usize,
+    ) -> HashMap<(String, String), usize> {
+        let per_label: Vec<_> = self
+            .word_freqs
+            .iter()
+            .map(Trainer::compute_pair_frequencies)
+            .collect();
+
+        pair_freqs
+            .into_iter()
+            .filter(|(pair, _)| {
+                let counts = per_label
+                    .iter()
+                    .map(|pair_freqs| pair_freqs.get(pair).copied().unwrap_or(0));
+                self.dominated[Self::dominant(counts)] < self.allowed(merges_learned + 1)
+            })
+            .collect()
+    }
+
+    /// Records `pair` as the next merge and applies it to every label's words.
+    pub(crate) fn merge(&mut self, pair: &(String, String)) {
+        let counts = self
+            .word_freqs
+            .iter()
+            .map(|word_freqs| Self::pair_count(word_freqs, pair));
+        let label = Self::dominant(counts);
+        self.dominated[label] += 1;
+        self.dominated_by.push(label);
+
+        for word_freqs in &mut self.word_freqs {
+            *word_freqs = Trainer::apply_merge(word_freqs, pair);
+        }
+    }
+
+    /// Returns the index of the label that dominated each merge, in merge order.
+    pub(crate) fn dominated_by(&self) -> &[usize] {
+        &self.dominated_by
+    }
+
+    /// Merges any one label may dominate out of `merges`; always at least one, so
+    /// a single label can start training.
+    fn allowed(&self, merges: usize) -> usize {
+        ((self.max_share * merges as f64).ceil() as usize).max(1)
+    }
+
+    /// Index of the label with the highest count, the first one on ties.
+    fn dominant(counts: impl Iterator<Item = usize>) -> usize {
+        counts
+            .enumerate()
+            .max_by(|(index_a, count_a), (index_b, count_b)| {
+                count_a.cmp(count_b).then(index_b.cmp(index_a))
+            })
+            .map_or(0, |(index, _)| index)
+    }
+
+    fn pair_count(word_freqs: &HashMap<Vec<String>, usize>, pair: &(String, String)) -> usize {
+        word_freqs
+            .iter()
+            .map(|(symbols, count)| {
+                let occurrences = symbols
+                    .windows(2)
+                    .filter(|window| window[0] == pair.0 && window[1] == pair.1)
+                    .count();
+                occurrences * count
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    fn words(entries: &[(&[&str], usize)]) -> HashMap<Vec<String>, usize> {
+        entries
+            .iter()
+            .map(|(symbols, count)| (symbols.iter().map(|s| s.to_string()).collect(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn labels_at_their_budget_lose_their_pairs() {
+        let english = words(&[(&["t", "h", "e"], 10)]);
+        let german = words(&[(&["d", "e", "r"], 3), (&["t", "h"], 1)]);
+        let mut budget = LabelBudget::new(0.5, vec![english.clone(), german.clone()]);
+        let mut all = english;
+        all.extend(german);
+        let pairs = || Trainer::compute_pair_frequencies(&all);
+
+        assert_eq!(budget.eligible(pairs(), 0).len(), 4);
+        budget.merge(&pair("t", "h"));
+        assert_eq!(budget.dominated_by(), &[0]);
+
+        let eligible = budget.eligible(pairs(), 1);
+        assert!(!eligible.contains_key(&pair("h", "e")));
+        assert!(eligible.contains_key(&pair("d", "e")));
+        assert_eq!(budget.allowed(3), 2);
+    }
+
+    #[test]
+    fn ties_go_to_the_first_label() {
+        assert_eq!(LabelBudget::dominant([2, 3, 3].into_iter()), 1);
+        assert_eq!(LabelBudget::dominant([0, 0].into_iter()), 0);
+        assert_eq!(
+            LabelBudget::pair_count(&words(&[(&["a", "b", "a", "b"], 2)]), &pair("a", "b")),
+            4
+        );
+    }
+}
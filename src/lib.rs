@@ -1,15 +1,126 @@
+mod analysis;
+mod bulk;
 mod byte_encoder;
+#[cfg(feature = "compat-fuzz")]
+mod compat_fuzz;
+mod config;
+mod corpus_hash;
+mod corpus_reader;
+mod count_min;
+#[cfg(feature = "datasets")]
+mod datasets;
 mod decoder;
+mod diagnostics;
+mod distill;
+mod embedding_init;
 mod encoder;
+mod encoding;
+mod error;
+mod gguf;
+mod golden;
+mod gpt2_split;
+mod id_remap;
+mod instrumentation;
+mod label_budget;
+mod longest_match;
+pub mod low_level;
+#[cfg(all(feature = "bincode", feature = "mmap"))]
+mod mapped_merges;
+#[cfg(feature = "mmap")]
+mod mapped_vocabulary;
+mod merge_graph;
+mod merges_file;
+mod metadata;
+mod minimize;
+mod normalizer;
 mod pre_tokenizer;
+mod prompt;
+mod registry;
+mod retokenize;
+mod roundtrip;
+mod segmentation;
+mod shard;
+mod shuffle;
+mod strictness;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "json")]
+mod test_vectors;
+mod tiktoken;
+mod token_reader;
+mod token_writer;
 pub mod tokenizer;
+#[cfg(feature = "json")]
+mod tokenizer_json;
+mod train_progress;
+mod train_report;
 mod trainer;
 mod vocabulary;
+#[cfg(feature = "bincode")]
+mod wire;
+mod word_cache;
 
+pub use analysis::{
+    CandidateImpact, DocumentStats, ExtraTokenSimulation, LanguageCoverage, LengthBucket,
+    MergeUsage, SliceDrift, TokenizerStats,
+};
+pub use bulk::{BulkTokenizer, MANIFEST_FILE, Manifest, ShardEntry, ShardStatus};
 pub use byte_encoder::{bytes_to_unicode, unicode_to_bytes};
-pub use decoder::Decoder;
-pub use encoder::Encoder;
-pub use pre_tokenizer::PreTokenizer;
+#[cfg(feature = "compat-fuzz")]
+pub use compat_fuzz::{CompatFuzzer, Divergence};
+pub use config::{ConfigError, TokenizerConfig};
+pub use corpus_hash::CorpusHasher;
+pub use corpus_reader::{CorpusSource, read_corpus};
+pub use count_min::{SketchAccuracy, SketchCounting};
+#[cfg(feature = "datasets")]
+pub use datasets::{Dataset, DatasetError};
+pub use decoder::{DecodeOptions, Decoder, UnknownIdMode};
+pub use diagnostics::{ByteCoverage, Warning};
+pub use embedding_init::{EmbeddingPlan, InitStrategy, RowInit};
+pub use encoder::{EncodeOptions, Encoder};
+pub use encoding::{DisplayUnit, Encoding, Field, FieldSpan, SpecialTokenMatch};
+pub use error::{
+    DecodeError, EncodeError, InvalidIds, MissingToken, TokenizerError, VocabularyConflict,
+    VocabularyError, VocabularyMismatch,
+};
+pub use gguf::GgufError;
+pub use golden::{IdChange, TokenizerDiff};
+pub use id_remap::IdRemap;
+pub use longest_match::EncodeStrategy;
+#[cfg(feature = "mmap")]
+pub use mapped_vocabulary::MAPPED_VOCABULARY_MAGIC;
+pub use merge_graph::{MergeGraph, Operand};
+pub use merges_file::{
+    MERGES_VERSION_HEADER, MergeLint, MergeLintKind, MergesError, ParseMode, format_merges,
+    lint_merges, parse_merges, parse_merges_with_mode, read_merges_file,
+    read_merges_file_with_mode, write_merges_file,
+};
+pub use metadata::Metadata;
+pub use minimize::{minimize_failure, minimize_roundtrip};
+pub use normalizer::{ControlChars, NewlineMode, NormalizationStats, Normalizer};
+pub use pre_tokenizer::{PreTokenizer, escape_pattern};
+pub use prompt::{Prompt, PromptBuilder, Segment, SegmentFit, Truncation};
+pub use registry::TokenizerRegistry;
+pub use retokenize::TokenEdit;
+pub use roundtrip::{Mismatch, verify_roundtrip};
+pub use segmentation::Segmentation;
+pub use shard::{SHARD_INDEX_MAGIC, ShardIndex, ShardReader, ShardWriter, shard_index_path};
+pub use shuffle::{DocumentRef, Shuffler};
+pub use strictness::{Strictness, set_strictness, strictness};
+pub use tiktoken::TiktokenError;
+pub use token_reader::{PackedSequence, TokenReader};
+pub use token_writer::{FinalSequence, TokenWriter};
 pub use tokenizer::BpeTokenizer;
-pub use trainer::Trainer;
-pub use vocabulary::Vocabulary;
+#[cfg(feature = "json")]
+pub use tokenizer_json::TokenizerJsonError;
+pub use train_progress::{MemoryUsage, TrainProgress};
+pub use train_report::{PairFrequency, ReproducibilityError, TrainConfig, TrainReport};
+pub use trainer::{MergeCriterion, StopRule, TieBreak, Trainer};
+pub use vocabulary::{TokenQuery, Vocabulary};
+#[cfg(feature = "bincode")]
+pub use wire::{
+    BINARY_FORMAT_MAGIC, BINARY_FORMAT_VERSION, WireError, encodings_from_bytes, encodings_to_bytes,
+};
+#[cfg(all(feature = "bincode", feature = "mmap"))]
+pub use wire::{MAPPED_FORMAT_VERSION, MAPPED_TOKENIZER_MAGIC};
+pub use word_cache::WordCacheStats;
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::Vocabulary;
+
+/// How an [`Encoder`](crate::Encoder) segments each pre-tokenized word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EncodeStrategy {
+    /// Apply merge rules in rank order, as in GPT-2 and Hugging Face BPE.
+    #[default]
+    MergeRank,
+    /// Take the longest vocabulary token that matches at each position, left to right.
+    ///
+    /// Merge ranks are ignored. Some deployed tokenizers use these semantics (e.g.
+    /// WordPiece-style greedy matching over a BPE vocabulary), and this reproduces
+    /// them for parity testing. Every byte is a base token, so the match never fails.
+    LongestMatch,
+}
+
+/// A character trie over the byte-level spelling of vocabulary tokens.
+pub(crate) struct TokenTrie {
+    nodes: Vec<TrieNode>,
+}
+
+#[derive(Default)]
+struct TrieNode {
+    children: HashMap<char, usize>,
+    terminal: bool,
+}
+
+impl TokenTrie {
+    /// Builds a trie of every non-special token in `vocabulary`.
+    ///
+    /// Special tokens occupy the first `special_count` IDs and are left out, so their
+    /// literal text is never matched inside ordinary words.
+    pub(crate) fn new(vocabulary: &Vocabulary, special_count: usize) -> Self {
+        let mut trie = TokenTrie {
+            nodes: vec![TrieNode::default()],
+        };
+
+        for (_, token) in vocabulary.iter().skip(special_count) {
+            let mut node = 0;
+            for ch in token.chars() {
+                node = match trie.nodes[node].children.get(&ch) {
+                    Some(&child) => child,
+                    None => {
+                        trie.nodes.push(TrieNode::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.insert(ch, child);
+                        child
+                    }
+                };
+            }
+            trie.nodes[node].terminal = true;
+        }
+
+        trie
+    }
+
+    /// Splits a byte-level word into the longest matching tokens, left to right.
+    pub(crate) fn segment(&self, word: &str) -> Vec<String> {
+        let mut tokens = Vec::new();
+        let mut rest = word;
+
+        while !rest.is_empty() {
+            let mut node = 0;
+            let mut longest = rest.chars().next().map_or(0, char::len_utf8);
+
+            for (offset, ch) in rest.char_indices() {
+                match self.nodes[node].children.get(&ch) {
+                    Some(&child) => node = child,
+                    None => break,
+                }
+                if self.nodes[node].terminal {
+                    longest = offset + ch.len_utf8();
+                }
+            }
+
+            tokens.push(rest[..longest].to_string());
+            rest = &rest[longest..];
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    #[test]
+    fn segment_prefers_longest_token() {
+        let vocab = Vocabulary::new(
+            vec![],
+            vec![pair("a", "b"), pair("ab", "c"), pair("c", "d")],
+        );
+        let trie = TokenTrie::new(&vocab, 0);
+
+        assert_eq!(trie.segment("abcd"), vec!["abc", "d"]);
+        assert_eq!(trie.segment("abd"), vec!["ab", "d"]);
+    }
+
+    #[test]
+    fn segment_backs_off_to_last_complete_token() {
+        let vocab = Vocabulary::new(
+            vec![],
+            vec![pair("a", "b"), pair("ab", "c"), pair("abc", "d")],
+        );
+        let trie = TokenTrie::new(&vocab, 0);
+
+        assert_eq!(trie.segment("abce"), vec!["abc", "e"]);
+    }
+
+    #[test]
+    fn segment_ignores_special_tokens() {
+        let vocab = Vocabulary::new(vec!["ab".to_string()], vec![]);
+        let trie = TokenTrie::new(&vocab, 1);
+
+        assert_eq!(trie.segment("ab"), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,453 @@
+//! Low-level building blocks of the encoder, for studying how single words are
+//! segmented.
+//!
+//! Everything here works on token strings in GPT-2's byte-level alphabet (see
+//! [`bytes_to_unicode`]) and never touches a vocabulary or
+//! token IDs. Words are taken as they are: no normalization, pre-tokenization or
+//! special token matching is applied, so pass single pre-tokenized chunks to get the
+//! same pieces [`BpeTokenizer`](crate::BpeTokenizer) would produce.
+//!
+//! [`SymbolMode::EndOfWord`] gives the symbols of the original word-level BPE
+//! (Sennrich et al.), where the last character carries a `</w>` marker, for comparing
+//! merge tables learned that way; [`MergeTable::merge_symbols`] applies them.
+//!
+//! # Examples
+//!
+//! ```
+//! use bpe_tokenizer_rs::low_level::MergeTable;
+//!
+//! let merges = vec![
+//!     ("l".to_string(), "o".to_string()),
+//!     ("lo".to_string(), "w".to_string()),
+//!     ("e".to_string(), "r".to_string()),
+//! ];
+//! let table = MergeTable::new(merges);
+//!
+//! assert_eq!(table.apply_merges("lower"), vec!["low", "er"]);
+//! assert_eq!(table.apply_merges("slow"), vec!["s", "low"]);
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(all(feature = "bincode", feature = "mmap"))]
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use crate::bytes_to_unicode;
+#[cfg(all(feature = "bincode", feature = "mmap"))]
+use crate::mapped_merges::MappedMerges;
+
+/// Converts `word` to its byte-level symbols, one per UTF-8 byte.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::low_level::word_to_symbols;
+///
+/// assert_eq!(word_to_symbols(" hi"), vec!["Ġ", "h", "i"]);
+/// assert_eq!(word_to_symbols("é"), vec!["Ã", "©"]);
+/// ```
+pub fn word_to_symbols(word: &str) -> Vec<String> {
+    static BYTE_TO_CHAR: OnceLock<Vec<char>> = OnceLock::new();
+    let byte_to_char = BYTE_TO_CHAR.get_or_init(|| bytes_to_unicode().into_values().collect());
+
+    word.bytes()
+        .map(|byte| byte_to_char[byte as usize].to_string())
+        .collect()
+}
+
+/// Marker that [`SymbolMode::EndOfWord`] appends to the last symbol of a word.
+pub const END_OF_WORD: &str = "</w>";
+
+/// How a word is cut into the symbols that merges start from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SymbolMode {
+    /// One byte-level character per UTF-8 byte, as this crate's tokenizers use.
+    #[default]
+    ByteLevel,
+    /// One symbol per character, with [`END_OF_WORD`] appended to the last one, so
+    /// merges can tell word-final pieces from word-internal ones.
+    EndOfWord,
+}
+
+impl SymbolMode {
+    /// Cuts `word` into symbols.
+    ///
+    /// # Errors
+    ///
+    /// Byte-level conversion accepts any text. End-of-word conversion fails for an
+    /// empty word, a word containing whitespace (the boundary the marker stands for)
+    /// or a word already containing [`END_OF_WORD`], since joined symbols could not
+    /// be split back into words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::low_level::{MergeTable, SymbolError, SymbolMode};
+    ///
+    /// let symbols = SymbolMode::EndOfWord.symbols("low").unwrap();
+    /// assert_eq!(symbols, vec!["l", "o", "w</w>"]);
+    ///
+    /// let table = MergeTable::new(vec![
+    ///     ("l".to_string(), "o".to_string()),
+    ///     ("lo".to_string(), "w</w>".to_string()),
+    /// ]);
+    /// assert_eq!(table.merge_symbols(symbols), vec!["low</w>"]);
+    ///
+    /// assert_eq!(SymbolMode::EndOfWord.symbols(""), Err(SymbolError::EmptyWord));
+    /// assert_eq!(SymbolMode::ByteLevel.symbols(" a").unwrap(), vec!["Ġ", "a"]);
+    /// ```
+    pub fn symbols(self, word: &str) -> Result<Vec<String>, SymbolError> {
+        match self {
+            SymbolMode::ByteLevel => Ok(word_to_symbols(word)),
+            SymbolMode::EndOfWord => {
+                if word.is_empty() {
+                    return Err(SymbolError::EmptyWord);
+                }
+                if word.chars().any(char::is_whitespace) {
+                    return Err(SymbolError::Whitespace(word.to_string()));
+                }
+                if word.contains(END_OF_WORD) {
+                    return Err(SymbolError::ContainsMarker(word.to_string()));
+                }
+
+                let mut symbols: Vec<String> = word.chars().map(String::from).collect();
+                if let Some(last) = symbols.last_mut() {
+                    last.push_str(END_OF_WORD);
+                }
+                Ok(symbols)
+            }
+        }
+    }
+}
+
+/// Error returned when a word cannot be cut into [`SymbolMode::EndOfWord`] symbols.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolError {
+    /// The word is empty, so no symbol can carry the end-of-word marker.
+    EmptyWord,
+    /// The word contains whitespace.
+    Whitespace(String),
+    /// The word already contains [`END_OF_WORD`].
+    ContainsMarker(String),
+}
+
+impl fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SymbolError::EmptyWord => write!(f, "cannot mark the end of an empty word"),
+            SymbolError::Whitespace(word) => {
+                write!(f, "word {:?} contains whitespace", word)
+            }
+            SymbolError::ContainsMarker(word) => {
+                write!(f, "word {:?} contains the marker {}", word, END_OF_WORD)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SymbolError {}
+
+/// Splits `word` into byte-level symbols and applies `merges` in rank order.
+///
+/// Builds a [`MergeTable`] on every call; build one yourself to segment many words.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::low_level::apply_merges;
+///
+/// let merges = [("Ġ".to_string(), "h".to_string()), ("Ġh".to_string(), "i".to_string())];
+///
+/// assert_eq!(apply_merges(&merges, " hi!"), vec!["Ġhi", "!"]);
+/// ```
+pub fn apply_merges(merges: &[(String, String)], word: &str) -> Vec<String> {
+    MergeTable::new(merges.to_vec()).apply_merges(word)
+}
+
+/// Merge rules indexed by rank, the core of BPE encoding.
+///
+/// At each step the adjacent pair with the lowest rank (earliest rule) is found and
+/// every non-overlapping occurrence of it is merged, scanning left to right, until
+/// no adjacent pair has a rule. If a pair is listed more than once, its first rank
+/// counts. This is exactly what [`Encoder`](crate::Encoder) does for each word under
+/// the default [`EncodeStrategy::MergeRank`](crate::EncodeStrategy::MergeRank).
+#[derive(Clone, Default)]
+pub struct MergeTable {
+    storage: Storage,
+}
+
+#[derive(Clone)]
+enum Storage {
+    Owned {
+        rules: Vec<(String, String)>,
+        ranks: HashMap<String, HashMap<String, usize>>,
+    },
+    /// Rules read in place from a file mapping shared by every clone.
+    #[cfg(all(feature = "bincode", feature = "mmap"))]
+    Mapped(Arc<MappedMerges>),
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Storage::Owned {
+            rules: Vec::new(),
+            ranks: HashMap::new(),
+        }
+    }
+}
+
+impl fmt::Debug for MergeTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MergeTable")
+            .field("rules", &self.rules())
+            .finish()
+    }
+}
+
+impl PartialEq for MergeTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && (0..self.len()).all(|rank| self.rule(rank) == other.rule(rank))
+    }
+}
+
+impl Eq for MergeTable {}
+
+impl MergeTable {
+    /// Creates a table from merge rules in priority order, as returned by
+    /// [`Trainer::train`](crate::Trainer::train) or
+    /// [`BpeTokenizer::merges`](crate::BpeTokenizer::merges).
+    pub fn new(rules: Vec<(String, String)>) -> Self {
+        let mut ranks: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        for (rank, (first, second)) in rules.iter().enumerate() {
+            let seconds = match ranks.get_mut(first) {
+                Some(seconds) => seconds,
+                None => ranks.entry(first.clone()).or_default(),
+            };
+            if !seconds.contains_key(second) {
+                seconds.insert(second.clone(), rank);
+            }
+        }
+
+        MergeTable {
+            storage: Storage::Owned { rules, ranks },
+        }
+    }
+
+    /// Creates a table that reads its rules from `merges`.
+    #[cfg(all(feature = "bincode", feature = "mmap"))]
+    pub(crate) fn from_mapped(merges: MappedMerges) -> Self {
+        MergeTable {
+            storage: Storage::Mapped(Arc::new(merges)),
+        }
+    }
+
+    /// Returns the rules in priority order.
+    ///
+    /// A table read from a file mapping copies its rules into `String`s the first
+    /// time this is called.
+    pub fn rules(&self) -> &[(String, String)] {
+        match &self.storage {
+            Storage::Owned { rules, .. } => rules,
+            #[cfg(all(feature = "bincode", feature = "mmap"))]
+            Storage::Mapped(merges) => merges.rules(),
+        }
+    }
+
+    /// Returns the number of rules.
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Owned { rules, .. } => rules.len(),
+            #[cfg(all(feature = "bincode", feature = "mmap"))]
+            Storage::Mapped(merges) => merges.len(),
+        }
+    }
+
+    /// Returns `true` if the table has no rules.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the rank of the rule merging `first` and `second`, if there is one.
+    ///
+    /// Lower ranks are applied first.
+    pub fn rank(&self, first: &str, second: &str) -> Option<usize> {
+        match &self.storage {
+            Storage::Owned { ranks, .. } => ranks.get(first)?.get(second).copied(),
+            #[cfg(all(feature = "bincode", feature = "mmap"))]
+            Storage::Mapped(merges) => merges.rank(first, second),
+        }
+    }
+
+    /// Calls `visit` with the UTF-8 bytes of the halves of every rule, in rank
+    /// order, whose halves are both at most `max_len` bytes long.
+    pub(crate) fn for_each_short_rule(&self, max_len: usize, mut visit: impl FnMut(&[u8], &[u8])) {
+        match &self.storage {
+            Storage::Owned { rules, .. } => {
+                for (first, second) in rules {
+                    if first.len() <= max_len && second.len() <= max_len {
+                        visit(first.as_bytes(), second.as_bytes());
+                    }
+                }
+            }
+            #[cfg(all(feature = "bincode", feature = "mmap"))]
+            Storage::Mapped(merges) => merges.for_each_short_rule(max_len, visit),
+        }
+    }
+
+    /// Returns the halves of the rule with `rank`, which must be below the length.
+    pub(crate) fn rule(&self, rank: usize) -> (&str, &str) {
+        match &self.storage {
+            Storage::Owned { rules, .. } => (&rules[rank].0, &rules[rank].1),
+            #[cfg(all(feature = "bincode", feature = "mmap"))]
+            Storage::Mapped(merges) => merges.rule(rank),
+        }
+    }
+
+    /// Splits `word` into byte-level symbols and merges them.
+    pub fn apply_merges(&self, word: &str) -> Vec<String> {
+        self.merge_symbols(word_to_symbols(word))
+    }
+
+    /// Merges symbols that are already in byte-level form, for example the
+    /// characters of a vocabulary entry.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::low_level::MergeTable;
+    ///
+    /// let table = MergeTable::new(vec![("a".to_string(), "a".to_string())]);
+    /// let symbols = vec!["a".to_string(); 5];
+    ///
+    /// assert_eq!(table.merge_symbols(symbols), vec!["aa", "aa", "a"]);
+    /// ```
+    pub fn merge_symbols(&self, symbols: Vec<String>) -> Vec<String> {
+        self.merge_symbols_observed(symbols, &mut |_, _| {})
+    }
+
+    /// Merges `symbols`, calling `on_merge(rank, count)` for each applied rule with
+    /// the number of positions it merged.
+    pub(crate) fn merge_symbols_observed(
+        &self,
+        mut symbols: Vec<String>,
+        on_merge: &mut impl FnMut(usize, usize),
+    ) -> Vec<String> {
+        while let Some(rank) = self.best_rank(&symbols) {
+            let (first, second) = self.rule(rank);
+            let merged = format!("{}{}", first, second);
+            let mut new_symbols = Vec::with_capacity(symbols.len());
+            let mut count = 0;
+            let mut i = 0;
+
+            while i < symbols.len() {
+                if i + 1 < symbols.len() && symbols[i] == first && symbols[i + 1] == second {
+                    new_symbols.push(merged.clone());
+                    count += 1;
+                    i += 2;
+                } else {
+                    new_symbols.push(std::mem::take(&mut symbols[i]));
+                    i += 1;
+                }
+            }
+
+            on_merge(rank, count);
+            symbols = new_symbols;
+        }
+
+        symbols
+    }
+
+    fn best_rank(&self, symbols: &[String]) -> Option<usize> {
+        symbols
+            .windows(2)
+            .filter_map(|pair| self.rank(&pair[0], &pair[1]))
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+    use crate::{BpeTokenizer, PreTokenizer, Trainer};
+
+    #[test]
+    fn applies_rules_by_rank_not_position() {
+        let table = MergeTable::new(vec![pair("b", "c"), pair("a", "b")]);
+
+        assert_eq!(table.apply_merges("abc"), vec!["a", "bc"]);
+    }
+
+    #[test]
+    fn first_rank_of_a_duplicate_rule_wins() {
+        let table = MergeTable::new(vec![pair("a", "b"), pair("c", "d"), pair("a", "b")]);
+
+        assert_eq!(table.rank("a", "b"), Some(0));
+        assert_eq!(table.rank("b", "a"), None);
+        assert_eq!(table.len(), 3);
+    }
+
+    #[test]
+    fn empty_table_leaves_byte_symbols() {
+        let table = MergeTable::default();
+
+        assert!(table.is_empty());
+        assert_eq!(table.apply_merges("a\n"), vec!["a", "Ċ"]);
+        assert!(table.apply_merges("").is_empty());
+    }
+
+    #[test]
+    fn end_of_word_symbols_mark_the_last_character() {
+        assert_eq!(SymbolMode::EndOfWord.symbols("é").unwrap(), vec!["é</w>"]);
+        assert_eq!(
+            SymbolMode::EndOfWord.symbols("a b"),
+            Err(SymbolError::Whitespace("a b".to_string()))
+        );
+        assert_eq!(
+            SymbolMode::EndOfWord.symbols("x</w>"),
+            Err(SymbolError::ContainsMarker("x</w>".to_string()))
+        );
+        assert_eq!(
+            SymbolMode::ByteLevel.symbols("é").unwrap(),
+            word_to_symbols("é")
+        );
+    }
+
+    #[test]
+    fn reports_merge_counts() {
+        let table = MergeTable::new(vec![pair("a", "a"), pair("aa", "aa")]);
+        let mut merges = Vec::new();
+
+        let symbols = table.merge_symbols_observed(word_to_symbols("aaaaa"), &mut |rank, count| {
+            merges.push((rank, count))
+        });
+
+        assert_eq!(symbols, vec!["aaaa", "a"]);
+        assert_eq!(merges, vec![(0, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn matches_tokenizer_segmentation() {
+        let corpus = [
+            "the lower lowest newer newest",
+            "wider widest slower slowest",
+            "naïve café über straße",
+        ];
+        let merges = Trainer::new(40).train(&corpus);
+        let tokenizer = BpeTokenizer::new(merges.clone(), vec![]);
+        let table = MergeTable::new(merges);
+
+        for text in corpus.iter().chain(&["flower power", "cafés"]) {
+            for word in PreTokenizer::new().pre_tokenize(text) {
+                let expected = tokenizer.encode_with_offsets(&word);
+                assert_eq!(
+                    table.apply_merges(&word),
+                    expected.tokens(),
+                    "word {:?}",
+                    word
+                );
+            }
+        }
+    }
+}
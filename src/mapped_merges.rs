@@ -0,0 +1,375 @@
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+
+use memmap2::Mmap;
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+
+use crate::mapped_vocabulary::{invalid, read_u32, read_u64, validate_strings};
+
+/// Marks an empty slot of the rank table.
+const EMPTY_SLOT: u32 = u32::MAX;
+
+/// Merge rules read in place from a file mapping, one section of a file written by
+/// `BpeTokenizer::save_mapped`.
+///
+/// The layout is the rule count `n` as a little-endian `u64`, `2n + 1`
+/// little-endian `u64` byte offsets of the rule halves within the string area, the
+/// slot count of the rank table as a little-endian `u64`, the rank table, then the
+/// string area: the first and second half of every rule in rank order.
+///
+/// The rank table is an open-addressing hash table of little-endian `u32` ranks,
+/// `u32::MAX` for empty slots, probed linearly from the XXH3-64 hash of the two
+/// halves joined, seeded with the length of the first. Its slot count is a power
+/// of two above `n`, and only the first rank of a repeated rule is in it. Mapping
+/// checks every slot and probes for every rule, so a damaged table is rejected
+/// instead of giving wrong ranks.
+pub(crate) struct MappedMerges {
+    map: Arc<Mmap>,
+    /// Where the offsets start in the mapping.
+    offsets: usize,
+    count: usize,
+    /// Where the rank table starts in the mapping.
+    table: usize,
+    slots: usize,
+    strings: Range<usize>,
+    /// The rules as `String`s, built the first time they are asked for.
+    rules: OnceLock<Vec<(String, String)>>,
+}
+
+impl MappedMerges {
+    /// Writes `rules`, given in rank order, in the mapped layout.
+    pub(crate) fn write_to(writer: &mut impl Write, rules: &[(String, String)]) -> io::Result<()> {
+        let slots = (rules.len() * 2).max(1).next_power_of_two();
+        let mut table = vec![EMPTY_SLOT; slots];
+        for (rank, rule @ (first, second)) in rules.iter().enumerate() {
+            let mut slot = pair_hash(first.as_bytes(), second.as_bytes()) as usize & (slots - 1);
+            while table[slot] != EMPTY_SLOT && rules[table[slot] as usize] != *rule {
+                slot = (slot + 1) & (slots - 1);
+            }
+            if table[slot] == EMPTY_SLOT {
+                table[slot] = rank as u32;
+            }
+        }
+
+        writer.write_all(&(rules.len() as u64).to_le_bytes())?;
+        let mut offset = 0u64;
+        writer.write_all(&offset.to_le_bytes())?;
+        for (first, second) in rules {
+            for half in [first, second] {
+                offset += half.len() as u64;
+                writer.write_all(&offset.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&(slots as u64).to_le_bytes())?;
+        for rank in table {
+            writer.write_all(&rank.to_le_bytes())?;
+        }
+        for (first, second) in rules {
+            writer.write_all(first.as_bytes())?;
+            writer.write_all(second.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads the layout filling `section` of `map` and checks it.
+    pub(crate) fn parse(map: Arc<Mmap>, section: Range<usize>) -> io::Result<Self> {
+        let bytes = &map[section.clone()];
+        let too_short = || invalid("merge section is shorter than its rule count requires");
+        if bytes.len() < 8 {
+            return Err(too_short());
+        }
+        let count = read_u64(bytes, 0);
+        // The count, the offsets and the slot count come before the rank table.
+        let table = count
+            .checked_mul(16)
+            .and_then(|n| n.checked_add(24))
+            .filter(|&n| n <= bytes.len() as u64)
+            .ok_or_else(too_short)? as usize;
+        let slots = read_u64(bytes, table - 8);
+        if !slots.is_power_of_two() || slots <= count {
+            return Err(invalid("rank table has too few slots"));
+        }
+        let strings_start = slots
+            .checked_mul(4)
+            .and_then(|n| n.checked_add(table as u64))
+            .filter(|&n| n <= bytes.len() as u64)
+            .ok_or_else(too_short)? as usize;
+
+        let merges = MappedMerges {
+            offsets: section.start + 8,
+            count: count as usize,
+            table: section.start + table,
+            slots: slots as usize,
+            strings: section.start + strings_start..section.end,
+            rules: OnceLock::new(),
+            map,
+        };
+        merges.validate()?;
+        Ok(merges)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        validate_strings(
+            &self.map,
+            self.offsets,
+            self.count * 2,
+            &self.map[self.strings.clone()],
+        )?;
+
+        let table = &self.map[self.table..self.table + self.slots * 4];
+        let rank_at = |slot: usize| read_u32(table, slot * 4);
+        // Every slot bumps a counter, empty and out-of-range ones one of 64 spare
+        // counters past the ranks: branching on the irregular empty slots is slow.
+        let mut uses = vec![0u8; self.count + 64];
+        let mut out_of_range = false;
+        for (slot, rank) in table.chunks_exact(4).enumerate() {
+            let rank = u32::from_le_bytes(rank.try_into().expect("four bytes"));
+            out_of_range |= (rank as usize >= self.count) & (rank != EMPTY_SLOT);
+            let index = (rank as usize).min(self.count + slot % 64);
+            uses[index] = uses[index].saturating_add(1);
+        }
+        if out_of_range || uses[..self.count].iter().any(|&uses| uses > 1) {
+            let slot = (0..self.slots)
+                .find(|&slot| {
+                    let rank = rank_at(slot);
+                    rank != EMPTY_SLOT && (rank as usize >= self.count || uses[rank as usize] > 1)
+                })
+                .expect("a slot is invalid or repeated");
+            return Err(invalid(&format!(
+                "rank table slot {} holds an invalid or repeated rank",
+                slot
+            )));
+        }
+
+        // Each rule is hashed from one slice, as its halves are stored together.
+        let strings = &self.map[self.strings.clone()];
+        let offsets = &self.map[self.offsets..self.offsets + (self.count * 2 + 1) * 8];
+        let offset = |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("eight bytes"));
+        let mut start = 0;
+        let hashes: Vec<u64> = offsets[8..]
+            .chunks_exact(16)
+            .map(|rule| {
+                let (middle, end) = (offset(&rule[..8]), offset(&rule[8..]));
+                let hash = rule_hash(&strings[start as usize..end as usize], middle - start);
+                start = end;
+                hash
+            })
+            .collect();
+
+        // Probing stops at the first slot holding the same rule, which must be the
+        // rule's first rank.
+        for (rank, &hash) in hashes.iter().enumerate() {
+            let mut slot = hash as usize & (self.slots - 1);
+            if rank_at(slot) == rank as u32 {
+                continue;
+            }
+            let found = loop {
+                let other = rank_at(slot);
+                if other == EMPTY_SLOT {
+                    break None;
+                }
+                let other = other as usize;
+                if other == rank
+                    || hashes[other] == hash
+                        && self.bytes(other * 2) == self.bytes(rank * 2)
+                        && self.bytes(other * 2 + 1) == self.bytes(rank * 2 + 1)
+                {
+                    break Some(other);
+                }
+                slot = (slot + 1) & (self.slots - 1);
+            };
+            // A repeated rule is found at its first rank and left out of the table.
+            let first_rank =
+                found.is_some_and(|found| found == rank || found < rank && uses[rank] == 0);
+            if !first_rank {
+                return Err(invalid(&format!("rank table does not find rule {}", rank)));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the halves of the rule with `rank`, which must be below the length.
+    pub(crate) fn rule(&self, rank: usize) -> (&str, &str) {
+        let half = |index| {
+            std::str::from_utf8(self.bytes(index)).expect("rules are validated when mapped")
+        };
+        (half(rank * 2), half(rank * 2 + 1))
+    }
+
+    pub(crate) fn rules(&self) -> &[(String, String)] {
+        self.rules.get_or_init(|| {
+            (0..self.count)
+                .map(|rank| {
+                    let (first, second) = self.rule(rank);
+                    (first.to_string(), second.to_string())
+                })
+                .collect()
+        })
+    }
+
+    /// Calls `visit` with the halves of every rule, in rank order, whose halves are
+    /// both at most `max_len` bytes long, reading the offsets in one pass.
+    pub(crate) fn for_each_short_rule(&self, max_len: usize, mut visit: impl FnMut(&[u8], &[u8])) {
+        let offsets = &self.map[self.offsets..self.offsets + (self.count * 2 + 1) * 8];
+        let strings = &self.map[self.strings.clone()];
+        let offset =
+            |bytes: &[u8]| u64::from_le_bytes(bytes.try_into().expect("eight bytes")) as usize;
+        let mut start = 0;
+        for rule in offsets[8..].chunks_exact(16) {
+            let (middle, end) = (offset(&rule[..8]), offset(&rule[8..]));
+            if middle - start <= max_len && end - middle <= max_len {
+                visit(&strings[start..middle], &strings[middle..end]);
+            }
+            start = end;
+        }
+    }
+
+    /// Finds the first rank of the rule merging `first` and `second`.
+    pub(crate) fn rank(&self, first: &str, second: &str) -> Option<usize> {
+        self.find(first.as_bytes(), second.as_bytes())
+    }
+
+    fn find(&self, first: &[u8], second: &[u8]) -> Option<usize> {
+        let mut slot = pair_hash(first, second) as usize & (self.slots - 1);
+        // Fewer ranks than slots, so probing reaches an empty slot.
+        loop {
+            let rank = self.slot(slot);
+            if rank == EMPTY_SLOT {
+                return None;
+            }
+            let rank = rank as usize;
+            if self.bytes(rank * 2) == first && self.bytes(rank * 2 + 1) == second {
+                return Some(rank);
+            }
+            slot = (slot + 1) & (self.slots - 1);
+        }
+    }
+
+    fn slot(&self, slot: usize) -> u32 {
+        read_u32(&self.map, self.table + slot * 4)
+    }
+
+    fn bytes(&self, index: usize) -> &[u8] {
+        let start = self.strings.start + read_u64(&self.map, self.offsets + index * 8) as usize;
+        let end = self.strings.start + read_u64(&self.map, self.offsets + index * 8 + 8) as usize;
+        &self.map[start..end]
+    }
+}
+
+/// Hash locating the rule merging `first` and `second` in the rank table.
+fn pair_hash(first: &[u8], second: &[u8]) -> u64 {
+    let mut rule = [0; 64];
+    match rule.get_mut(..first.len() + second.len()) {
+        Some(rule) => {
+            rule[..first.len()].copy_from_slice(first);
+            rule[first.len()..].copy_from_slice(second);
+            rule_hash(rule, first.len() as u64)
+        }
+        None => rule_hash(&[first, second].concat(), first.len() as u64),
+    }
+}
+
+/// Hash of a rule given as its halves joined, the first `first_len` bytes long.
+fn rule_hash(rule: &[u8], first_len: u64) -> u64 {
+    xxh3_64_with_seed(rule, first_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::low_level::MergeTable;
+    use crate::mapped_vocabulary::map_file;
+
+    fn mapped(rules: &[(String, String)]) -> MappedMerges {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merges.bin");
+        let mut bytes = Vec::new();
+        MappedMerges::write_to(&mut bytes, rules).unwrap();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let map = map_file(&path).unwrap();
+        MappedMerges::parse(map, 0..bytes.len()).unwrap()
+    }
+
+    #[test]
+    fn ranks_match_an_owned_table() {
+        let rules: Vec<(String, String)> = [("a", "b"), ("ab", "c"), ("é", ""), ("a", "b")]
+            .iter()
+            .map(|(first, second)| (first.to_string(), second.to_string()))
+            .collect();
+        let owned = MergeTable::new(rules.clone());
+        let merges = mapped(&rules);
+
+        assert_eq!(merges.rules(), rules);
+        for (first, second) in &rules {
+            assert_eq!(merges.rank(first, second), owned.rank(first, second));
+        }
+        assert_eq!(merges.rank("a", "b"), Some(0));
+        assert_eq!(merges.rank("b", "a"), None);
+        assert_eq!(merges.rank("", "é"), None);
+        assert_eq!(mapped(&[]).rank("a", "b"), None);
+
+        // Enough rules that many are displaced from the slot they hash to.
+        let many: Vec<(String, String)> = (0..3000)
+            .map(|index| (format!("{}", index % 1000), format!("{}", index % 7)))
+            .collect();
+        let owned = MergeTable::new(many.clone());
+        let merges = mapped(&many);
+        for (first, second) in &many {
+            assert_eq!(merges.rank(first, second), owned.rank(first, second));
+        }
+    }
+
+    #[test]
+    fn rejects_damaged_sections() {
+        let rules = vec![("h".to_string(), "i".to_string())];
+        let mut bytes = Vec::new();
+        MappedMerges::write_to(&mut bytes, &rules).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merges.bin");
+        let parse = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            let map = map_file(&path).unwrap();
+            MappedMerges::parse(map, 0..bytes.len())
+                .err()
+                .unwrap()
+                .to_string()
+        };
+
+        assert!(parse(&bytes[..20]).contains("shorter"));
+        assert!(parse(&bytes[..bytes.len() - 1]).contains("span"));
+        let mut no_slots = bytes.clone();
+        no_slots[32..40].copy_from_slice(&1u64.to_le_bytes());
+        assert!(parse(&no_slots).contains("too few slots"));
+        // The two table slots follow the count, three offsets and the slot count.
+        let table = 40..48;
+        let mut out_of_range = bytes.clone();
+        out_of_range[table.clone()].copy_from_slice(&[1, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(parse(&out_of_range).contains("slot 0 holds an invalid or repeated rank"));
+        let mut repeated = bytes.clone();
+        repeated[table.clone()].copy_from_slice(&[0; 8]);
+        assert!(parse(&repeated).contains("slot 0 holds an invalid or repeated rank"));
+        let mut swapped = bytes.clone();
+        swapped[table].rotate_left(4);
+        assert!(parse(&swapped).contains("does not find rule 0"));
+        let repeated_rule = [rules[0].clone(), rules[0].clone()];
+        let mut bytes_repeated = Vec::new();
+        MappedMerges::write_to(&mut bytes_repeated, &repeated_rule).unwrap();
+        // Four slots after the count, five offsets and the slot count; rank 1 is
+        // left out because it repeats rank 0.
+        let empty = (56..72)
+            .step_by(4)
+            .find(|&slot| bytes_repeated[slot..slot + 4] == [0xFF; 4])
+            .unwrap();
+        bytes_repeated[empty..empty + 4].copy_from_slice(&1u32.to_le_bytes());
+        assert!(parse(&bytes_repeated).contains("does not find rule 1"));
+        let mut invalid_utf8 = bytes.clone();
+        *invalid_utf8.last_mut().unwrap() = 0xFF;
+        assert!(parse(&invalid_utf8).contains("UTF-8"));
+    }
+}
@@ -0,0 +1,334 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use memmap2::Mmap;
+
+/// Magic bytes at the start of every file written by
+/// [`Vocabulary::save_mapped`](crate::Vocabulary::save_mapped).
+pub const MAPPED_VOCABULARY_MAGIC: [u8; 8] = *b"BPEVOC01";
+
+/// Bytes before the offsets: magic and token count.
+const HEADER_LEN: usize = 16;
+
+/// Maps the file at `path` read-only.
+pub(crate) fn map_file(path: impl AsRef<Path>) -> io::Result<Arc<Mmap>> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is read-only, and every access goes through bounds-checked
+    // slices validated when a section is parsed. Another process truncating or
+    // rewriting the file while it is mapped is outside this crate's control, as
+    // documented on `Vocabulary::open_mapped`.
+    Ok(Arc::new(unsafe { Mmap::map(&file)? }))
+}
+
+/// Reads the little-endian `u64` at `start`.
+pub(crate) fn read_u64(bytes: &[u8], start: usize) -> u64 {
+    u64::from_le_bytes(bytes[start..start + 8].try_into().expect("eight bytes"))
+}
+
+/// Reads the little-endian `u32` at `start`.
+pub(crate) fn read_u32(bytes: &[u8], start: usize) -> u32 {
+    u32::from_le_bytes(bytes[start..start + 4].try_into().expect("four bytes"))
+}
+
+/// Checks that `count + 1` ascending offsets starting at `offsets` span the
+/// string area `strings` and split it at character boundaries, so every string
+/// between two of them is valid UTF-8.
+pub(crate) fn validate_strings(
+    bytes: &[u8],
+    offsets: usize,
+    count: usize,
+    strings: &[u8],
+) -> io::Result<()> {
+    if read_u64(bytes, offsets) != 0 || read_u64(bytes, offsets + count * 8) != strings.len() as u64
+    {
+        return Err(invalid("offsets do not span the string area"));
+    }
+    let strings =
+        std::str::from_utf8(strings).map_err(|_| invalid("strings are not valid UTF-8"))?;
+    let mut start = 0;
+    let ends = bytes[offsets + 8..offsets + (count + 1) * 8].chunks_exact(8);
+    for (index, end) in ends.enumerate() {
+        let end = u64::from_le_bytes(end.try_into().expect("eight bytes"));
+        if end < start {
+            return Err(invalid("offsets are not ascending"));
+        }
+        if !strings.is_char_boundary(end as usize) {
+            return Err(invalid(&format!("string {} is not valid UTF-8", index)));
+        }
+        start = end;
+    }
+    Ok(())
+}
+
+/// Token strings read in place from a file mapping.
+///
+/// The layout is [`MAPPED_VOCABULARY_MAGIC`], the token count `n` as a
+/// little-endian `u64`, `n + 1` little-endian `u64` byte offsets of the tokens
+/// within the string area, `n` little-endian `u32` IDs ordered by token bytes (for
+/// binary-search lookups), then the string area: every token's UTF-8 bytes in ID
+/// order. It fills a whole file written by
+/// [`Vocabulary::save_mapped`](crate::Vocabulary::save_mapped), or one section of
+/// a file written by `BpeTokenizer::save_mapped`.
+pub(crate) struct MappedTokens {
+    map: Arc<Mmap>,
+    /// Where the layout starts in the mapping.
+    start: usize,
+    count: usize,
+    strings: Range<usize>,
+}
+
+impl MappedTokens {
+    /// Writes `tokens`, given in ID order, in the mapped layout to a new file.
+    pub(crate) fn write(path: impl AsRef<Path>, tokens: &[&str]) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        Self::write_to(&mut writer, tokens)?;
+        writer.flush()
+    }
+
+    /// Writes `tokens`, given in ID order, in the mapped layout.
+    pub(crate) fn write_to(writer: &mut impl Write, tokens: &[&str]) -> io::Result<()> {
+        let mut sorted: Vec<(&str, u32)> = tokens.iter().copied().zip(0..).collect();
+        sorted.sort_unstable();
+
+        writer.write_all(&MAPPED_VOCABULARY_MAGIC)?;
+        writer.write_all(&(sorted.len() as u64).to_le_bytes())?;
+        let mut offset = 0u64;
+        writer.write_all(&offset.to_le_bytes())?;
+        for token in tokens {
+            offset += token.len() as u64;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for (_, id) in &sorted {
+            writer.write_all(&id.to_le_bytes())?;
+        }
+        for token in tokens {
+            writer.write_all(token.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Maps the file at `path` and checks its layout, so later lookups cannot fail.
+    pub(crate) fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let map = map_file(path)?;
+        let len = map.len();
+        Self::parse(map, 0..len)
+    }
+
+    /// Reads the layout filling `section` of `map` and checks it.
+    pub(crate) fn parse(map: Arc<Mmap>, section: Range<usize>) -> io::Result<Self> {
+        let bytes = &map[section.clone()];
+        if bytes.len() < HEADER_LEN || bytes[..8] != MAPPED_VOCABULARY_MAGIC {
+            return Err(invalid("missing mapped vocabulary header"));
+        }
+        let count = read_u64(bytes, 8);
+        let strings_start = count
+            .checked_add(1)
+            .and_then(|n| n.checked_mul(8))
+            .and_then(|n| n.checked_add(count.checked_mul(4)?))
+            .and_then(|n| n.checked_add(HEADER_LEN as u64))
+            .filter(|&n| n <= bytes.len() as u64)
+            .ok_or_else(|| invalid("file is shorter than its token count requires"))?;
+
+        let tokens = MappedTokens {
+            count: count as usize,
+            strings: section.start + strings_start as usize..section.end,
+            start: section.start,
+            map,
+        };
+        tokens.validate()?;
+        Ok(tokens)
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        validate_strings(
+            &self.map,
+            self.start + HEADER_LEN,
+            self.count,
+            &self.map[self.strings.clone()],
+        )?;
+
+        let mut seen = vec![false; self.count];
+        for rank in 0..self.count {
+            let id = self.sorted_id(rank) as usize;
+            if id >= self.count || std::mem::replace(&mut seen[id], true) {
+                return Err(invalid("sorted index is not a permutation of the IDs"));
+            }
+            if rank > 0 && self.sort_key(rank - 1) > self.sort_key(rank) {
+                return Err(invalid("sorted index is out of order"));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.count
+    }
+
+    pub(crate) fn token(&self, id: u32) -> Option<&str> {
+        let id = id as usize;
+        (id < self.count)
+            .then(|| std::str::from_utf8(self.bytes(id)).expect("tokens are validated when mapped"))
+    }
+
+    /// Returns the number of characters in each token, in ID order.
+    pub(crate) fn char_lens(&self) -> Box<[usize]> {
+        let offsets = self.start + HEADER_LEN;
+        let offsets = &self.map[offsets..offsets + (self.count + 1) * 8];
+        let strings = &self.map[self.strings.clone()];
+        let mut start = 0;
+        offsets[8..]
+            .chunks_exact(8)
+            .map(|end| {
+                let end = u64::from_le_bytes(end.try_into().expect("eight bytes")) as usize;
+                // Every character has exactly one byte that is not a continuation byte.
+                let chars = strings[start..end]
+                    .iter()
+                    .filter(|&&byte| byte & 0xC0 != 0x80)
+                    .count();
+                start = end;
+                chars
+            })
+            .collect()
+    }
+
+    /// Finds the ID of `token`; the highest one if the file repeats it, as in
+    /// [`Vocabulary::new`](crate::Vocabulary::new).
+    pub(crate) fn id(&self, token: &str) -> Option<u32> {
+        let mut low = 0;
+        let mut high = self.count;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.sort_key(middle) <= (token.as_bytes(), u32::MAX) {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+
+        let last = low.checked_sub(1)?;
+        (self.sort_key(last).0 == token.as_bytes()).then(|| self.sorted_id(last))
+    }
+
+    fn offset(&self, index: usize) -> usize {
+        read_u64(&self.map, self.start + HEADER_LEN + index * 8) as usize
+    }
+
+    fn sorted_id(&self, rank: usize) -> u32 {
+        read_u32(
+            &self.map,
+            self.start + HEADER_LEN + (self.count + 1) * 8 + rank * 4,
+        )
+    }
+
+    fn bytes(&self, id: usize) -> &[u8] {
+        let start = self.strings.start + self.offset(id);
+        let end = self.strings.start + self.offset(id + 1);
+        &self.map[start..end]
+    }
+
+    fn sort_key(&self, rank: usize) -> (&[u8], u32) {
+        let id = self.sorted_id(rank);
+        (self.bytes(id as usize), id)
+    }
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support;
+    use crate::{BpeTokenizer, Vocabulary};
+
+    fn trained() -> BpeTokenizer {
+        test_support::trained(&["the cat sat on the mat", "naïve café 日本語"], 20)
+    }
+
+    #[test]
+    fn mapped_vocabulary_matches_owned() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vocab.bin");
+        let owned = trained().vocabulary().clone();
+
+        owned.save_mapped(&path).unwrap();
+        let mapped = Vocabulary::open_mapped(&path).unwrap();
+
+        assert!(mapped.is_mapped() && !owned.is_mapped());
+        assert!(mapped.iter().eq(owned.iter()));
+        for (id, token) in owned.iter() {
+            assert_eq!(mapped.token_to_id(token), Some(id));
+        }
+        assert_eq!(mapped.token_to_id("not a token"), None);
+        assert_eq!(mapped.token_to_id(""), None);
+        assert_eq!(mapped.id_to_token(owned.len() as u32), None);
+    }
+
+    #[test]
+    fn repeated_tokens_resolve_to_the_highest_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vocab.bin");
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "c".to_string()),
+            ("a".to_string(), "bc".to_string()),
+        ];
+        let owned = Vocabulary::new(vec![], merges);
+
+        owned.save_mapped(&path).unwrap();
+        let mapped = Vocabulary::open_mapped(&path).unwrap();
+
+        assert_eq!(mapped.token_to_id("abc"), owned.token_to_id("abc"));
+        assert_eq!(mapped.token_to_id("abc"), Some(258));
+    }
+
+    #[test]
+    fn tokenizer_keeps_a_mapped_vocabulary() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vocab.bin");
+        let tokenizer = trained();
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["the mat"]);
+        ranked.vocabulary().save_mapped(&path).unwrap();
+
+        let mapped = BpeTokenizer::new(tokenizer.merges().to_vec(), vec!["<|endoftext|>".into()])
+            .with_vocabulary(Vocabulary::open_mapped(&path).unwrap())
+            .unwrap();
+
+        let text = "the café<|endoftext|>";
+        assert!(mapped.vocabulary().is_mapped());
+        assert_eq!(mapped.encode(text), ranked.encode(text));
+        assert_eq!(mapped.decode(&mapped.encode(text)), text);
+
+        let other = BpeTokenizer::new(vec![], vec![]);
+        let vocabulary = Vocabulary::open_mapped(&path).unwrap();
+        assert!(other.with_vocabulary(vocabulary).is_err());
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vocab.bin");
+        Vocabulary::new(vec![], vec![]).save_mapped(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let open = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            Vocabulary::open_mapped(&path).err().unwrap().to_string()
+        };
+
+        assert!(open(b"merges.txt").contains("header"));
+        assert!(open(&bytes[..bytes.len() / 2]).contains("shorter"));
+        assert!(open(&bytes[..bytes.len() - 1]).contains("span"));
+
+        let mut invalid_utf8 = bytes.clone();
+        *invalid_utf8.last_mut().unwrap() = 0xFF;
+        assert!(open(&invalid_utf8).contains("UTF-8"));
+
+        let mut unsorted = bytes.clone();
+        let index = 16 + 257 * 8;
+        unsorted.swap(index, index + 4);
+        assert!(open(&unsorted).contains("out of order"));
+    }
+}
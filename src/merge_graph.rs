@@ -0,0 +1,275 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::bytes_to_unicode;
+
+/// Where one operand of a merge rule comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// A single byte-level base token.
+    Byte,
+    /// The product of the merge rule with the given rank.
+    Merge(usize),
+    /// Neither a base token nor the product of any merge rule.
+    Missing,
+}
+
+/// The dependency DAG of a merge list.
+///
+/// Merge `k` depends on merge `i` when the token produced by `i` is one of the operands
+/// of `k`. Operands that are byte-level base tokens have no dependency. Because a merge
+/// product is always longer than either operand, the graph is acyclic.
+///
+/// If the same token is produced by several rules, the highest rank is treated as its
+/// producer, mirroring how [`Vocabulary`](crate::Vocabulary) gives the token the ID
+/// of the last rule producing it.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{MergeGraph, Operand};
+///
+/// let merges = vec![
+///     ("a".to_string(), "b".to_string()),
+///     ("c".to_string(), "d".to_string()),
+///     ("ab".to_string(), "cd".to_string()),
+/// ];
+/// let graph = MergeGraph::new(&merges);
+///
+/// assert_eq!(graph.dependencies(2), &[0, 1]);
+/// assert_eq!(graph.dependents(0), &[2]);
+/// assert_eq!(graph.operands(0), Some([Operand::Byte, Operand::Byte]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MergeGraph {
+    operands: Vec<[Operand; 2]>,
+    dependencies: Vec<Vec<usize>>,
+    dependents: Vec<Vec<usize>>,
+}
+
+impl MergeGraph {
+    /// Builds the dependency graph for the given merge rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `merges` - Merge rules as (token1, token2) pairs, in rank order
+    pub fn new(merges: &[(String, String)]) -> Self {
+        let base_tokens: HashSet<String> = bytes_to_unicode()
+            .values()
+            .map(|ch| ch.to_string())
+            .collect();
+
+        let mut producers: HashMap<String, usize> = HashMap::with_capacity(merges.len());
+        for (rank, (first, second)) in merges.iter().enumerate() {
+            producers.insert(format!("{}{}", first, second), rank);
+        }
+
+        let resolve = |token: &str| {
+            if let Some(&rank) = producers.get(token) {
+                Operand::Merge(rank)
+            } else if base_tokens.contains(token) {
+                Operand::Byte
+            } else {
+                Operand::Missing
+            }
+        };
+
+        let operands: Vec<[Operand; 2]> = merges
+            .iter()
+            .map(|(first, second)| [resolve(first), resolve(second)])
+            .collect();
+
+        let mut dependencies = vec![Vec::new(); merges.len()];
+        let mut dependents = vec![Vec::new(); merges.len()];
+
+        for (rank, pair) in operands.iter().enumerate() {
+            for operand in pair {
+                if let Operand::Merge(producer) = *operand
+                    && !dependencies[rank].contains(&producer)
+                {
+                    dependencies[rank].push(producer);
+                    dependents[producer].push(rank);
+                }
+            }
+            dependencies[rank].sort_unstable();
+        }
+
+        MergeGraph {
+            operands,
+            dependencies,
+            dependents,
+        }
+    }
+
+    /// Returns the number of merge rules in the graph.
+    pub fn len(&self) -> usize {
+        self.operands.len()
+    }
+
+    /// Returns `true` if the graph contains no merge rules.
+    pub fn is_empty(&self) -> bool {
+        self.operands.is_empty()
+    }
+
+    /// Returns where each operand of the merge at `rank` comes from, or `None`
+    /// if the rank is out of range.
+    pub fn operands(&self, rank: usize) -> Option<[Operand; 2]> {
+        self.operands.get(rank).copied()
+    }
+
+    /// Returns the ranks of merges whose products are direct operands of `rank`,
+    /// in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank` is out of range.
+    pub fn dependencies(&self, rank: usize) -> &[usize] {
+        &self.dependencies[rank]
+    }
+
+    /// Returns the ranks of merges that use the product of `rank` as a direct operand,
+    /// in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank` is out of range.
+    pub fn dependents(&self, rank: usize) -> &[usize] {
+        &self.dependents[rank]
+    }
+
+    /// Returns every merge that `rank` transitively depends on, in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank` is out of range.
+    pub fn ancestors(&self, rank: usize) -> Vec<usize> {
+        Self::reachable(rank, &self.dependencies)
+    }
+
+    /// Returns every merge that transitively depends on `rank`, in ascending order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rank` is out of range.
+    pub fn descendants(&self, rank: usize) -> Vec<usize> {
+        Self::reachable(rank, &self.dependents)
+    }
+
+    /// Returns the ranks of merges whose operands are both base tokens.
+    pub fn roots(&self) -> Vec<usize> {
+        self.operands
+            .iter()
+            .enumerate()
+            .filter(|(_, pair)| pair.iter().all(|operand| *operand == Operand::Byte))
+            .map(|(rank, _)| rank)
+            .collect()
+    }
+
+    /// Returns the ranks of merges that depend on a later merge.
+    ///
+    /// Such rules can still fire during encoding, but they are unusual and often
+    /// indicate a reordered or hand-edited merge list.
+    pub fn forward_references(&self) -> Vec<usize> {
+        self.dependencies
+            .iter()
+            .enumerate()
+            .filter(|(rank, deps)| deps.iter().any(|dep| dep > rank))
+            .map(|(rank, _)| rank)
+            .collect()
+    }
+
+    fn reachable(start: usize, edges: &[Vec<usize>]) -> Vec<usize> {
+        let mut visited = vec![false; edges.len()];
+        let mut stack = edges[start].clone();
+
+        while let Some(rank) = stack.pop() {
+            if !visited[rank] {
+                visited[rank] = true;
+                stack.extend_from_slice(&edges[rank]);
+            }
+        }
+
+        (0..edges.len()).filter(|&rank| visited[rank]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    #[test]
+    fn graph_base_merges_are_roots() {
+        let merges = vec![pair("a", "b"), pair("ab", "c"), pair("x", "y")];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.roots(), vec![0, 2]);
+        assert!(graph.dependencies(0).is_empty());
+    }
+
+    #[test]
+    fn graph_records_direct_dependencies() {
+        let merges = vec![pair("a", "b"), pair("ab", "c"), pair("abc", "ab")];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.dependencies(1), &[0]);
+        assert_eq!(graph.dependencies(2), &[0, 1]);
+        assert_eq!(graph.dependents(0), &[1, 2]);
+    }
+
+    #[test]
+    fn graph_same_operand_twice_counts_once() {
+        let merges = vec![pair("n", "a"), pair("na", "na")];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.dependencies(1), &[0]);
+        assert_eq!(graph.dependents(0), &[1]);
+    }
+
+    #[test]
+    fn graph_transitive_ancestors_and_descendants() {
+        let merges = vec![
+            pair("a", "b"),
+            pair("ab", "c"),
+            pair("abc", "d"),
+            pair("x", "y"),
+        ];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.ancestors(2), vec![0, 1]);
+        assert_eq!(graph.descendants(0), vec![1, 2]);
+        assert!(graph.descendants(3).is_empty());
+    }
+
+    #[test]
+    fn graph_marks_missing_operands() {
+        let merges = vec![pair("ab", "c")];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.operands(0), Some([Operand::Missing, Operand::Byte]));
+        assert_eq!(graph.operands(1), None);
+    }
+
+    #[test]
+    fn graph_duplicate_product_resolves_like_the_vocabulary() {
+        let merges = vec![
+            pair("a", "bc"),
+            pair("b", "c"),
+            pair("ab", "c"),
+            pair("abc", "d"),
+        ];
+        let graph = MergeGraph::new(&merges);
+        let vocabulary = crate::Vocabulary::new(vec![], merges.clone());
+
+        assert_eq!(graph.dependencies(3), &[2]);
+        assert_eq!(vocabulary.token_to_id("abc"), Some(256 + 2));
+    }
+
+    #[test]
+    fn graph_detects_forward_references() {
+        let merges = vec![pair("ab", "c"), pair("a", "b")];
+        let graph = MergeGraph::new(&merges);
+
+        assert_eq!(graph.operands(0), Some([Operand::Merge(1), Operand::Byte]));
+        assert_eq!(graph.forward_references(), vec![0]);
+    }
+}
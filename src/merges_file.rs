@@ -0,0 +1,669 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::bytes_to_unicode;
+use crate::merge_graph::{MergeGraph, Operand};
+use crate::metadata::Metadata;
+
+/// Error returned when a merges file cannot be read or fails validation.
+///
+/// Line numbers are 1-based and refer to the original file, including the
+/// `#version` header if present.
+#[derive(Debug)]
+pub enum MergesError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The line does not contain exactly two space-separated tokens.
+    Malformed { line: usize, content: String },
+    /// An operand is neither a byte-level base token nor produced by an earlier rule.
+    UnknownOperand { line: usize, operand: String },
+}
+
+impl fmt::Display for MergesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergesError::Io(err) => write!(f, "failed to read merges file: {}", err),
+            MergesError::Malformed { line, content } => write!(
+                f,
+                "line {}: expected two space-separated tokens, found '{}'",
+                line, content
+            ),
+            MergesError::UnknownOperand { line, operand } => write!(
+                f,
+                "line {}: operand '{}' is not a base token or the product of an earlier merge",
+                line, operand
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MergesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MergesError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MergesError {
+    fn from(err: io::Error) -> Self {
+        MergesError::Io(err)
+    }
+}
+
+/// How tolerant [`parse_merges_with_mode`] is of formatting noise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Every line must be exactly two tokens separated by a single space.
+    #[default]
+    Strict,
+    /// Blank lines are skipped, and leading, trailing or repeated whitespace around
+    /// the two tokens is ignored. Byte-level tokens never contain whitespace, so this
+    /// cannot change the meaning of a rule.
+    Lenient,
+}
+
+/// Header line written by [`format_merges`], matching the files produced by GPT-2
+/// and Hugging Face `tokenizers`.
+pub const MERGES_VERSION_HEADER: &str = "#version: 0.2";
+
+/// Parses and validates merge rules in the `merges.txt` format.
+///
+/// Each line holds one rule as two tokens separated by a single space, in rank order.
+/// A leading `#version` line is skipped. Every operand must be a byte-level base token
+/// or the product of a rule on an earlier line; otherwise the rule could never fire
+/// and the vocabulary would be inconsistent.
+///
+/// # Arguments
+///
+/// * `contents` - The text of a merges file
+///
+/// # Returns
+///
+/// The merge rules as (token1, token2) pairs, or the first error found.
+///
+/// This is [`parse_merges_with_mode`] in [`ParseMode::Strict`].
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::parse_merges;
+///
+/// let merges = parse_merges("#version: 0.2\nh e\nhe y\n").unwrap();
+/// assert_eq!(merges[1], ("he".to_string(), "y".to_string()));
+///
+/// let err = parse_merges("h e\nhx y\n").unwrap_err();
+/// assert_eq!(err.to_string(), "line 2: operand 'hx' is not a base token or the product of an earlier merge");
+/// ```
+pub fn parse_merges(contents: &str) -> Result<Vec<(String, String)>, MergesError> {
+    parse_merges_with_mode(contents, ParseMode::Strict)
+}
+
+/// Parses and validates merge rules like [`parse_merges`], with a choice of how
+/// strictly the line format is enforced.
+///
+/// Line numbers in errors always refer to the original file, including skipped
+/// blank lines.
+///
+/// # Arguments
+///
+/// * `contents` - The text of a merges file
+/// * `mode` - Whether to tolerate blank lines and stray whitespace
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{ParseMode, parse_merges_with_mode};
+///
+/// let contents = "#version: 0.2\n\nh  e \n";
+/// assert!(parse_merges_with_mode(contents, ParseMode::Strict).is_err());
+///
+/// let merges = parse_merges_with_mode(contents, ParseMode::Lenient).unwrap();
+/// assert_eq!(merges, vec![("h".to_string(), "e".to_string())]);
+/// ```
+pub fn parse_merges_with_mode(
+    contents: &str,
+    mode: ParseMode,
+) -> Result<Vec<(String, String)>, MergesError> {
+    let mut known_tokens: HashSet<String> = bytes_to_unicode()
+        .values()
+        .map(|ch| ch.to_string())
+        .collect();
+    let mut merges = Vec::new();
+
+    let mut seen_content = false;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+
+        if mode == ParseMode::Lenient && line.trim().is_empty() {
+            continue;
+        }
+
+        let header = match mode {
+            ParseMode::Strict => line,
+            ParseMode::Lenient => line.trim_start(),
+        };
+        if !seen_content && header.starts_with("#version") {
+            seen_content = true;
+            continue;
+        }
+        seen_content = true;
+
+        let rule = match mode {
+            ParseMode::Strict => split_rule(line),
+            ParseMode::Lenient => split_rule_lenient(line),
+        };
+        let (first, second) = rule.ok_or_else(|| MergesError::Malformed {
+            line: line_number,
+            content: line.to_string(),
+        })?;
+
+        for operand in [first, second] {
+            if !known_tokens.contains(operand) {
+                return Err(MergesError::UnknownOperand {
+                    line: line_number,
+                    operand: operand.to_string(),
+                });
+            }
+        }
+
+        known_tokens.insert(format!("{}{}", first, second));
+        merges.push((first.to_string(), second.to_string()));
+    }
+
+    Ok(merges)
+}
+
+/// Reads a `merges.txt` file from disk and validates it with [`parse_merges`].
+///
+/// # Arguments
+///
+/// * `path` - Path to the merges file
+///
+/// # Returns
+///
+/// The merge rules as (token1, token2) pairs, or the first error found.
+pub fn read_merges_file(path: impl AsRef<Path>) -> Result<Vec<(String, String)>, MergesError> {
+    read_merges_file_with_mode(path, ParseMode::Strict)
+}
+
+/// Reads a `merges.txt` file from disk and validates it with [`parse_merges_with_mode`].
+pub fn read_merges_file_with_mode(
+    path: impl AsRef<Path>,
+    mode: ParseMode,
+) -> Result<Vec<(String, String)>, MergesError> {
+    let contents = fs::read_to_string(path)?;
+    parse_merges_with_mode(&contents, mode)
+}
+
+/// Serializes merge rules in the `merges.txt` format.
+///
+/// The output starts with [`MERGES_VERSION_HEADER`] and has one rule per line in rank
+/// order, so [`parse_merges`] reproduces the input exactly for any merges whose tokens
+/// contain no whitespace, which holds for all byte-level tokens.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{format_merges, parse_merges};
+///
+/// let merges = vec![
+///     ("Ġ".to_string(), "t".to_string()),
+///     ("Ġt".to_string(), "he".to_string()),
+/// ];
+/// let text = format_merges(&merges);
+///
+/// assert_eq!(text, "#version: 0.2\nĠ t\nĠt he\n");
+/// ```
+pub fn format_merges(merges: &[(String, String)]) -> String {
+    format_merges_with_metadata(merges, &Metadata::default())
+}
+
+/// Serializes merge rules like [`format_merges`], appending the set metadata fields to
+/// the `#version` header line.
+pub(crate) fn format_merges_with_metadata(
+    merges: &[(String, String)],
+    metadata: &Metadata,
+) -> String {
+    let fields = metadata.to_header_fields();
+    let mut text = String::with_capacity(
+        MERGES_VERSION_HEADER.len()
+            + fields.len()
+            + 2
+            + merges
+                .iter()
+                .map(|(first, second)| first.len() + second.len() + 2)
+                .sum::<usize>(),
+    );
+    text.push_str(MERGES_VERSION_HEADER);
+    if !fields.is_empty() {
+        text.push(' ');
+        text.push_str(&fields);
+    }
+    text.push('\n');
+
+    for (first, second) in merges {
+        text.push_str(first);
+        text.push(' ');
+        text.push_str(second);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// Reads the metadata stored in the `#version` header line, if any.
+pub(crate) fn header_metadata(contents: &str) -> Metadata {
+    contents
+        .lines()
+        .map(str::trim_start)
+        .find(|line| !line.is_empty())
+        .and_then(|line| line.strip_prefix("#version"))
+        .map(|rest| {
+            let rest = rest.trim_start_matches(':').trim_start();
+            let fields = rest.split_once(' ').map_or("", |(_, fields)| fields);
+            Metadata::from_header_fields(fields)
+        })
+        .unwrap_or_default()
+}
+
+/// Writes merge rules to disk in the format produced by [`format_merges`].
+///
+/// # Errors
+///
+/// Returns any I/O error from creating or writing the file.
+pub fn write_merges_file(path: impl AsRef<Path>, merges: &[(String, String)]) -> io::Result<()> {
+    fs::write(path, format_merges(merges))
+}
+
+/// The kind of problem found by [`lint_merges`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeLintKind {
+    /// The same pair already appears at `first_rank`, so this rule never fires.
+    DuplicateRule { first_rank: usize },
+    /// A different pair at `first_rank` already produces the same token.
+    ///
+    /// Both rules can fire, but they share one vocabulary entry: the token maps to the
+    /// later ID, so the ID of the earlier rule is never emitted.
+    DuplicateProduct { first_rank: usize, token: String },
+    /// An operand is neither a base token nor produced by any rule, so this rule never fires.
+    UnknownOperand { operand: String },
+    /// An operand is only produced by the later rule at `producer_rank`.
+    ///
+    /// The rule can still fire once the later rule has, but the ordering is unusual
+    /// and often points at a reordered or hand-edited file.
+    ForwardReference { producer_rank: usize },
+}
+
+/// One finding reported by [`lint_merges`], attached to the rank of the offending rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeLint {
+    /// Rank (0-based position) of the offending rule.
+    pub rank: usize,
+    /// What is wrong with it.
+    pub kind: MergeLintKind,
+}
+
+impl MergeLint {
+    /// Returns true if the rule can never fire during encoding and may be removed.
+    pub fn is_dead(&self) -> bool {
+        matches!(
+            self.kind,
+            MergeLintKind::DuplicateRule { .. } | MergeLintKind::UnknownOperand { .. }
+        )
+    }
+}
+
+impl fmt::Display for MergeLint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rank {}: ", self.rank)?;
+        match &self.kind {
+            MergeLintKind::DuplicateRule { first_rank } => {
+                write!(f, "duplicate of rank {}; never fires", first_rank)
+            }
+            MergeLintKind::DuplicateProduct { first_rank, token } => write!(
+                f,
+                "produces '{}', already produced by rank {}",
+                token, first_rank
+            ),
+            MergeLintKind::UnknownOperand { operand } => write!(
+                f,
+                "operand '{}' is not a base token or the product of any merge; never fires",
+                operand
+            ),
+            MergeLintKind::ForwardReference { producer_rank } => {
+                write!(f, "depends on later rank {}", producer_rank)
+            }
+        }
+    }
+}
+
+/// Checks merge rules for dead and suspicious entries without rejecting them.
+///
+/// Community merge files often carry duplicate rules and entries that can never
+/// fire. Unlike [`parse_merges`], which stops at the first problem, this reports
+/// every finding, ordered by rank, so a file can be cleaned up in one pass.
+/// Use [`MergeLint::is_dead`] to select the rules that are safe to drop.
+///
+/// # Arguments
+///
+/// * `merges` - Merge rules as (token1, token2) pairs, in rank order
+///
+/// # Returns
+///
+/// All findings, ordered by rank. An empty list means the merges are clean.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::lint_merges;
+///
+/// let merges = vec![
+///     ("a".to_string(), "b".to_string()),
+///     ("a".to_string(), "b".to_string()),
+/// ];
+/// let lints = lint_merges(&merges);
+///
+/// assert_eq!(lints.len(), 1);
+/// assert!(lints[0].is_dead());
+/// assert_eq!(lints[0].to_string(), "rank 1: duplicate of rank 0; never fires");
+/// ```
+pub fn lint_merges(merges: &[(String, String)]) -> Vec<MergeLint> {
+    let graph = MergeGraph::new(merges);
+    let mut first_pair: HashMap<(&str, &str), usize> = HashMap::with_capacity(merges.len());
+    let mut first_product: HashMap<String, usize> = HashMap::with_capacity(merges.len());
+    let mut lints = Vec::new();
+
+    for (rank, (first, second)) in merges.iter().enumerate() {
+        if let Some(&first_rank) = first_pair.get(&(first.as_str(), second.as_str())) {
+            lints.push(MergeLint {
+                rank,
+                kind: MergeLintKind::DuplicateRule { first_rank },
+            });
+            continue;
+        }
+        first_pair.insert((first, second), rank);
+
+        let token = format!("{}{}", first, second);
+        if let Some(&first_rank) = first_product.get(&token) {
+            lints.push(MergeLint {
+                rank,
+                kind: MergeLintKind::DuplicateProduct { first_rank, token },
+            });
+        } else {
+            first_product.insert(token, rank);
+        }
+
+        let operands = graph.operands(rank).expect("graph covers every rank");
+        for (operand, source) in [first, second].into_iter().zip(operands) {
+            match source {
+                Operand::Missing => lints.push(MergeLint {
+                    rank,
+                    kind: MergeLintKind::UnknownOperand {
+                        operand: operand.clone(),
+                    },
+                }),
+                Operand::Merge(producer_rank) if producer_rank > rank => lints.push(MergeLint {
+                    rank,
+                    kind: MergeLintKind::ForwardReference { producer_rank },
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    lints
+}
+
+fn split_rule_lenient(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.split_whitespace();
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(first), Some(second), None) => Some((first, second)),
+        _ => None,
+    }
+}
+
+fn split_rule(line: &str) -> Option<(&str, &str)> {
+    let (first, second) = line.split_once(' ')?;
+
+    if first.is_empty() || second.is_empty() || second.contains(' ') {
+        return None;
+    }
+
+    Some((first, second))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::pair;
+
+    #[test]
+    fn lint_merges_clean_list_has_no_findings() {
+        let merges = vec![pair("a", "b"), pair("ab", "c")];
+
+        assert!(lint_merges(&merges).is_empty());
+    }
+
+    #[test]
+    fn lint_merges_flags_duplicate_rule() {
+        let merges = vec![pair("a", "b"), pair("c", "d"), pair("a", "b")];
+        let lints = lint_merges(&merges);
+
+        assert_eq!(
+            lints,
+            vec![MergeLint {
+                rank: 2,
+                kind: MergeLintKind::DuplicateRule { first_rank: 0 },
+            }]
+        );
+        assert!(lints[0].is_dead());
+    }
+
+    #[test]
+    fn lint_merges_flags_duplicate_product() {
+        let merges = vec![
+            pair("a", "b"),
+            pair("b", "c"),
+            pair("ab", "c"),
+            pair("a", "bc"),
+        ];
+        let lints = lint_merges(&merges);
+
+        assert_eq!(
+            lints,
+            vec![MergeLint {
+                rank: 3,
+                kind: MergeLintKind::DuplicateProduct {
+                    first_rank: 2,
+                    token: "abc".to_string(),
+                },
+            }]
+        );
+        assert!(!lints[0].is_dead());
+    }
+
+    #[test]
+    fn lint_merges_flags_unknown_operand() {
+        let merges = vec![pair("a", "b"), pair("ab", "xyz")];
+        let lints = lint_merges(&merges);
+
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].rank, 1);
+        assert!(lints[0].is_dead());
+        assert_eq!(
+            lints[0].to_string(),
+            "rank 1: operand 'xyz' is not a base token or the product of any merge; never fires"
+        );
+    }
+
+    #[test]
+    fn lint_merges_flags_forward_reference() {
+        let merges = vec![pair("ab", "c"), pair("a", "b")];
+        let lints = lint_merges(&merges);
+
+        assert_eq!(
+            lints,
+            vec![MergeLint {
+                rank: 0,
+                kind: MergeLintKind::ForwardReference { producer_rank: 1 },
+            }]
+        );
+        assert!(!lints[0].is_dead());
+    }
+
+    #[test]
+    fn lint_merges_reports_all_findings_in_rank_order() {
+        let merges = vec![
+            pair("a", "b"),
+            pair("a", "b"),
+            pair("q", "zz"),
+            pair("a", "b"),
+        ];
+        let ranks: Vec<usize> = lint_merges(&merges).iter().map(|lint| lint.rank).collect();
+
+        assert_eq!(ranks, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn parse_merges_reads_rules_in_order() {
+        let merges = parse_merges("a b\nab c\n").unwrap();
+
+        assert_eq!(
+            merges,
+            vec![
+                ("a".to_string(), "b".to_string()),
+                ("ab".to_string(), "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_merges_skips_version_header() {
+        let merges = parse_merges("#version: 0.2\nĠ t\n").unwrap();
+
+        assert_eq!(merges, vec![("Ġ".to_string(), "t".to_string())]);
+    }
+
+    #[test]
+    fn parse_merges_empty_file() {
+        let merges = parse_merges("").unwrap();
+
+        assert!(merges.is_empty());
+    }
+
+    #[test]
+    fn parse_merges_reports_malformed_line() {
+        let err = parse_merges("#version: 0.2\na b\nabc\n").unwrap_err();
+
+        match err {
+            MergesError::Malformed { line, content } => {
+                assert_eq!(line, 3);
+                assert_eq!(content, "abc");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_merges_rejects_extra_tokens() {
+        let err = parse_merges("a b c\n").unwrap_err();
+
+        assert!(matches!(err, MergesError::Malformed { line: 1, .. }));
+    }
+
+    #[test]
+    fn parse_merges_rejects_operand_from_later_rule() {
+        let err = parse_merges("ab c\na b\n").unwrap_err();
+
+        match err {
+            MergesError::UnknownOperand { line, operand } => {
+                assert_eq!(line, 1);
+                assert_eq!(operand, "ab");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_merges_rejects_non_byte_level_operand() {
+        let err = parse_merges("日 x\n").unwrap_err();
+
+        assert!(matches!(err, MergesError::UnknownOperand { line: 1, .. }));
+    }
+
+    #[test]
+    fn read_merges_file_missing_file_is_io_error() {
+        let err = read_merges_file("/nonexistent/merges.txt").unwrap_err();
+
+        assert!(matches!(err, MergesError::Io(_)));
+    }
+
+    #[test]
+    fn strict_mode_rejects_blank_lines() {
+        let err = parse_merges("a b\n\nab c\n").unwrap_err();
+
+        assert!(matches!(err, MergesError::Malformed { line: 2, .. }));
+    }
+
+    #[test]
+    fn lenient_mode_skips_blank_lines_and_stray_whitespace() {
+        let contents = "\n  #version: 0.2\r\n a\tb \r\n\n   \nab    c\n";
+        let merges = parse_merges_with_mode(contents, ParseMode::Lenient).unwrap();
+
+        assert_eq!(merges, vec![pair("a", "b"), pair("ab", "c")]);
+    }
+
+    #[test]
+    fn lenient_mode_reports_original_line_numbers() {
+        let err = parse_merges_with_mode("\na b\n\na b c\n", ParseMode::Lenient).unwrap_err();
+
+        assert!(matches!(err, MergesError::Malformed { line: 4, .. }));
+    }
+
+    #[test]
+    fn format_merges_round_trips_through_parse() {
+        let merges = vec![
+            pair("Ġ", "t"),
+            pair("h", "e"),
+            pair("Ġt", "he"),
+            pair("Ċ", "Ċ"),
+        ];
+
+        assert_eq!(parse_merges(&format_merges(&merges)).unwrap(), merges);
+        assert_eq!(format_merges(&[]), "#version: 0.2\n");
+    }
+
+    #[test]
+    fn write_merges_file_round_trips_through_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merges.txt");
+        let merges = vec![pair("a", "b"), pair("ab", "ab")];
+
+        write_merges_file(&path, &merges).unwrap();
+
+        assert_eq!(read_merges_file(&path).unwrap(), merges);
+    }
+
+    #[test]
+    fn metadata_round_trips_through_header_line() {
+        let metadata = Metadata::default().with_name("tiny bpe").with_created(42);
+        let text = format_merges_with_metadata(&[pair("a", "b")], &metadata);
+
+        assert_eq!(text, "#version: 0.2 name=tiny%20bpe created=42\na b\n");
+        assert_eq!(header_metadata(&text), metadata);
+        assert_eq!(parse_merges(&text).unwrap(), vec![pair("a", "b")]);
+    }
+
+    #[test]
+    fn header_metadata_is_empty_without_version_line() {
+        assert!(header_metadata("a b\n").is_empty());
+        assert!(header_metadata("#version: 0.2\na b\n").is_empty());
+    }
+}
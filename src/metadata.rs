@@ -0,0 +1,199 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Descriptive information stored alongside a tokenizer's merges.
+///
+/// All fields are optional. [`Metadata::new`] fills in the creation time and the
+/// version of this crate; the rest is up to the caller. Metadata is written by
+/// [`BpeTokenizer::save_merges`](crate::BpeTokenizer::save_merges) into the
+/// `#version` header line of `merges.txt`, which other readers such as Hugging Face
+/// `tokenizers` skip, and read back by
+/// [`BpeTokenizer::from_merges_file`](crate::BpeTokenizer::from_merges_file).
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, Metadata};
+///
+/// let metadata = Metadata::new()
+///     .with_name("support-bot")
+///     .with_version("2.1")
+///     .with_corpus("support tickets, 2025 export");
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]).with_metadata(metadata);
+///
+/// assert_eq!(tokenizer.metadata().name.as_deref(), Some("support-bot"));
+/// assert_eq!(tokenizer.metadata().crate_version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metadata {
+    /// Name of the tokenizer.
+    pub name: Option<String>,
+    /// Version of the tokenizer artifact, in whatever scheme the owner uses.
+    pub version: Option<String>,
+    /// Free-form description of the training corpus.
+    pub corpus: Option<String>,
+    /// Creation time in seconds since the Unix epoch.
+    pub created: Option<u64>,
+    /// Version of this crate that created the artifact.
+    pub crate_version: Option<String>,
+}
+
+impl Metadata {
+    /// Creates metadata stamped with the current time and this crate's version.
+    pub fn new() -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .ok();
+
+        Metadata {
+            created,
+            crate_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            ..Metadata::default()
+        }
+    }
+
+    /// Sets the tokenizer name.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the artifact version.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Sets the training corpus description.
+    pub fn with_corpus(mut self, corpus: impl Into<String>) -> Self {
+        self.corpus = Some(corpus.into());
+        self
+    }
+
+    /// Sets the creation time in seconds since the Unix epoch.
+    pub fn with_created(mut self, created: u64) -> Self {
+        self.created = Some(created);
+        self
+    }
+
+    /// Returns true if no field is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    /// Encodes the set fields as space-separated `key=value` pairs.
+    ///
+    /// Values are percent-encoded so they never contain spaces, `=`, `%` or line breaks.
+    pub(crate) fn to_header_fields(&self) -> String {
+        let created = self.created.map(|created| created.to_string());
+        let fields = [
+            ("name", self.name.as_deref()),
+            ("version", self.version.as_deref()),
+            ("corpus", self.corpus.as_deref()),
+            ("created", created.as_deref()),
+            ("crate_version", self.crate_version.as_deref()),
+        ];
+
+        fields
+            .iter()
+            .filter_map(|(key, value)| value.map(|value| format!("{}={}", key, escape(value))))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Parses the `key=value` pairs written by [`to_header_fields`](Metadata::to_header_fields).
+    ///
+    /// Unknown keys and malformed pairs are ignored, so headers from other tools (e.g.
+    /// `#version: 0.2 - Trained by huggingface/tokenizers`) yield empty metadata.
+    pub(crate) fn from_header_fields(fields: &str) -> Self {
+        let mut metadata = Metadata::default();
+
+        for (key, value) in fields.split(' ').filter_map(|pair| pair.split_once('=')) {
+            let Some(value) = unescape(value) else {
+                continue;
+            };
+            match key {
+                "name" => metadata.name = Some(value),
+                "version" => metadata.version = Some(value),
+                "corpus" => metadata.corpus = Some(value),
+                "created" => metadata.created = value.parse().ok(),
+                "crate_version" => metadata.crate_version = Some(value),
+                _ => {}
+            }
+        }
+
+        metadata
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for ch in value.chars() {
+        match ch {
+            ' ' | '=' | '%' | '\n' | '\r' => escaped.push_str(&format!("%{:02X}", ch as u32)),
+            _ => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_fields_round_trip() {
+        let metadata = Metadata::new()
+            .with_name("tiny")
+            .with_version("1.0-rc=1")
+            .with_corpus("wiki 100% en\nnews")
+            .with_created(1_700_000_000);
+
+        let fields = metadata.to_header_fields();
+
+        assert!(!fields.contains('\n'));
+        assert_eq!(Metadata::from_header_fields(&fields), metadata);
+    }
+
+    #[test]
+    fn empty_metadata_has_no_fields() {
+        assert!(Metadata::default().is_empty());
+        assert_eq!(Metadata::default().to_header_fields(), "");
+    }
+
+    #[test]
+    fn foreign_header_text_is_ignored() {
+        let metadata = Metadata::from_header_fields("- Trained by huggingface/tokenizers");
+
+        assert!(metadata.is_empty());
+    }
+
+    #[test]
+    fn malformed_escapes_are_skipped() {
+        let metadata = Metadata::from_header_fields("name=bad%G1 version=ok%20go");
+
+        assert_eq!(metadata.name, None);
+        assert_eq!(metadata.version.as_deref(), Some("ok go"));
+    }
+}
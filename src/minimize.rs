@@ -0,0 +1,152 @@
+use crate::{BpeTokenizer, verify_roundtrip};
+
+/// Shrinks a failing input to a short substring that still fails.
+///
+/// `fails` is the check being debugged, such as "our encoding differs from
+/// HuggingFace's" or "the text does not round-trip". The search first binary-searches
+/// the shortest failing prefix, then the latest start that still fails within it,
+/// and finally trims single characters from either end while the check keeps
+/// failing. It only ever cuts at character boundaries and calls `fails` roughly
+/// `2·log₂(n)` times plus a few per removed edge character, so it stays cheap
+/// even for long documents.
+///
+/// Failures that need two distant parts of the input are kept whole, since the
+/// result is always a contiguous substring of `text`.
+///
+/// Returns `None` if `text` itself does not fail.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::minimize_failure;
+///
+/// let text = "a long document where the word crab triggers a bug";
+/// let minimal = minimize_failure(text, |candidate| candidate.contains("crab"));
+///
+/// assert_eq!(minimal, Some("crab"));
+/// ```
+pub fn minimize_failure<F>(text: &str, mut fails: F) -> Option<&str>
+where
+    F: FnMut(&str) -> bool,
+{
+    if !fails(text) {
+        return None;
+    }
+
+    let boundaries: Vec<usize> = text
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let last = boundaries.len() - 1;
+
+    // Shortest failing prefix: `end` indexes `boundaries`, and text[..end] fails.
+    let (mut low, mut end) = (0, last);
+    while low < end {
+        let middle = (low + end) / 2;
+        if fails(&text[..boundaries[middle]]) {
+            end = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+
+    // Latest start that still fails within that prefix.
+    let (mut start, mut high) = (0, end);
+    while start < high {
+        let middle = (start + high).div_ceil(2);
+        if fails(&text[boundaries[middle]..boundaries[end]]) {
+            start = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+
+    // The check need not be monotonic, so trim what binary search may have missed.
+    loop {
+        if start < end && fails(&text[boundaries[start + 1]..boundaries[end]]) {
+            start += 1;
+        } else if start < end && fails(&text[boundaries[start]..boundaries[end - 1]]) {
+            end -= 1;
+        } else {
+            break;
+        }
+    }
+
+    Some(&text[boundaries[start]..boundaries[end]])
+}
+
+/// Shrinks a text that does not round-trip through `tokenizer` to a short substring
+/// that still does not.
+///
+/// Returns `None` if `text` round-trips. See [`minimize_failure`] and
+/// [`verify_roundtrip`].
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, ControlChars, Normalizer, minimize_roundtrip};
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![])
+///     .with_normalizer(Normalizer::new().with_control_chars(ControlChars::Strip));
+///
+/// assert_eq!(
+///     minimize_roundtrip(&tokenizer, "plenty of fine text \u{7} and more"),
+///     Some("\u{7}")
+/// );
+/// ```
+pub fn minimize_roundtrip<'a>(tokenizer: &BpeTokenizer, text: &'a str) -> Option<&'a str> {
+    minimize_failure(text, |candidate| {
+        !verify_roundtrip(tokenizer, &[candidate]).is_empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_none_when_input_passes() {
+        assert_eq!(minimize_failure("fine", |_| false), None);
+    }
+
+    #[test]
+    fn keeps_failures_that_need_the_whole_input() {
+        assert_eq!(minimize_failure("abc", |text| text == "abc"), Some("abc"));
+    }
+
+    #[test]
+    fn cuts_only_at_char_boundaries() {
+        let minimal = minimize_failure("ünïcödé 🦀 text", |text| text.contains('🦀'));
+
+        assert_eq!(minimal, Some("🦀"));
+    }
+
+    #[test]
+    fn handles_non_monotonic_checks() {
+        // Fails only for odd lengths containing 'x': binary search can overshoot.
+        let minimal = minimize_failure("aaxaaaa", |text| {
+            text.contains('x') && text.chars().count() % 2 == 1
+        });
+
+        // Every candidate kept along the way was checked, so the result still fails.
+        let minimal = minimal.unwrap();
+        assert!(minimal.contains('x'));
+        assert!(minimal.chars().count() % 2 == 1);
+        assert!(minimal.len() <= 3);
+    }
+
+    #[test]
+    fn counts_calls_logarithmically() {
+        let text = "a".repeat(1 << 12) + "x" + &"a".repeat(1 << 12);
+        let mut calls = 0;
+
+        let minimal = minimize_failure(&text, |candidate| {
+            calls += 1;
+            candidate.contains('x')
+        });
+
+        assert_eq!(minimal, Some("x"));
+        assert!(calls < 40, "{} calls", calls);
+    }
+}
@@ -0,0 +1,500 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use unicode_normalization::{IsNormalized, UnicodeNormalization, is_nfc_quick};
+
+/// How line endings are treated before tokenization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NewlineMode {
+    /// Leave `\r\n` and `\r` untouched (strict mode, matches HuggingFace ByteLevel).
+    #[default]
+    Preserve,
+    /// Convert `\r\n` and lone `\r` to `\n`.
+    Unix,
+}
+
+/// How control characters are treated before tokenization.
+///
+/// Control characters are the Unicode `Cc` category: C0 (`U+0000`–`U+001F`), `DEL`
+/// and C1 (`U+0080`–`U+009F`). Tab and line feed are never affected. Carriage return is
+/// a control character too; combine with [`NewlineMode::Unix`] to keep line breaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ControlChars {
+    /// Leave control characters untouched.
+    #[default]
+    Keep,
+    /// Remove control characters.
+    Strip,
+    /// Replace each control character with the given character.
+    Replace(char),
+}
+
+/// Counts of inputs seen and changed by a [`Normalizer`].
+///
+/// Returned by [`Normalizer::stats`] for live traffic and by [`Normalizer::analyze`]
+/// for a dry run over a corpus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NormalizationStats {
+    /// Number of texts passed through the normalizer.
+    pub inputs: usize,
+    /// Number of texts whose output differs from the input.
+    pub changed: usize,
+    /// Number of texts changed by the NFC step alone.
+    pub nfc_changed: usize,
+}
+
+impl NormalizationStats {
+    /// Returns the fraction of inputs that were changed, or `0.0` if there were none.
+    pub fn changed_ratio(&self) -> f64 {
+        if self.inputs == 0 {
+            0.0
+        } else {
+            self.changed as f64 / self.inputs as f64
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    inputs: AtomicUsize,
+    changed: AtomicUsize,
+    nfc_changed: AtomicUsize,
+}
+
+impl Clone for Counters {
+    fn clone(&self) -> Self {
+        Counters::default()
+    }
+}
+
+/// Normalizes text before it is pre-tokenized.
+///
+/// Normalization runs before both training and encoding so that the merges learned
+/// from a corpus match the text seen at inference time. A default normalizer leaves
+/// text unchanged, which keeps the tokenizer compatible with HuggingFace's ByteLevel
+/// configuration.
+///
+/// Normalization is lossy: decoding returns the normalized text, not the original.
+///
+/// With [`with_stats`](Normalizer::with_stats), every call to
+/// [`normalize`](Normalizer::normalize) is counted, so [`stats`](Normalizer::stats)
+/// shows how much real traffic the configured steps actually touch. Counting is off
+/// by default to keep encoding free of shared atomic updates. Clones start with
+/// fresh counters.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{NewlineMode, Normalizer};
+///
+/// let normalizer = Normalizer::new().with_newlines(NewlineMode::Unix);
+///
+/// assert_eq!(normalizer.normalize("a\r\nb\rc"), "a\nb\nc");
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Normalizer {
+    nfc: bool,
+    newlines: NewlineMode,
+    control_chars: ControlChars,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    counters: Option<Counters>,
+}
+
+impl Normalizer {
+    /// Creates a normalizer that leaves text unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Normalizer;
+    ///
+    /// let normalizer = Normalizer::new();
+    /// assert_eq!(normalizer.normalize("a\r\nb"), "a\r\nb");
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns whether NFC composition is enabled.
+    pub fn nfc(&self) -> bool {
+        self.nfc
+    }
+
+    /// Returns how line endings are treated.
+    pub fn newlines(&self) -> NewlineMode {
+        self.newlines
+    }
+
+    /// Returns how control characters are treated.
+    pub fn control_chars(&self) -> ControlChars {
+        self.control_chars
+    }
+
+    /// Describes the configuration in a stable, human-readable form.
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "nfc={} newlines={:?} control_chars={:?}",
+            self.nfc, self.newlines, self.control_chars
+        )
+    }
+
+    /// Enables or disables Unicode NFC composition.
+    ///
+    /// NFC runs before the other steps. It is off by default because it changes the
+    /// bytes of some inputs (e.g. decomposed accents), which changes their token IDs.
+    /// Use [`analyze`](Normalizer::analyze) on a sample of your data to see how many
+    /// inputs would be affected before enabling it in production.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Normalizer;
+    ///
+    /// let normalizer = Normalizer::new().with_nfc(true);
+    /// assert_eq!(normalizer.normalize("e\u{301}"), "\u{e9}");
+    /// ```
+    pub fn with_nfc(mut self, enabled: bool) -> Self {
+        self.nfc = enabled;
+        self
+    }
+
+    /// Enables or disables counting of the inputs passed to
+    /// [`normalize`](Normalizer::normalize), reported by [`stats`](Normalizer::stats).
+    ///
+    /// Each counted call updates shared atomic counters and compares the output with
+    /// the input, so leave this off on hot paths that do not read the stats.
+    /// Disabling it drops the counts gathered so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Normalizer;
+    ///
+    /// let normalizer = Normalizer::new().with_nfc(true).with_stats(true);
+    /// normalizer.normalize("cafe\u{301}");
+    ///
+    /// assert_eq!(normalizer.stats().nfc_changed, 1);
+    /// ```
+    pub fn with_stats(mut self, enabled: bool) -> Self {
+        self.counters = enabled.then(|| self.counters.take().unwrap_or_default());
+        self
+    }
+
+    /// Sets how line endings are treated.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - [`NewlineMode::Unix`] to convert `\r\n`/`\r` to `\n`,
+    ///   [`NewlineMode::Preserve`] to keep them as-is
+    pub fn with_newlines(mut self, mode: NewlineMode) -> Self {
+        self.newlines = mode;
+        self
+    }
+
+    /// Sets how control characters other than tab and line feed are treated.
+    ///
+    /// Filtering runs after newline normalization.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - [`ControlChars::Strip`] to remove them, [`ControlChars::Replace`] to
+    ///   substitute a visible character, [`ControlChars::Keep`] to leave them as-is
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{ControlChars, Normalizer};
+    ///
+    /// let normalizer = Normalizer::new().with_control_chars(ControlChars::Strip);
+    /// assert_eq!(normalizer.normalize("a\u{0}b\tc\u{85}"), "ab\tc");
+    /// ```
+    pub fn with_control_chars(mut self, mode: ControlChars) -> Self {
+        self.control_chars = mode;
+        self
+    }
+
+    /// Normalizes text according to the configured options.
+    ///
+    /// Returns the input unchanged (without allocating) when no option applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to normalize
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let (normalized, nfc_changed) = self.apply(text);
+
+        if let Some(counters) = &self.counters {
+            counters.inputs.fetch_add(1, Ordering::Relaxed);
+            if nfc_changed {
+                counters.nfc_changed.fetch_add(1, Ordering::Relaxed);
+            }
+            // Borrowed output is the input itself, so only owned output is compared.
+            if matches!(&normalized, Cow::Owned(owned) if owned != text) {
+                counters.changed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        normalized
+    }
+
+    /// Returns counts of the inputs normalized so far by this instance, or all
+    /// zeros unless counting is enabled with [`with_stats`](Normalizer::with_stats).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Normalizer;
+    ///
+    /// let normalizer = Normalizer::new().with_nfc(true).with_stats(true);
+    /// normalizer.normalize("cafe\u{301}");
+    /// normalizer.normalize("plain");
+    ///
+    /// let stats = normalizer.stats();
+    /// assert_eq!((stats.inputs, stats.changed, stats.nfc_changed), (2, 1, 1));
+    /// ```
+    pub fn stats(&self) -> NormalizationStats {
+        let Some(counters) = &self.counters else {
+            return NormalizationStats::default();
+        };
+
+        NormalizationStats {
+            inputs: counters.inputs.load(Ordering::Relaxed),
+            changed: counters.changed.load(Ordering::Relaxed),
+            nfc_changed: counters.nfc_changed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets the counters reported by [`stats`](Normalizer::stats).
+    pub fn reset_stats(&self) {
+        if let Some(counters) = &self.counters {
+            counters.inputs.store(0, Ordering::Relaxed);
+            counters.changed.store(0, Ordering::Relaxed);
+            counters.nfc_changed.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Reports how many of `texts` this normalizer would change, without touching
+    /// the live counters.
+    ///
+    /// # Arguments
+    ///
+    /// * `texts` - Sample inputs to check
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Normalizer;
+    ///
+    /// let candidate = Normalizer::new().with_nfc(true);
+    /// let stats = candidate.analyze(&["re\u{301}sume\u{301}", "resume"]);
+    ///
+    /// assert_eq!(stats.nfc_changed, 1);
+    /// assert_eq!(stats.changed_ratio(), 0.5);
+    /// ```
+    pub fn analyze(&self, texts: &[&str]) -> NormalizationStats {
+        let mut stats = NormalizationStats::default();
+
+        for text in texts {
+            let (normalized, nfc_changed) = self.apply(text);
+
+            stats.inputs += 1;
+            stats.nfc_changed += nfc_changed as usize;
+            stats.changed += (normalized != *text) as usize;
+        }
+
+        stats
+    }
+
+    fn apply<'a>(&self, text: &'a str) -> (Cow<'a, str>, bool) {
+        let composed = self.compose(text);
+        let nfc_changed = matches!(composed, Cow::Owned(ref owned) if owned != text);
+        let text = self.normalize_newlines(composed);
+
+        (self.filter_control_chars(text), nfc_changed)
+    }
+
+    fn compose<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.nfc || is_nfc_quick(text.chars()) == IsNormalized::Yes {
+            return Cow::Borrowed(text);
+        }
+
+        Cow::Owned(text.nfc().collect())
+    }
+
+    fn normalize_newlines<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        match self.newlines {
+            NewlineMode::Unix if text.contains('\r') => {
+                Cow::Owned(text.replace("\r\n", "\n").replace('\r', "\n"))
+            }
+            _ => text,
+        }
+    }
+
+    fn filter_control_chars<'a>(&self, text: Cow<'a, str>) -> Cow<'a, str> {
+        let is_filtered = |ch: char| ch.is_control() && ch != '\t' && ch != '\n';
+
+        if self.control_chars == ControlChars::Keep || !text.chars().any(is_filtered) {
+            return text;
+        }
+
+        let filtered = text
+            .chars()
+            .filter_map(|ch| match self.control_chars {
+                _ if !is_filtered(ch) => Some(ch),
+                ControlChars::Replace(replacement) => Some(replacement),
+                _ => None,
+            })
+            .collect();
+
+        Cow::Owned(filtered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_default_preserves_text() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(normalizer.normalize("a\r\nb\rc\n"), "a\r\nb\rc\n");
+    }
+
+    #[test]
+    fn normalize_unix_converts_crlf() {
+        let normalizer = Normalizer::new().with_newlines(NewlineMode::Unix);
+
+        assert_eq!(normalizer.normalize("line1\r\nline2\r\n"), "line1\nline2\n");
+    }
+
+    #[test]
+    fn normalize_unix_converts_lone_cr() {
+        let normalizer = Normalizer::new().with_newlines(NewlineMode::Unix);
+
+        assert_eq!(normalizer.normalize("a\rb\r\r\nc"), "a\nb\n\nc");
+    }
+
+    #[test]
+    fn normalize_strips_c0_and_c1_controls() {
+        let normalizer = Normalizer::new().with_control_chars(ControlChars::Strip);
+
+        assert_eq!(
+            normalizer.normalize("a\u{1}b\u{1b}[0m\u{7f}c\u{9f}"),
+            "ab[0mc"
+        );
+    }
+
+    #[test]
+    fn normalize_keeps_tab_and_newline() {
+        let normalizer = Normalizer::new().with_control_chars(ControlChars::Strip);
+
+        assert_eq!(normalizer.normalize("a\tb\nc"), "a\tb\nc");
+    }
+
+    #[test]
+    fn normalize_replaces_controls() {
+        let normalizer = Normalizer::new().with_control_chars(ControlChars::Replace(' '));
+
+        assert_eq!(normalizer.normalize("a\u{0}b\u{8}"), "a b ");
+    }
+
+    #[test]
+    fn normalize_newlines_before_control_filter() {
+        let normalizer = Normalizer::new()
+            .with_newlines(NewlineMode::Unix)
+            .with_control_chars(ControlChars::Strip);
+
+        assert_eq!(normalizer.normalize("a\r\nb\rc"), "a\nb\nc");
+    }
+
+    #[test]
+    fn normalize_nfc_is_off_by_default() {
+        let normalizer = Normalizer::new();
+
+        assert_eq!(normalizer.normalize("e\u{301}"), "e\u{301}");
+        assert_eq!(normalizer.stats().nfc_changed, 0);
+    }
+
+    #[test]
+    fn normalize_nfc_composes_and_counts() {
+        let normalizer = Normalizer::new().with_nfc(true).with_stats(true);
+
+        assert_eq!(normalizer.normalize("A\u{30a}"), "\u{c5}");
+        assert_eq!(normalizer.normalize("\u{c5}"), "\u{c5}");
+        assert_eq!(
+            normalizer.stats(),
+            NormalizationStats {
+                inputs: 2,
+                changed: 1,
+                nfc_changed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn stats_count_changes_from_any_step() {
+        let normalizer = Normalizer::new()
+            .with_nfc(true)
+            .with_newlines(NewlineMode::Unix)
+            .with_stats(true);
+
+        normalizer.normalize("a\r\n");
+        normalizer.normalize("b");
+
+        let stats = normalizer.stats();
+        assert_eq!(stats.changed, 1);
+        assert_eq!(stats.nfc_changed, 0);
+    }
+
+    #[test]
+    fn stats_are_off_by_default() {
+        let normalizer = Normalizer::new().with_nfc(true);
+        normalizer.normalize("A\u{30a}");
+
+        assert_eq!(normalizer.stats(), NormalizationStats::default());
+        assert_eq!(normalizer.with_stats(true).stats().inputs, 0);
+    }
+
+    #[test]
+    fn reset_stats_clears_counters() {
+        let normalizer = Normalizer::new().with_stats(true);
+        normalizer.normalize("a");
+
+        normalizer.reset_stats();
+
+        assert_eq!(normalizer.stats(), NormalizationStats::default());
+    }
+
+    #[test]
+    fn clone_starts_with_fresh_counters() {
+        let normalizer = Normalizer::new().with_stats(true);
+        normalizer.normalize("a");
+
+        let cloned = normalizer.clone();
+
+        assert_eq!(cloned.stats().inputs, 0);
+        assert_eq!(normalizer.stats().inputs, 1);
+    }
+
+    #[test]
+    fn analyze_does_not_touch_live_counters() {
+        let normalizer = Normalizer::new().with_nfc(true).with_stats(true);
+
+        let stats = normalizer.analyze(&["o\u{308}", "o"]);
+
+        assert_eq!(stats.inputs, 2);
+        assert_eq!(stats.nfc_changed, 1);
+        assert_eq!(normalizer.stats().inputs, 0);
+    }
+
+    #[test]
+    fn normalize_unix_borrows_when_unchanged() {
+        let normalizer = Normalizer::new().with_newlines(NewlineMode::Unix);
+
+        assert!(matches!(normalizer.normalize("a\nb"), Cow::Borrowed(_)));
+    }
+}
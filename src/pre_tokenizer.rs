@@ -1,20 +1,104 @@
+#[cfg(feature = "regex")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "regex")]
 use regex::Regex;
 
+use crate::Warning;
+use crate::gpt2_split::Gpt2Splits;
+
+/// The GPT-2 splitting pattern, applied by the hand-written scanner and available
+/// as a regex for reference.
+#[cfg(any(feature = "regex", feature = "json"))]
+pub(crate) const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+";
+
+/// OpenAI's original GPT-2 pattern, which the scanner follows with
+/// [`PreTokenizer::with_whitespace_lookahead`]. The `regex` crate cannot compile it.
+#[cfg(feature = "serde")]
+const GPT2_LOOKAHEAD_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// Escapes `text` so that it matches only itself when used in a regex pattern.
+///
+/// Special tokens such as `[PAD]` or `<|endoftext|>` contain characters with
+/// meaning in regex syntax. Escape them before embedding them in a pattern, e.g.
+/// one passed to `PreTokenizer::with_regex` (feature `regex`). The output is the same as that of
+/// `regex::escape` but available without the `regex` feature.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::escape_pattern;
+///
+/// assert_eq!(escape_pattern("<|endoftext|>"), r"<\|endoftext\|>");
+/// assert_eq!(escape_pattern("[PAD]"), r"\[PAD\]");
+/// ```
+pub fn escape_pattern(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        if matches!(
+            ch,
+            '\\' | '.'
+                | '+'
+                | '*'
+                | '?'
+                | '('
+                | ')'
+                | '|'
+                | '['
+                | ']'
+                | '{'
+                | '}'
+                | '^'
+                | '$'
+                | '#'
+                | '&'
+                | '-'
+                | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+
+    escaped
+}
+
 /// Pre-tokenizes text into chunks before BPE encoding.
 ///
 /// The pre-tokenizer splits text into words, punctuation, and whitespace chunks
-/// using a regex pattern compatible with GPT-2's tokenization strategy. This ensures
-/// that BPE merges don't cross logical word boundaries.
+/// following GPT-2's tokenization strategy. This ensures that BPE merges don't
+/// cross logical word boundaries.
 ///
 /// # Pattern
 ///
-/// The regex pattern matches:
+/// The splitting rules match:
 /// - Contractions: `'s`, `'t`, `'re`, `'ve`, `'m`, `'ll`, `'d`
 /// - Letters (with optional leading space): ` ?\p{L}+`
 /// - Numbers (with optional leading space): ` ?\p{N}+`
 /// - Punctuation (with optional leading space): ` ?[^\s\p{L}\p{N}]+`
 /// - Remaining whitespace: `\s+`
 ///
+/// The rules are applied by a hand-written scanner, which avoids the per-match
+/// overhead of the regex engine on short inputs. With the default `regex` feature,
+/// `PreTokenizer::with_regex` uses an arbitrary pattern instead, and
+/// `PreTokenizer::gpt2_regex` gives the regex formulation of the default rules.
+/// The public `pattern` field holds the regex the chunks follow; assigning another
+/// regex to it makes the pre-tokenizer split by that regex, as
+/// `PreTokenizer::with_regex` does.
+///
+/// OpenAI's original pattern and Hugging Face's `ByteLevel` pre-tokenizer also
+/// have `\s+(?!\S)` before the final `\s+`, which the `regex` crate cannot
+/// express. [`with_whitespace_lookahead`](PreTokenizer::with_whitespace_lookahead)
+/// makes the scanner follow it.
+///
+/// With the `serde` feature enabled, a `PreTokenizer` serializes to an object with
+/// the regex `pattern` (`null` for the built-in scanner, OpenAI's original pattern
+/// for the scanner with whitespace lookahead) and the `whitespace_runs` buckets (or
+/// `null`). Deserializing any other pattern needs the `regex` feature.
+///
 /// # Examples
 ///
 /// ```
@@ -25,8 +109,78 @@ use regex::Regex;
 ///
 /// assert_eq!(tokens, vec!["Hello", ",", " world", "!"]);
 /// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "PreTokenizerData", into = "PreTokenizerData")
+)]
 pub struct PreTokenizer {
+    /// The regex the chunks follow: the GPT-2 pattern for the built-in rules (also
+    /// with whitespace lookahead, which no `regex` pattern can express), or the
+    /// pattern passed to `with_regex`.
+    #[cfg(feature = "regex")]
     pub pattern: Regex,
+    /// Whether the built-in scanner applies the rules while `pattern` is still the
+    /// GPT-2 pattern it was created with.
+    #[cfg(feature = "regex")]
+    scanner: bool,
+    whitespace_buckets: Option<Vec<usize>>,
+    whitespace_lookahead: bool,
+}
+
+/// Serialized form of [`PreTokenizer`], with the regex as its source pattern.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PreTokenizerData {
+    pattern: Option<String>,
+    whitespace_runs: Option<Vec<usize>>,
+}
+
+#[cfg(feature = "serde")]
+impl From<PreTokenizer> for PreTokenizerData {
+    fn from(pre_tokenizer: PreTokenizer) -> Self {
+        #[cfg(feature = "regex")]
+        let pattern = pre_tokenizer
+            .custom_pattern()
+            .map(|pattern| pattern.as_str().to_string());
+        #[cfg(not(feature = "regex"))]
+        let pattern = None;
+
+        PreTokenizerData {
+            pattern: pattern.or_else(|| {
+                pre_tokenizer
+                    .whitespace_lookahead
+                    .then(|| GPT2_LOOKAHEAD_PATTERN.to_string())
+            }),
+            whitespace_runs: pre_tokenizer.whitespace_buckets,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<PreTokenizerData> for PreTokenizer {
+    type Error = String;
+
+    fn try_from(data: PreTokenizerData) -> Result<Self, Self::Error> {
+        let pre_tokenizer = match data.pattern {
+            Some(pattern) if pattern == GPT2_LOOKAHEAD_PATTERN => {
+                PreTokenizer::new().with_whitespace_lookahead(true)
+            }
+            #[cfg(feature = "regex")]
+            Some(pattern) => {
+                PreTokenizer::with_regex(Regex::new(&pattern).map_err(|err| err.to_string())?)
+            }
+            #[cfg(not(feature = "regex"))]
+            Some(_) => return Err("regex patterns need the `regex` feature".to_string()),
+            None => PreTokenizer::new(),
+        };
+
+        Ok(match data.whitespace_runs {
+            Some(buckets) => pre_tokenizer.with_whitespace_runs(&buckets),
+            None => pre_tokenizer,
+        })
+    }
 }
 
 impl Default for PreTokenizer {
@@ -36,7 +190,7 @@ impl Default for PreTokenizer {
 }
 
 impl PreTokenizer {
-    /// Creates a new pre-tokenizer with GPT-2 style regex pattern.
+    /// Creates a new pre-tokenizer with the GPT-2 splitting rules.
     ///
     /// # Examples
     ///
@@ -46,11 +200,164 @@ impl PreTokenizer {
     /// let pre_tokenizer = PreTokenizer::new();
     /// ```
     pub fn new() -> Self {
-        let pattern =
-            Regex::new(r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+")
-                .unwrap();
+        PreTokenizer {
+            #[cfg(feature = "regex")]
+            pattern: gpt2_pattern().clone(),
+            #[cfg(feature = "regex")]
+            scanner: true,
+            whitespace_buckets: None,
+            whitespace_lookahead: false,
+        }
+    }
+
+    /// Creates a pre-tokenizer that splits on the matches of `pattern`.
+    ///
+    /// Text between matches is dropped, exactly as with `Regex::find_iter`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::PreTokenizer;
+    /// use regex::Regex;
+    ///
+    /// let pre_tokenizer = PreTokenizer::with_regex(Regex::new(r"\S+").unwrap());
+    /// assert_eq!(pre_tokenizer.pre_tokenize("a bc"), vec!["a", "bc"]);
+    /// ```
+    #[cfg(feature = "regex")]
+    pub fn with_regex(pattern: Regex) -> Self {
+        PreTokenizer {
+            pattern,
+            scanner: false,
+            whitespace_buckets: None,
+            whitespace_lookahead: false,
+        }
+    }
+
+    /// Creates a pre-tokenizer that applies the GPT-2 rules through the regex engine.
+    ///
+    /// It produces the same chunks as [`PreTokenizer::new`] and serves as the
+    /// reference implementation in tests and benchmarks.
+    #[cfg(feature = "regex")]
+    pub fn gpt2_regex() -> Self {
+        Self::with_regex(gpt2_pattern().clone())
+    }
+
+    /// Makes the built-in rules split whitespace like OpenAI's GPT-2 encoder and
+    /// Hugging Face's `ByteLevel` pre-tokenizer.
+    ///
+    /// Their pattern tries `\s+(?!\S)` before `\s+`, so a run of several
+    /// whitespace characters followed by a word leaves its last character to prefix
+    /// that word. Without this mode the whole run is one chunk. Has no effect on a
+    /// pre-tokenizer created with `with_regex`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::PreTokenizer;
+    ///
+    /// let pre_tokenizer = PreTokenizer::new().with_whitespace_lookahead(true);
+    /// assert_eq!(pre_tokenizer.pre_tokenize("a   dog"), vec!["a", "  ", " dog"]);
+    ///
+    /// let pre_tokenizer = PreTokenizer::new();
+    /// assert_eq!(pre_tokenizer.pre_tokenize("a   dog"), vec!["a", "   ", "dog"]);
+    /// ```
+    pub fn with_whitespace_lookahead(mut self, enabled: bool) -> Self {
+        self.whitespace_lookahead = enabled;
+        self
+    }
+
+    /// Splits whitespace chunks into runs of a single character, for source code.
+    ///
+    /// By default a whitespace chunk such as `"\n\t\t  "` is one blob, so indentation
+    /// is learned mixed with newlines. In this mode every run of identical whitespace
+    /// characters becomes its own chunk, and runs longer than a bucket size are cut
+    /// greedily into bucket-sized pieces, largest first. With buckets `[8, 4]`, twelve
+    /// spaces become chunks of 8 and 4 spaces, letting training learn dedicated
+    /// indentation tokens. Chunks are only split, never altered, so concatenating them
+    /// still reproduces the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::PreTokenizer;
+    ///
+    /// let pre_tokenizer = PreTokenizer::new().with_whitespace_runs(&[4]);
+    /// let chunks = pre_tokenizer.pre_tokenize("{\n\t\t      x");
+    ///
+    /// assert_eq!(chunks, vec!["{", "\n", "\t\t", "    ", "  ", "x"]);
+    /// ```
+    pub fn with_whitespace_runs(mut self, buckets: &[usize]) -> Self {
+        let mut buckets: Vec<usize> = buckets.iter().copied().filter(|&b| b > 0).collect();
+        buckets.sort_unstable_by(|a, b| b.cmp(a));
+        buckets.dedup();
+        self.whitespace_buckets = Some(buckets);
+        self
+    }
+
+    /// Returns the regex used for splitting, or `None` for the built-in scanner.
+    #[cfg(feature = "regex")]
+    pub(crate) fn custom_pattern(&self) -> Option<&Regex> {
+        (!self.scanner || self.pattern.as_str() != GPT2_PATTERN).then_some(&self.pattern)
+    }
+
+    /// Returns whether the built-in rules split whitespace with
+    /// [`with_whitespace_lookahead`](PreTokenizer::with_whitespace_lookahead).
+    pub fn whitespace_lookahead(&self) -> bool {
+        self.whitespace_lookahead
+    }
+
+    /// Returns the whitespace run buckets set by
+    /// [`with_whitespace_runs`](PreTokenizer::with_whitespace_runs), largest first.
+    pub fn whitespace_runs(&self) -> Option<&[usize]> {
+        self.whitespace_buckets.as_deref()
+    }
+
+    /// Returns whether this splits exactly like OpenAI's GPT-2 encoder: the built-in
+    /// rules with whitespace lookahead and without whitespace runs.
+    pub(crate) fn is_gpt2(&self) -> bool {
+        #[cfg(feature = "regex")]
+        if self.custom_pattern().is_some() {
+            return false;
+        }
+
+        self.whitespace_lookahead && self.whitespace_buckets.is_none()
+    }
+
+    /// Describes the splitting rules in a stable, human-readable form.
+    pub(crate) fn describe(&self) -> String {
+        let scanner = if self.whitespace_lookahead {
+            "gpt2 whitespace_lookahead"
+        } else {
+            "gpt2"
+        };
+        #[cfg(feature = "regex")]
+        let mut description = match self.custom_pattern() {
+            Some(pattern) => format!("regex:{}", pattern.as_str()),
+            None => scanner.to_string(),
+        };
+        #[cfg(not(feature = "regex"))]
+        let mut description = scanner.to_string();
 
-        PreTokenizer { pattern }
+        if let Some(buckets) = &self.whitespace_buckets {
+            description.push_str(&format!(" whitespace_runs={:?}", buckets));
+        }
+
+        description
+    }
+
+    /// Returns warnings about the splitting configuration.
+    ///
+    /// The built-in GPT-2 rules never produce warnings. A custom regex that can match
+    /// the empty string yields [`Warning::EmptyPreTokenizerMatch`].
+    pub fn warnings(&self) -> Vec<Warning> {
+        #[cfg(feature = "regex")]
+        if let Some(pattern) = self.custom_pattern()
+            && pattern.find_iter("a 1 !\n").any(|m| m.is_empty())
+        {
+            return vec![Warning::EmptyPreTokenizerMatch];
+        }
+
+        Vec::new()
     }
 
     /// Pre-tokenizes text into chunks.
@@ -77,11 +384,96 @@ impl PreTokenizer {
     /// assert_eq!(tokens, vec!["I", "'m", " happy", "!"]);
     /// ```
     pub fn pre_tokenize(&self, text: &str) -> Vec<String> {
-        self.pattern
-            .find_iter(text)
-            .map(|m| m.as_str().to_string())
+        self.pre_tokenize_with_offsets(text)
+            .into_iter()
+            .map(|(_, chunk)| chunk.to_string())
             .collect()
     }
+
+    /// Pre-tokenizes text into chunks together with their byte offsets.
+    ///
+    /// Each item is `(start, chunk)`, where `start` is the byte offset of the chunk
+    /// in `text`. The chunks are the same as those returned by
+    /// [`pre_tokenize`](PreTokenizer::pre_tokenize).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::PreTokenizer;
+    ///
+    /// let pre_tokenizer = PreTokenizer::new();
+    /// let chunks = pre_tokenizer.pre_tokenize_with_offsets("Hi there");
+    ///
+    /// assert_eq!(chunks, vec![(0, "Hi"), (2, " there")]);
+    /// ```
+    pub fn pre_tokenize_with_offsets<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        let chunks: Vec<(usize, &str)> = self.split(text);
+
+        match &self.whitespace_buckets {
+            None => chunks,
+            Some(buckets) => chunks
+                .into_iter()
+                .flat_map(|(start, chunk)| split_whitespace_runs(start, chunk, buckets))
+                .collect(),
+        }
+    }
+
+    fn split<'a>(&self, text: &'a str) -> Vec<(usize, &'a str)> {
+        #[cfg(feature = "regex")]
+        if let Some(pattern) = self.custom_pattern() {
+            return pattern
+                .find_iter(text)
+                .map(|m| (m.start(), m.as_str()))
+                .collect();
+        }
+
+        Gpt2Splits::new(text, self.whitespace_lookahead).collect()
+    }
+}
+
+/// Returns the compiled GPT-2 pattern, shared by every pre-tokenizer.
+#[cfg(feature = "regex")]
+fn gpt2_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(GPT2_PATTERN).unwrap())
+}
+
+/// Splits an all-whitespace chunk into runs of one character, cutting each run into
+/// pieces of the given sizes (sorted in descending order). Other chunks are returned
+/// unchanged.
+fn split_whitespace_runs<'a>(
+    start: usize,
+    chunk: &'a str,
+    buckets: &[usize],
+) -> Vec<(usize, &'a str)> {
+    if !chunk.chars().all(char::is_whitespace) {
+        return vec![(start, chunk)];
+    }
+
+    let mut pieces = Vec::new();
+    let mut rest = chunk;
+    let mut offset = start;
+
+    while let Some(ch) = rest.chars().next() {
+        let run_len = rest.chars().take_while(|&c| c == ch).count();
+        let mut remaining = run_len;
+
+        while remaining > 0 {
+            let piece_len = buckets
+                .iter()
+                .copied()
+                .find(|&bucket| bucket <= remaining)
+                .unwrap_or(remaining);
+            let piece_bytes = piece_len * ch.len_utf8();
+
+            pieces.push((offset, &rest[..piece_bytes]));
+            rest = &rest[piece_bytes..];
+            offset += piece_bytes;
+            remaining -= piece_len;
+        }
+    }
+
+    pieces
 }
 
 #[cfg(test)]
@@ -128,6 +520,14 @@ mod tests {
         assert_eq!(result, vec!["Hello", "...", " What", "?!"]);
     }
 
+    #[test]
+    fn pre_tokenize_with_offsets_reports_byte_starts() {
+        let tokenizer = PreTokenizer::new();
+        let result = tokenizer.pre_tokenize_with_offsets("日本 is");
+
+        assert_eq!(result, vec![(0, "日本"), (6, " is")]);
+    }
+
     #[test]
     fn pre_tokenize_keeps_spaces_with_words() {
         let tokenizer = PreTokenizer::new();
@@ -135,4 +535,136 @@ mod tests {
 
         assert_eq!(result, vec!["Hello", " world"]);
     }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn scanner_matches_regex_oracle() {
+        let scanner = PreTokenizer::new();
+        let oracle = PreTokenizer::gpt2_regex();
+        let samples = [
+            "Hello, world! It's 2024 and we're testing.",
+            "don't  stop\n\n  believin'   ",
+            "  leading and trailing  ",
+            "'s'S 'll'd 'x'",
+            "日本語のテキスト、句読点。 Ünïcödé café naïve",
+            "١٢٣ ⅻ ½ x²  \t\r\n\u{a0}\u{3000}tab",
+            "emoji 👨‍👩‍👧 🇯🇵 and ZWJ\u{200d}",
+            "mixed123abc 456def!!! ???",
+            "controls \u{b}\u{c}\u{1c}\u{1f}\u{7f}\u{85} end",
+            "\u{301}combining e\u{301} marks",
+            "",
+            " ",
+        ];
+
+        for sample in samples {
+            assert_eq!(
+                scanner.pre_tokenize_with_offsets(sample),
+                oracle.pre_tokenize_with_offsets(sample),
+                "sample: {:?}",
+                sample
+            );
+        }
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn with_regex_uses_custom_pattern() {
+        let tokenizer = PreTokenizer::with_regex(Regex::new(r"\d+").unwrap());
+        let result = tokenizer.pre_tokenize_with_offsets("a1 22");
+
+        assert_eq!(result, vec![(1, "1"), (3, "22")]);
+        assert!(PreTokenizer::new().custom_pattern().is_none());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn assigning_the_pattern_field_switches_to_that_regex() {
+        let mut pre_tokenizer = PreTokenizer::new();
+        assert_eq!(pre_tokenizer.pattern.as_str(), GPT2_PATTERN);
+
+        pre_tokenizer.pattern = Regex::new(r"\S+").unwrap();
+
+        assert_eq!(
+            pre_tokenizer.pre_tokenize("it's a  dog"),
+            vec!["it's", "a", "dog"]
+        );
+        assert_eq!(pre_tokenizer.describe(), r"regex:\S+");
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn warns_about_patterns_matching_empty_string() {
+        let empty = PreTokenizer::with_regex(Regex::new(r"\d*").unwrap());
+
+        assert_eq!(empty.warnings(), vec![Warning::EmptyPreTokenizerMatch]);
+        assert!(PreTokenizer::gpt2_regex().warnings().is_empty());
+        assert!(PreTokenizer::new().warnings().is_empty());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn escape_pattern_matches_regex_escape() {
+        for text in [
+            "<|endoftext|>",
+            "[PAD]",
+            r"a.b*c+d?\e",
+            "(x){2}^$#&-~",
+            "日本 ok",
+        ] {
+            assert_eq!(escape_pattern(text), regex::escape(text));
+
+            let pattern = Regex::new(&format!("^{}$", escape_pattern(text))).unwrap();
+            assert!(pattern.is_match(text));
+        }
+    }
+
+    #[test]
+    fn whitespace_runs_are_split_by_character() {
+        let tokenizer = PreTokenizer::new().with_whitespace_runs(&[]);
+        let result = tokenizer.pre_tokenize("a\n\n\t  \tb");
+
+        assert_eq!(result, vec!["a", "\n\n", "\t", "  ", "\t", "b"]);
+    }
+
+    #[test]
+    fn whitespace_runs_are_bucketed_largest_first() {
+        let tokenizer = PreTokenizer::new().with_whitespace_runs(&[4, 8]);
+        let text = format!("x\n{}", " ".repeat(14));
+
+        let result = tokenizer.pre_tokenize_with_offsets(&text);
+
+        assert_eq!(
+            result,
+            vec![
+                (0, "x"),
+                (1, "\n"),
+                (2, "        "),
+                (10, "    "),
+                (14, "  ")
+            ]
+        );
+    }
+
+    #[test]
+    fn whitespace_runs_preserve_text() {
+        let tokenizer = PreTokenizer::new().with_whitespace_runs(&[4]);
+        let text = "fn main() {\r\n\t    let x = 1;\n\n        \u{3000}\u{3000}y\n}";
+
+        let joined: String = tokenizer.pre_tokenize(text).concat();
+
+        assert_eq!(joined, text);
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn whitespace_lookahead_serializes_as_the_original_pattern() {
+        let tokenizer = PreTokenizer::new().with_whitespace_lookahead(true);
+
+        let json = serde_json::to_string(&tokenizer).unwrap();
+        let loaded: PreTokenizer = serde_json::from_str(&json).unwrap();
+
+        assert!(json.contains(r"\\s+(?!\\S)"), "{}", json);
+        assert!(loaded.whitespace_lookahead());
+        assert_eq!(loaded.pre_tokenize("a  b"), vec!["a", " ", " b"]);
+    }
 }
@@ -0,0 +1,203 @@
+use crate::{BpeTokenizer, EncodeError};
+
+/// What [`PromptBuilder`] does with a segment that does not fit the remaining
+/// budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Truncation {
+    /// Leave the segment out entirely.
+    #[default]
+    Drop,
+    /// Keep as many tokens from the start as fit, e.g. for a long document.
+    KeepStart,
+    /// Keep as many tokens from the end as fit, e.g. for conversation history
+    /// where the latest turns matter most.
+    KeepEnd,
+}
+
+/// A piece of text to place in a prompt built by [`PromptBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    text: String,
+    priority: u32,
+    truncation: Truncation,
+}
+
+impl Segment {
+    /// Creates a segment; segments with a higher `priority` claim the budget first.
+    pub fn new(text: impl Into<String>, priority: u32) -> Self {
+        Segment {
+            text: text.into(),
+            priority,
+            truncation: Truncation::default(),
+        }
+    }
+
+    /// Sets what happens when the segment does not fit. Defaults to
+    /// [`Truncation::Drop`].
+    pub fn with_truncation(mut self, truncation: Truncation) -> Self {
+        self.truncation = truncation;
+        self
+    }
+}
+
+/// How much of a segment made it into a [`Prompt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentFit {
+    /// The whole segment, encoded as `tokens` tokens.
+    Kept { tokens: usize },
+    /// `kept` of the segment's `total` tokens.
+    Truncated { kept: usize, total: usize },
+    /// Nothing of the segment.
+    Dropped,
+}
+
+/// Token IDs assembled by [`PromptBuilder::build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Prompt {
+    /// IDs of the kept segments, in the order the segments were added.
+    pub ids: Vec<u32>,
+    /// What happened to each segment, in the order the segments were added.
+    pub segments: Vec<SegmentFit>,
+}
+
+/// Assembles a prompt from prioritized segments within a token budget.
+///
+/// Segments claim the budget in order of priority, ties going to the segment added
+/// first. A segment that does not fit whole is dropped or truncated according to
+/// its [`Truncation`]; a later, shorter segment may still fit after a drop. Segments
+/// are encoded one at a time when the prompt is built, and once the budget is used
+/// up the remaining segments are not encoded at all.
+///
+/// Each segment is encoded on its own, so no token spans two segments, as in chat
+/// templates that place each turn between special tokens. Truncation cuts on a
+/// token boundary, which may fall inside a multi-byte character.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, PromptBuilder, Segment, SegmentFit, Truncation};
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// let prompt = PromptBuilder::new(&tokenizer, 15)
+///     .with_segment(Segment::new("Be brief.", 3))
+///     .with_segment(Segment::new("old turn, newer turn", 1).with_truncation(Truncation::KeepEnd))
+///     .with_segment(Segment::new("Hi?", 2))
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(prompt.ids.len(), 15);
+/// assert_eq!(tokenizer.decode(&prompt.ids), "Be brief.urnHi?");
+/// assert_eq!(prompt.segments[1], SegmentFit::Truncated { kept: 3, total: 20 });
+/// ```
+pub struct PromptBuilder<'a> {
+    tokenizer: &'a BpeTokenizer,
+    budget: usize,
+    segments: Vec<Segment>,
+}
+
+impl<'a> PromptBuilder<'a> {
+    /// Creates a builder that fits segments into `budget` tokens of `tokenizer`.
+    pub fn new(tokenizer: &'a BpeTokenizer, budget: usize) -> Self {
+        PromptBuilder {
+            tokenizer,
+            budget,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Adds a segment after the ones added so far.
+    pub fn with_segment(mut self, segment: Segment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    /// Encodes the segments that fit and concatenates their IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] if a segment produces a token missing from the
+    /// vocabulary.
+    pub fn build(&self) -> Result<Prompt, EncodeError> {
+        let mut order: Vec<usize> = (0..self.segments.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse(self.segments[index].priority));
+
+        let mut remaining = self.budget;
+        let mut encoded = vec![Vec::new(); self.segments.len()];
+        let mut fits = vec![SegmentFit::Dropped; self.segments.len()];
+        for index in order {
+            if remaining == 0 {
+                break;
+            }
+            let segment = &self.segments[index];
+            let ids = self.tokenizer.try_encode(&segment.text)?;
+            let total = ids.len();
+
+            let kept = if total <= remaining {
+                fits[index] = SegmentFit::Kept { tokens: total };
+                ids
+            } else {
+                let kept = match segment.truncation {
+                    Truncation::Drop => continue,
+                    Truncation::KeepStart => ids[..remaining].to_vec(),
+                    Truncation::KeepEnd => ids[total - remaining..].to_vec(),
+                };
+                fits[index] = SegmentFit::Truncated {
+                    kept: remaining,
+                    total,
+                };
+                kept
+            };
+            remaining -= kept.len();
+            encoded[index] = kept;
+        }
+
+        Ok(Prompt {
+            ids: encoded.concat(),
+            segments: fits,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_whole_segments_and_lets_shorter_ones_fit() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let prompt = PromptBuilder::new(&tokenizer, 5)
+            .with_segment(Segment::new("abc", 2))
+            .with_segment(Segment::new("too long", 1))
+            .with_segment(Segment::new("de", 1))
+            .with_segment(Segment::new("never encoded", 0))
+            .build()
+            .unwrap();
+
+        assert_eq!(tokenizer.decode(&prompt.ids), "abcde");
+        assert_eq!(
+            prompt.segments,
+            vec![
+                SegmentFit::Kept { tokens: 3 },
+                SegmentFit::Dropped,
+                SegmentFit::Kept { tokens: 2 },
+                SegmentFit::Dropped,
+            ]
+        );
+    }
+
+    #[test]
+    fn truncates_from_the_chosen_side() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let build = |truncation| {
+            let prompt = PromptBuilder::new(&tokenizer, 3)
+                .with_segment(Segment::new("abcdef", 0).with_truncation(truncation))
+                .build()
+                .unwrap();
+            tokenizer.decode(&prompt.ids)
+        };
+
+        assert_eq!(build(Truncation::KeepStart), "abc");
+        assert_eq!(build(Truncation::KeepEnd), "def");
+        assert_eq!(build(Truncation::Drop), "");
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+use std::thread;
+
+use crate::BpeTokenizer;
+
+/// Named tokenizers of several models, for comparing what the same text costs
+/// under each of them.
+///
+/// A router service that picks a backend partly by prompt size can keep one
+/// registry of all backend tokenizers and call [`count_all`](TokenizerRegistry::count_all)
+/// per request.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, TokenizerRegistry};
+///
+/// let merges = vec![("h".to_string(), "i".to_string())];
+/// let registry = TokenizerRegistry::new()
+///     .with_tokenizer("bytes", BpeTokenizer::new(vec![], vec![]))
+///     .with_tokenizer("merged", BpeTokenizer::new(merges, vec![]));
+///
+/// let counts = registry.count_all("hi hi");
+/// assert_eq!(counts["bytes"], 5);
+/// assert_eq!(counts["merged"], 3);
+/// ```
+#[derive(Default)]
+pub struct TokenizerRegistry {
+    tokenizers: Vec<(String, BpeTokenizer)>,
+}
+
+impl TokenizerRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `tokenizer` under `name`, replacing any tokenizer already registered
+    /// with that name.
+    pub fn with_tokenizer(mut self, name: impl Into<String>, tokenizer: BpeTokenizer) -> Self {
+        let name = name.into();
+        match self.tokenizers.iter_mut().find(|(other, _)| *other == name) {
+            Some((_, existing)) => *existing = tokenizer,
+            None => self.tokenizers.push((name, tokenizer)),
+        }
+        self
+    }
+
+    /// Returns the tokenizer registered under `name`.
+    pub fn get(&self, name: &str) -> Option<&BpeTokenizer> {
+        self.tokenizers
+            .iter()
+            .find(|(other, _)| other == name)
+            .map(|(_, tokenizer)| tokenizer)
+    }
+
+    /// Returns the registered names, in the order they were first added.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.tokenizers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the number of registered tokenizers.
+    pub fn len(&self) -> usize {
+        self.tokenizers.len()
+    }
+
+    /// Returns true if no tokenizer is registered.
+    pub fn is_empty(&self) -> bool {
+        self.tokenizers.is_empty()
+    }
+
+    /// Counts the tokens of `text` under every registered tokenizer, keyed and
+    /// ordered by name.
+    ///
+    /// Each tokenizer encodes the text on its own thread, so the call takes about
+    /// as long as the slowest tokenizer rather than the sum of all of them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a tokenizer panics while encoding, see [`BpeTokenizer::encode`].
+    pub fn count_all(&self, text: &str) -> BTreeMap<String, usize> {
+        if let [(name, tokenizer)] = self.tokenizers.as_slice() {
+            return BTreeMap::from([(name.clone(), tokenizer.encode(text).len())]);
+        }
+
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .tokenizers
+                .iter()
+                .map(|(name, tokenizer)| (name, scope.spawn(move || tokenizer.encode(text).len())))
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|(name, handle)| {
+                    let count = handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    (name.clone(), count)
+                })
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Trainer;
+
+    #[test]
+    fn count_all_matches_each_tokenizer() {
+        let corpus = ["the cat sat on the mat", "the end"];
+        let trained = BpeTokenizer::from_trainer(&Trainer::new(20), &corpus, vec![]);
+        let registry = TokenizerRegistry::new()
+            .with_tokenizer("bytes", BpeTokenizer::new(vec![], vec![]))
+            .with_tokenizer("trained", trained)
+            .with_tokenizer("single", BpeTokenizer::new(vec![], vec!["<s>".into()]));
+        let text = "the cat<s>";
+
+        let counts = registry.count_all(text);
+
+        assert_eq!(counts.len(), 3);
+        for name in registry.names() {
+            let expected = registry.get(name).unwrap().encode(text).len();
+            assert_eq!(counts[name], expected, "{}", name);
+        }
+        assert!(counts["trained"] < counts["bytes"]);
+        assert_eq!(
+            counts.keys().collect::<Vec<_>>(),
+            ["bytes", "single", "trained"]
+        );
+        assert!(TokenizerRegistry::new().count_all(text).is_empty());
+    }
+
+    #[test]
+    fn registering_a_name_again_replaces_the_tokenizer() {
+        let registry = TokenizerRegistry::new()
+            .with_tokenizer("model", BpeTokenizer::new(vec![], vec![]))
+            .with_tokenizer("model", BpeTokenizer::new(vec![], vec!["<s>".into()]));
+
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.count_all("<s>")["model"], 1);
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["model"]);
+    }
+}
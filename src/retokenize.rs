@@ -0,0 +1,165 @@
+use std::ops::Range;
+
+use crate::{EncodeError, Encoder, Encoding};
+
+/// The tokens that change when a document is edited, as returned by
+/// [`BpeTokenizer::retokenize`](crate::BpeTokenizer::retokenize).
+///
+/// Splicing `ids` into `range` of the old IDs gives the encoding of the new text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenEdit {
+    /// Indices of the old tokens that no longer appear in the new encoding.
+    pub range: Range<usize>,
+    /// Token IDs that take their place.
+    pub ids: Vec<u32>,
+}
+
+impl TokenEdit {
+    /// Replaces the changed tokens of the old encoding's `ids` with the new ones.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds for `ids`.
+    pub fn apply(&self, ids: &mut Vec<u32>) {
+        ids.splice(self.range.clone(), self.ids.iter().copied());
+    }
+
+    /// Returns how many tokens the edit added, negative if it removed tokens.
+    pub fn token_delta(&self) -> isize {
+        self.ids.len() as isize - self.range.len() as isize
+    }
+
+    /// Returns whether the edit leaves the encoding unchanged.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty() && self.ids.is_empty()
+    }
+}
+
+/// Finds the tokens of `old` that change when `old_text` becomes `new_text`.
+///
+/// Special tokens and pre-tokenized words are encoded independently, so only the
+/// words that differ between the two texts are merged again; the unchanged words
+/// on either side keep their tokens. The result is then trimmed to the IDs that
+/// actually differ.
+pub(crate) fn diff(
+    encoder: &Encoder,
+    old_text: &str,
+    old: &Encoding,
+    new_text: &str,
+) -> Result<TokenEdit, EncodeError> {
+    let old_text = encoder.normalizer().normalize(old_text);
+    let new_text = encoder.normalizer().normalize(new_text);
+    let old_chunks = encoder.chunks(&old_text);
+    let new_chunks = encoder.chunks(&new_text);
+
+    let prefix = old_chunks
+        .iter()
+        .zip(&new_chunks)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let max_suffix = old_chunks.len().min(new_chunks.len()) - prefix;
+    let suffix = old_chunks
+        .iter()
+        .rev()
+        .zip(new_chunks.iter().rev())
+        .take(max_suffix)
+        .take_while(
+            |((old_start, old_chunk, old_special), (new_start, new_chunk, new_special))| {
+                old_chunk == new_chunk
+                    && old_special == new_special
+                    && old_text.len() - old_start == new_text.len() - new_start
+            },
+        )
+        .count();
+
+    let byte_start = |chunks: &[(usize, String, bool)], index: usize, len: usize| {
+        chunks.get(index).map_or(len, |(start, _, _)| *start)
+    };
+    let old_start = byte_start(&old_chunks, prefix, old_text.len());
+    let old_end = byte_start(&old_chunks, old_chunks.len() - suffix, old_text.len());
+    let offsets = old.offsets();
+    let mut start = offsets.partition_point(|&(offset, _)| offset < old_start);
+    let mut end = offsets.partition_point(|&(offset, _)| offset < old_end);
+
+    let mut ids = Vec::new();
+    for (_, chunk, is_special) in &new_chunks[prefix..new_chunks.len() - suffix] {
+        ids.extend(encoder.chunk_ids(chunk, *is_special)?);
+    }
+
+    let old_ids = old.ids();
+    let same_start = old_ids[start..end]
+        .iter()
+        .zip(&ids)
+        .take_while(|(old, new)| old == new)
+        .count();
+    start += same_start;
+    ids.drain(..same_start);
+    let same_end = old_ids[start..end]
+        .iter()
+        .rev()
+        .zip(ids.iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    end -= same_end;
+    ids.truncate(ids.len() - same_end);
+
+    Ok(TokenEdit {
+        range: start..end,
+        ids,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BpeTokenizer;
+    use crate::test_support;
+
+    fn trained() -> BpeTokenizer {
+        test_support::trained(&["the cat sat on the mat", "the cats   sat\n\nthere"], 30)
+    }
+
+    #[test]
+    fn edits_reproduce_the_full_encoding() {
+        let tokenizer = trained();
+        let old_text = "the cat sat<|endoftext|>on the   mat\n\nthere";
+        let edited = [
+            "the cats sat<|endoftext|>on the   mat\n\nthere",
+            "the cat sat<|endoftext|>on the  mat\n\nthere",
+            "xthe cat sat<|endoftext|>on the   mat\n\nthere",
+            "the cat sat<|endoftext|>on the   mat\n\nthere!",
+            "the cat sat<|endoftext|on the   mat\n\nthere",
+            "the cat sat on the mat",
+            "",
+            old_text,
+        ];
+        let old = tokenizer.encode_with_offsets(old_text);
+
+        for new_text in edited {
+            let edit = tokenizer.retokenize(old_text, &old, new_text).unwrap();
+            let mut ids = old.ids().to_vec();
+            edit.apply(&mut ids);
+
+            assert_eq!(ids, tokenizer.encode(new_text), "{:?}", new_text);
+            assert_eq!(old.len() as isize + edit.token_delta(), ids.len() as isize);
+        }
+    }
+
+    #[test]
+    fn edit_covers_only_the_changed_tokens() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let old = tokenizer.encode_with_offsets("one two three");
+
+        let edit = tokenizer
+            .retokenize("one two three", &old, "one twos three")
+            .unwrap();
+
+        assert_eq!(edit.range, 7..7);
+        assert_eq!(edit.ids, tokenizer.encode("s"));
+        assert!(
+            tokenizer
+                .retokenize("one two three", &old, "one two three")
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
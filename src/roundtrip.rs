@@ -0,0 +1,163 @@
+use std::fmt;
+
+use crate::{BpeTokenizer, TokenizerError};
+
+/// Number of characters shown on each side of a difference by [`Mismatch`]'s
+/// `Display` implementation.
+const CONTEXT_CHARS: usize = 20;
+
+/// A text that did not survive an encode → decode round trip, as reported by
+/// [`verify_roundtrip`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the text in the checked slice.
+    pub index: usize,
+    /// Byte offset in the original text of the first difference (0 if the round trip
+    /// failed outright).
+    pub position: usize,
+    /// The original text.
+    pub expected: String,
+    /// The decoded text, or the error that stopped encoding or decoding.
+    pub actual: Result<String, TokenizerError>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.actual {
+            Ok(actual) => write!(
+                f,
+                "text {} differs at byte {}: expected {:?}, got {:?}",
+                self.index,
+                self.position,
+                excerpt(&self.expected, self.position),
+                excerpt(actual, self.position),
+            ),
+            Err(err) => write!(f, "text {} failed to round-trip: {}", self.index, err),
+        }
+    }
+}
+
+/// Returns up to [`CONTEXT_CHARS`] characters on each side of `position`, which is
+/// clamped to the nearest character boundary at or before it.
+fn excerpt(text: &str, position: usize) -> &str {
+    let mut position = position.min(text.len());
+    while !text.is_char_boundary(position) {
+        position -= 1;
+    }
+
+    let start = text[..position]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(0, |(index, _)| index);
+    let end = text[position..]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(text.len(), |(index, _)| position + index);
+
+    &text[start..end]
+}
+
+/// Returns the byte offset of the first character where `a` and `b` differ, or
+/// `None` if they are equal.
+fn first_difference(a: &str, b: &str) -> Option<usize> {
+    if a == b {
+        return None;
+    }
+
+    let common = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, x), y)| x != y)
+        .map(|((index, _), _)| index);
+
+    Some(common.unwrap_or_else(|| a.len().min(b.len())))
+}
+
+/// Encodes and decodes every text and reports each one that does not come back
+/// unchanged.
+///
+/// A byte-level tokenizer without a normalizer round-trips any text, so an empty
+/// result is the expected outcome; a non-empty one points to a broken artifact (such
+/// as a vocabulary out of sync with its merges) or a lossy configuration (such as a
+/// [`Normalizer`](crate::Normalizer) that rewrites the input). Run it over a sample of
+/// your own data before rolling out a new tokenizer.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, NewlineMode, Normalizer, verify_roundtrip};
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// assert!(verify_roundtrip(&tokenizer, &["hello", "naïve 🦀"]).is_empty());
+///
+/// let lossy = BpeTokenizer::new(vec![], vec![])
+///     .with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+/// let mismatches = verify_roundtrip(&lossy, &["fine", "a\r\nb"]);
+///
+/// assert_eq!(mismatches.len(), 1);
+/// assert_eq!((mismatches[0].index, mismatches[0].position), (1, 1));
+/// ```
+pub fn verify_roundtrip(tokenizer: &BpeTokenizer, texts: &[&str]) -> Vec<Mismatch> {
+    texts
+        .iter()
+        .zip(tokenizer.try_encode_batch(texts))
+        .enumerate()
+        .filter_map(|(index, (text, encoded))| {
+            let actual =
+                encoded.and_then(|ids| tokenizer.try_decode(&ids).map_err(TokenizerError::from));
+            let position = match &actual {
+                Ok(decoded) => first_difference(text, decoded)?,
+                Err(_) => 0,
+            };
+
+            Some(Mismatch {
+                index,
+                position,
+                expected: text.to_string(),
+                actual,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ControlChars, Normalizer};
+
+    #[test]
+    fn first_difference_reports_char_boundaries() {
+        assert_eq!(first_difference("abc", "abc"), None);
+        assert_eq!(first_difference("héllo", "hèllo"), Some(1));
+        assert_eq!(first_difference("ab", "abc"), Some(2));
+        assert_eq!(first_difference("abc", "ab"), Some(2));
+    }
+
+    #[test]
+    fn excerpt_shows_context_around_position() {
+        let text = "a".repeat(30) + "X" + &"b".repeat(30);
+
+        let shown = excerpt(&text, 30);
+
+        assert_eq!(shown, "a".repeat(20) + "X" + &"b".repeat(19));
+        assert_eq!(excerpt("é", 1), "é");
+    }
+
+    #[test]
+    fn reports_lossy_normalization() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![])
+            .with_normalizer(Normalizer::new().with_control_chars(ControlChars::Strip));
+
+        let mismatches = verify_roundtrip(&tokenizer, &["ok", "bell\u{7}!"]);
+
+        assert_eq!(mismatches.len(), 1);
+        let mismatch = &mismatches[0];
+        assert_eq!((mismatch.index, mismatch.position), (1, 4));
+        assert_eq!(mismatch.actual, Ok("bell!".to_string()));
+        assert_eq!(
+            mismatch.to_string(),
+            "text 1 differs at byte 4: expected \"bell\\u{7}!\", got \"bell!\""
+        );
+    }
+}
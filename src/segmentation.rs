@@ -0,0 +1,183 @@
+/// How a training file is cut into the texts the [`Trainer`](crate::Trainer) sees.
+///
+/// Training never merges across text boundaries, so the choice matters: one line per
+/// text suits sentence-per-line corpora, while code loses its indentation structure
+/// when split that way and is better served by paragraphs or byte windows.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::Segmentation;
+///
+/// let text = "fn main() {\n    run();\n}\n\nfn run() {}\n";
+///
+/// assert_eq!(Segmentation::Lines.segments(text).len(), 4);
+/// assert_eq!(
+///     Segmentation::Paragraphs.segments(text),
+///     vec!["fn main() {\n    run();\n}", "fn run() {}"]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Segmentation {
+    /// One text per line, without the line terminator. Blank lines are skipped.
+    #[default]
+    Lines,
+    /// One text per run of lines separated by blank lines. Leading and trailing line
+    /// breaks are dropped; line breaks inside a paragraph are kept.
+    Paragraphs,
+    /// Consecutive windows of at most `bytes` bytes covering the whole input.
+    ///
+    /// Each window ends just before the last whitespace character that fits, so a
+    /// word and its leading space stay in the same text, as the GPT-2 pre-tokenizer
+    /// expects. A window with no whitespace is cut at the last character boundary
+    /// that fits; a single character longer than `bytes` becomes its own window.
+    Window { bytes: usize },
+    /// The whole input as a single text.
+    Whole,
+}
+
+impl Segmentation {
+    /// Cuts `text` into training texts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `self` is [`Window`](Segmentation::Window) with `bytes` set to 0.
+    pub fn segments<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        match *self {
+            Segmentation::Lines => text.lines().filter(|line| !is_blank(line)).collect(),
+            Segmentation::Paragraphs => paragraphs(text),
+            Segmentation::Window { bytes } => {
+                assert!(bytes > 0, "window size must be at least one byte");
+                windows(text, bytes)
+            }
+            Segmentation::Whole if text.is_empty() => Vec::new(),
+            Segmentation::Whole => vec![text],
+        }
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+fn paragraphs(text: &str) -> Vec<&str> {
+    let mut paragraphs = Vec::new();
+    let mut start = None;
+    let mut end = 0;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if is_blank(content) {
+            if let Some(begin) = start.take() {
+                paragraphs.push(&text[begin..end]);
+            }
+        } else {
+            start.get_or_insert(offset);
+            end = offset + content.len();
+        }
+        offset += line.len();
+    }
+    if let Some(begin) = start {
+        paragraphs.push(&text[begin..end]);
+    }
+
+    paragraphs
+}
+
+fn windows(text: &str, bytes: usize) -> Vec<&str> {
+    let mut windows = Vec::new();
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let cut = window_end(rest, bytes);
+        windows.push(&rest[..cut]);
+        rest = &rest[cut..];
+    }
+
+    windows
+}
+
+fn window_end(text: &str, bytes: usize) -> usize {
+    if text.len() <= bytes {
+        return text.len();
+    }
+
+    let last_whitespace = text
+        .char_indices()
+        .skip(1)
+        .take_while(|&(index, _)| index <= bytes)
+        .filter(|(_, c)| c.is_whitespace())
+        .map(|(index, _)| index)
+        .last();
+    if let Some(index) = last_whitespace {
+        return index;
+    }
+
+    let mut boundary = bytes;
+    while !text.is_char_boundary(boundary) {
+        boundary -= 1;
+    }
+    if boundary == 0 {
+        text.chars().next().map_or(0, char::len_utf8)
+    } else {
+        boundary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_skip_blank_lines_and_terminators() {
+        assert_eq!(
+            Segmentation::Lines.segments("a\r\n\n  \nb\n"),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn paragraphs_keep_inner_line_breaks() {
+        let text = "\n\none\ntwo\r\n\r\n\r\nthree\n";
+
+        assert_eq!(
+            Segmentation::Paragraphs.segments(text),
+            vec!["one\ntwo", "three"]
+        );
+    }
+
+    #[test]
+    fn windows_snap_before_whitespace() {
+        let segments = Segmentation::Window { bytes: 8 }.segments("hello big world");
+
+        assert_eq!(segments, vec!["hello", " big", " world"]);
+        assert_eq!(segments.concat(), "hello big world");
+    }
+
+    #[test]
+    fn windows_without_whitespace_respect_char_boundaries() {
+        let segments = Segmentation::Window { bytes: 3 }.segments("ééé");
+
+        assert_eq!(segments, vec!["é", "é", "é"]);
+    }
+
+    #[test]
+    fn oversized_character_becomes_its_own_window() {
+        let segments = Segmentation::Window { bytes: 1 }.segments("a😀b");
+
+        assert_eq!(segments, vec!["a", "😀", "b"]);
+    }
+
+    #[test]
+    fn whole_returns_input_unless_empty() {
+        assert_eq!(Segmentation::Whole.segments("a\n\nb"), vec!["a\n\nb"]);
+        assert!(Segmentation::Whole.segments("").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "window size must be at least one byte")]
+    fn zero_byte_window_panics() {
+        Segmentation::Window { bytes: 0 }.segments("text");
+    }
+}
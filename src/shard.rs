@@ -0,0 +1,431 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of every shard index file.
+pub const SHARD_INDEX_MAGIC: [u8; 8] = *b"BPEIDX01";
+
+/// Document offsets for a token shard, allowing random access to any document.
+///
+/// A shard is a flat file of little-endian `u32` token ids. Its index stores, for
+/// each document, the token offset where it starts, plus the total token count, so
+/// document `k` spans tokens `offsets[k]..offsets[k + 1]`.
+///
+/// The index file is [`SHARD_INDEX_MAGIC`], the document count as a little-endian
+/// `u64`, then `count + 1` little-endian `u64` offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardIndex {
+    offsets: Vec<u64>,
+}
+
+impl ShardIndex {
+    /// Creates an index from document lengths in tokens.
+    pub fn from_lengths(lengths: impl IntoIterator<Item = usize>) -> Self {
+        let mut offsets = vec![0];
+        for length in lengths {
+            let start = *offsets.last().expect("offsets start with zero");
+            offsets.push(start + length as u64);
+        }
+
+        ShardIndex { offsets }
+    }
+
+    /// Creates an index for a packed dataset written by
+    /// [`TokenWriter`](crate::TokenWriter): `tokens` tokens cut into sequences of
+    /// `sequence_length`, with a shorter final sequence if they do not divide evenly.
+    ///
+    /// Each sequence then counts as one document, giving random access to sequences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::ShardIndex;
+    ///
+    /// let index = ShardIndex::packed(10, 4);
+    /// assert_eq!(index.len(), 3);
+    /// assert_eq!(index.range(2), Some(8..10));
+    /// ```
+    pub fn packed(tokens: u64, sequence_length: usize) -> Self {
+        assert!(sequence_length > 0, "sequence length must be positive");
+
+        let length = sequence_length as u64;
+        let mut offsets: Vec<u64> = (0..tokens.div_ceil(length)).map(|k| k * length).collect();
+        offsets.push(tokens);
+
+        ShardIndex { offsets }
+    }
+
+    /// Returns the number of documents.
+    pub fn len(&self) -> usize {
+        self.offsets.len() - 1
+    }
+
+    /// Returns `true` if the shard has no documents.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total number of tokens in the shard.
+    pub fn tokens(&self) -> u64 {
+        *self.offsets.last().expect("offsets start with zero")
+    }
+
+    /// Returns the token range of document `k`, or `None` if out of bounds.
+    pub fn range(&self, k: usize) -> Option<std::ops::Range<u64>> {
+        Some(*self.offsets.get(k)?..*self.offsets.get(k + 1)?)
+    }
+
+    /// Serializes the index in the on-disk format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.offsets.len() * 8);
+        bytes.extend_from_slice(&SHARD_INDEX_MAGIC);
+        bytes.extend_from_slice(&(self.len() as u64).to_le_bytes());
+        for offset in &self.offsets {
+            bytes.extend_from_slice(&offset.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Parses an index in the on-disk format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::InvalidData`] if the magic bytes are wrong, the length
+    /// does not match the document count, or the offsets are not ascending from zero.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < 16 || bytes[..8] != SHARD_INDEX_MAGIC {
+            return Err(invalid_index("missing shard index header"));
+        }
+        let count = u64::from_le_bytes(bytes[8..16].try_into().expect("eight bytes"));
+        let body = &bytes[16..];
+        if count.checked_add(1).and_then(|n| n.checked_mul(8)) != Some(body.len() as u64) {
+            return Err(invalid_index("length does not match document count"));
+        }
+
+        let offsets: Vec<u64> = body
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("eight bytes")))
+            .collect();
+        if offsets[0] != 0 || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(invalid_index("offsets are not ascending from zero"));
+        }
+
+        Ok(ShardIndex { offsets })
+    }
+
+    /// Reads an index file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is malformed.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        ShardIndex::from_bytes(&std::fs::read(path)?)
+    }
+}
+
+fn invalid_index(detail: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid shard index: {}", detail),
+    )
+}
+
+/// Returns the index path for a shard: the shard path with an `.idx` extension.
+pub fn shard_index_path(shard: impl AsRef<Path>) -> PathBuf {
+    shard.as_ref().with_extension("idx")
+}
+
+/// Writes documents to a token shard while building its [`ShardIndex`].
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{ShardReader, ShardWriter};
+/// use std::io::Cursor;
+///
+/// let mut writer = ShardWriter::new(Vec::new());
+/// writer.write_document(&[1, 2, 3]).unwrap();
+/// writer.write_document(&[4]).unwrap();
+/// let (bytes, index) = writer.finish().unwrap();
+///
+/// let mut reader = ShardReader::new(Cursor::new(bytes), index);
+/// assert_eq!(reader.len(), 2);
+/// assert_eq!(reader.document(1).unwrap(), vec![4]);
+/// ```
+#[derive(Debug)]
+pub struct ShardWriter<W: Write> {
+    writer: W,
+    lengths: Vec<usize>,
+}
+
+impl<W: Write> ShardWriter<W> {
+    /// Creates a writer appending tokens to `writer`.
+    pub fn new(writer: W) -> Self {
+        ShardWriter {
+            writer,
+            lengths: Vec::new(),
+        }
+    }
+
+    /// Appends one document.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn write_document(&mut self, ids: &[u32]) -> io::Result<()> {
+        for id in ids {
+            self.writer.write_all(&id.to_le_bytes())?;
+        }
+        self.lengths.push(ids.len());
+
+        Ok(())
+    }
+
+    /// Returns the number of documents written so far.
+    pub fn documents_written(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// Flushes the underlying writer and returns it with the finished index.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn finish(mut self) -> io::Result<(W, ShardIndex)> {
+        self.writer.flush()?;
+
+        Ok((self.writer, ShardIndex::from_lengths(self.lengths)))
+    }
+}
+
+impl ShardWriter<BufWriter<File>> {
+    /// Creates a shard file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(ShardWriter::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Finishes the shard and writes its index next to it, at
+    /// [`shard_index_path`]`(path)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file cannot be written.
+    pub fn finish_with_index(self, path: impl AsRef<Path>) -> io::Result<ShardIndex> {
+        let (writer, index) = self.finish()?;
+        writer
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        std::fs::write(shard_index_path(path), index.to_bytes())?;
+
+        Ok(index)
+    }
+}
+
+/// Reads individual documents from a token shard using its [`ShardIndex`].
+///
+/// Reading document `k` seeks straight to it, so a dataloader can fetch documents
+/// in any order without scanning the shard.
+#[derive(Debug)]
+pub struct ShardReader<R: Read + Seek> {
+    reader: R,
+    index: ShardIndex,
+}
+
+impl<R: Read + Seek> ShardReader<R> {
+    /// Creates a reader over shard data `reader` described by `index`.
+    pub fn new(reader: R, index: ShardIndex) -> Self {
+        ShardReader { reader, index }
+    }
+
+    /// Returns the shard index.
+    pub fn index(&self) -> &ShardIndex {
+        &self.index
+    }
+
+    /// Returns the number of documents in the shard.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Returns `true` if the shard has no documents.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Reads the tokens of document `k`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::ErrorKind::InvalidInput`] if `k` is out of bounds, and any error
+    /// from reading the shard, including [`io::ErrorKind::UnexpectedEof`] if the shard
+    /// is shorter than its index says.
+    pub fn document(&mut self, k: usize) -> io::Result<Vec<u32>> {
+        let range = self.index.range(k).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "document {} out of range for shard of {} documents",
+                    k,
+                    self.len()
+                ),
+            )
+        })?;
+
+        self.reader.seek(SeekFrom::Start(range.start * 4))?;
+        let mut bytes = vec![0; ((range.end - range.start) * 4) as usize];
+        self.reader.read_exact(&mut bytes)?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().expect("four bytes")))
+            .collect())
+    }
+}
+
+impl ShardReader<BufReader<File>> {
+    /// Opens the shard at `path` and its index at [`shard_index_path`]`(path)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either file cannot be read, the index is malformed, or the
+    /// shard size does not match the index ([`io::ErrorKind::InvalidData`]).
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let index = ShardIndex::load(shard_index_path(path))?;
+        let file = File::open(path)?;
+        if file.metadata()?.len() != index.tokens() * 4 {
+            return Err(invalid_index("shard size does not match its index"));
+        }
+
+        Ok(ShardReader::new(BufReader::new(file), index))
+    }
+
+    /// Opens a packed dataset written by [`TokenWriter`](crate::TokenWriter), treating
+    /// each sequence of `sequence_length` tokens as one document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or its size is not a whole
+    /// number of tokens ([`io::ErrorKind::InvalidData`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is zero.
+    pub fn open_packed(path: impl AsRef<Path>, sequence_length: usize) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let bytes = file.metadata()?.len();
+        if bytes % 4 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "token stream ends in the middle of a token",
+            ));
+        }
+
+        Ok(ShardReader::new(
+            BufReader::new(file),
+            ShardIndex::packed(bytes / 4, sequence_length),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn index_roundtrips_through_bytes() {
+        let index = ShardIndex::from_lengths([3, 0, 2]);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.tokens(), 5);
+        assert_eq!(index.range(1), Some(3..3));
+        assert_eq!(index.range(3), None);
+        assert_eq!(ShardIndex::from_bytes(&index.to_bytes()).unwrap(), index);
+    }
+
+    #[test]
+    fn from_bytes_rejects_corrupt_indexes() {
+        let mut bytes = ShardIndex::from_lengths([1, 2]).to_bytes();
+        assert!(ShardIndex::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+
+        bytes[24] = 9;
+        let err = ShardIndex::from_bytes(&bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("ascending"));
+
+        assert!(ShardIndex::from_bytes(b"NOTANIDX\0\0\0\0\0\0\0\0").is_err());
+    }
+
+    #[test]
+    fn reader_fetches_documents_in_any_order() {
+        let mut writer = ShardWriter::new(Vec::new());
+        for document in [&[1, 2][..], &[], &[3, 4, 5]] {
+            writer.write_document(document).unwrap();
+        }
+        let (bytes, index) = writer.finish().unwrap();
+        let mut reader = ShardReader::new(Cursor::new(bytes), index);
+
+        assert_eq!(reader.document(2).unwrap(), vec![3, 4, 5]);
+        assert_eq!(reader.document(0).unwrap(), vec![1, 2]);
+        assert!(reader.document(1).unwrap().is_empty());
+        assert_eq!(
+            reader.document(3).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn open_reads_shard_and_index_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shard.bin");
+        let mut writer = ShardWriter::create(&path).unwrap();
+        writer.write_document(&[7, 8]).unwrap();
+        writer.write_document(&[9]).unwrap();
+        writer.finish_with_index(&path).unwrap();
+
+        let mut reader = ShardReader::open(&path).unwrap();
+
+        assert_eq!(reader.document(1).unwrap(), vec![9]);
+    }
+
+    #[test]
+    fn open_rejects_truncated_shard() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shard.bin");
+        let mut writer = ShardWriter::create(&path).unwrap();
+        writer.write_document(&[7, 8]).unwrap();
+        writer.finish_with_index(&path).unwrap();
+        std::fs::write(&path, [0; 4]).unwrap();
+
+        let err = ShardReader::open(&path).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn open_packed_reads_sequences_written_by_token_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("packed.bin");
+        let mut writer = crate::TokenWriter::new(Vec::new(), 3, 0)
+            .with_final_sequence(crate::FinalSequence::Keep);
+        writer.write_document(&[5, 6, 7, 8]).unwrap();
+        std::fs::write(&path, writer.finish().unwrap()).unwrap();
+
+        let mut reader = ShardReader::open_packed(&path, 3).unwrap();
+
+        assert_eq!(reader.len(), 2);
+        assert_eq!(reader.document(1).unwrap(), vec![8, 0]);
+        assert!(ShardIndex::packed(0, 3).is_empty());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::ShardIndex;
+
+/// A document in a set of shards: the `document`-th entry of shard `shard`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DocumentRef {
+    /// Position of the shard in the indexes passed to the [`Shuffler`].
+    pub shard: usize,
+    /// Position of the document within its shard's [`ShardIndex`].
+    pub document: usize,
+}
+
+/// Seeded, reproducible document orders over a set of [`ShardIndex`]es.
+///
+/// Only the indexes are read, never the token data, so shuffling a multi-terabyte
+/// dataset costs one `DocumentRef` per document. The same seed and indexes give the
+/// same order on every platform and in every release of this crate; the generator is
+/// SplitMix64 and is part of the format, not an implementation detail.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{DocumentRef, ShardIndex, Shuffler};
+///
+/// let shards = [ShardIndex::from_lengths([5, 3]), ShardIndex::from_lengths([2])];
+/// let order = Shuffler::new(42).shuffle(&shards);
+///
+/// assert_eq!(order.len(), 3);
+/// assert_eq!(order, Shuffler::new(42).shuffle(&shards));
+/// assert!(order.contains(&DocumentRef { shard: 1, document: 0 }));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shuffler {
+    seed: u64,
+}
+
+impl Shuffler {
+    /// Creates a shuffler with the given seed.
+    pub fn new(seed: u64) -> Self {
+        Shuffler { seed }
+    }
+
+    /// Returns the seed.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns every document of every shard in a uniformly random order.
+    pub fn shuffle(&self, indexes: &[ShardIndex]) -> Vec<DocumentRef> {
+        let mut order: Vec<DocumentRef> = indexes
+            .iter()
+            .enumerate()
+            .flat_map(|(shard, index)| {
+                (0..index.len()).map(move |document| DocumentRef { shard, document })
+            })
+            .collect();
+
+        let mut rng = SplitMix64::new(self.seed);
+        for i in (1..order.len()).rev() {
+            let j = rng.below(i as u64 + 1) as usize;
+            order.swap(i, j);
+        }
+
+        order
+    }
+
+    /// Interleaves the shards randomly while keeping each shard's own order.
+    ///
+    /// At every step the next shard is drawn with probability proportional to its
+    /// remaining documents, so shards are consumed at an even rate and finish
+    /// together. Reads stay sequential within each shard, which suits shards that
+    /// were shuffled when written or live on storage where seeks are expensive.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{ShardIndex, Shuffler};
+    ///
+    /// let shards = [ShardIndex::from_lengths([1; 4]), ShardIndex::from_lengths([1; 4])];
+    /// let order = Shuffler::new(7).interleave(&shards);
+    ///
+    /// let first_shard: Vec<usize> = order
+    ///     .iter()
+    ///     .filter(|doc| doc.shard == 0)
+    ///     .map(|doc| doc.document)
+    ///     .collect();
+    /// assert_eq!(first_shard, vec![0, 1, 2, 3]);
+    /// ```
+    pub fn interleave(&self, indexes: &[ShardIndex]) -> Vec<DocumentRef> {
+        let mut next: Vec<usize> = vec![0; indexes.len()];
+        let mut remaining: u64 = indexes.iter().map(|index| index.len() as u64).sum();
+        let mut order = Vec::with_capacity(remaining as usize);
+
+        let mut rng = SplitMix64::new(self.seed);
+        while remaining > 0 {
+            let mut pick = rng.below(remaining);
+            let shard = indexes
+                .iter()
+                .zip(&next)
+                .position(|(index, &taken)| {
+                    let left = (index.len() - taken) as u64;
+                    if pick < left {
+                        true
+                    } else {
+                        pick -= left;
+                        false
+                    }
+                })
+                .expect("pick is below the number of remaining documents");
+
+            order.push(DocumentRef {
+                shard,
+                document: next[shard],
+            });
+            next[shard] += 1;
+            remaining -= 1;
+        }
+
+        order
+    }
+}
+
+/// SplitMix64 generator (Steele, Lea and Flood), chosen for being tiny and fully
+/// specified, so orders never change between releases.
+pub(crate) struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..bound` by multiply-shift range reduction.
+    pub(crate) fn below(&mut self, bound: u64) -> u64 {
+        ((u128::from(self.next_u64()) * u128::from(bound)) >> 64) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shards() -> Vec<ShardIndex> {
+        vec![
+            ShardIndex::from_lengths([1; 6]),
+            ShardIndex::from_lengths([]),
+            ShardIndex::from_lengths([1; 3]),
+        ]
+    }
+
+    #[test]
+    fn splitmix_matches_reference_output() {
+        let mut rng = SplitMix64::new(0);
+
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn shuffle_is_a_seeded_permutation() {
+        let shards = shards();
+        let order = Shuffler::new(1).shuffle(&shards);
+
+        let mut sorted = order.clone();
+        sorted.sort();
+        assert_eq!(sorted.len(), 9);
+        sorted.dedup();
+        assert_eq!(sorted.len(), 9);
+
+        assert_eq!(order, Shuffler::new(1).shuffle(&shards));
+        assert_ne!(order, Shuffler::new(2).shuffle(&shards));
+    }
+
+    /// Orders are part of the format: a change here reshuffles every user's
+    /// dataset, so it needs a major release.
+    #[test]
+    fn orders_match_the_pinned_golden_orders() {
+        let pairs = |order: Vec<DocumentRef>| -> Vec<(usize, usize)> {
+            order.iter().map(|doc| (doc.shard, doc.document)).collect()
+        };
+
+        assert_eq!(
+            pairs(Shuffler::new(42).shuffle(&shards())),
+            [
+                (0, 5),
+                (2, 2),
+                (0, 4),
+                (0, 3),
+                (0, 0),
+                (0, 2),
+                (2, 1),
+                (0, 1),
+                (2, 0)
+            ]
+        );
+        assert_eq!(
+            pairs(Shuffler::new(42).interleave(&shards())),
+            [
+                (2, 0),
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (0, 3),
+                (2, 1),
+                (0, 4),
+                (2, 2),
+                (0, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn interleave_preserves_order_within_shards() {
+        let shards = shards();
+        let order = Shuffler::new(3).interleave(&shards);
+
+        assert_eq!(order.len(), 9);
+        for shard in [0, 2] {
+            let documents: Vec<usize> = order
+                .iter()
+                .filter(|doc| doc.shard == shard)
+                .map(|doc| doc.document)
+                .collect();
+            assert_eq!(documents, (0..shards[shard].len()).collect::<Vec<_>>());
+        }
+        assert_eq!(order, Shuffler::new(3).interleave(&shards));
+    }
+
+    #[test]
+    fn empty_inputs_give_empty_orders() {
+        assert!(Shuffler::new(0).shuffle(&[]).is_empty());
+        assert!(
+            Shuffler::new(0)
+                .interleave(&[ShardIndex::from_lengths([])])
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Process-wide policy for what the infallible `encode` and `decode` methods do
+/// when they hit a problem.
+///
+/// The fallible `try_*` methods always return errors and are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Panic on unknown tokens, unknown IDs and invalid UTF-8.
+    #[default]
+    Strict,
+    /// Never panic. Unknown tokens are encoded as their byte-level tokens,
+    /// unknown IDs decode to U+FFFD, and invalid UTF-8 and characters with no
+    /// byte-level form are replaced with U+FFFD.
+    Lenient,
+}
+
+static STRICTNESS: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the [`Strictness`] used by every tokenizer in the process.
+///
+/// Intended to be called once at startup, so a deployment chooses its failure
+/// behavior in one place instead of at every call site.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, Strictness, set_strictness};
+///
+/// set_strictness(Strictness::Lenient);
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// assert_eq!(tokenizer.decode(&[32, 9999]), "A\u{FFFD}");
+/// ```
+pub fn set_strictness(strictness: Strictness) {
+    let value = match strictness {
+        Strictness::Strict => 0,
+        Strictness::Lenient => 1,
+    };
+    STRICTNESS.store(value, Ordering::Relaxed);
+}
+
+/// Returns the current process-wide [`Strictness`].
+pub fn strictness() -> Strictness {
+    match STRICTNESS.load(Ordering::Relaxed) {
+        0 => Strictness::Strict,
+        _ => Strictness::Lenient,
+    }
+}
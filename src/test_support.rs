@@ -0,0 +1,26 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use crate::{BpeTokenizer, Trainer};
+
+/// Returns the merge rule joining `first` and `second`.
+pub(crate) fn pair(first: &str, second: &str) -> (String, String) {
+    (first.to_string(), second.to_string())
+}
+
+/// Returns the merge rules joining each pair of `pairs`, in order.
+pub(crate) fn merges(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    pairs
+        .iter()
+        .map(|&(first, second)| pair(first, second))
+        .collect()
+}
+
+/// Trains `num_merges` merges on `corpus`, with `<|endoftext|>` as the only special
+/// token.
+pub(crate) fn trained(corpus: &[&str], num_merges: usize) -> BpeTokenizer {
+    BpeTokenizer::from_trainer(
+        &Trainer::new(num_merges),
+        corpus,
+        vec!["<|endoftext|>".into()],
+    )
+}
@@ -0,0 +1,151 @@
+use std::io::Write;
+
+use serde_json::{Value, json};
+
+use crate::tokenizer_json::TokenizerJsonError;
+use crate::{BpeTokenizer, TokenizerError};
+
+/// Version of the layout written by [`BpeTokenizer::write_test_vectors`].
+const TEST_VECTORS_VERSION: u32 = 1;
+
+/// Inputs that exercise the parts of the pipeline other implementations most often
+/// get wrong: contractions, whitespace runs, digits, line endings, non-Latin
+/// scripts, combining marks and characters outside the Basic Multilingual Plane.
+///
+/// The whitespace runs pin how the tokenizer's pre-tokenizer splits several spaces
+/// before a word. The default scanner keeps the whole run as one chunk, so
+/// `"    indented"` splits into `"    "` and `"indented"`. With
+/// [`PreTokenizer::with_whitespace_lookahead`](crate::PreTokenizer::with_whitespace_lookahead),
+/// as in GPT-2 and Hugging Face's `ByteLevel`, the last space joins the word:
+/// `"   "` and `" indented"`. The `"pre_tokenizer"` setting in the document tells
+/// the two apart.
+const CANONICAL_TEXTS: &[&str] = &[
+    "",
+    " ",
+    "Hello, world!",
+    "hello world",
+    "  leading and trailing spaces  ",
+    "tabs\tand\nnewlines\r\nand\n\n\nblank lines",
+    "I'm sure they'll say it's what we'd've done",
+    "DON'T SHOUT",
+    "3.14159 1,000,000 2024-06-01 0x1F",
+    "fn main() { println!(\"{}\", x + 1); }",
+    "    indented = [1, 2, 3]",
+    "naïve café résumé",
+    "e\u{301}",
+    "日本語のテキスト",
+    "Привет, мир",
+    "مرحبا بالعالم",
+    "🦀 emoji 👩‍👩‍👧 and flags 🇺🇦",
+    "\u{0}\u{7f}\u{feff}",
+];
+
+/// Returns the canonical inputs plus one text per special token, with the token
+/// between ordinary words.
+pub(crate) fn canonical_texts(tokenizer: &BpeTokenizer) -> Vec<String> {
+    let mut texts: Vec<String> = CANONICAL_TEXTS
+        .iter()
+        .map(|text| text.to_string())
+        .collect();
+    texts.extend(
+        tokenizer
+            .special_tokens()
+            .iter()
+            .map(|token| format!("before{}after {}", token, token)),
+    );
+    texts
+}
+
+/// Writes the settings of `tokenizer` and the IDs and tokens of every text as JSON.
+pub(crate) fn write(
+    tokenizer: &BpeTokenizer,
+    texts: &[&str],
+    mut writer: impl Write,
+) -> Result<(), TokenizerError> {
+    let vectors = texts
+        .iter()
+        .map(|text| -> Result<Value, TokenizerError> {
+            let encoding = tokenizer.try_encode_with_offsets(text)?;
+            Ok(json!({
+                "text": text,
+                "ids": encoding.ids(),
+                "tokens": encoding.tokens(),
+            }))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let document = json!({
+        "version": TEST_VECTORS_VERSION,
+        "crate_version": env!("CARGO_PKG_VERSION"),
+        "tokenizer": {
+            "name": tokenizer.metadata().name,
+            "vocab_size": tokenizer.vocabulary().len(),
+            "merges": tokenizer.merges().len(),
+            "special_tokens": tokenizer.special_tokens(),
+            "normalizer": tokenizer.normalizer().describe(),
+            "pre_tokenizer": tokenizer.pre_tokenizer().describe(),
+            "encode_strategy": format!("{:?}", tokenizer.encode_strategy()),
+        },
+        "vectors": vectors,
+    });
+
+    serde_json::to_writer_pretty(&mut writer, &document).map_err(TokenizerJsonError::from)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PreTokenizer, Trainer};
+
+    #[test]
+    fn vectors_pin_how_runs_of_spaces_split() {
+        let merges = vec![("Ġ".to_string(), "Ġ".to_string())];
+        let tokens = |pre_tokenizer: PreTokenizer| {
+            let tokenizer =
+                BpeTokenizer::new(merges.clone(), vec![]).with_pre_tokenizer(pre_tokenizer);
+            let mut bytes = Vec::new();
+            write(&tokenizer, &["    indented"], &mut bytes).unwrap();
+            let document: Value = serde_json::from_slice(&bytes).unwrap();
+            let tokens = document["vectors"][0]["tokens"].as_array().unwrap()[..3].to_vec();
+            (document["tokenizer"]["pre_tokenizer"].clone(), tokens)
+        };
+
+        let (default, default_tokens) = tokens(PreTokenizer::new());
+        let (lookahead, lookahead_tokens) =
+            tokens(PreTokenizer::new().with_whitespace_lookahead(true));
+
+        assert_eq!(default_tokens, vec![json!("ĠĠ"), json!("ĠĠ"), json!("i")]);
+        assert_eq!(lookahead_tokens, vec![json!("ĠĠ"), json!("Ġ"), json!("Ġ")]);
+        assert_ne!(default, lookahead);
+    }
+
+    #[test]
+    fn vectors_match_encode_and_cover_special_tokens() {
+        let corpus = ["the cat sat on the mat", "naïve café"];
+        let tokenizer =
+            BpeTokenizer::from_trainer(&Trainer::new(20), &corpus, vec!["<|endoftext|>".into()]);
+        let texts = canonical_texts(&tokenizer);
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let mut bytes = Vec::new();
+
+        write(&tokenizer, &texts, &mut bytes).unwrap();
+        let document: Value = serde_json::from_slice(&bytes).unwrap();
+
+        let vectors = document["vectors"].as_array().unwrap();
+        assert_eq!(vectors.len(), CANONICAL_TEXTS.len() + 1);
+        for (vector, text) in vectors.iter().zip(&texts) {
+            assert_eq!(vector["text"], *text);
+            let ids: Vec<u32> = serde_json::from_value(vector["ids"].clone()).unwrap();
+            assert_eq!(ids, tokenizer.encode(text));
+        }
+        assert!(
+            vectors.last().unwrap()["ids"]
+                .as_array()
+                .unwrap()
+                .contains(&json!(0))
+        );
+        assert_eq!(document["tokenizer"]["merges"], tokenizer.merges().len());
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use crate::id_remap;
+use crate::{BpeTokenizer, PreTokenizer, bytes_to_unicode};
+
+/// Error returned when a tiktoken `.tiktoken` rank file cannot be loaded.
+#[derive(Debug)]
+pub enum TiktokenError {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The line is not a base64 token followed by a space and a decimal rank.
+    Malformed { line: usize, content: String },
+    /// The ranks do not describe a byte-level BPE vocabulary, for example because a
+    /// byte has no rank or a token cannot be built from two lower-ranked tokens.
+    InvalidRanks(String),
+}
+
+impl fmt::Display for TiktokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiktokenError::Io(err) => write!(f, "failed to read tiktoken file: {}", err),
+            TiktokenError::Malformed { line, content } => write!(
+                f,
+                "line {}: expected a base64 token and a rank, found '{}'",
+                line, content
+            ),
+            TiktokenError::InvalidRanks(reason) => write!(f, "invalid tiktoken ranks: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for TiktokenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TiktokenError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TiktokenError {
+    fn from(err: io::Error) -> Self {
+        TiktokenError::Io(err)
+    }
+}
+
+/// Builds a tokenizer from the contents of a `.tiktoken` file and tiktoken's special
+/// token table.
+///
+/// Each multi-byte token becomes the merge rule that produces it when its bytes are
+/// merged with lower-ranked tokens only, which is how tiktoken itself encodes, and
+/// rules are ordered by rank. IDs that neither the file nor `special_tokens` assign
+/// are filled with `<|reserved_N|>` special tokens, since IDs must be contiguous.
+pub(crate) fn from_tiktoken(
+    contents: &str,
+    special_tokens: &[(&str, u32)],
+) -> Result<BpeTokenizer, TiktokenError> {
+    let mut ranked = parse_ranks(contents)?;
+    ranked.sort_by_key(|(_, rank)| *rank);
+    let ranks: HashMap<&[u8], u32> = ranked
+        .iter()
+        .map(|(token, rank)| (token.as_slice(), *rank))
+        .collect();
+    if ranks.len() != ranked.len() {
+        return Err(TiktokenError::InvalidRanks(
+            "a token is listed more than once".to_string(),
+        ));
+    }
+    if let Some(byte) = (0..=255u8).find(|byte| !ranks.contains_key([*byte].as_slice())) {
+        return Err(TiktokenError::InvalidRanks(format!(
+            "byte 0x{:02x} has no rank",
+            byte
+        )));
+    }
+
+    let byte_encoder = bytes_to_unicode();
+    let byte_level = |bytes: &[u8]| -> String { bytes.iter().map(|b| byte_encoder[b]).collect() };
+
+    let mut merges = Vec::new();
+    for (token, rank) in ranked.iter().filter(|(token, _)| token.len() > 1) {
+        let split = split_point(token, *rank, &ranks).ok_or_else(|| {
+            TiktokenError::InvalidRanks(format!(
+                "token {:?} of rank {} is not two lower-ranked tokens merged",
+                String::from_utf8_lossy(token),
+                rank
+            ))
+        })?;
+        merges.push((byte_level(&token[..split]), byte_level(&token[split..])));
+    }
+
+    let mut ids: HashMap<String, u32> = ranked
+        .iter()
+        .map(|(token, rank)| (byte_level(token), *rank))
+        .collect();
+    let mut specials: Vec<(String, u32)> = special_tokens
+        .iter()
+        .map(|(token, id)| (token.to_string(), *id))
+        .collect();
+    let assigned: Vec<u32> = ids
+        .values()
+        .chain(specials.iter().map(|(_, id)| id))
+        .copied()
+        .collect();
+    let end = assigned.iter().max().map_or(0, |&max| u64::from(max) + 1);
+    // Gaps are filled with reserved tokens, so a stray large ID would otherwise
+    // create millions of them; real encodings leave only a handful.
+    let count = assigned.len() as u64;
+    if end.saturating_sub(count) > count {
+        return Err(TiktokenError::InvalidRanks(format!(
+            "ID {} leaves more IDs unassigned than the {} assigned",
+            end - 1,
+            assigned.len()
+        )));
+    }
+    let end = end as usize;
+    let mut used = vec![false; end];
+    for &id in &assigned {
+        used[id as usize] = true;
+    }
+    specials.extend(
+        (0..end as u32)
+            .filter(|&id| !used[id as usize])
+            .map(|id| (format!("<|reserved_{}|>", id), id)),
+    );
+    specials.sort_by_key(|(_, id)| *id);
+    ids.extend(specials.iter().cloned());
+
+    // r50k_base and p50k_base split text with GPT-2's original pattern.
+    let tokenizer = BpeTokenizer::new(
+        merges,
+        specials.into_iter().map(|(token, _)| token).collect(),
+    )
+    .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true));
+    let ids: HashMap<&str, u32> = ids
+        .iter()
+        .map(|(token, &id)| (token.as_str(), id))
+        .collect();
+
+    match id_remap::file_order(tokenizer.vocabulary(), &ids) {
+        Ok(Some(new_to_old)) => Ok(tokenizer.renumbered(&new_to_old)),
+        Ok(None) => Ok(tokenizer),
+        Err(reason) => Err(TiktokenError::InvalidRanks(reason)),
+    }
+}
+
+/// Parses `<base64 token> <rank>` lines, skipping blank ones.
+fn parse_ranks(contents: &str) -> Result<Vec<(Vec<u8>, u32)>, TiktokenError> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let malformed = || TiktokenError::Malformed {
+                line: index + 1,
+                content: line.to_string(),
+            };
+            let (token, rank) = line.split_once(' ').ok_or_else(malformed)?;
+            let token = decode_base64(token).filter(|token| !token.is_empty());
+            match (token, rank.trim_end().parse()) {
+                (Some(token), Ok(rank)) => Ok((token, rank)),
+                _ => Err(malformed()),
+            }
+        })
+        .collect()
+}
+
+/// Returns where `token` splits into the two parts that tiktoken merges last, found
+/// by repeatedly merging the adjacent pair of lowest rank below `rank`.
+fn split_point(token: &[u8], rank: u32, ranks: &HashMap<&[u8], u32>) -> Option<usize> {
+    let mut boundaries: Vec<usize> = (0..=token.len()).collect();
+
+    loop {
+        let best = boundaries
+            .windows(3)
+            .enumerate()
+            .filter_map(|(i, bounds)| {
+                let pair_rank = *ranks.get(&token[bounds[0]..bounds[2]])?;
+                (pair_rank < rank).then_some((pair_rank, i))
+            })
+            .min();
+        match best {
+            Some((_, i)) => {
+                boundaries.remove(i + 1);
+            }
+            None => break,
+        }
+    }
+
+    (boundaries.len() == 3).then(|| boundaries[1])
+}
+
+/// Decodes standard base64 with `=` padding, as written by tiktoken.
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let sextet = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        _ => None,
+    };
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let groups = text.len() / 4;
+    let mut bytes = Vec::with_capacity(groups * 3);
+    for (index, chunk) in text.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && index + 1 < groups) {
+            return None;
+        }
+
+        let mut group = 0u32;
+        for &c in &chunk[..4 - padding] {
+            group = group << 6 | u32::from(sextet(c)?);
+        }
+        group <<= 6 * padding;
+        bytes.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Trainer, unicode_to_bytes};
+
+    fn encode_base64(bytes: &[u8]) -> String {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = String::new();
+        for chunk in bytes.chunks(3) {
+            let mut group = [0u8; 3];
+            group[..chunk.len()].copy_from_slice(chunk);
+            let group = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+            for i in 0..4 {
+                if i <= chunk.len() {
+                    text.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+                } else {
+                    text.push('=');
+                }
+            }
+        }
+        text
+    }
+
+    /// Writes the non-special tokens of `tokenizer` as a `.tiktoken` file, with ranks
+    /// starting at 0 in ID order.
+    fn tiktoken_file(tokenizer: &BpeTokenizer) -> String {
+        let unicode_to_byte = unicode_to_bytes();
+        let skip = tokenizer.special_tokens().len();
+
+        tokenizer
+            .vocabulary()
+            .iter()
+            .skip(skip)
+            .map(|(id, token)| {
+                let bytes: Vec<u8> = token.chars().map(|ch| unicode_to_byte[&ch]).collect();
+                format!("{} {}\n", encode_base64(&bytes), id as usize - skip)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("SGVsbG8="), Some(b"Hello".to_vec()));
+        assert_eq!(decode_base64("IQ=="), Some(b"!".to_vec()));
+        assert_eq!(decode_base64("/w=="), Some(vec![0xff]));
+        assert_eq!(decode_base64("IQ==IQ=="), None);
+        assert_eq!(decode_base64("IQ="), None);
+        assert_eq!(decode_base64("I*=="), None);
+
+        for bytes in [&b""[..], b"a", b"ab", b"abc", b"\x00\xfe\xff\x80"] {
+            assert_eq!(decode_base64(&encode_base64(bytes)), Some(bytes.to_vec()));
+        }
+    }
+
+    #[test]
+    fn reproduces_the_tokenizer_the_ranks_came_from() {
+        let corpus = ["the cat sat on the mat", "naïve café, the end", "aaaa bbbb"];
+        let trained = BpeTokenizer::from_trainer(&Trainer::new(40), &corpus, vec![]);
+        let special = [("<|endoftext|>", 296)];
+
+        let loaded = from_tiktoken(&tiktoken_file(&trained), &special).unwrap();
+
+        assert_eq!(loaded.merges(), trained.merges());
+        for text in corpus.iter().chain(&["the end<|endoftext|>", "cats"]) {
+            let ids = loaded.encode(text);
+            assert_eq!(loaded.decode(&ids), *text);
+        }
+        assert_eq!(loaded.encode("the cat"), trained.encode("the cat"));
+        assert_eq!(loaded.encode("<|endoftext|>"), vec![296]);
+        assert!(loaded.pre_tokenizer().whitespace_lookahead());
+    }
+
+    #[test]
+    fn fills_id_gaps_with_reserved_tokens() {
+        let trained = BpeTokenizer::new(vec![], vec![]);
+        let special = [("<|endoftext|>", 257), ("<|fim|>", 259)];
+
+        let loaded = from_tiktoken(&tiktoken_file(&trained), &special).unwrap();
+
+        assert_eq!(loaded.vocabulary().len(), 260);
+        assert_eq!(
+            loaded.vocabulary().id_to_token(256),
+            Some("<|reserved_256|>")
+        );
+        assert_eq!(
+            loaded.vocabulary().id_to_token(258),
+            Some("<|reserved_258|>")
+        );
+        assert_eq!(loaded.encode("<|fim|>!"), vec![259, 0]);
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        for line in ["IQ==", "IQ== x", "I*== 0", " 0"] {
+            let err = from_tiktoken(&format!("\n{}\n", line), &[]).err().unwrap();
+            assert!(
+                matches!(err, TiktokenError::Malformed { line: 2, .. }),
+                "{:?}: {}",
+                line,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_ranks_that_are_not_byte_level_bpe() {
+        let base = tiktoken_file(&BpeTokenizer::new(vec![], vec![]));
+
+        let missing_byte = base.lines().skip(1).collect::<Vec<_>>().join("\n");
+        let err = from_tiktoken(&missing_byte, &[]).err().unwrap();
+        assert!(err.to_string().contains("has no rank"), "{}", err);
+
+        // "abc" needs "ab" or "bc" at a lower rank.
+        let unreachable = format!("{}{} 256\n", base, encode_base64(b"abc"));
+        let err = from_tiktoken(&unreachable, &[]).err().unwrap();
+        assert!(err.to_string().contains("\"abc\""), "{}", err);
+
+        let gap_collision = from_tiktoken(&base, &[("<|endoftext|>", 3)]).err().unwrap();
+        assert!(
+            gap_collision.to_string().contains("more than one"),
+            "{}",
+            gap_collision
+        );
+
+        for id in [100_000_000, u32::MAX] {
+            let err = from_tiktoken(&base, &[("<|endoftext|>", id)])
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("unassigned"), "{}", err);
+        }
+        let far_rank = format!("{}{} {}\n", base, encode_base64(b"ab"), u32::MAX);
+        let err = from_tiktoken(&far_rank, &[]).err().unwrap();
+        assert!(err.to_string().contains("unassigned"), "{}", err);
+    }
+}
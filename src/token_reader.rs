@@ -0,0 +1,318 @@
+use std::io::{self, Read};
+
+/// One fixed-length sequence read back by a [`TokenReader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedSequence {
+    /// Token ids of the sequence.
+    pub tokens: Vec<u32>,
+    /// Positions of end-of-sequence tokens, i.e. the last position of each document
+    /// that ends inside this sequence, in ascending order. Padding set with
+    /// [`TokenReader::with_padding`] is never listed.
+    pub document_ends: Vec<usize>,
+}
+
+impl PackedSequence {
+    /// Returns, for every position, the index of the document segment it belongs to
+    /// within this sequence.
+    ///
+    /// Segments are numbered from zero and an end-of-sequence token belongs to the
+    /// document it terminates. Comparing the segment ids of two positions tells
+    /// whether attention or loss may cross between them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::PackedSequence;
+    ///
+    /// let sequence = PackedSequence { tokens: vec![1, 0, 2, 3], document_ends: vec![1] };
+    /// assert_eq!(sequence.segment_ids(), vec![0, 0, 1, 1]);
+    /// ```
+    pub fn segment_ids(&self) -> Vec<usize> {
+        let mut ids = Vec::with_capacity(self.tokens.len());
+        let mut segment = 0;
+        let mut ends = self.document_ends.iter().peekable();
+
+        for position in 0..self.tokens.len() {
+            ids.push(segment);
+
+            if ends.next_if_eq(&&position).is_some() {
+                segment += 1;
+            }
+        }
+
+        ids
+    }
+}
+
+/// Reads sequences written by a [`TokenWriter`](crate::TokenWriter).
+///
+/// The reader yields one [`PackedSequence`] per `sequence_length` tokens and
+/// records where documents end so that document-level loss masking can be applied
+/// downstream. A shorter trailing sequence, as produced by
+/// [`FinalSequence::Keep`](crate::FinalSequence::Keep), is yielded as is. Data
+/// written with [`FinalSequence::Pad`](crate::FinalSequence::Pad) needs
+/// [`with_padding`](TokenReader::with_padding) when the pad id is the
+/// end-of-sequence id.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{TokenReader, TokenWriter};
+///
+/// let mut writer = TokenWriter::new(Vec::new(), 4, 0);
+/// writer.write_document(&[5, 6]).unwrap();
+/// writer.write_document(&[7]).unwrap();
+/// let bytes = writer.finish().unwrap();
+///
+/// let sequences: Vec<_> = TokenReader::new(bytes.as_slice(), 4, 0)
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(sequences[0].tokens, vec![5, 6, 0, 7]);
+/// assert_eq!(sequences[0].document_ends, vec![2]);
+/// ```
+#[derive(Debug)]
+pub struct TokenReader<R: Read> {
+    reader: R,
+    sequence_length: usize,
+    eos_id: u32,
+    pad_id: Option<u32>,
+    /// Bytes read past the current sequence to tell whether it is the last one.
+    carry: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> TokenReader<R> {
+    /// Creates a reader for sequences of `sequence_length` tokens separated by
+    /// `eos_id`. Both must match the values the data was written with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is zero.
+    pub fn new(reader: R, sequence_length: usize, eos_id: u32) -> Self {
+        assert!(sequence_length > 0, "sequence length must be positive");
+
+        TokenReader {
+            reader,
+            sequence_length,
+            eos_id,
+            pad_id: None,
+            carry: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Sets the id the final sequence was padded with by
+    /// [`FinalSequence::Pad`](crate::FinalSequence::Pad).
+    ///
+    /// Padding only matters when it equals the end-of-sequence id: the trailing
+    /// run of that id in the last sequence is then read as the end of the last
+    /// document followed by padding, not as a series of empty documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{FinalSequence, TokenReader, TokenWriter};
+    ///
+    /// let mut writer = TokenWriter::new(Vec::new(), 4, 0).with_final_sequence(FinalSequence::Pad(0));
+    /// writer.write_document(&[5]).unwrap();
+    /// let bytes = writer.finish().unwrap();
+    ///
+    /// let sequence = TokenReader::new(bytes.as_slice(), 4, 0)
+    ///     .with_padding(0)
+    ///     .next()
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(sequence.tokens, vec![5, 0, 0, 0]);
+    /// assert_eq!(sequence.document_ends, vec![1]);
+    /// ```
+    pub fn with_padding(mut self, pad_id: u32) -> Self {
+        self.pad_id = Some(pad_id);
+        self
+    }
+
+    /// Reads into `bytes` until it is full or the input ends, returning how many
+    /// bytes were read.
+    fn fill(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < bytes.len() {
+            match self.reader.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
+    }
+
+    fn read_sequence(&mut self) -> io::Result<Option<PackedSequence>> {
+        let mut bytes = vec![0; self.sequence_length * 4];
+        let carried = self.carry.len();
+        bytes[..carried].copy_from_slice(&self.carry);
+        let filled = carried + self.fill(&mut bytes[carried..])?;
+
+        // Only the last sequence can hold padding, so look one token ahead.
+        let mut next = [0; 4];
+        let ahead = if filled == bytes.len() && self.pad_id == Some(self.eos_id) {
+            self.fill(&mut next)?
+        } else {
+            0
+        };
+        self.carry = next[..ahead].to_vec();
+        let is_last = filled < bytes.len() || ahead == 0;
+
+        if filled == 0 {
+            return Ok(None);
+        }
+        if !filled.is_multiple_of(4) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "token stream ends in the middle of a token",
+            ));
+        }
+
+        let tokens: Vec<u32> = bytes[..filled]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        let mut content = tokens.len();
+        if is_last && self.pad_id == Some(self.eos_id) {
+            let padding = tokens
+                .iter()
+                .rev()
+                .take_while(|&&id| id == self.eos_id)
+                .count();
+            content -= padding.saturating_sub(1);
+        }
+        let document_ends = tokens[..content]
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| **id == self.eos_id)
+            .map(|(position, _)| position)
+            .collect();
+
+        Ok(Some(PackedSequence {
+            tokens,
+            document_ends,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for TokenReader<R> {
+    type Item = io::Result<PackedSequence>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_sequence().transpose();
+        if !matches!(result, Some(Ok(_))) {
+            self.done = true;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FinalSequence, TokenWriter};
+
+    fn pack(
+        documents: &[&[u32]],
+        sequence_length: usize,
+        final_sequence: FinalSequence,
+    ) -> Vec<u8> {
+        let mut writer =
+            TokenWriter::new(Vec::new(), sequence_length, 0).with_final_sequence(final_sequence);
+        for document in documents {
+            writer.write_document(document).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn reads_back_written_sequences() {
+        let bytes = pack(&[&[1, 2, 3], &[4, 5]], 3, FinalSequence::Drop);
+
+        let sequences: Vec<_> = TokenReader::new(bytes.as_slice(), 3, 0)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(sequences.len(), 2);
+        assert_eq!(sequences[0].tokens, vec![1, 2, 3]);
+        assert_eq!(sequences[0].document_ends, Vec::<usize>::new());
+        assert_eq!(sequences[1].tokens, vec![0, 4, 5]);
+        assert_eq!(sequences[1].document_ends, vec![0]);
+    }
+
+    #[test]
+    fn yields_short_final_sequence() {
+        let bytes = pack(&[&[1, 2, 3, 4]], 4, FinalSequence::Keep);
+
+        let sequences: Vec<_> = TokenReader::new(bytes.as_slice(), 4, 0)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(sequences[1].tokens, vec![0]);
+        assert_eq!(sequences[1].segment_ids(), vec![0]);
+    }
+
+    #[test]
+    fn segment_ids_split_at_document_ends() {
+        let bytes = pack(&[&[1], &[2, 3], &[4]], 6, FinalSequence::Drop);
+
+        let sequence = TokenReader::new(bytes.as_slice(), 6, 0)
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(sequence.segment_ids(), vec![0, 0, 1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn padding_equal_to_eos_is_not_a_document_end() {
+        let bytes = pack(&[&[1, 2], &[3, 4, 5], &[6]], 4, FinalSequence::Pad(0));
+        let read = |reader: TokenReader<&[u8]>| -> Vec<Vec<usize>> {
+            reader
+                .map(|sequence| sequence.unwrap().document_ends)
+                .collect()
+        };
+
+        let padded = read(TokenReader::new(bytes.as_slice(), 4, 0).with_padding(0));
+
+        assert_eq!(padded, vec![vec![2], vec![2], vec![0]]);
+        assert_eq!(
+            read(TokenReader::new(bytes.as_slice(), 4, 0)),
+            vec![vec![2], vec![2], vec![0, 1, 2, 3]]
+        );
+    }
+
+    #[test]
+    fn padding_leaves_full_sequences_ending_in_empty_documents() {
+        let bytes = pack(&[&[1, 2], &[], &[3], &[]], 3, FinalSequence::Pad(0));
+
+        let sequences: Vec<_> = TokenReader::new(bytes.as_slice(), 3, 0)
+            .with_padding(0)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(sequences[0].tokens, vec![1, 2, 0]);
+        assert_eq!(sequences[1].tokens, vec![0, 3, 0]);
+        assert_eq!(sequences[1].document_ends, vec![0, 2]);
+        assert_eq!(sequences[2].tokens, vec![0, 0, 0]);
+        assert_eq!(sequences[2].document_ends, vec![0]);
+    }
+
+    #[test]
+    fn truncated_token_is_an_error() {
+        let bytes = pack(&[&[1, 2, 3]], 4, FinalSequence::Drop);
+
+        let mut reader = TokenReader::new(&bytes[..bytes.len() - 1], 4, 0);
+
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert!(reader.next().is_none());
+    }
+}
@@ -0,0 +1,227 @@
+use std::io::{self, Write};
+
+use crate::DocumentStats;
+
+/// What [`TokenWriter::finish`] does with a final sequence shorter than the
+/// configured length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinalSequence {
+    /// Discard the leftover tokens so every written sequence has full length.
+    #[default]
+    Drop,
+    /// Fill the leftover sequence up to full length with the given token id.
+    Pad(u32),
+    /// Write the leftover tokens as a shorter final sequence.
+    Keep,
+}
+
+/// Packs encoded documents into fixed-length token sequences for language-model
+/// training.
+///
+/// Documents are concatenated into one stream with the end-of-sequence id
+/// appended after each document, and the stream is cut into sequences of exactly
+/// `sequence_length` tokens. A document may therefore span two sequences. Tokens
+/// are written as little-endian `u32` values with no header, so the output can be
+/// memory-mapped directly as a `[u32]` array of shape `[sequences, sequence_length]`.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{FinalSequence, TokenWriter};
+///
+/// let mut writer = TokenWriter::new(Vec::new(), 4, 0).with_final_sequence(FinalSequence::Pad(9));
+/// writer.write_document(&[1, 2, 3]).unwrap();
+/// writer.write_document(&[4]).unwrap();
+///
+/// let bytes = writer.finish().unwrap();
+/// let tokens: Vec<u32> = bytes
+///     .chunks(4)
+///     .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+///     .collect();
+/// assert_eq!(tokens, vec![1, 2, 3, 0, 4, 0, 9, 9]);
+/// ```
+#[derive(Debug)]
+pub struct TokenWriter<W: Write> {
+    writer: W,
+    sequence_length: usize,
+    eos_id: u32,
+    final_sequence: FinalSequence,
+    buffer: Vec<u32>,
+    sequences_written: usize,
+    document_stats: Option<DocumentStats>,
+}
+
+impl<W: Write> TokenWriter<W> {
+    /// Creates a writer producing sequences of `sequence_length` tokens, with
+    /// `eos_id` separating documents. Leftover tokens are dropped on
+    /// [`finish`](TokenWriter::finish) unless configured otherwise.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sequence_length` is zero.
+    pub fn new(writer: W, sequence_length: usize, eos_id: u32) -> Self {
+        assert!(sequence_length > 0, "sequence length must be positive");
+
+        TokenWriter {
+            writer,
+            sequence_length,
+            eos_id,
+            final_sequence: FinalSequence::default(),
+            buffer: Vec::with_capacity(sequence_length),
+            sequences_written: 0,
+            document_stats: None,
+        }
+    }
+
+    /// Sets how a final, partially filled sequence is handled.
+    pub fn with_final_sequence(mut self, final_sequence: FinalSequence) -> Self {
+        self.final_sequence = final_sequence;
+        self
+    }
+
+    /// Records the token count of every written document, excluding the
+    /// end-of-sequence id, in a [`DocumentStats`].
+    pub fn with_document_stats(mut self) -> Self {
+        self.document_stats = Some(DocumentStats::new());
+        self
+    }
+
+    /// Returns the collected per-document statistics, if enabled with
+    /// [`with_document_stats`](TokenWriter::with_document_stats).
+    pub fn document_stats(&self) -> Option<&DocumentStats> {
+        self.document_stats.as_ref()
+    }
+
+    /// Appends a document followed by the end-of-sequence id, writing out every
+    /// sequence that becomes full.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn write_document(&mut self, ids: &[u32]) -> io::Result<()> {
+        if let Some(stats) = &mut self.document_stats {
+            stats.record(ids.len());
+        }
+
+        let eos_id = self.eos_id;
+
+        for &id in ids.iter().chain(std::iter::once(&eos_id)) {
+            self.buffer.push(id);
+
+            if self.buffer.len() == self.sequence_length {
+                self.flush_sequence()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of sequences written so far.
+    pub fn sequences_written(&self) -> usize {
+        self.sequences_written
+    }
+
+    /// Handles the final partial sequence according to the [`FinalSequence`]
+    /// policy, flushes the underlying writer and returns it.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buffer.is_empty() {
+            match self.final_sequence {
+                FinalSequence::Drop => self.buffer.clear(),
+                FinalSequence::Pad(pad_id) => {
+                    self.buffer.resize(self.sequence_length, pad_id);
+                    self.flush_sequence()?;
+                }
+                FinalSequence::Keep => self.flush_sequence()?,
+            }
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn flush_sequence(&mut self) -> io::Result<()> {
+        let bytes: Vec<u8> = self.buffer.iter().flat_map(|id| id.to_le_bytes()).collect();
+        self.writer.write_all(&bytes)?;
+        self.buffer.clear();
+        self.sequences_written += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_tokens(bytes: &[u8]) -> Vec<u32> {
+        bytes
+            .chunks(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn documents_span_sequence_boundaries() {
+        let mut writer = TokenWriter::new(Vec::new(), 3, 0);
+        writer.write_document(&[1, 2, 3, 4]).unwrap();
+        writer.write_document(&[5]).unwrap();
+
+        assert_eq!(writer.sequences_written(), 2);
+        assert_eq!(
+            read_tokens(&writer.finish().unwrap()),
+            vec![1, 2, 3, 4, 0, 5]
+        );
+    }
+
+    #[test]
+    fn drop_discards_partial_sequence() {
+        let mut writer = TokenWriter::new(Vec::new(), 4, 0);
+        writer.write_document(&[1, 2, 3, 4, 5]).unwrap();
+
+        assert_eq!(read_tokens(&writer.finish().unwrap()), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn keep_writes_short_final_sequence() {
+        let mut writer =
+            TokenWriter::new(Vec::new(), 4, 0).with_final_sequence(FinalSequence::Keep);
+        writer.write_document(&[1, 2, 3, 4, 5]).unwrap();
+
+        assert_eq!(
+            read_tokens(&writer.finish().unwrap()),
+            vec![1, 2, 3, 4, 5, 0]
+        );
+    }
+
+    #[test]
+    fn empty_document_writes_separator() {
+        let mut writer = TokenWriter::new(Vec::new(), 2, 7);
+        writer.write_document(&[]).unwrap();
+        writer.write_document(&[]).unwrap();
+
+        assert_eq!(read_tokens(&writer.finish().unwrap()), vec![7, 7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "sequence length must be positive")]
+    fn zero_sequence_length_panics() {
+        TokenWriter::new(Vec::new(), 0, 0);
+    }
+
+    #[test]
+    fn document_stats_are_collected_when_enabled() {
+        let mut writer = TokenWriter::new(Vec::new(), 4, 0).with_document_stats();
+        writer.write_document(&[1, 2, 3]).unwrap();
+        writer.write_document(&[]).unwrap();
+
+        assert_eq!(writer.document_stats().unwrap().counts(), &[3, 0]);
+        assert!(
+            TokenWriter::new(Vec::new(), 4, 0)
+                .document_stats()
+                .is_none()
+        );
+    }
+}
@@ -1,4 +1,29 @@
-use crate::{Decoder, Encoder, PreTokenizer, Trainer, Vocabulary};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::diagnostics::{byte_coverage, special_token_warnings};
+use crate::distill;
+use crate::gguf;
+use crate::id_remap;
+use crate::instrumentation;
+use crate::merges_file::{format_merges, format_merges_with_metadata, header_metadata};
+use crate::retokenize;
+#[cfg(feature = "json")]
+use crate::test_vectors;
+use crate::tiktoken;
+#[cfg(feature = "json")]
+use crate::tokenizer_json::{self, TokenizerJsonError};
+#[cfg(feature = "bincode")]
+use crate::wire::{self, WireError};
+use crate::{
+    ByteCoverage, CandidateImpact, ConfigError, DecodeError, DecodeOptions, Decoder, EncodeError,
+    EncodeOptions, EncodeStrategy, Encoder, Encoding, ExtraTokenSimulation, Field, GgufError,
+    IdRemap, InvalidIds, LanguageCoverage, MergeGraph, MergeUsage, MergesError, Metadata,
+    Normalizer, PreTokenizer, SliceDrift, TiktokenError, TokenEdit, TokenizerConfig, TokenizerDiff,
+    TokenizerError, TokenizerStats, Trainer, UnknownIdMode, Vocabulary, Warning, WordCacheStats,
+    parse_merges,
+};
 
 /// A complete Byte Pair Encoding (BPE) tokenizer for encoding and decoding text.
 ///
@@ -40,6 +65,7 @@ use crate::{Decoder, Encoder, PreTokenizer, Trainer, Vocabulary};
 pub struct BpeTokenizer {
     encoder: Encoder,
     decoder: Decoder,
+    metadata: Metadata,
 }
 
 impl BpeTokenizer {
@@ -62,21 +88,1515 @@ impl BpeTokenizer {
     pub fn new(merges: Vec<(String, String)>, special_tokens: Vec<String>) -> Self {
         let pre_tokenizer = PreTokenizer::new();
         let vocabulary = Vocabulary::new(special_tokens.clone(), merges.clone());
-        let encoder = Encoder::new(merges, pre_tokenizer, vocabulary.clone(), special_tokens);
-        let decoder = Decoder::new(vocabulary);
+        let decoder = Decoder::new(vocabulary.clone()).with_special_tokens(&special_tokens);
+        let encoder = Encoder::new(merges, pre_tokenizer, vocabulary, special_tokens);
+
+        BpeTokenizer {
+            encoder,
+            decoder,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Creates a tokenizer that uses only the first `n` merge rules.
+    ///
+    /// Token IDs are identical to those of a tokenizer built from the full list for
+    /// every token the prefix contains, because merges are assigned IDs in rank order.
+    /// This makes ablations and vocabulary-size sweeps cheap: train once, then build
+    /// tokenizers from prefixes. If `n` exceeds the number of merges, all are used.
+    ///
+    /// # Arguments
+    ///
+    /// * `merges` - Full list of merge rules, in rank order
+    /// * `n` - Number of leading merge rules to use
+    /// * `special_tokens` - List of special tokens (e.g., `<|endoftext|>`, `[PAD]`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let merges = vec![
+    ///     ("a".to_string(), "b".to_string()),
+    ///     ("ab".to_string(), "c".to_string()),
+    /// ];
+    /// let full = BpeTokenizer::new(merges.clone(), vec![]);
+    /// let prefix = BpeTokenizer::with_merge_prefix(&merges, 1, vec![]);
+    ///
+    /// assert_eq!(prefix.encode("abc"), vec![256, 66]);
+    /// assert_eq!(full.encode("ab"), prefix.encode("ab"));
+    /// ```
+    pub fn with_merge_prefix(
+        merges: &[(String, String)],
+        n: usize,
+        special_tokens: Vec<String>,
+    ) -> Self {
+        let prefix = &merges[..n.min(merges.len())];
+
+        Self::new(prefix.to_vec(), special_tokens)
+    }
+
+    /// Sets how each pre-tokenized word is split into tokens.
+    ///
+    /// See [`EncodeStrategy`] for the available strategies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, EncodeStrategy};
+    ///
+    /// let merges = vec![
+    ///     ("b".to_string(), "c".to_string()),
+    ///     ("a".to_string(), "b".to_string()),
+    /// ];
+    /// let tokenizer =
+    ///     BpeTokenizer::new(merges, vec![]).with_encode_strategy(EncodeStrategy::LongestMatch);
+    ///
+    /// let ids = tokenizer.encode("abc");
+    /// assert_eq!(tokenizer.decode(&ids), "abc");
+    /// assert_eq!(ids, vec![257, 66]);
+    /// ```
+    pub fn with_encode_strategy(mut self, strategy: EncodeStrategy) -> Self {
+        self.encoder = self.encoder.with_strategy(strategy);
+        self
+    }
+
+    /// Returns how each pre-tokenized word is split into tokens.
+    pub fn encode_strategy(&self) -> EncodeStrategy {
+        self.encoder.strategy()
+    }
+
+    /// Emits the ID of `token` for merged tokens missing from the vocabulary,
+    /// instead of panicking or returning an [`EncodeError`].
+    ///
+    /// `token` must be in the vocabulary, usually as a special token. Like other
+    /// runtime settings it is not saved in [`BpeTokenizer::config`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["[UNK]".to_string()])
+    ///     .with_unknown_token("[UNK]")
+    ///     .unwrap();
+    /// assert_eq!(tokenizer.unknown_token(), Some("[UNK]"));
+    ///
+    /// // The fallback token must have an ID of its own.
+    /// assert!(BpeTokenizer::new(vec![], vec![]).with_unknown_token("[UNK]").is_err());
+    /// ```
+    pub fn with_unknown_token(mut self, token: impl Into<String>) -> Result<Self, ConfigError> {
+        self.encoder = self.encoder.with_unknown_token(token)?;
+        Ok(self)
+    }
+
+    /// Sets a callback run with the missing token every time the unknown token
+    /// stands in for it. Has no effect until
+    /// [`BpeTokenizer::with_unknown_token`] is set.
+    pub fn with_unknown_token_callback(
+        mut self,
+        callback: impl Fn(&EncodeError) + Send + Sync + 'static,
+    ) -> Self {
+        self.encoder = self.encoder.with_unknown_token_callback(callback);
+        self
+    }
+
+    /// Returns the token emitted for merged tokens missing from the vocabulary, if
+    /// any.
+    pub fn unknown_token(&self) -> Option<&str> {
+        self.encoder.unknown_token()
+    }
+
+    /// Caches the tokens of up to `capacity` pre-tokenized words, so repeated words
+    /// skip the merge loop. A `capacity` of 0 removes the cache.
+    ///
+    /// See [`Encoder::with_word_cache`]. Like other runtime settings it is not saved
+    /// in [`BpeTokenizer::config`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]).with_word_cache(10_000);
+    /// tokenizer.encode_batch(&["the cat", "the dog", "the cat"]);
+    ///
+    /// let stats = tokenizer.word_cache_stats().unwrap();
+    /// assert_eq!(stats.len, 3);
+    /// assert_eq!(stats.hit_rate(), 0.5);
+    /// ```
+    pub fn with_word_cache(mut self, capacity: usize) -> Self {
+        self.encoder = self.encoder.with_word_cache(capacity);
+        self
+    }
+
+    /// Returns the size and hit counts of the word cache, if one is set.
+    pub fn word_cache_stats(&self) -> Option<WordCacheStats> {
+        self.encoder.word_cache_stats()
+    }
+
+    /// Empties the word cache and resets its hit counts.
+    pub fn clear_word_cache(&self) {
+        self.encoder.clear_word_cache();
+    }
+
+    /// Sets what lenient decoding writes for IDs outside the vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, UnknownIdMode};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]).with_unknown_id_mode(UnknownIdMode::Skip);
+    /// assert_eq!(tokenizer.decode_lenient(&[32, 9999]), "A");
+    /// ```
+    pub fn with_unknown_id_mode(mut self, mode: UnknownIdMode) -> Self {
+        self.decoder = self.decoder.with_unknown_id_mode(mode);
+        self
+    }
+
+    /// Sets the normalizer applied to text before encoding.
+    ///
+    /// Normalization is lossy, so `decode(encode(text))` returns the normalized text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, NewlineMode, Normalizer};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![])
+    ///     .with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+    ///
+    /// let ids = tokenizer.encode("a\r\nb");
+    /// assert_eq!(tokenizer.decode(&ids), "a\nb");
+    /// ```
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.encoder = self.encoder.with_normalizer(normalizer);
+        self
+    }
+
+    /// Sets the pre-tokenizer used to split text before merging.
+    ///
+    /// It must match the pre-tokenizer the merges were trained with;
+    /// [`BpeTokenizer::from_trainer`] copies it automatically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, PreTokenizer};
+    ///
+    /// let merges = vec![("Ġ".to_string(), "Ġ".to_string())];
+    /// let tokenizer = BpeTokenizer::new(merges, vec![])
+    ///     .with_pre_tokenizer(PreTokenizer::new().with_whitespace_runs(&[2]));
+    ///
+    /// assert_eq!(tokenizer.encode("\n    "), vec![198, 256, 256]);
+    /// ```
+    pub fn with_pre_tokenizer(mut self, pre_tokenizer: PreTokenizer) -> Self {
+        self.encoder = self.encoder.with_pre_tokenizer(pre_tokenizer);
+        self
+    }
+
+    /// Returns the pre-tokenizer used to split text before merging.
+    pub fn pre_tokenizer(&self) -> &PreTokenizer {
+        self.encoder.pre_tokenizer()
+    }
+
+    /// Returns the normalizer applied before encoding.
+    pub fn normalizer(&self) -> &Normalizer {
+        self.encoder.normalizer()
+    }
+
+    /// Creates a tokenizer from an external `merges.txt` file.
+    ///
+    /// The file is validated with [`parse_merges`] before the tokenizer
+    /// is built, so malformed lines and rules whose operands cannot be produced are reported
+    /// with their line number instead of surfacing later as encode-time panics. Metadata
+    /// written by [`save_merges`](BpeTokenizer::save_merges) is restored.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the merges file
+    /// * `special_tokens` - List of special tokens (e.g., `<|endoftext|>`, `[PAD]`)
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`MergesError`] if the file cannot be read or fails validation.
+    pub fn from_merges_file(
+        path: impl AsRef<Path>,
+        special_tokens: Vec<String>,
+    ) -> Result<Self, MergesError> {
+        let contents = fs::read_to_string(path)?;
+        let merges = parse_merges(&contents)?;
+
+        Ok(Self::new(merges, special_tokens).with_metadata(header_metadata(&contents)))
+    }
+
+    /// Writes this tokenizer's merge rules to a `merges.txt` file.
+    ///
+    /// The file can be loaded again with [`from_merges_file`](BpeTokenizer::from_merges_file);
+    /// special tokens are not part of the format and must be passed separately. Any
+    /// [`Metadata`] is appended to the `#version` header line, which other readers ignore.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from creating or writing the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Metadata};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("merges.txt");
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// let tokenizer = BpeTokenizer::new(merges, vec![])
+    ///     .with_metadata(Metadata::new().with_name("greeter"));
+    ///
+    /// tokenizer.save_merges(&path).unwrap();
+    /// let loaded = BpeTokenizer::from_merges_file(&path, vec![]).unwrap();
+    ///
+    /// assert_eq!(loaded.metadata(), tokenizer.metadata());
+    /// assert_eq!(loaded.merges(), tokenizer.merges());
+    /// ```
+    pub fn save_merges(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(
+            path,
+            format_merges_with_metadata(self.merges(), &self.metadata),
+        )
+    }
+
+    /// Saves the tokenizer as a Hugging Face `tokenizer.json` file (feature `json`).
+    ///
+    /// The file holds the vocabulary with its current IDs, the merges, the special
+    /// tokens (as added tokens) and the normalizer and pre-tokenizer settings, and
+    /// loads in the `tokenizers` crate and Python library as well as with
+    /// [`from_file`](BpeTokenizer::from_file). The default GPT-2 splitting rules are
+    /// written as a `ByteLevel` pre-tokenizer, and a custom regex as a `Split` step
+    /// before it. [`Metadata`] is not part of the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerJsonError::Unsupported`] for settings that `tokenizers`
+    /// cannot express, namely whitespace-run splitting and
+    /// [`EncodeStrategy::LongestMatch`], and [`TokenizerJsonError::Io`] if the file
+    /// cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("tokenizer.json");
+    /// let corpus = ["hello world", "hello there"];
+    /// let tokenizer =
+    ///     BpeTokenizer::from_trainer(&Trainer::new(5), &corpus, vec!["<|endoftext|>".to_string()]);
+    ///
+    /// tokenizer.save(&path).unwrap();
+    /// let loaded = BpeTokenizer::from_file(&path).unwrap();
+    ///
+    /// assert_eq!(loaded.encode("hello<|endoftext|>"), tokenizer.encode("hello<|endoftext|>"));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TokenizerJsonError> {
+        fs::write(path, tokenizer_json::to_json(self)?)?;
+        Ok(())
+    }
+
+    /// Writes the encoding of every text as JSON test vectors (feature `json`), so
+    /// reimplementations in other languages can check their output against this
+    /// crate.
+    ///
+    /// The document records the tokenizer's settings under `"tokenizer"` and, under
+    /// `"vectors"`, one object per text with its `"text"`, `"ids"` and byte-level
+    /// `"tokens"`. [`canonical_test_texts`](BpeTokenizer::canonical_test_texts)
+    /// provides a default set of tricky inputs.
+    ///
+    /// The vectors pin how runs of spaces are split, which differs between the
+    /// default pre-tokenizer and one with
+    /// [`with_whitespace_lookahead`](PreTokenizer::with_whitespace_lookahead): the
+    /// default keeps `"  dog"` as `"  "` and `"dog"`, lookahead as `" "` and `" dog"`
+    /// like GPT-2. A port must match the mode recorded under `"pre_tokenizer"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::Io`] or [`TokenizerError::TokenizerJson`] if writing
+    /// fails, and [`TokenizerError::Encode`] if a text produces a token missing from
+    /// the vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let mut json = Vec::new();
+    /// tokenizer.write_test_vectors(&["Hi"], &mut json).unwrap();
+    ///
+    /// let json = String::from_utf8(json).unwrap();
+    /// assert!(json.contains(r#""text": "Hi""#));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn write_test_vectors(
+        &self,
+        texts: &[&str],
+        writer: impl std::io::Write,
+    ) -> Result<(), TokenizerError> {
+        test_vectors::write(self, texts, writer)
+    }
+
+    /// Returns inputs that exercise whitespace handling, contractions, digits,
+    /// several scripts and emoji, plus one text per special token, for
+    /// [`write_test_vectors`](BpeTokenizer::write_test_vectors) (feature `json`).
+    #[cfg(feature = "json")]
+    pub fn canonical_test_texts(&self) -> Vec<String> {
+        test_vectors::canonical_texts(self)
+    }
+
+    /// Loads a tokenizer from a Hugging Face `tokenizer.json` file (feature `json`).
+    ///
+    /// Accepts files written by [`save`](BpeTokenizer::save) and byte-level BPE
+    /// tokenizers from the `tokenizers` library, such as GPT-2's. Added tokens
+    /// become special tokens, and token IDs are taken from the file even where they
+    /// differ from the layout [`BpeTokenizer::new`] would choose (for example GPT-2's
+    /// `<|endoftext|>` at the end of the vocabulary).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerJsonError::Unsupported`] for pipeline settings this crate
+    /// cannot reproduce exactly, such as a non-BPE model, `add_prefix_space`, padding
+    /// or a template post-processor, and [`TokenizerJsonError::InvalidVocabulary`] if
+    /// the vocabulary does not give one ID to each token the merges and added tokens
+    /// define.
+    #[cfg(feature = "json")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, TokenizerJsonError> {
+        tokenizer_json::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Loads the original GPT-2 or RoBERTa artifacts: an `encoder.json` mapping tokens
+    /// to IDs and a `vocab.bpe` merges file (feature `json`).
+    ///
+    /// The merges are validated like [`from_merges_file`](BpeTokenizer::from_merges_file).
+    /// Encoder entries that are neither byte-level base tokens nor merge products,
+    /// such as `<|endoftext|>` or RoBERTa's `<s>` and `<mask>`, become special
+    /// tokens. IDs are taken from `encoder.json`, so they match the published model
+    /// even where its order differs from [`Vocabulary::new`]'s (GPT-2 puts
+    /// `<|endoftext|>` last, RoBERTa puts its special tokens at both ends). The
+    /// pre-tokenizer splits whitespace like GPT-2's encoder, see
+    /// [`PreTokenizer::with_whitespace_lookahead`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerJsonError::Merges`] for a malformed merges file and
+    /// [`TokenizerJsonError::InvalidVocabulary`] if the encoder does not give one ID
+    /// to each base token, merge product and special token.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let gpt2 = BpeTokenizer::from_gpt2_files("encoder.json", "vocab.bpe").unwrap();
+    ///
+    /// assert_eq!(gpt2.encode("Hello world"), vec![15496, 995]);
+    /// assert_eq!(gpt2.encode("<|endoftext|>"), vec![50256]);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn from_gpt2_files(
+        encoder_json: impl AsRef<Path>,
+        merges_bpe: impl AsRef<Path>,
+    ) -> Result<Self, TokenizerJsonError> {
+        tokenizer_json::from_gpt2(
+            &fs::read_to_string(encoder_json)?,
+            &fs::read_to_string(merges_bpe)?,
+        )
+    }
+
+    /// Returns OpenAI's GPT-2 tokenizer from the `encoder.json` and `vocab.bpe`
+    /// files embedded in the crate (feature `pretrained-gpt2`).
+    ///
+    /// Encodes exactly like [`from_gpt2_files`](BpeTokenizer::from_gpt2_files) on the
+    /// published files, with `<|endoftext|>` as the only special token, without
+    /// reading or downloading anything at run time. The embedded files are parsed on
+    /// every call, so build the tokenizer once and keep it.
+    ///
+    /// The files are in `data/gpt2`, redistributed under OpenAI's modified MIT
+    /// license.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let gpt2 = BpeTokenizer::gpt2();
+    ///
+    /// assert_eq!(gpt2.encode("Hello world"), vec![15496, 995]);
+    /// assert_eq!(gpt2.encode("<|endoftext|>"), vec![50256]);
+    /// ```
+    #[cfg(feature = "pretrained-gpt2")]
+    pub fn gpt2() -> Self {
+        tokenizer_json::from_gpt2(
+            include_str!("../data/gpt2/encoder.json"),
+            include_str!("../data/gpt2/vocab.bpe"),
+        )
+        .expect("bundled GPT-2 files are valid")
+    }
+
+    /// Writes the merge rules as a plain `merges.txt` file for other BPE
+    /// implementations, such as Hugging Face's `BPE::from_file`.
+    ///
+    /// Unlike [`save_merges`](BpeTokenizer::save_merges), the `#version` header
+    /// carries no [`Metadata`], so strict readers accept the file. Pair it with
+    /// `export_vocab_json` (feature `json`) for the token IDs.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from creating or writing the file.
+    pub fn export_merges_txt(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        fs::write(path, format_merges(self.merges()))
+    }
+
+    /// Writes the vocabulary as a `vocab.json` object mapping every token, special
+    /// tokens included, to its ID (feature `json`).
+    ///
+    /// Together with [`export_merges_txt`](BpeTokenizer::export_merges_txt) this is
+    /// the layout of GPT-2's `encoder.json` and `vocab.bpe`, which Hugging Face's
+    /// `BPE::from_file` and [`from_gpt2_files`](BpeTokenizer::from_gpt2_files) load.
+    /// The files hold only the model: to match [`encode`](BpeTokenizer::encode) from
+    /// Python, pair the model with a `ByteLevel` pre-tokenizer and decoder and
+    /// register the special tokens, or export a complete
+    /// [`tokenizer.json`](BpeTokenizer::save) instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerJsonError::Io`] if the file cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let (vocab, merges) = (dir.path().join("vocab.json"), dir.path().join("merges.txt"));
+    /// let corpus = ["hello world", "hello there"];
+    /// let tokenizer =
+    ///     BpeTokenizer::from_trainer(&Trainer::new(5), &corpus, vec!["<|endoftext|>".to_string()]);
+    ///
+    /// tokenizer.export_vocab_json(&vocab).unwrap();
+    /// tokenizer.export_merges_txt(&merges).unwrap();
+    /// let loaded = BpeTokenizer::from_gpt2_files(&vocab, &merges).unwrap();
+    ///
+    /// assert_eq!(loaded.encode("hello<|endoftext|>"), tokenizer.encode("hello<|endoftext|>"));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn export_vocab_json(&self, path: impl AsRef<Path>) -> Result<(), TokenizerJsonError> {
+        fs::write(path, tokenizer_json::to_vocab_json(self.vocabulary())?)?;
+        Ok(())
+    }
+
+    /// Writes the tokenizer as GGUF key/value metadata for llama.cpp-based runtimes.
+    ///
+    /// The file holds no tensors, only the `tokenizer.ggml.*` keys of a GPT-2 style
+    /// BPE vocabulary: tokens in ID order, their types (special tokens are `CONTROL`),
+    /// merges, and `<|endoftext|>` as the BOS and EOS token when it is a special
+    /// token. `general.architecture` is `gpt2` and `general.name` comes from the
+    /// [`Metadata`]. Copy the keys into a model's GGUF file with llama.cpp's
+    /// `gguf-py` tools, or load the file directly as a vocabulary-only model, e.g.
+    /// with `llama-tokenize --vocab-only`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`GgufError::Unsupported`] if llama.cpp would tokenize differently:
+    /// with a non-default normalizer, an encode strategy other than
+    /// [`EncodeStrategy::MergeRank`], or a pre-tokenizer other than GPT-2's with
+    /// [whitespace lookahead](PreTokenizer::with_whitespace_lookahead), which is what
+    /// llama.cpp's `gpt-2` pre-tokenizer applies. Returns [`GgufError::Io`] if the
+    /// file cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, PreTokenizer, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("tokenizer.gguf");
+    /// let tokenizer =
+    ///     BpeTokenizer::from_trainer(&Trainer::new(5), &["hello world"], vec!["<|endoftext|>".into()])
+    ///         .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true));
+    ///
+    /// tokenizer.export_gguf(&path).unwrap();
+    ///
+    /// assert!(std::fs::read(&path).unwrap().starts_with(b"GGUF"));
+    /// ```
+    pub fn export_gguf(&self, path: impl AsRef<Path>) -> Result<(), GgufError> {
+        gguf::write(self, std::io::BufWriter::new(fs::File::create(path)?))
+    }
+
+    /// Loads an OpenAI tiktoken encoding from the contents of its `.tiktoken` rank
+    /// file and its special token table, so IDs match tiktoken's.
+    ///
+    /// Each line of the file is a base64 token and its rank, which becomes its ID.
+    /// Merge rules are recovered from the ranks: a token is split where tiktoken's
+    /// own merge loop, restricted to lower-ranked tokens, leaves two parts. IDs that
+    /// neither the file nor `special_tokens` use, such as the gaps in
+    /// `cl100k_base`'s special range, are filled with unused `<|reserved_N|>`
+    /// special tokens.
+    ///
+    /// The pre-tokenizer is GPT-2's with
+    /// [whitespace lookahead](PreTokenizer::with_whitespace_lookahead), which is
+    /// exactly what `r50k_base` and `p50k_base` use. `cl100k_base` and `o200k_base`
+    /// split text with other patterns (case-insensitive contractions, digits in
+    /// groups of three, newlines kept apart), so this is an approximation for them:
+    /// set a closer one with [`with_pre_tokenizer`](BpeTokenizer::with_pre_tokenizer)
+    /// and expect counts to differ from tiktoken's.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TiktokenError::Malformed`] for a line that is not a base64 token and
+    /// a rank, and [`TiktokenError::InvalidRanks`] if some byte has no rank, a token
+    /// cannot be built from two lower-ranked ones, two tokens share an ID, or the
+    /// IDs leave more gaps than there are tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let ranks = std::fs::read_to_string("cl100k_base.tiktoken").unwrap();
+    /// let special = [
+    ///     ("<|endoftext|>", 100257),
+    ///     ("<|fim_prefix|>", 100258),
+    ///     ("<|fim_middle|>", 100259),
+    ///     ("<|fim_suffix|>", 100260),
+    ///     ("<|endofprompt|>", 100276),
+    /// ];
+    /// let cl100k = BpeTokenizer::from_tiktoken(&ranks, &special).unwrap();
+    ///
+    /// assert_eq!(cl100k.vocabulary().len(), 100277);
+    /// assert_eq!(cl100k.encode("<|endofprompt|>"), vec![100276]);
+    /// ```
+    pub fn from_tiktoken(
+        contents: &str,
+        special_tokens: &[(&str, u32)],
+    ) -> Result<Self, TiktokenError> {
+        tiktoken::from_tiktoken(contents, special_tokens)
+    }
+
+    /// Reads a `.tiktoken` rank file and loads it with
+    /// [`from_tiktoken`](BpeTokenizer::from_tiktoken).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let r50k = BpeTokenizer::from_tiktoken_file(
+    ///     "r50k_base.tiktoken",
+    ///     &[("<|endoftext|>", 50256)],
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(r50k.encode("Hello world"), vec![15496, 995]);
+    /// ```
+    pub fn from_tiktoken_file(
+        path: impl AsRef<Path>,
+        special_tokens: &[(&str, u32)],
+    ) -> Result<Self, TiktokenError> {
+        tiktoken::from_tiktoken(&fs::read_to_string(path)?, special_tokens)
+    }
+
+    /// Returns the tokenizer's complete state as a [`TokenizerConfig`], for example to
+    /// serialize it with the `serde` feature.
+    ///
+    /// The vocabulary is only included when its IDs differ from the layout
+    /// [`Vocabulary::new`] would give.
+    pub fn config(&self) -> TokenizerConfig {
+        let derived = Vocabulary::new(self.special_tokens().to_vec(), self.merges().to_vec());
+        let vocabulary = self.vocabulary();
+        let default_layout =
+            vocabulary.len() == derived.len() && vocabulary.iter().eq(derived.iter());
+
+        TokenizerConfig {
+            vocabulary: (!default_layout).then(|| vocabulary.clone()),
+            merges: self.merges().to_vec(),
+            special_tokens: self.special_tokens().to_vec(),
+            normalizer: self.normalizer().clone(),
+            pre_tokenizer: self.pre_tokenizer().clone(),
+            encode_strategy: self.encode_strategy(),
+            unknown_id_mode: self.decoder.unknown_id_mode().clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
+
+    /// Builds a tokenizer from a [`TokenizerConfig`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the config has a vocabulary that does not give
+    /// one ID to each base token, merge product and special token.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let (ranked, _) = tokenizer.rank_ids_by_frequency(&["zzz"]);
+    ///
+    /// let rebuilt = BpeTokenizer::from_config(ranked.config()).unwrap();
+    /// assert_eq!(rebuilt.encode("z"), vec![0]);
+    /// ```
+    pub fn from_config(config: TokenizerConfig) -> Result<Self, ConfigError> {
+        let tokenizer = BpeTokenizer::new(config.merges, config.special_tokens)
+            .with_normalizer(config.normalizer)
+            .with_pre_tokenizer(config.pre_tokenizer)
+            .with_encode_strategy(config.encode_strategy)
+            .with_unknown_id_mode(config.unknown_id_mode)
+            .with_metadata(config.metadata);
+        let Some(vocabulary) = config.vocabulary else {
+            return Ok(tokenizer);
+        };
+
+        let ids: HashMap<&str, u32> = vocabulary.iter().map(|(id, token)| (token, id)).collect();
+        match id_remap::file_order(tokenizer.vocabulary(), &ids) {
+            Ok(Some(new_to_old)) => Ok(tokenizer.renumbered(&new_to_old)),
+            Ok(None) => Ok(tokenizer),
+            Err(reason) => Err(ConfigError(reason)),
+        }
+    }
+
+    /// Replaces the vocabulary with `vocabulary`, which must hold the same tokens,
+    /// possibly under different IDs.
+    ///
+    /// The tokenizer keeps `vocabulary` itself rather than a copy, so a vocabulary
+    /// opened with `Vocabulary::open_mapped` (feature `mmap`) stays mapped: worker processes that
+    /// load the merges and map the same vocabulary file share its token strings.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] unless `vocabulary` gives every token of the
+    /// merges and special tokens a distinct ID below its length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// let tokenizer = BpeTokenizer::new(merges.clone(), vec![]);
+    /// let (ranked, _) = tokenizer.rank_ids_by_frequency(&["hi hi"]);
+    ///
+    /// let restored = BpeTokenizer::new(merges, vec![])
+    ///     .with_vocabulary(ranked.vocabulary().clone())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(restored.encode("hi"), ranked.encode("hi"));
+    /// ```
+    pub fn with_vocabulary(self, vocabulary: Vocabulary) -> Result<Self, ConfigError> {
+        let ids: HashMap<&str, u32> = vocabulary.iter().map(|(id, token)| (token, id)).collect();
+        id_remap::file_order(self.vocabulary(), &ids).map_err(ConfigError)?;
+
+        Ok(self.with_replaced_vocabulary(vocabulary))
+    }
+
+    /// Saves the tokenizer in a compact binary form (feature `bincode`).
+    ///
+    /// The file holds the same state as [`config`](BpeTokenizer::config) encoded
+    /// with bincode, and loads several times faster than `merges.txt` or
+    /// `tokenizer.json` because no text has to be parsed or validated. It is meant
+    /// for service startup, not for exchange: the layout follows this crate's types.
+    /// Loading still builds the vocabulary and merge hash tables; `save_mapped` and
+    /// `open_mapped` (features `bincode` and `mmap`) avoid that.
+    ///
+    /// The payload follows a 28-byte header: [`BINARY_FORMAT_MAGIC`], the
+    /// [`BINARY_FORMAT_VERSION`] as a little-endian `u32`, then the payload length
+    /// and its XXH3-64 checksum as little-endian `u64`s. Releases that change the
+    /// layout bump the version and keep reading older files; a file from a newer
+    /// release is rejected rather than misread.
+    ///
+    /// [`BINARY_FORMAT_MAGIC`]: crate::BINARY_FORMAT_MAGIC
+    /// [`BINARY_FORMAT_VERSION`]: crate::BINARY_FORMAT_VERSION
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WireError`] if the file cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("tokenizer.bin");
+    /// let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(5), &["hello hello"], vec![]);
+    ///
+    /// tokenizer.save_binary(&path).unwrap();
+    /// let loaded = BpeTokenizer::load_binary(&path).unwrap();
+    ///
+    /// assert_eq!(loaded.encode("hello"), tokenizer.encode("hello"));
+    /// ```
+    #[cfg(feature = "bincode")]
+    pub fn save_binary(&self, path: impl AsRef<Path>) -> Result<(), WireError> {
+        let mut writer = std::io::BufWriter::new(fs::File::create(path)?);
+        wire::write_config(&mut writer, &self.config())?;
+        Ok(std::io::Write::flush(&mut writer)?)
+    }
+
+    /// Loads a tokenizer saved with [`save_binary`](BpeTokenizer::save_binary)
+    /// (feature `bincode`).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`WireError::NotATokenizer`] for a file without the header,
+    /// [`WireError::UnsupportedVersion`] for one written by a newer release,
+    /// [`WireError::LengthMismatch`] or [`WireError::ChecksumMismatch`] for a
+    /// truncated or corrupted file, and [`WireError::InvalidConfig`] if the
+    /// vocabulary does not match the merges.
+    #[cfg(feature = "bincode")]
+    pub fn load_binary(path: impl AsRef<Path>) -> Result<Self, WireError> {
+        let bytes = fs::read(path)?;
+        Ok(Self::from_config(wire::read_config(bytes.as_slice())?)?)
+    }
+
+    /// Saves the tokenizer in a layout that [`open_mapped`](BpeTokenizer::open_mapped)
+    /// reads in place (features `bincode` and `mmap`).
+    ///
+    /// The file holds, in order:
+    ///
+    /// - a 28-byte header like that of [`save_binary`](BpeTokenizer::save_binary):
+    ///   [`MAPPED_TOKENIZER_MAGIC`], the layout version
+    ///   ([`MAPPED_FORMAT_VERSION`]) as a little-endian `u32`, then the payload
+    ///   length and the XXH3-64 checksum of the payload as little-endian `u64`s;
+    /// - the special tokens, normalizer, pre-tokenizer, encode strategy, unknown-ID
+    ///   mode and metadata as bincode, after their length as a little-endian `u64`;
+    /// - the vocabulary in the layout of [`Vocabulary::save_mapped`], after its
+    ///   length as a little-endian `u64`;
+    /// - the merge rules: their count `n` as a little-endian `u64`, `2n + 1`
+    ///   little-endian `u64` offsets of the rule halves, the slot count of the rank
+    ///   table as a little-endian `u64`, the table's little-endian `u32` ranks
+    ///   (`u32::MAX` in empty slots, probed linearly from the XXH3-64 hash of a
+    ///   rule's halves joined, seeded with the first half's length), then the
+    ///   halves' UTF-8 bytes in rank order.
+    ///
+    /// Everything but the settings is used in place when opening, so nothing has
+    /// to be rebuilt.
+    ///
+    /// [`MAPPED_TOKENIZER_MAGIC`]: crate::MAPPED_TOKENIZER_MAGIC
+    /// [`MAPPED_FORMAT_VERSION`]: crate::MAPPED_FORMAT_VERSION
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`WireError`] if the file cannot be written.
+    #[cfg(all(feature = "bincode", feature = "mmap"))]
+    pub fn save_mapped(&self, path: impl AsRef<Path>) -> Result<(), WireError> {
+        let settings = TokenizerConfig {
+            vocabulary: None,
+            merges: Vec::new(),
+            ..self.config()
+        };
+        let mut writer = std::io::BufWriter::new(fs::File::create(path)?);
+        wire::write_mapped(&mut writer, &settings, self.vocabulary(), self.merges())?;
+        Ok(std::io::Write::flush(&mut writer)?)
+    }
+
+    /// Opens a tokenizer written by [`save_mapped`](BpeTokenizer::save_mapped) as a
+    /// read-only memory mapping (features `bincode` and `mmap`).
+    ///
+    /// This is the fastest way to start a tokenizer: the vocabulary and the merge
+    /// rules are read from the mapping instead of being copied into `String`s and
+    /// hash maps, so a GPT-2-sized tokenizer opens in about a millisecond rather
+    /// than the tens of milliseconds [`load_binary`](BpeTokenizer::load_binary)
+    /// spends building them. Opening still checks the whole file: the checksum,
+    /// every offset, the order of the vocabulary index and every slot of the merge
+    /// rank table, so a damaged or hand-edited file is rejected rather than giving
+    /// wrong tokens later. Worker processes that open the same file share one copy
+    /// through the page cache. Encoding looks merge ranks up in the file's hash
+    /// table and tokens by binary search, which is somewhat slower than for a
+    /// tokenizer built in memory. The encoder's filter of mergeable byte pairs and
+    /// the decoder's token lengths are built on first use, and
+    /// [`merges`](BpeTokenizer::merges) copies the rules out the first time it is
+    /// called.
+    ///
+    /// The file must not be modified or truncated while it is mapped, as for
+    /// [`Vocabulary::open_mapped`].
+    ///
+    /// # Errors
+    ///
+    /// Returns the header errors of [`load_binary`](BpeTokenizer::load_binary), and
+    /// [`WireError::Io`] with [`std::io::ErrorKind::InvalidData`] if a section does
+    /// not have the expected layout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("tokenizer.map");
+    /// let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(5), &["hello hello"], vec![]);
+    ///
+    /// tokenizer.save_mapped(&path).unwrap();
+    /// let mapped = BpeTokenizer::open_mapped(&path).unwrap();
+    ///
+    /// assert!(mapped.vocabulary().is_mapped());
+    /// assert_eq!(mapped.encode("hello"), tokenizer.encode("hello"));
+    /// ```
+    #[cfg(all(feature = "bincode", feature = "mmap"))]
+    pub fn open_mapped(path: impl AsRef<Path>) -> Result<Self, WireError> {
+        let (settings, vocabulary, merge_table) = wire::open_mapped(path)?;
+        let decoder =
+            Decoder::new(vocabulary.clone()).with_special_tokens(&settings.special_tokens);
+        let encoder = Encoder::from_merge_table(
+            merge_table,
+            PreTokenizer::new(),
+            vocabulary.clone(),
+            settings.special_tokens,
+        );
+
+        Ok(BpeTokenizer {
+            encoder,
+            decoder,
+            metadata: Metadata::default(),
+        }
+        .with_normalizer(settings.normalizer)
+        .with_pre_tokenizer(settings.pre_tokenizer)
+        .with_encode_strategy(settings.encode_strategy)
+        .with_unknown_id_mode(settings.unknown_id_mode)
+        .with_metadata(settings.metadata))
+    }
+
+    /// Attaches descriptive [`Metadata`] that is saved with the merges.
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Returns the metadata attached to this tokenizer. It is empty unless set with
+    /// [`with_metadata`](BpeTokenizer::with_metadata) or loaded from a file.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Encodes text into a sequence of token IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to encode
+    ///
+    /// # Returns
+    ///
+    /// A vector of token IDs representing the encoded text.
+    ///
+    /// # Panics
+    ///
+    /// Under [`Strictness::Strict`](crate::Strictness::Strict), panics if a produced
+    /// token is missing from the vocabulary; see [`try_encode`](BpeTokenizer::try_encode).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let ids = tokenizer.encode("AB");
+    /// assert_eq!(ids, vec![32, 33]);
+    /// ```
+    pub fn encode(&self, text: &str) -> Vec<u32> {
+        self.encoder.encode(text)
+    }
+
+    /// Encodes text into a sequence of token IDs, returning an error instead of
+    /// panicking when a token is missing from the vocabulary.
+    ///
+    /// Tokenizers built by this crate always have every token their merges produce,
+    /// so this only fails for a vocabulary assembled or loaded out of sync with the
+    /// merges. Services that load tokenizers from untrusted configuration should use
+    /// it to turn such a mismatch into an error response instead of a crash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] naming the missing token and the chunk of input it
+    /// was produced from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![("a".into(), "b".into())], vec![]);
+    ///
+    /// assert_eq!(tokenizer.try_encode("ab"), Ok(vec![256]));
+    /// ```
+    pub fn try_encode(&self, text: &str) -> Result<Vec<u32>, EncodeError> {
+        self.encoder.try_encode(text)
+    }
+
+    /// Encodes text with special tokens, unknown-token handling and an input length
+    /// limit set for this call only.
+    ///
+    /// See [`EncodeOptions`]. The tokenizer itself is not changed, so trusted and
+    /// untrusted text can be encoded by the same instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::InputTooLong`] if the text exceeds the length limit,
+    /// [`TokenizerError::UnknownToken`] if an extra token is not in the vocabulary,
+    /// and [`TokenizerError::Encode`] if a produced token is missing from it under
+    /// [`Strictness::Strict`](crate::Strictness::Strict).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, EncodeOptions, TokenizerError};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+    /// let untrusted = EncodeOptions::new().disable_all().with_max_input_len(20);
+    ///
+    /// let ids = tokenizer.encode_with_options("<|endoftext|>", &untrusted).unwrap();
+    /// assert!(!ids.contains(&0));
+    /// assert_eq!(tokenizer.decode(&ids), "<|endoftext|>");
+    /// assert_eq!(
+    ///     tokenizer.encode_with_options(&"x".repeat(21), &untrusted),
+    ///     Err(TokenizerError::InputTooLong { len: 21, max: 20 })
+    /// );
+    /// ```
+    pub fn encode_with_options(
+        &self,
+        text: &str,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u32>, TokenizerError> {
+        self.encoder.encode_with_options(text, options)
+    }
+
+    /// Encodes text and keeps token strings and byte offsets alongside the IDs.
+    ///
+    /// Offsets refer to the normalized text, which is the input itself unless a
+    /// normalizer is configured. See [`Encoding`] for grapheme-aware views of the
+    /// offsets suitable for highlighting tokens in a UI.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to encode
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// let tokenizer = BpeTokenizer::new(merges, vec![]);
+    ///
+    /// let encoding = tokenizer.encode_with_offsets("hi!");
+    /// assert_eq!(encoding.tokens(), &["hi", "!"]);
+    /// assert_eq!(encoding.offsets(), &[(0, 2), (2, 3)]);
+    /// ```
+    pub fn encode_with_offsets(&self, text: &str) -> Encoding {
+        self.encoder.encode_with_offsets(text)
+    }
+
+    /// Encodes text with offsets, returning an error instead of panicking when a
+    /// token is missing from the vocabulary.
+    ///
+    /// See [`Encoder::try_encode_with_offsets`].
+    pub fn try_encode_with_offsets(&self, text: &str) -> Result<Encoding, EncodeError> {
+        self.encoder.try_encode_with_offsets(text)
+    }
+
+    /// Finds the tokens that change when `old_text`, encoded as `old`, is edited into
+    /// `new_text`, for editors and caches that keep token counts up to date on every
+    /// keystroke.
+    ///
+    /// `old` must be the result of [`encode_with_offsets`](BpeTokenizer::encode_with_offsets)
+    /// for `old_text`. Both texts are still pre-tokenized in full, but only the words
+    /// that differ are merged again, and the returned [`TokenEdit`] covers just the
+    /// tokens that differ.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`EncodeError`] if a changed word produces a token missing from
+    /// the vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let old = tokenizer.encode_with_offsets("fn main() {}");
+    ///
+    /// let edit = tokenizer.retokenize("fn main() {}", &old, "fn main(x) {}").unwrap();
+    /// assert_eq!(edit.token_delta(), 1);
+    ///
+    /// let mut ids = old.ids().to_vec();
+    /// edit.apply(&mut ids);
+    /// assert_eq!(ids, tokenizer.encode("fn main(x) {}"));
+    /// ```
+    pub fn retokenize(
+        &self,
+        old_text: &str,
+        old: &Encoding,
+        new_text: &str,
+    ) -> Result<TokenEdit, EncodeError> {
+        retokenize::diff(&self.encoder, old_text, old, new_text)
+    }
+
+    /// Encodes a structured record, such as title, body and metadata, in one call.
+    ///
+    /// Each field's separator must be one of the tokenizer's special tokens. Special
+    /// tokens inside field text are encoded as ordinary text, so untrusted fields
+    /// cannot inject separators. The result records the token and byte range of every field in
+    /// [`Encoding::fields`], so retrieval and embedding pipelines can pool or
+    /// truncate fields individually.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TokenizerError::UnknownToken`] if a separator is not a special token
+    /// and [`TokenizerError::Encode`] if a token produced from field text is missing
+    /// from the vocabulary.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Field};
+    ///
+    /// let special_tokens = vec!["<title>".to_string(), "<body>".to_string()];
+    /// let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+    ///
+    /// let encoding = tokenizer
+    ///     .encode_fields(&[
+    ///         Field::new("title", "<title>", "BPE"),
+    ///         Field::new("body", "<body>", "Byte pairs."),
+    ///     ])
+    ///     .unwrap();
+    ///
+    /// let title = encoding.field("title").unwrap();
+    /// assert_eq!(encoding.tokens()[title.tokens.clone()], ["B", "P", "E"]);
+    /// ```
+    pub fn encode_fields(&self, fields: &[Field<'_>]) -> Result<Encoding, TokenizerError> {
+        self.encoder.encode_fields(fields)
+    }
+
+    /// Decodes a sequence of token IDs back into text.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - Slice of token IDs to decode
+    ///
+    /// # Returns
+    ///
+    /// The decoded text as a UTF-8 string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let text = tokenizer.decode(&[32, 33]);
+    /// assert_eq!(text, "AB");
+    /// ```
+    pub fn decode(&self, ids: &[u32]) -> String {
+        self.decoder.decode(ids)
+    }
+
+    /// Decodes token IDs, returning an error instead of panicking.
+    ///
+    /// Inference servers decoding IDs from clients or from a model with a larger
+    /// output layer should use this, or [`decode_lenient`](BpeTokenizer::decode_lenient),
+    /// so a bad sequence fails one request rather than the process.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for an ID outside the vocabulary,
+    /// [`DecodeError::NotByteLevel`] for a token that has no byte-level form and
+    /// [`DecodeError::InvalidUtf8`] if the IDs split a multi-byte character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, DecodeError};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    ///
+    /// assert_eq!(tokenizer.try_decode(&[32, 33]), Ok("AB".to_string()));
+    /// assert_eq!(tokenizer.try_decode(&[32, 9999]), Err(DecodeError::UnknownId(9999)));
+    /// ```
+    pub fn try_decode(&self, ids: &[u32]) -> Result<String, DecodeError> {
+        self.decoder.try_decode(ids)
+    }
+
+    /// Decodes token IDs, replacing split multi-byte characters with U+FFFD.
+    ///
+    /// See [`Decoder::decode_lossy`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for an ID outside the vocabulary.
+    pub fn decode_lossy(&self, ids: &[u32]) -> Result<String, DecodeError> {
+        self.decoder.decode_lossy(ids)
+    }
+
+    /// Decodes each ID on its own, returning the text it contributes paired with
+    /// the ID, for views that color decoded text by token such as log-probability
+    /// visualizers.
+    ///
+    /// The fragments concatenate to what [`decode_lenient`](BpeTokenizer::decode_lenient)
+    /// returns. A multi-byte character split across tokens is attributed to the token
+    /// completing it, so the tokens before it get an empty fragment. Unknown IDs are
+    /// rendered according to the [`UnknownIdMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["<|endoftext|>".to_string()]);
+    /// let ids = tokenizer.encode("hé<|endoftext|>");
+    ///
+    /// let fragments = tokenizer.split_decoded_by_tokens(&ids);
+    /// let texts: Vec<&str> = fragments.iter().map(|(text, _)| text.as_str()).collect();
+    /// assert_eq!(texts, ["h", "", "é", "<|endoftext|>"]);
+    /// assert!(fragments.iter().map(|&(_, id)| id).eq(ids));
+    /// ```
+    pub fn split_decoded_by_tokens(&self, ids: &[u32]) -> Vec<(String, u32)> {
+        self.decoder.split_decoded(ids)
+    }
+
+    /// Decodes token IDs without failing, regardless of the global strictness.
+    ///
+    /// See [`Decoder::decode_lenient`].
+    pub fn decode_lenient(&self, ids: &[u32]) -> String {
+        self.decoder.decode_lenient(ids)
+    }
+
+    /// Decodes token IDs with per-call settings, such as skipping IDs past the
+    /// vocabulary in model output.
+    ///
+    /// See [`DecodeOptions`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DecodeError::UnknownId`] for an ID outside the vocabulary, unless
+    /// the options set an [`UnknownIdMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, DecodeOptions, UnknownIdMode};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let options = DecodeOptions::new().with_unknown_id_mode(UnknownIdMode::Replace("<?>".into()));
+    ///
+    /// assert_eq!(tokenizer.decode_with_options(&[32, 70000], &options).unwrap(), "A<?>");
+    /// ```
+    pub fn decode_with_options(
+        &self,
+        ids: &[u32],
+        options: &DecodeOptions,
+    ) -> Result<String, DecodeError> {
+        self.decoder.decode_with_options(ids, options)
+    }
+
+    /// Returns the byte length of the text `ids` decode to, without decoding it.
+    ///
+    /// See [`Decoder::decoded_len`] for how unknown IDs are counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let ids = tokenizer.encode("héllo");
+    ///
+    /// assert_eq!(tokenizer.decoded_len(&ids), "héllo".len());
+    /// ```
+    pub fn decoded_len(&self, ids: &[u32]) -> usize {
+        self.decoder.decoded_len(ids)
+    }
+
+    /// Checks that every ID is inside the vocabulary, reporting all offending IDs.
+    ///
+    /// Intended for IDs received from external clients: the error lists each bad ID
+    /// with its position, so a payload can be rejected with a precise message before
+    /// it reaches [`decode`](BpeTokenizer::decode).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidIds`] if any ID is not below the vocabulary size.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// assert!(tokenizer.validate_ids(&[32, 33]).is_ok());
+    ///
+    /// let err = tokenizer.validate_ids(&[32, 300, 33, 9999]).unwrap_err();
+    /// assert_eq!(err.invalid, vec![(1, 300), (3, 9999)]);
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "2 token ID(s) out of range for vocabulary of size 256: 300 at position 1; 9999 at position 3;"
+    /// );
+    /// ```
+    pub fn validate_ids(&self, ids: &[u32]) -> Result<(), InvalidIds> {
+        let vocab_size = self.vocabulary().len();
+        let invalid: Vec<(usize, u32)> = ids
+            .iter()
+            .enumerate()
+            .filter(|&(_, &id)| id as usize >= vocab_size)
+            .map(|(position, &id)| (position, id))
+            .collect();
+
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidIds {
+                vocab_size,
+                invalid,
+            })
+        }
+    }
+
+    /// Encodes a batch of texts.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any text fails to encode. Use
+    /// [`try_encode_batch`](BpeTokenizer::try_encode_batch) to isolate failures.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// assert_eq!(tokenizer.encode_batch(&["A", "BC"]), vec![vec![32], vec![33, 34]]);
+    /// ```
+    pub fn encode_batch(&self, texts: &[&str]) -> Vec<Vec<u32>> {
+        instrumentation::record_batch(texts.len());
+        texts.iter().map(|text| self.encode(text)).collect()
+    }
+
+    /// Encodes a batch of texts, returning one result per text.
+    ///
+    /// A failing text does not affect the others, so a single bad record cannot
+    /// abort a long bulk job.
+    pub fn try_encode_batch(&self, texts: &[&str]) -> Vec<Result<Vec<u32>, TokenizerError>> {
+        instrumentation::record_batch(texts.len());
+        texts
+            .iter()
+            .map(|text| Ok(self.encoder.try_encode(text)?))
+            .collect()
+    }
+
+    /// Decodes a batch of ID sequences.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any sequence fails to decode. Use
+    /// [`try_decode_batch`](BpeTokenizer::try_decode_batch) to isolate failures.
+    pub fn decode_batch<S: AsRef<[u32]>>(&self, batch: &[S]) -> Vec<String> {
+        instrumentation::record_batch(batch.len());
+        batch.iter().map(|ids| self.decode(ids.as_ref())).collect()
+    }
+
+    /// Decodes a batch of ID sequences, returning one result per sequence.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, TokenizerError};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let results = tokenizer.try_decode_batch(&[vec![32], vec![9999]]);
+    ///
+    /// assert_eq!(results[0], Ok("A".to_string()));
+    /// assert_eq!(results[1], Err(TokenizerError::UnknownTokenId(9999)));
+    /// ```
+    pub fn try_decode_batch<S: AsRef<[u32]>>(
+        &self,
+        batch: &[S],
+    ) -> Vec<Result<String, TokenizerError>> {
+        instrumentation::record_batch(batch.len());
+        batch
+            .iter()
+            .map(|ids| Ok(self.decoder.try_decode(ids.as_ref())?))
+            .collect()
+    }
 
-        BpeTokenizer { encoder, decoder }
+    /// Returns warnings about the tokenizer configuration, such as empty, duplicate
+    /// or shadowed special tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Warning};
+    ///
+    /// let special_tokens = vec!["<s>".to_string(), "<s>".to_string()];
+    /// let tokenizer = BpeTokenizer::new(vec![], special_tokens);
+    ///
+    /// assert_eq!(
+    ///     tokenizer.warnings(),
+    ///     vec![Warning::DuplicateSpecialToken { token: "<s>".to_string() }]
+    /// );
+    /// ```
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = special_token_warnings(self.special_tokens());
+        warnings.extend(self.encoder.pre_tokenizer().warnings());
+        warnings
     }
 
-    /// Encodes text into a sequence of token IDs.
+    /// Checks that all 256 byte-level base tokens are in the vocabulary and can be
+    /// emitted, so that any input can be encoded.
+    ///
+    /// Vocabularies built by this crate hold every base token, but a normalizer can
+    /// still make some bytes unreachable, as the example shows. Vocabularies loaded
+    /// from an external file can also lack base tokens; run it after loading one to
+    /// report which are missing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, NewlineMode, Normalizer};
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// assert!(tokenizer.validate_byte_coverage().is_complete());
+    ///
+    /// let unix = tokenizer.with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+    /// assert_eq!(unix.validate_byte_coverage().unreachable, vec![b'\r']);
+    /// ```
+    pub fn validate_byte_coverage(&self) -> ByteCoverage {
+        byte_coverage(self.vocabulary(), self.normalizer())
+    }
+
+    /// Compares this tokenizer with a stored golden copy and lists what changed:
+    /// added and removed merges and special tokens, tokens whose ID moved, and
+    /// differing settings.
+    ///
+    /// Load the golden copy with whichever loader matches how it was stored, such
+    /// as [`BpeTokenizer::from_merges_file`], and fail the pipeline when the diff
+    /// is not empty, so vocabulary drift is caught before a model is trained on it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let golden = BpeTokenizer::new(vec![("h".into(), "e".into())], vec![]);
+    /// let rebuilt = BpeTokenizer::new(vec![("h".into(), "i".into())], vec![]);
+    ///
+    /// assert!(golden.diff_against_golden(&golden).is_empty());
+    /// let diff = rebuilt.diff_against_golden(&golden);
+    /// assert_eq!(diff.to_string(), "- merge 0: \"h\" \"e\"\n+ merge 0: \"h\" \"i\"\n");
+    /// ```
+    pub fn diff_against_golden(&self, golden: &BpeTokenizer) -> TokenizerDiff {
+        TokenizerDiff::new(golden, self)
+    }
+
+    /// Returns the merge rules used by this tokenizer, in rank order.
+    pub fn merges(&self) -> &[(String, String)] {
+        self.encoder.merge_rules()
+    }
+
+    /// Returns the special tokens recognized by this tokenizer.
+    pub fn special_tokens(&self) -> &[String] {
+        self.encoder.special_tokens()
+    }
+
+    /// Returns the vocabulary shared by the encoder and decoder.
+    pub fn vocabulary(&self) -> &Vocabulary {
+        self.encoder.vocabulary()
+    }
+
+    /// Returns every `(token, id)` pair of the vocabulary, ordered by ID.
+    ///
+    /// Unlike a hash map, the order is stable across runs, so the result is safe to
+    /// snapshot in tests or diff between tokenizer versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![("h".to_string(), "i".to_string())], vec![]);
+    /// let vocab = tokenizer.get_vocab();
+    ///
+    /// assert_eq!(vocab.len(), 257);
+    /// assert_eq!(vocab[256], ("hi".to_string(), 256));
+    /// ```
+    pub fn get_vocab(&self) -> Vec<(String, u32)> {
+        self.vocabulary()
+            .iter()
+            .map(|(id, token)| (token.to_string(), id))
+            .collect()
+    }
+
+    pub(crate) fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    /// Builds the dependency graph of this tokenizer's merge rules.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let merges = vec![
+    ///     ("h".to_string(), "e".to_string()),
+    ///     ("he".to_string(), "y".to_string()),
+    /// ];
+    /// let tokenizer = BpeTokenizer::new(merges, vec![]);
+    ///
+    /// assert_eq!(tokenizer.merge_graph().dependencies(1), &[0]);
+    /// ```
+    pub fn merge_graph(&self) -> MergeGraph {
+        MergeGraph::new(self.merges())
+    }
+
+    /// Encodes every text in `corpus` and records how often each merge rule fires.
+    ///
+    /// Rules that never fire are reported by [`MergeUsage::dead_rules`], which makes
+    /// this useful for pruning a vocabulary or judging whether it is oversized for
+    /// the target domain.
     ///
     /// # Arguments
     ///
-    /// * `text` - The text to encode
+    /// * `corpus` - Texts to encode
     ///
     /// # Returns
     ///
-    /// A vector of token IDs representing the encoded text.
+    /// A [`MergeUsage`] with one count per merge rule, indexed by rank.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// let tokenizer = BpeTokenizer::new(merges, vec![]);
+    ///
+    /// let usage = tokenizer.merge_usage(&["hi hi", "bye"]);
+    /// assert_eq!(usage.counts(), &[2]);
+    /// ```
+    pub fn merge_usage(&self, corpus: &[&str]) -> MergeUsage {
+        let mut counts = vec![0; self.merges().len()];
+
+        for text in corpus {
+            self.encoder
+                .encode_observing_merges(text, &mut |rule_idx, count| counts[rule_idx] += count);
+        }
+
+        MergeUsage::new(counts)
+    }
+
+    /// Measures compression on a corpus.
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - Texts to encode
     ///
     /// # Examples
     ///
@@ -84,22 +1604,118 @@ impl BpeTokenizer {
     /// use bpe_tokenizer_rs::BpeTokenizer;
     ///
     /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
-    /// let ids = tokenizer.encode("AB");
-    /// assert_eq!(ids, vec![32, 33]);
+    /// let stats = tokenizer.stats(&["hello", "world"]);
+    ///
+    /// assert_eq!(stats.tokens, 10);
+    /// assert_eq!(stats.bytes_per_token(), 1.0);
     /// ```
-    pub fn encode(&self, text: &str) -> Vec<u32> {
-        self.encoder.encode(text)
+    pub fn stats(&self, corpus: &[&str]) -> TokenizerStats {
+        TokenizerStats {
+            vocab_size: self.vocabulary().len(),
+            texts: corpus.len(),
+            characters: corpus.iter().map(|text| text.chars().count()).sum(),
+            bytes: corpus.iter().map(|text| text.len()).sum(),
+            tokens: corpus.iter().map(|text| self.encode(text).len()).sum(),
+        }
     }
 
-    /// Decodes a sequence of token IDs back into text.
+    /// Measures how many tokens per character this tokenizer needs for each language.
+    ///
+    /// Each entry of `samples` pairs a caller-chosen label with sample texts in that
+    /// language. Comparing the resulting [`LanguageCoverage::tokens_per_char`] values
+    /// shows which languages a vocabulary serves poorly, which helps decide corpus
+    /// mixing weights before retraining. Results are returned in input order.
     ///
     /// # Arguments
     ///
-    /// * `ids` - Slice of token IDs to decode
+    /// * `samples` - `(label, texts)` pairs, one per language
     ///
-    /// # Returns
+    /// # Examples
     ///
-    /// The decoded text as a UTF-8 string.
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let report = tokenizer.language_coverage(&[("en", &["hello"]), ("ru", &["привет"])]);
+    ///
+    /// assert_eq!(report[0].tokens_per_char(), 1.0);
+    /// assert_eq!(report[1].tokens_per_char(), 2.0);
+    /// ```
+    pub fn language_coverage(&self, samples: &[(&str, &[&str])]) -> Vec<LanguageCoverage> {
+        samples
+            .iter()
+            .map(|(language, texts)| LanguageCoverage {
+                language: language.to_string(),
+                texts: texts.len(),
+                characters: texts.iter().map(|text| text.chars().count()).sum(),
+                tokens: texts.iter().map(|text| self.encode(text).len()).sum(),
+            })
+            .collect()
+    }
+
+    /// Measures how many tokens per word this tokenizer needs on each time slice of a
+    /// corpus.
+    ///
+    /// Each entry of `slices` pairs a caller-chosen label, such as a month or a
+    /// quarter, with texts from that period, oldest first. As language moves on, new
+    /// names and terms appear that the vocabulary never learned and splits into
+    /// several tokens, so [`SliceDrift::tokens_per_word`] and
+    /// [`SliceDrift::split_word_share`] creep up. Compare each slice with the one the
+    /// tokenizer was trained on using [`SliceDrift::drift_from`]; a sustained rise of
+    /// a few percent is a sign that retraining or extending the vocabulary would pay
+    /// off. Results are returned in input order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let corpus = ["the weather is nice today", "the weather was nice"];
+    /// let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(40), &corpus, vec![]);
+    ///
+    /// let report = tokenizer.token_drift(&[
+    ///     ("2023", &["the weather is nice"]),
+    ///     ("2024", &["the rizz is unhinged"]),
+    /// ]);
+    ///
+    /// assert_eq!(report[0].tokens_per_word(), 1.0);
+    /// assert!(report[1].drift_from(&report[0]) > 0.5);
+    /// ```
+    pub fn token_drift(&self, slices: &[(&str, &[&str])]) -> Vec<SliceDrift> {
+        slices
+            .iter()
+            .map(|(slice, texts)| {
+                let mut drift = SliceDrift {
+                    slice: slice.to_string(),
+                    texts: texts.len(),
+                    words: 0,
+                    tokens: 0,
+                    split_words: 0,
+                };
+                for text in *texts {
+                    self.encoder.for_each_word(text, &mut |word, tokens| {
+                        if word.chars().any(|ch| !ch.is_whitespace()) {
+                            drift.words += 1;
+                            drift.tokens += tokens;
+                            drift.split_words += usize::from(tokens > 1);
+                        }
+                    });
+                }
+                drift
+            })
+            .collect()
+    }
+
+    /// Shows how the token count of `text` would change if each candidate string
+    /// became a single token, before committing to retraining or extending the
+    /// vocabulary.
+    ///
+    /// Candidates are matched in the normalized text like special tokens, before
+    /// pre-tokenization, so they may span spaces and punctuation; special tokens
+    /// still take precedence. [`ExtraTokenSimulation::tokens_after`] adds every
+    /// candidate at once, while each [`CandidateImpact::tokens_saved`] adds that
+    /// candidate alone, so overlapping candidates can save less together than
+    /// their savings sum to. Empty candidates never match.
     ///
     /// # Examples
     ///
@@ -107,17 +1723,237 @@ impl BpeTokenizer {
     /// use bpe_tokenizer_rs::BpeTokenizer;
     ///
     /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
-    /// let text = tokenizer.decode(&[32, 33]);
-    /// assert_eq!(text, "AB");
+    /// let text = "call tokio::spawn, then tokio::spawn again";
+    ///
+    /// let simulation = tokenizer.simulate_with_extra_tokens(text, &["tokio::spawn", "again"]);
+    ///
+    /// assert_eq!(simulation.tokens_before, text.len());
+    /// assert_eq!(simulation.candidates[0].occurrences, 2);
+    /// assert_eq!(simulation.candidates[0].tokens_saved, 22);
+    /// assert_eq!(simulation.tokens_after, text.len() - 22 - 4);
     /// ```
-    pub fn decode(&self, ids: &[u32]) -> String {
-        self.decoder.decode(ids)
+    pub fn simulate_with_extra_tokens(
+        &self,
+        text: &str,
+        candidate_tokens: &[&str],
+    ) -> ExtraTokenSimulation {
+        let (tokens_before, _) = self.encoder.count_with_added_tokens(text, &[]);
+        let (tokens_after, occurrences) =
+            self.encoder.count_with_added_tokens(text, candidate_tokens);
+        let candidates = candidate_tokens
+            .iter()
+            .zip(occurrences)
+            .map(|(&token, occurrences)| {
+                let (tokens, _) = self.encoder.count_with_added_tokens(text, &[token]);
+                CandidateImpact {
+                    token: token.to_string(),
+                    occurrences,
+                    tokens_saved: tokens_before.saturating_sub(tokens),
+                }
+            })
+            .collect();
+
+        ExtraTokenSimulation {
+            tokens_before,
+            tokens_after,
+            candidates,
+        }
+    }
+
+    /// Proposes strings worth adding as single tokens for a domain corpus, ranked by
+    /// estimated token savings.
+    ///
+    /// Candidates are spans of one to three consecutive pre-tokenized words, never
+    /// crossing a special token, that encode to several tokens and occur at least
+    /// twice. A span seen `n` times that takes `k` tokens is estimated to save
+    /// `n * (k - 1)` tokens. Overlapping candidates, such as a word and a phrase
+    /// containing it, share their savings, so check the chosen set with
+    /// [`simulate_with_extra_tokens`](BpeTokenizer::simulate_with_extra_tokens).
+    /// At most `limit` candidates are returned, ties in savings ordered by string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+    /// let corpus = ["let handle = tokio::spawn(task);", "let other = tokio::spawn(other);"];
+    ///
+    /// let candidates = tokenizer.mine_candidates(&corpus, 5);
+    /// let chosen: Vec<&str> = candidates.iter().map(|c| c.token.as_str()).collect();
+    ///
+    /// assert_eq!(chosen[0], " tokio::spawn");
+    /// let simulation = tokenizer.simulate_with_extra_tokens(corpus[1], &chosen[..1]);
+    /// assert_eq!(simulation.tokens_after, simulation.tokens_before - 12);
+    /// ```
+    pub fn mine_candidates(&self, corpus: &[&str], limit: usize) -> Vec<CandidateImpact> {
+        const MAX_SPAN_WORDS: usize = 3;
+
+        // Occurrences and tokens per occurrence of every multi-token span.
+        let mut spans: HashMap<String, (usize, usize)> = HashMap::new();
+        for text in corpus {
+            for run in self.encoder.word_runs(text) {
+                for start in 0..run.len() {
+                    let mut span = String::new();
+                    let mut tokens = 0;
+                    for (word, word_tokens) in run[start..].iter().take(MAX_SPAN_WORDS) {
+                        span.push_str(word);
+                        tokens += word_tokens;
+                        if tokens > 1 {
+                            spans.entry(span.clone()).or_insert((0, tokens)).0 += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut candidates: Vec<CandidateImpact> = spans
+            .into_iter()
+            .filter(|(_, (occurrences, _))| *occurrences > 1)
+            .map(|(token, (occurrences, tokens))| CandidateImpact {
+                token,
+                occurrences,
+                tokens_saved: occurrences * (tokens - 1),
+            })
+            .collect();
+        candidates.sort_unstable_by(|a, b| {
+            b.tokens_saved
+                .cmp(&a.tokens_saved)
+                .then_with(|| a.token.cmp(&b.token))
+        });
+        candidates.truncate(limit);
+        candidates
+    }
+
+    /// Builds a smaller tokenizer that keeps only the most useful merges for `corpus`.
+    ///
+    /// Merge usage is measured on `corpus` and rules are kept from most to least used
+    /// until the vocabulary reaches `target_size` tokens (special tokens and the 256
+    /// byte-level tokens included). A rule is only kept together with the earlier rules
+    /// that produce its operands, so the result always encodes and decodes correctly.
+    /// Rules that never fire on `corpus` are dropped. Surviving rules keep their relative
+    /// order, and special tokens are carried over unchanged. Token IDs change; use
+    /// [`IdRemap::between`] on the two vocabularies to translate stored IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - Representative texts used to rank merges
+    /// * `target_size` - Maximum vocabulary size of the distilled tokenizer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let corpus = ["hello world", "hello there"];
+    /// let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(20), &corpus, vec![]);
+    ///
+    /// let distilled = tokenizer.distill(&corpus, 256 + 4);
+    /// assert!(distilled.vocabulary().len() <= 256 + 4);
+    /// assert_eq!(distilled.decode(&distilled.encode("hello")), "hello");
+    /// ```
+    pub fn distill(&self, corpus: &[&str], target_size: usize) -> BpeTokenizer {
+        let base_size = self.special_tokens().len() + 256;
+        let merge_budget = target_size.saturating_sub(base_size);
+        let usage = self.merge_usage(corpus);
+
+        let merges = distill::select_merges(self.merges(), usage.counts(), merge_budget)
+            .into_iter()
+            .map(|rank| self.merges()[rank].clone())
+            .collect();
+
+        Self::new(merges, self.special_tokens().to_vec())
+    }
+
+    /// Renumbers the non-special token IDs by how often each token occurs in `corpus`.
+    ///
+    /// The most frequent token gets the lowest ID after the special tokens, which keeps
+    /// token dumps more compressible and embedding lookups more cache friendly. Ties,
+    /// including tokens that never occur, keep their original order. Merge rules, their
+    /// ranks and therefore the segmentation are unchanged; only the IDs move. Special
+    /// tokens keep their IDs.
+    ///
+    /// The ID order is not part of the merge list, so keep the returned [`IdRemap`]
+    /// alongside the merges to rebuild the renumbered tokenizer or convert stored IDs.
+    ///
+    /// # Arguments
+    ///
+    /// * `corpus` - Representative texts used to count tokens
+    ///
+    /// # Returns
+    ///
+    /// The renumbered tokenizer and the mapping from old to new IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::BpeTokenizer;
+    ///
+    /// let tokenizer = BpeTokenizer::new(vec![], vec!["<s>".to_string()]);
+    /// let (ranked, remap) = tokenizer.rank_ids_by_frequency(&["aab"]);
+    ///
+    /// assert_eq!(ranked.encode("<s>ab"), vec![0, 1, 2]);
+    /// assert_eq!(ranked.decode(&[1, 2]), "ab");
+    /// assert_eq!(remap.new_id(tokenizer.encode("a")[0]), Some(1));
+    /// ```
+    pub fn rank_ids_by_frequency(&self, corpus: &[&str]) -> (BpeTokenizer, IdRemap) {
+        let mut counts = vec![0; self.vocabulary().len()];
+        for text in corpus {
+            for id in self.encode(text) {
+                counts[id as usize] += 1;
+            }
+        }
+
+        let order = id_remap::frequency_order(&counts, self.special_tokens().len());
+
+        (self.renumbered(&order), IdRemap::from_permutation(&order))
+    }
+
+    /// Returns a copy of this tokenizer in which new ID `i` is old ID `new_to_old[i]`.
+    /// `new_to_old` must be a permutation of all IDs.
+    pub(crate) fn renumbered(&self, new_to_old: &[u32]) -> BpeTokenizer {
+        self.with_replaced_vocabulary(self.vocabulary().permuted(new_to_old))
+    }
+
+    /// Returns a copy of this tokenizer that uses `vocabulary`, which must hold the
+    /// same tokens.
+    fn with_replaced_vocabulary(&self, vocabulary: Vocabulary) -> BpeTokenizer {
+        self.with_merges_and_vocabulary(self.merges().to_vec(), vocabulary)
+    }
+
+    /// Returns a copy of this tokenizer with `merges` and `vocabulary`, which must
+    /// give an ID to each merge product, keeping every other setting.
+    fn with_merges_and_vocabulary(
+        &self,
+        merges: Vec<(String, String)>,
+        vocabulary: Vocabulary,
+    ) -> BpeTokenizer {
+        let encoder = Encoder::new(
+            merges,
+            self.pre_tokenizer().clone(),
+            vocabulary.clone(),
+            self.special_tokens().to_vec(),
+        )
+        .with_normalizer(self.normalizer().clone())
+        .with_strategy(self.encode_strategy())
+        .with_unknown_token_of(&self.encoder)
+        .with_word_cache(self.word_cache_stats().map_or(0, |stats| stats.capacity));
+        let decoder = Decoder::new(vocabulary)
+            .with_special_tokens(self.special_tokens())
+            .with_unknown_id_mode(self.decoder.unknown_id_mode().clone());
+
+        BpeTokenizer {
+            encoder,
+            decoder,
+            metadata: self.metadata.clone(),
+        }
     }
 
     /// Creates a tokenizer by training on the provided texts.
     ///
     /// This is a convenience method that trains a BPE model and creates a tokenizer
-    /// in one step.
+    /// in one step. The trainer's normalizer and pre-tokenizer are carried over to the
+    /// tokenizer.
     ///
     /// # Arguments
     ///
@@ -152,12 +1988,53 @@ impl BpeTokenizer {
         let merges = trainer.train(training_texts);
 
         Self::new(merges, special_tokens)
+            .with_normalizer(trainer.normalizer().clone())
+            .with_pre_tokenizer(trainer.pre_tokenizer().clone())
+    }
+
+    /// Returns a tokenizer whose first IDs are exactly this tokenizer's vocabulary,
+    /// followed by merges newly learned on `training_texts`.
+    ///
+    /// The existing region is frozen: every token keeps its ID and every merge its
+    /// rank, so models trained on this tokenizer only need new embedding rows for
+    /// IDs at or above [`vocabulary().len()`](Vocabulary::len). See
+    /// [`Trainer::train_extension`] for how the new merges are learned; all other
+    /// settings, including the unknown token and the word cache capacity, are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let base = BpeTokenizer::from_trainer(&Trainer::new(3), &["the cat"], vec![]);
+    /// let domain = ["kinase kinase phosphorylates kinase"];
+    /// let extended = base.extend(&Trainer::new(5), &domain);
+    ///
+    /// let frozen = base.vocabulary().len();
+    /// assert_eq!(extended.vocabulary().len(), frozen + 5);
+    /// assert!(base.vocabulary().iter().eq(extended.vocabulary().iter().take(frozen)));
+    /// assert!(extended.encode(domain[0]).len() < base.encode(domain[0]).len());
+    /// ```
+    pub fn extend(&self, trainer: &Trainer, training_texts: &[&str]) -> BpeTokenizer {
+        let extension = trainer.train_extension(self, training_texts);
+        let vocabulary = self.vocabulary().extended(
+            extension
+                .iter()
+                .map(|(first, second)| format!("{}{}", first, second)),
+        );
+        let mut merges = self.merges().to_vec();
+        merges.extend(extension);
+
+        // The frozen region is already valid, and extension merges only add tokens
+        // the vocabulary lacks, so there is nothing to check again.
+        self.with_merges_and_vocabulary(merges, vocabulary)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::NewlineMode;
 
     #[test]
     fn new_creates_tokenizer_with_no_merges() {
@@ -194,7 +2071,7 @@ mod tests {
 
         let ids = tokenizer.encode("");
 
-        assert_eq!(ids, vec![]);
+        assert_eq!(ids, Vec::<u32>::new());
     }
 
     #[test]
@@ -403,6 +2280,41 @@ mod tests {
         assert_eq!(end_ids, vec![1]);
     }
 
+    #[test]
+    fn from_trainer_copies_normalizer() {
+        let trainer =
+            Trainer::new(5).with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+        let tokenizer = BpeTokenizer::from_trainer(&trainer, &["a\r\nb"], vec![]);
+
+        assert_eq!(tokenizer.encode("a\r\nb"), tokenizer.encode("a\nb"));
+    }
+
+    #[test]
+    fn with_merge_prefix_keeps_ids_consistent() {
+        let trainer = Trainer::new(10);
+        let merges = trainer.train(&["hello hello world world"]);
+        let full = BpeTokenizer::new(merges.clone(), vec![]);
+
+        let prefix = BpeTokenizer::with_merge_prefix(&merges, 3, vec![]);
+
+        assert_eq!(prefix.merges(), &merges[..3]);
+        for id in 0..prefix.vocabulary().len() as u32 {
+            assert_eq!(
+                prefix.vocabulary().id_to_token(id),
+                full.vocabulary().id_to_token(id)
+            );
+        }
+    }
+
+    #[test]
+    fn with_merge_prefix_clamps_to_available_merges() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+
+        let tokenizer = BpeTokenizer::with_merge_prefix(&merges, 10, vec![]);
+
+        assert_eq!(tokenizer.merges().len(), 1);
+    }
+
     #[test]
     fn special_tokens_with_text() {
         let special_tokens = vec!["<|endoftext|>".to_string()];
@@ -412,4 +2324,175 @@ mod tests {
 
         assert_eq!(ids, vec![0, 33]);
     }
+
+    #[test]
+    fn try_decode_handles_special_tokens_with_spaces() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<my token>".to_string()]);
+        let ids = tokenizer.encode("hi<my token>");
+
+        assert_eq!(tokenizer.try_decode(&ids).unwrap(), "hi<my token>");
+        assert_eq!(tokenizer.decode(&ids), "hi<my token>");
+    }
+
+    #[test]
+    fn try_decode_batch_isolates_failures() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let e_acute = tokenizer.encode("é");
+
+        let results = tokenizer.try_decode_batch(&[&e_acute[..1], &[9999], &e_acute[..]]);
+
+        assert!(matches!(results[0], Err(TokenizerError::InvalidUtf8(_))));
+        assert_eq!(results[1], Err(TokenizerError::UnknownTokenId(9999)));
+        assert_eq!(results[2], Ok("é".to_string()));
+    }
+
+    #[test]
+    fn batch_decode_writes_special_tokens_verbatim() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<my token>".to_string()]);
+        let ids = tokenizer.encode("hi<my token>");
+
+        assert_eq!(tokenizer.decode_batch(&[&ids]), ["hi<my token>"]);
+        assert_eq!(
+            tokenizer.try_decode_batch(&[&ids]),
+            [Ok("hi<my token>".to_string())]
+        );
+        assert_eq!(tokenizer.decoded_len(&ids), "hi<my token>".len());
+    }
+
+    #[test]
+    fn batch_methods_match_single_calls() {
+        let merges = vec![("h".to_string(), "i".to_string())];
+        let tokenizer = BpeTokenizer::new(merges, vec!["<|endoftext|>".to_string()]);
+        let texts = ["hi there", "<|endoftext|>", ""];
+
+        let batch = tokenizer.encode_batch(&texts);
+
+        for (text, ids) in texts.iter().zip(&batch) {
+            assert_eq!(&tokenizer.encode(text), ids);
+        }
+        assert_eq!(tokenizer.decode_batch(&batch), texts);
+        assert!(tokenizer.try_encode_batch(&texts).iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn rank_ids_by_frequency_preserves_segmentation() {
+        let corpus = ["hello world", "hello there", "the world"];
+        let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(10), &corpus, vec!["<s>".into()]);
+        let (ranked, remap) = tokenizer.rank_ids_by_frequency(&corpus);
+
+        for text in corpus.iter().chain(&["<s>unseen text ✓"]) {
+            let old_ids = tokenizer.encode(text);
+            let new_ids = ranked.encode(text);
+            assert_eq!(remap.apply(&old_ids), new_ids);
+            assert_eq!(ranked.decode(&new_ids), *text);
+        }
+        assert_eq!(ranked.merges(), tokenizer.merges());
+        assert_eq!(ranked.vocabulary().len(), tokenizer.vocabulary().len());
+    }
+
+    #[test]
+    fn rank_ids_by_frequency_assigns_lowest_ids_to_common_tokens() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["zzzyyx"]);
+
+        assert_eq!(ranked.encode("zyx"), vec![0, 1, 2]);
+        assert_eq!(ranked.vocabulary().id_to_token(0), Some("z"));
+    }
+
+    #[test]
+    fn validate_ids_accepts_whole_vocabulary() {
+        let tokenizer = BpeTokenizer::new(vec![("a".into(), "b".into())], vec!["<s>".into()]);
+        let ids: Vec<u32> = (0..tokenizer.vocabulary().len() as u32).collect();
+
+        assert_eq!(tokenizer.validate_ids(&ids), Ok(()));
+        assert_eq!(
+            tokenizer.validate_ids(&[258]),
+            Err(InvalidIds {
+                vocab_size: 258,
+                invalid: vec![(0, 258)],
+            })
+        );
+    }
+
+    #[test]
+    fn longest_match_roundtrips_and_survives_id_ranking() {
+        let corpus = ["the theme of the thesis", "then there were three"];
+        let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(30), &corpus, vec![])
+            .with_encode_strategy(EncodeStrategy::LongestMatch);
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&corpus);
+
+        for text in corpus {
+            assert_eq!(tokenizer.decode(&tokenizer.encode(text)), text);
+        }
+        assert_eq!(ranked.encode_strategy(), EncodeStrategy::LongestMatch);
+    }
+
+    #[test]
+    fn export_merges_txt_leaves_out_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("merges.txt");
+        let merges = vec![("h".to_string(), "i".to_string())];
+        let tokenizer =
+            BpeTokenizer::new(merges, vec![]).with_metadata(Metadata::new().with_name("tiny"));
+
+        tokenizer.export_merges_txt(&path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "#version: 0.2\nh i\n");
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn exported_files_load_in_huggingface_bpe() {
+        use tokenizers::models::bpe::BPE;
+        use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (vocab, merges) = (dir.path().join("vocab.json"), dir.path().join("merges.txt"));
+        let corpus = ["the theme of the thesis", "then there were three ünïcödé"];
+        let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(30), &corpus, vec![]);
+        tokenizer.export_vocab_json(&vocab).unwrap();
+        tokenizer.export_merges_txt(&merges).unwrap();
+
+        let model = BPE::from_file(vocab.to_str().unwrap(), merges.to_str().unwrap())
+            .build()
+            .unwrap();
+        let huggingface = tokenizers::TokenizerBuilder::new()
+            .with_model(model)
+            .with_pre_tokenizer(Some(ByteLevel::default().add_prefix_space(false)))
+            .with_decoder(None::<ByteLevel>)
+            .with_normalizer(None::<tokenizers::normalizers::Sequence>)
+            .with_post_processor(None::<tokenizers::processors::sequence::Sequence>)
+            .build()
+            .unwrap();
+
+        for text in corpus.iter().chain(&["thirty three thesauri"]) {
+            let expected = huggingface.encode(*text, false).unwrap();
+            assert_eq!(tokenizer.encode(text), expected.get_ids(), "{}", text);
+        }
+    }
+
+    #[cfg(feature = "pretrained-gpt2")]
+    #[test]
+    fn bundled_gpt2_matches_published_ids() {
+        let gpt2 = BpeTokenizer::gpt2();
+        let text = "Hello world<|endoftext|> naïve café";
+
+        assert_eq!(gpt2.vocabulary().len(), 50257);
+        assert_eq!(gpt2.merges().len(), 50000);
+        assert_eq!(gpt2.encode("Hello world"), vec![15496, 995]);
+        assert_eq!(gpt2.encode("<|endoftext|>"), vec![50256]);
+        assert_eq!(gpt2.decode(&gpt2.encode(text)), text);
+    }
+
+    #[test]
+    fn unknown_token_survives_vocabulary_replacement() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["[UNK]".to_string()])
+            .with_unknown_token("[UNK]")
+            .unwrap();
+
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["abc"]);
+
+        assert_eq!(ranked.unknown_token(), Some("[UNK]"));
+        assert_eq!(BpeTokenizer::new(vec![], vec![]).unknown_token(), None);
+    }
 }
@@ -0,0 +1,689 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Value, json};
+
+use crate::id_remap;
+use crate::pre_tokenizer::GPT2_PATTERN;
+use crate::{
+    BpeTokenizer, ControlChars, EncodeStrategy, MergesError, NewlineMode, Normalizer, PreTokenizer,
+    Vocabulary, parse_merges,
+};
+
+/// Pattern of the `Replace` normalizer written for [`ControlChars`]: every `Cc`
+/// character except tab and line feed.
+const CONTROL_CHARS_PATTERN: &str = r"[\x{0}-\x{8}\x{B}-\x{1F}\x{7F}-\x{9F}]";
+
+/// Error returned when a tokenizer cannot be saved to or loaded from a Hugging Face
+/// `tokenizer.json` file or GPT-2's `encoder.json` and `vocab.bpe` files.
+#[derive(Debug)]
+pub enum TokenizerJsonError {
+    /// A file could not be read or written.
+    Io(io::Error),
+    /// A file is not valid JSON or does not have the expected layout.
+    Json(serde_json::Error),
+    /// The merges file is malformed.
+    Merges(MergesError),
+    /// A setting has no equivalent on the other side, such as a `WordPiece` model in
+    /// the file or a whitespace-run pre-tokenizer in this crate.
+    Unsupported(String),
+    /// The vocabulary does not assign one ID to each token that the merges and
+    /// added tokens define.
+    InvalidVocabulary(String),
+}
+
+impl fmt::Display for TokenizerJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizerJsonError::Io(err) => write!(f, "failed to access tokenizer file: {}", err),
+            TokenizerJsonError::Json(err) => write!(f, "invalid tokenizer JSON: {}", err),
+            TokenizerJsonError::Merges(err) => write!(f, "invalid merges: {}", err),
+            TokenizerJsonError::Unsupported(setting) => {
+                write!(f, "unsupported tokenizer.json setting: {}", setting)
+            }
+            TokenizerJsonError::InvalidVocabulary(reason) => {
+                write!(f, "inconsistent vocabulary: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TokenizerJsonError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TokenizerJsonError::Io(err) => Some(err),
+            TokenizerJsonError::Json(err) => Some(err),
+            TokenizerJsonError::Merges(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TokenizerJsonError {
+    fn from(err: io::Error) -> Self {
+        TokenizerJsonError::Io(err)
+    }
+}
+
+impl From<MergesError> for TokenizerJsonError {
+    fn from(err: MergesError) -> Self {
+        match err {
+            MergesError::Io(err) => TokenizerJsonError::Io(err),
+            err => TokenizerJsonError::Merges(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for TokenizerJsonError {
+    fn from(err: serde_json::Error) -> Self {
+        TokenizerJsonError::Json(err)
+    }
+}
+
+/// The parts of the `tokenizers` crate's serialization format this crate reads and
+/// writes. Pipeline components are kept as raw JSON and matched against the exact
+/// shapes this crate can reproduce.
+#[derive(Serialize, Deserialize)]
+struct TokenizerJson {
+    version: String,
+    #[serde(default)]
+    truncation: Value,
+    #[serde(default)]
+    padding: Value,
+    #[serde(default)]
+    added_tokens: Vec<AddedToken>,
+    #[serde(default)]
+    normalizer: Value,
+    #[serde(default)]
+    pre_tokenizer: Value,
+    #[serde(default)]
+    post_processor: Value,
+    #[serde(default)]
+    decoder: Value,
+    model: Model,
+}
+
+#[derive(Serialize, Deserialize)]
+struct AddedToken {
+    id: u32,
+    content: String,
+    #[serde(default)]
+    single_word: bool,
+    #[serde(default)]
+    lstrip: bool,
+    #[serde(default)]
+    rstrip: bool,
+    #[serde(default)]
+    normalized: bool,
+    #[serde(default)]
+    special: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Model {
+    #[serde(rename = "type", default = "bpe_model_type")]
+    kind: String,
+    #[serde(default)]
+    dropout: Option<f32>,
+    #[serde(default)]
+    unk_token: Option<String>,
+    #[serde(default)]
+    continuing_subword_prefix: Option<String>,
+    #[serde(default)]
+    end_of_word_suffix: Option<String>,
+    #[serde(default)]
+    fuse_unk: bool,
+    #[serde(default)]
+    byte_fallback: bool,
+    #[serde(default)]
+    ignore_merges: bool,
+    vocab: Vocab,
+    merges: Vec<MergeRule>,
+}
+
+fn bpe_model_type() -> String {
+    "BPE".to_string()
+}
+
+/// Model vocabulary, written as a JSON object in ID order like `tokenizers` does.
+struct Vocab(Vec<(String, u32)>);
+
+impl Serialize for Vocab {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (token, id) in &self.0 {
+            map.serialize_entry(token, id)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Vocab {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = HashMap::<String, u32>::deserialize(deserializer)?;
+        let mut entries: Vec<(String, u32)> = map.into_iter().collect();
+        entries.sort_by_key(|(_, id)| *id);
+
+        Ok(Vocab(entries))
+    }
+}
+
+fn vocab(vocabulary: &Vocabulary) -> Vocab {
+    Vocab(
+        vocabulary
+            .iter()
+            .map(|(id, token)| (token.to_string(), id))
+            .collect(),
+    )
+}
+
+/// A merge rule, written as a two-element array by current `tokenizers` releases
+/// and as a space-separated string by older ones.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum MergeRule {
+    Pair(String, String),
+    Joined(String),
+}
+
+/// Serializes `tokenizer` as a `tokenizer.json` document.
+pub(crate) fn to_json(tokenizer: &BpeTokenizer) -> Result<String, TokenizerJsonError> {
+    if tokenizer.encode_strategy() != EncodeStrategy::MergeRank {
+        return Err(TokenizerJsonError::Unsupported(format!(
+            "encode strategy {:?}",
+            tokenizer.encode_strategy()
+        )));
+    }
+
+    let vocabulary = tokenizer.vocabulary();
+    let added_tokens = tokenizer
+        .special_tokens()
+        .iter()
+        .map(|token| AddedToken {
+            id: vocabulary
+                .token_to_id(token)
+                .expect("special tokens are in the vocabulary"),
+            content: token.clone(),
+            single_word: false,
+            lstrip: false,
+            rstrip: false,
+            normalized: false,
+            special: true,
+        })
+        .collect();
+
+    let document = TokenizerJson {
+        version: "1.0".to_string(),
+        truncation: Value::Null,
+        padding: Value::Null,
+        added_tokens,
+        normalizer: write_normalizer(tokenizer.normalizer()),
+        pre_tokenizer: write_pre_tokenizer(tokenizer.pre_tokenizer())?,
+        post_processor: Value::Null,
+        decoder: byte_level(true, true),
+        model: Model {
+            kind: bpe_model_type(),
+            dropout: None,
+            unk_token: None,
+            continuing_subword_prefix: None,
+            end_of_word_suffix: None,
+            fuse_unk: false,
+            byte_fallback: false,
+            ignore_merges: false,
+            vocab: vocab(vocabulary),
+            merges: tokenizer
+                .merges()
+                .iter()
+                .map(|(first, second)| MergeRule::Pair(first.clone(), second.clone()))
+                .collect(),
+        },
+    };
+
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+/// Serializes `vocabulary` as a `vocab.json` object in ID order.
+pub(crate) fn to_vocab_json(vocabulary: &Vocabulary) -> Result<String, TokenizerJsonError> {
+    Ok(serde_json::to_string_pretty(&vocab(vocabulary))?)
+}
+
+/// Builds a tokenizer from a `tokenizer.json` document.
+pub(crate) fn from_json(contents: &str) -> Result<BpeTokenizer, TokenizerJsonError> {
+    let document: TokenizerJson = serde_json::from_str(contents)?;
+
+    for (name, value) in [
+        ("truncation", &document.truncation),
+        ("padding", &document.padding),
+    ] {
+        if !value.is_null() {
+            return Err(unsupported(name, value));
+        }
+    }
+    for (name, value) in [
+        ("post_processor", &document.post_processor),
+        ("decoder", &document.decoder),
+    ] {
+        if !value.is_null() && value["type"] != "ByteLevel" {
+            return Err(unsupported(name, value));
+        }
+    }
+
+    let model = &document.model;
+    if model.kind != "BPE" {
+        return Err(TokenizerJsonError::Unsupported(format!(
+            "model type {}",
+            model.kind
+        )));
+    }
+    let model_options = [
+        ("dropout", model.dropout.is_some()),
+        (
+            "continuing_subword_prefix",
+            model.continuing_subword_prefix.is_some(),
+        ),
+        ("end_of_word_suffix", model.end_of_word_suffix.is_some()),
+        ("byte_fallback", model.byte_fallback),
+        ("ignore_merges", model.ignore_merges),
+    ];
+    if let Some((name, _)) = model_options.iter().find(|(_, set)| *set) {
+        return Err(TokenizerJsonError::Unsupported(format!("model.{}", name)));
+    }
+
+    let mut added_tokens: Vec<&AddedToken> = document.added_tokens.iter().collect();
+    added_tokens.sort_by_key(|token| token.id);
+    if let Some(token) = added_tokens
+        .iter()
+        .find(|token| token.single_word || token.lstrip || token.rstrip)
+    {
+        return Err(TokenizerJsonError::Unsupported(format!(
+            "matching options of added token {:?}",
+            token.content
+        )));
+    }
+
+    let merges = model
+        .merges
+        .iter()
+        .map(|rule| match rule {
+            MergeRule::Pair(first, second) => Ok((first.clone(), second.clone())),
+            MergeRule::Joined(joined) => match joined.split_once(' ') {
+                Some((first, second)) if !second.contains(' ') => {
+                    Ok((first.to_string(), second.to_string()))
+                }
+                _ => Err(TokenizerJsonError::InvalidVocabulary(format!(
+                    "merge {:?} is not two space-separated tokens",
+                    joined
+                ))),
+            },
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let special_tokens = added_tokens
+        .iter()
+        .map(|token| token.content.clone())
+        .collect();
+
+    let tokenizer = BpeTokenizer::new(merges, special_tokens)
+        .with_normalizer(read_normalizer(&document.normalizer)?)
+        .with_pre_tokenizer(read_pre_tokenizer(&document.pre_tokenizer)?);
+
+    let mut ids: HashMap<&str, u32> = model
+        .vocab
+        .0
+        .iter()
+        .map(|(token, id)| (token.as_str(), *id))
+        .collect();
+    ids.extend(
+        added_tokens
+            .iter()
+            .map(|token| (token.content.as_str(), token.id)),
+    );
+
+    with_file_ids(tokenizer, &ids)
+}
+
+/// Builds a tokenizer from the contents of GPT-2's `encoder.json` (token → ID) and
+/// `vocab.bpe` (merges) files.
+///
+/// Tokens of the encoder that are neither byte-level base tokens nor merge products,
+/// such as `<|endoftext|>` or RoBERTa's `<s>`, become special tokens. Text is
+/// split with GPT-2's whitespace lookahead, as its encoder does.
+pub(crate) fn from_gpt2(encoder: &str, merges: &str) -> Result<BpeTokenizer, TokenizerJsonError> {
+    let ids: HashMap<String, u32> = serde_json::from_str(encoder)?;
+    let merges = parse_merges(merges)?;
+
+    let derived = Vocabulary::new(vec![], merges.clone());
+    let mut special_tokens: Vec<(&String, u32)> = ids
+        .iter()
+        .filter(|(token, _)| derived.token_to_id(token).is_none())
+        .map(|(token, &id)| (token, id))
+        .collect();
+    special_tokens.sort_by_key(|(_, id)| *id);
+    let special_tokens = special_tokens
+        .into_iter()
+        .map(|(token, _)| token.clone())
+        .collect();
+
+    let tokenizer = BpeTokenizer::new(merges, special_tokens)
+        .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true));
+    let ids: HashMap<&str, u32> = ids
+        .iter()
+        .map(|(token, &id)| (token.as_str(), id))
+        .collect();
+
+    with_file_ids(tokenizer, &ids)
+}
+
+/// Renumbers `tokenizer` to the IDs listed in the file.
+fn with_file_ids(
+    tokenizer: BpeTokenizer,
+    ids: &HashMap<&str, u32>,
+) -> Result<BpeTokenizer, TokenizerJsonError> {
+    match id_remap::file_order(tokenizer.vocabulary(), ids) {
+        Ok(Some(new_to_old)) => Ok(tokenizer.renumbered(&new_to_old)),
+        Ok(None) => Ok(tokenizer),
+        Err(reason) => Err(TokenizerJsonError::InvalidVocabulary(reason)),
+    }
+}
+
+fn unsupported(name: &str, value: &Value) -> TokenizerJsonError {
+    let kind = value["type"].as_str().unwrap_or("with these options");
+    TokenizerJsonError::Unsupported(format!("{} {}", name, kind))
+}
+
+fn byte_level(add_prefix_space: bool, use_regex: bool) -> Value {
+    json!({
+        "type": "ByteLevel",
+        "add_prefix_space": add_prefix_space,
+        "trim_offsets": true,
+        "use_regex": use_regex,
+    })
+}
+
+fn is_byte_level(value: &Value, use_regex: bool) -> bool {
+    value["type"] == "ByteLevel"
+        && value["add_prefix_space"].as_bool() != Some(true)
+        && value["use_regex"].as_bool().unwrap_or(true) == use_regex
+}
+
+fn write_pre_tokenizer(pre_tokenizer: &PreTokenizer) -> Result<Value, TokenizerJsonError> {
+    if pre_tokenizer.whitespace_runs().is_some() {
+        return Err(TokenizerJsonError::Unsupported(
+            "pre-tokenizer with whitespace runs".to_string(),
+        ));
+    }
+
+    #[cfg(feature = "regex")]
+    if let Some(pattern) = pre_tokenizer.custom_pattern() {
+        return Ok(split_then_byte_level(pattern.as_str()));
+    }
+
+    if pre_tokenizer.whitespace_lookahead() {
+        return Ok(byte_level(false, true));
+    }
+
+    // `ByteLevel`'s own regex has the `\s+(?!\S)` alternative that the built-in
+    // rules lack, so they are written out as an explicit split.
+    Ok(split_then_byte_level(GPT2_PATTERN))
+}
+
+/// A split keeping the matches of `pattern` followed by a byte-level mapping.
+fn split_then_byte_level(pattern: &str) -> Value {
+    // Inverting the pattern and removing the "delimiters" keeps exactly the
+    // matches, like `Regex::find_iter`.
+    json!({
+        "type": "Sequence",
+        "pretokenizers": [
+            {
+                "type": "Split",
+                "pattern": { "Regex": pattern },
+                "behavior": "Removed",
+                "invert": true,
+            },
+            byte_level(false, false),
+        ],
+    })
+}
+
+fn read_pre_tokenizer(value: &Value) -> Result<PreTokenizer, TokenizerJsonError> {
+    if is_byte_level(value, true) {
+        return Ok(PreTokenizer::new().with_whitespace_lookahead(true));
+    }
+
+    if let [split, byte] = value["pretokenizers"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice)
+        && value["type"] == "Sequence"
+        && split["type"] == "Split"
+        && split["behavior"] == "Removed"
+        && split["invert"] == true
+        && is_byte_level(byte, false)
+        && let Some(pattern) = split["pattern"]["Regex"].as_str()
+    {
+        if pattern == GPT2_PATTERN {
+            return Ok(PreTokenizer::new());
+        }
+        #[cfg(feature = "regex")]
+        return regex::Regex::new(pattern)
+            .map(PreTokenizer::with_regex)
+            .map_err(|err| {
+                TokenizerJsonError::Unsupported(format!("split pattern {:?}: {}", pattern, err))
+            });
+        #[cfg(not(feature = "regex"))]
+        return Err(TokenizerJsonError::Unsupported(format!(
+            "split pattern {:?} without the `regex` feature",
+            pattern
+        )));
+    }
+
+    Err(unsupported("pre_tokenizer", value))
+}
+
+fn replace(pattern: Value, content: &str) -> Value {
+    json!({ "type": "Replace", "pattern": pattern, "content": content })
+}
+
+/// The `Replace` steps equivalent to [`NewlineMode::Unix`].
+fn unix_newlines() -> [Value; 2] {
+    [
+        replace(json!({ "String": "\r\n" }), "\n"),
+        replace(json!({ "String": "\r" }), "\n"),
+    ]
+}
+
+fn write_normalizer(normalizer: &Normalizer) -> Value {
+    let mut steps = Vec::new();
+
+    if normalizer.nfc() {
+        steps.push(json!({ "type": "NFC" }));
+    }
+    if normalizer.newlines() == NewlineMode::Unix {
+        steps.extend(unix_newlines());
+    }
+    let pattern = json!({ "Regex": CONTROL_CHARS_PATTERN });
+    match normalizer.control_chars() {
+        ControlChars::Keep => {}
+        ControlChars::Strip => steps.push(replace(pattern, "")),
+        ControlChars::Replace(ch) => steps.push(replace(pattern, &ch.to_string())),
+    }
+
+    match steps.len() {
+        0 => Value::Null,
+        1 => steps.remove(0),
+        _ => json!({ "type": "Sequence", "normalizers": steps }),
+    }
+}
+
+fn read_normalizer(value: &Value) -> Result<Normalizer, TokenizerJsonError> {
+    let steps = match value {
+        Value::Null => &[][..],
+        _ if value["type"] == "Sequence" => value["normalizers"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice),
+        _ => std::slice::from_ref(value),
+    };
+    let mut normalizer = Normalizer::new();
+    let mut rest = steps;
+
+    if let [first, tail @ ..] = rest
+        && first["type"] == "NFC"
+    {
+        normalizer = normalizer.with_nfc(true);
+        rest = tail;
+    }
+    if rest.starts_with(&unix_newlines()) {
+        normalizer = normalizer.with_newlines(NewlineMode::Unix);
+        rest = &rest[2..];
+    }
+    if let [step] = rest
+        && step["type"] == "Replace"
+        && step["pattern"]["Regex"] == CONTROL_CHARS_PATTERN
+        && let Some(content) = step["content"].as_str()
+    {
+        let mut chars = content.chars();
+        let mode = match (chars.next(), chars.next()) {
+            (None, _) => ControlChars::Strip,
+            (Some(ch), None) => ControlChars::Replace(ch),
+            _ => return Err(unsupported("normalizer", step)),
+        };
+        normalizer = normalizer.with_control_chars(mode);
+        rest = &[];
+    }
+
+    match rest {
+        [] => Ok(normalizer),
+        [step, ..] => Err(unsupported("normalizer", step)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_pipeline_settings() {
+        let normalizer = Normalizer::new()
+            .with_nfc(true)
+            .with_newlines(NewlineMode::Unix)
+            .with_control_chars(ControlChars::Replace('?'));
+        let merges = vec![("h".to_string(), "i".to_string())];
+        let tokenizer =
+            BpeTokenizer::new(merges, vec!["<s>".to_string()]).with_normalizer(normalizer.clone());
+
+        let loaded = from_json(&to_json(&tokenizer).unwrap()).unwrap();
+
+        assert_eq!(loaded.merges(), tokenizer.merges());
+        assert_eq!(loaded.special_tokens(), tokenizer.special_tokens());
+        assert_eq!(loaded.normalizer().describe(), normalizer.describe());
+        assert_eq!(
+            loaded.encode("<s>hi\r\n\u{7}"),
+            tokenizer.encode("<s>hi\r\n\u{7}")
+        );
+    }
+
+    #[test]
+    fn round_trips_both_whitespace_modes() {
+        for pre_tokenizer in [
+            PreTokenizer::new(),
+            PreTokenizer::new().with_whitespace_lookahead(true),
+        ] {
+            let tokenizer = BpeTokenizer::new(vec![], vec![]).with_pre_tokenizer(pre_tokenizer);
+
+            let loaded = from_json(&to_json(&tokenizer).unwrap()).unwrap();
+
+            assert_eq!(
+                loaded.pre_tokenizer().describe(),
+                tokenizer.pre_tokenizer().describe()
+            );
+        }
+    }
+
+    #[test]
+    fn restores_renumbered_ids() {
+        let tokenizer = BpeTokenizer::new(vec![], vec!["<s>".to_string()]);
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["zzy"]);
+
+        let loaded = from_json(&to_json(&ranked).unwrap()).unwrap();
+
+        assert_eq!(loaded.encode("<s>zy"), ranked.encode("<s>zy"));
+        assert_eq!(loaded.encode("z"), vec![1]);
+    }
+
+    #[test]
+    fn reads_joined_merges() {
+        let mut document: Value =
+            serde_json::from_str(&to_json(&BpeTokenizer::new(vec![], vec![])).unwrap()).unwrap();
+        document["model"]["merges"] = json!(["h i"]);
+        document["model"]["vocab"]["hi"] = json!(256);
+
+        let loaded = from_json(&document.to_string()).unwrap();
+
+        assert_eq!(loaded.merges(), &[("h".to_string(), "i".to_string())]);
+    }
+
+    #[test]
+    fn loads_gpt2_layout_with_special_token_last() {
+        let mut encoder: HashMap<String, u32> = Vocabulary::new(vec![], vec![])
+            .iter()
+            .map(|(id, token)| (token.to_string(), id))
+            .collect();
+        encoder.insert("hi".to_string(), 256);
+        encoder.insert("<|endoftext|>".to_string(), 257);
+        let encoder = serde_json::to_string(&encoder).unwrap();
+
+        let tokenizer = from_gpt2(&encoder, "#version: 0.2\nh i\n").unwrap();
+
+        assert_eq!(tokenizer.special_tokens(), &["<|endoftext|>".to_string()]);
+        assert_eq!(tokenizer.encode("hi<|endoftext|>A"), vec![256, 257, 32]);
+        assert_eq!(tokenizer.decode(&[256, 257]), "hi<|endoftext|>");
+    }
+
+    #[test]
+    fn gpt2_files_must_agree() {
+        let encoder = r#"{"h": 0, "i": 1}"#;
+
+        let err = from_gpt2(encoder, "h i\n").err().unwrap();
+        assert!(matches!(err, TokenizerJsonError::InvalidVocabulary(_)));
+
+        let err = from_gpt2(encoder, "h\n").err().unwrap();
+        assert!(matches!(
+            err,
+            TokenizerJsonError::Merges(MergesError::Malformed { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_inconsistent_vocabulary() {
+        let tokenizer = BpeTokenizer::new(vec![("h".to_string(), "i".to_string())], vec![]);
+        let mut document: Value = serde_json::from_str(&to_json(&tokenizer).unwrap()).unwrap();
+        document["model"]["vocab"]["hi"] = json!(0);
+
+        let err = from_json(&document.to_string()).err().unwrap();
+
+        assert!(matches!(err, TokenizerJsonError::InvalidVocabulary(_)));
+        assert_eq!(
+            err.to_string(),
+            "inconsistent vocabulary: ID 0 is assigned to more than one token"
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_settings() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![])
+            .with_pre_tokenizer(PreTokenizer::new().with_whitespace_runs(&[4]));
+        assert!(matches!(
+            to_json(&tokenizer),
+            Err(TokenizerJsonError::Unsupported(_))
+        ));
+
+        let mut document: Value =
+            serde_json::from_str(&to_json(&BpeTokenizer::new(vec![], vec![])).unwrap()).unwrap();
+        document["normalizer"] = json!({ "type": "Lowercase" });
+        assert_eq!(
+            from_json(&document.to_string()).err().unwrap().to_string(),
+            "unsupported tokenizer.json setting: normalizer Lowercase"
+        );
+    }
+}
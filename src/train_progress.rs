@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+
+use crate::SketchAccuracy;
+
+/// Progress of a training run, passed to the callback set with
+/// [`Trainer::with_progress`](crate::Trainer::with_progress).
+///
+/// The callback runs once after the training texts have been counted, with
+/// `merges_learned` at zero, and again after every learned merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrainProgress {
+    /// Merge rules learned so far.
+    pub merges_learned: usize,
+    /// Merge rules the run will learn at most.
+    pub num_merges: usize,
+    /// Distinct pre-tokenized words in the training data.
+    pub unique_words: usize,
+    /// Approximate memory held by the training tables, or `None` unless enabled with
+    /// [`Trainer::with_memory_accounting`](crate::Trainer::with_memory_accounting).
+    pub memory: Option<MemoryUsage>,
+    /// Accuracy of the sketched counts the next merge is chosen from, or `None`
+    /// unless training with
+    /// [`Trainer::with_sketch_counting`](crate::Trainer::with_sketch_counting).
+    pub sketch: Option<SketchAccuracy>,
+}
+
+/// Approximate bytes held by the tables a [`Trainer`](crate::Trainer) keeps while
+/// learning merges.
+///
+/// Counts the hash table slots at their allocated capacity plus the heap buffers of
+/// every word and symbol, but not allocator overhead, so real usage is somewhat
+/// higher. Merging only shortens words and folds identical ones together, so the
+/// figures reported before the first merge are close to the peak of the whole run.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::{Arc, Mutex};
+///
+/// use bpe_tokenizer_rs::Trainer;
+///
+/// let peak = Arc::new(Mutex::new(0));
+/// let recorded = Arc::clone(&peak);
+/// let trainer = Trainer::new(10)
+///     .with_memory_accounting(true)
+///     .with_progress(move |progress| {
+///         let total = progress.memory.map_or(0, |memory| memory.total());
+///         let mut peak = recorded.lock().unwrap();
+///         *peak = total.max(*peak);
+///     });
+///
+/// trainer.train(&["hello world", "hello there"]);
+/// assert!(*peak.lock().unwrap() > 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryUsage {
+    /// Bytes held by the word-frequency table.
+    pub word_frequencies: usize,
+    /// Bytes held by the pair-frequency table, or by the sketch and its candidates
+    /// when training with
+    /// [`Trainer::with_sketch_counting`](crate::Trainer::with_sketch_counting).
+    pub pair_frequencies: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the bytes held by both tables.
+    pub fn total(&self) -> usize {
+        self.word_frequencies + self.pair_frequencies
+    }
+
+    /// Bytes held by the word-frequency table.
+    pub(crate) fn word_table_bytes(word_freqs: &HashMap<Vec<String>, usize>) -> usize {
+        let words: usize = word_freqs
+            .keys()
+            .map(|symbols| {
+                symbols.capacity() * size_of::<String>()
+                    + symbols.iter().map(String::capacity).sum::<usize>()
+            })
+            .sum();
+
+        table_bytes(word_freqs) + words
+    }
+
+    /// Bytes held by a pair-frequency table.
+    pub(crate) fn pair_table_bytes(pair_freqs: &HashMap<(String, String), usize>) -> usize {
+        let pairs: usize = pair_freqs
+            .keys()
+            .map(|(first, second)| first.capacity() + second.capacity())
+            .sum();
+
+        table_bytes(pair_freqs) + pairs
+    }
+}
+
+/// Bytes of the slots and control bytes of `table` at its current capacity.
+fn table_bytes<K, V>(table: &HashMap<K, V>) -> usize {
+    table.capacity() * (size_of::<(K, V)>() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measure_counts_slots_and_buffers() {
+        assert_eq!(MemoryUsage::word_table_bytes(&HashMap::new()), 0);
+        assert_eq!(MemoryUsage::pair_table_bytes(&HashMap::new()), 0);
+
+        let mut words = HashMap::new();
+        words.insert(vec!["a".to_string(), "bc".to_string()], 3);
+        let mut pairs = HashMap::new();
+        pairs.insert(("a".to_string(), "bc".to_string()), 3);
+
+        let usage = MemoryUsage {
+            word_frequencies: MemoryUsage::word_table_bytes(&words),
+            pair_frequencies: MemoryUsage::pair_table_bytes(&pairs),
+        };
+
+        let slot = size_of::<(Vec<String>, usize)>() + 1;
+        let buffers = 2 * size_of::<String>() + 3;
+        assert!(usage.word_frequencies >= slot + buffers);
+        assert!(usage.pair_frequencies >= size_of::<((String, String), usize)>() + 1 + 3);
+        assert_eq!(
+            usage.total(),
+            usage.word_frequencies + usage.pair_frequencies
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use crate::Warning;
+
+/// Frequency of one adjacent symbol pair in the training data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PairFrequency {
+    pub first: String,
+    pub second: String,
+    pub count: usize,
+}
+
+/// Detailed outcome of a training run, produced by
+/// [`Trainer::train_with_report`](crate::Trainer::train_with_report).
+///
+/// Besides the learned merges it records how frequent each merged pair was when it
+/// was selected and the pair-frequency table left over after the last merge. The
+/// leftover table shows which pairs would have been merged next and is the input
+/// needed to reproduce selection decisions offline.
+///
+/// With the `serde` feature the report can be stored, for example as JSON next to
+/// the trained tokenizer, and passed to [`Trainer::reproduce`](crate::Trainer::reproduce)
+/// in a later process or release.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::Trainer;
+///
+/// let report = Trainer::new(1).train_with_report(&["aab aab"]);
+///
+/// assert_eq!(report.merges, vec![("a".to_string(), "a".to_string())]);
+/// assert_eq!(report.merge_frequencies, vec![2]);
+/// assert_eq!(report.pair_frequencies[0].count, 2);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TrainReport {
+    /// Learned merge rules, in the order they were learned.
+    pub merges: Vec<(String, String)>,
+    /// Frequency of each merged pair at the moment it was selected, aligned with `merges`.
+    pub merge_frequencies: Vec<usize>,
+    /// Pair frequencies after the last merge, sorted by descending count and then by pair.
+    pub pair_frequencies: Vec<PairFrequency>,
+    /// Everything that determined the learned merges, for
+    /// [`Trainer::reproduce`](crate::Trainer::reproduce).
+    pub config: TrainConfig,
+    /// Differences from the recorded run that [`Trainer::reproduce`](crate::Trainer::reproduce)
+    /// tolerated, such as another crate version. Always empty for a new run and not
+    /// serialized.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub warnings: Vec<Warning>,
+}
+
+impl TrainReport {
+    /// Writes the final pair-frequency table as tab-separated values.
+    ///
+    /// The output has a `first\tsecond\tcount` header followed by one row per pair in
+    /// the order of [`pair_frequencies`](TrainReport::pair_frequencies). Tokens are
+    /// written in their byte-level form, which never contains tabs or newlines.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by `writer`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let report = Trainer::new(0).train_with_report(&["ab"]);
+    /// let mut out = Vec::new();
+    /// report.write_pair_frequencies(&mut out).unwrap();
+    ///
+    /// assert_eq!(String::from_utf8(out).unwrap(), "first\tsecond\tcount\na\tb\t1\n");
+    /// ```
+    pub fn write_pair_frequencies(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "first\tsecond\tcount")?;
+
+        for pair in &self.pair_frequencies {
+            writeln!(writer, "{}\t{}\t{}", pair.first, pair.second, pair.count)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The full configuration of a training run, recorded in every [`TrainReport`].
+///
+/// Two runs with equal configurations learn byte-identical merges. The corpus is
+/// identified by its number of texts and its [`CorpusHasher`](crate::CorpusHasher)
+/// hash under the trainer's normalizer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub struct TrainConfig {
+    /// Version of this crate that ran the training. Not compared by
+    /// [`Trainer::reproduce`](crate::Trainer::reproduce), which reports a different
+    /// version as [`Warning::CrateVersionChanged`] instead.
+    pub crate_version: String,
+    /// Requested number of merges.
+    pub num_merges: usize,
+    /// Pre-tokenizer splitting rules.
+    pub pre_tokenizer: String,
+    /// Normalizer settings.
+    pub normalizer: String,
+    /// Script boosts as `start..=end*factor` code point ranges, in the order added.
+    pub script_boosts: Vec<String>,
+    /// How ties between equally frequent pairs are broken (see
+    /// [`TieBreak`](crate::TieBreak)).
+    pub tie_break: String,
+    /// How the next merge is chosen (see [`MergeCriterion`](crate::MergeCriterion)).
+    pub merge_criterion: String,
+    /// Early stopping rule, if any (see [`StopRule`](crate::StopRule)).
+    pub stop_rule: Option<String>,
+    /// Cap on distinct words kept while counting (see
+    /// [`Trainer::with_max_unique_words`](crate::Trainer::with_max_unique_words)).
+    pub max_unique_words: Option<usize>,
+    /// How pairs are counted: `exact`, or the sketch settings of
+    /// [`Trainer::with_sketch_counting`](crate::Trainer::with_sketch_counting).
+    pub pair_counting: String,
+    /// Number of training texts.
+    pub corpus_texts: usize,
+    /// XXH3 hash of the normalized training texts.
+    pub corpus_hash: u64,
+}
+
+impl TrainConfig {
+    /// Lists the fields that differ from `other` as `(field, self, other)`, except
+    /// `crate_version`.
+    pub(crate) fn differences(&self, other: &TrainConfig) -> Vec<(&'static str, String, String)> {
+        let fields = [
+            ("pre_tokenizer", &self.pre_tokenizer, &other.pre_tokenizer),
+            ("normalizer", &self.normalizer, &other.normalizer),
+            ("tie_break", &self.tie_break, &other.tie_break),
+            (
+                "merge_criterion",
+                &self.merge_criterion,
+                &other.merge_criterion,
+            ),
+            ("pair_counting", &self.pair_counting, &other.pair_counting),
+        ];
+        let mut differences: Vec<(&'static str, String, String)> = fields
+            .into_iter()
+            .filter(|(_, a, b)| a != b)
+            .map(|(name, a, b)| (name, a.clone(), b.clone()))
+            .collect();
+
+        let numbers = [
+            (
+                "num_merges",
+                self.num_merges as u64,
+                other.num_merges as u64,
+            ),
+            (
+                "corpus_texts",
+                self.corpus_texts as u64,
+                other.corpus_texts as u64,
+            ),
+            ("corpus_hash", self.corpus_hash, other.corpus_hash),
+        ];
+        differences.extend(
+            numbers
+                .into_iter()
+                .filter(|(_, a, b)| a != b)
+                .map(|(name, a, b)| (name, a.to_string(), b.to_string())),
+        );
+        if self.max_unique_words != other.max_unique_words {
+            differences.push((
+                "max_unique_words",
+                format!("{:?}", self.max_unique_words),
+                format!("{:?}", other.max_unique_words),
+            ));
+        }
+        if self.stop_rule != other.stop_rule {
+            differences.push((
+                "stop_rule",
+                format!("{:?}", self.stop_rule),
+                format!("{:?}", other.stop_rule),
+            ));
+        }
+        if self.script_boosts != other.script_boosts {
+            differences.push((
+                "script_boosts",
+                format!("{:?}", self.script_boosts),
+                format!("{:?}", other.script_boosts),
+            ));
+        }
+
+        differences
+    }
+}
+
+/// Error returned by [`Trainer::reproduce`](crate::Trainer::reproduce) when a run
+/// cannot reproduce a recorded report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReproducibilityError {
+    /// The trainer or corpus differs from the recorded configuration.
+    ConfigMismatch {
+        /// Each differing field as `(field, expected, actual)`.
+        fields: Vec<(&'static str, String, String)>,
+    },
+    /// The configuration matches but training learned different merges, starting at `rank`.
+    MergeMismatch {
+        /// Rank of the first differing merge.
+        rank: usize,
+        /// The recorded merge at `rank`, or `None` if the recorded run learned fewer.
+        expected: Option<(String, String)>,
+        /// The merge learned at `rank` now, or `None` if this run learned fewer.
+        actual: Option<(String, String)>,
+    },
+}
+
+impl fmt::Display for ReproducibilityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReproducibilityError::ConfigMismatch { fields } => {
+                write!(f, "training configuration differs from the recorded run:")?;
+                for (field, expected, actual) in fields {
+                    write!(
+                        f,
+                        " {} expected '{}' but got '{}';",
+                        field, expected, actual
+                    )?;
+                }
+                Ok(())
+            }
+            ReproducibilityError::MergeMismatch {
+                rank,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "merge {} differs from the recorded run: expected {:?}, got {:?}",
+                rank, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReproducibilityError {}
+
+/// Compares a recorded report with a new one, configuration first.
+pub(crate) fn check_reproduced(
+    expected: &TrainReport,
+    actual: &TrainReport,
+) -> Result<(), ReproducibilityError> {
+    let fields = expected.config.differences(&actual.config);
+    if !fields.is_empty() {
+        return Err(ReproducibilityError::ConfigMismatch { fields });
+    }
+
+    let len = expected.merges.len().max(actual.merges.len());
+    match (0..len).find(|&rank| expected.merges.get(rank) != actual.merges.get(rank)) {
+        Some(rank) => Err(ReproducibilityError::MergeMismatch {
+            rank,
+            expected: expected.merges.get(rank).cloned(),
+            actual: actual.merges.get(rank).cloned(),
+        }),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Trainer;
+
+    #[test]
+    fn report_merges_match_train() {
+        let trainer = Trainer::new(5);
+        let texts = ["hello world", "hello there"];
+
+        let report = trainer.train_with_report(&texts);
+
+        assert_eq!(report.merges, trainer.train(&texts));
+        assert_eq!(report.merge_frequencies.len(), report.merges.len());
+    }
+
+    #[test]
+    fn report_pair_frequencies_are_sorted() {
+        let report = Trainer::new(0).train_with_report(&["ab ab cd"]);
+
+        let counts: Vec<usize> = report.pair_frequencies.iter().map(|p| p.count).collect();
+        assert_eq!(counts, vec![2, 1, 1, 1]);
+        assert_eq!(report.pair_frequencies[0].first, "a");
+        assert_eq!(report.pair_frequencies[1].first, "c");
+    }
+
+    #[test]
+    fn report_pair_frequencies_reflect_final_state() {
+        let report = Trainer::new(1).train_with_report(&["abc abc"]);
+
+        assert_eq!(report.merge_frequencies, vec![2]);
+        assert_eq!(report.pair_frequencies.len(), 2);
+        assert!(
+            report
+                .pair_frequencies
+                .iter()
+                .all(|p| p.first == "ab" || p.first == "Ġ")
+        );
+    }
+
+    #[test]
+    fn report_empty_training_data() {
+        let report = Trainer::new(3).train_with_report(&[]);
+
+        assert!(report.merges.is_empty());
+        assert!(report.pair_frequencies.is_empty());
+    }
+
+    #[test]
+    fn check_reproduced_reports_first_differing_merge() {
+        let expected = Trainer::new(2).train_with_report(&["aab aab"]);
+        let mut actual = expected.clone();
+        actual.merges[1] = ("x".to_string(), "y".to_string());
+
+        let err = check_reproduced(&expected, &actual).unwrap_err();
+
+        assert!(matches!(
+            err,
+            ReproducibilityError::MergeMismatch { rank: 1, .. }
+        ));
+    }
+}
@@ -1,5 +1,16 @@
-use crate::{PreTokenizer, bytes_to_unicode};
+use crate::count_min::{CountMinSketch, TopCandidates};
+use crate::label_budget::LabelBudget;
+use crate::low_level::MergeTable;
+use crate::train_report::check_reproduced;
+use crate::{
+    BpeTokenizer, CorpusHasher, CorpusSource, MemoryUsage, Normalizer, PairFrequency, PreTokenizer,
+    ReproducibilityError, Segmentation, SketchAccuracy, SketchCounting, TokenizerStats,
+    TrainConfig, TrainProgress, TrainReport, Vocabulary, Warning, bytes_to_unicode,
+};
 use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
+use std::path::Path;
 
 /// Trains a BPE tokenizer by learning merge rules from training data.
 ///
@@ -15,7 +26,7 @@ use std::collections::HashMap;
 /// The training process uses GPT-2 style byte-level encoding with a pre-tokenizer
 /// that splits on whitespace and punctuation. Merge rules are learned by:
 /// - Finding the most frequent adjacent pair of tokens
-/// - Breaking ties by preferring pairs with lower token IDs
+/// - Breaking ties between equally frequent pairs by the trainer's [`TieBreak`]
 /// - Creating a new merged token from the pair
 /// - Updating all occurrences in the training data
 ///
@@ -32,6 +43,225 @@ use std::collections::HashMap;
 pub struct Trainer {
     num_merges: usize,
     pre_tokenizer: PreTokenizer,
+    normalizer: Normalizer,
+    script_boosts: Vec<(RangeInclusive<char>, usize)>,
+    tie_break: TieBreak,
+    merge_criterion: MergeCriterion,
+    stop_rule: Option<StopRule>,
+    max_label_share: Option<f64>,
+    progress: Option<ProgressCallback>,
+    memory_accounting: bool,
+    max_unique_words: Option<usize>,
+    sketch_counting: Option<SketchCounting>,
+}
+
+type ProgressCallback = Box<dyn Fn(&TrainProgress) + Send + Sync>;
+
+/// How a [`Trainer`] chooses between pairs with the same frequency.
+///
+/// Ties are common: every pair of a word that occurs once in a small corpus has
+/// frequency 1, and large corpora still produce exact ties among rare pairs near the
+/// end of training. The rule decides which merge is learned first, and since later
+/// counts depend on earlier merges, a different rule can change the whole tail of the
+/// merge list.
+///
+/// HuggingFace's `BpeTrainer` takes the pair with the lowest token IDs, which is the
+/// default here, and learns the same merges when configured as in
+/// `tests/huggingface_compatibility.rs`. That order is often described as
+/// alphabetical, but it is only alphabetical for the 256 base tokens, whose IDs follow
+/// the code points of their byte-level characters. Merged tokens are numbered in the
+/// order they were learned, so `("b", "x")` (IDs 65, 87) wins over `("aa", "x")` (IDs
+/// 256 and up, 87) even though `"aa"` sorts before `"b"`.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{TieBreak, Trainer};
+///
+/// // "aa", "ax" and "bx" tie, then "aa"+"x" ties with "b"+"x".
+/// let corpus = ["aax", "aax", "bx", "bx"];
+///
+/// let by_ids = Trainer::new(2).train(&corpus);
+/// assert_eq!(by_ids[1], ("b".to_string(), "x".to_string()));
+///
+/// let by_strings = Trainer::new(2)
+///     .with_tie_break(TieBreak::Lexicographic)
+///     .train(&corpus);
+/// assert_eq!(by_strings[1], ("aa".to_string(), "x".to_string()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TieBreak {
+    /// Prefer the pair with the lowest `(first, second)` token IDs, as HuggingFace's
+    /// `BpeTrainer` does.
+    #[default]
+    LowestIds,
+    /// Prefer the pair whose `(first, second)` token strings sort first.
+    ///
+    /// Matches trainers that order candidate pairs by their text, such as tools that
+    /// keep pair counts in a sorted map. Differs from [`TieBreak::LowestIds`] only
+    /// once a merged token ties with a pair of other tokens.
+    Lexicographic,
+}
+
+impl TieBreak {
+    /// Returns the stable name recorded in [`TrainConfig::tie_break`].
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            TieBreak::LowestIds => "lowest-token-ids",
+            TieBreak::Lexicographic => "lexicographic",
+        }
+    }
+}
+
+/// How a [`Trainer`] ranks adjacent pairs when choosing the next merge.
+///
+/// Raw frequency, the classic BPE rule and the default, favours pairs of very common
+/// symbols even when they occur together only as often as chance predicts. On some
+/// corpora that spends early merges on whitespace and punctuation runs such as
+/// `" ,"` or `"Ġ("`, which compress little beyond the text they came from.
+/// [`MergeCriterion::LikelihoodGain`] ranks pairs by how much merging them improves
+/// a unigram model of the training words instead, so pairs that belong together
+/// win over pairs that are merely frequent.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{MergeCriterion, Trainer};
+///
+/// // " ," is the most frequent pair, but spaces also precede every other word,
+/// // while "q" and "u" only ever occur together.
+/// let corpus = ["a , b , c , d , e , qu qu qu"];
+///
+/// let by_frequency = Trainer::new(1).train(&corpus);
+/// assert_eq!(by_frequency[0], ("Ġ".to_string(), ",".to_string()));
+///
+/// let by_likelihood = Trainer::new(1)
+///     .with_merge_criterion(MergeCriterion::LikelihoodGain)
+///     .train(&corpus);
+/// assert_eq!(by_likelihood[0], ("q".to_string(), "u".to_string()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeCriterion {
+    /// Merge the most frequent pair, as classic BPE and HuggingFace's `BpeTrainer` do.
+    #[default]
+    Frequency,
+    /// Merge the pair whose merge most increases the log-likelihood of the training
+    /// words under a unigram model of their symbols.
+    ///
+    /// A pair `(a, b)` seen `n_ab` times scores `n_ab * ln(n_ab * N / (n_a * n_b))`,
+    /// where `n_a` and `n_b` count the symbols and `N` is the total number of
+    /// symbols: its frequency weighted by its pointwise mutual information. Unlike
+    /// pointwise mutual information alone, the frequency factor keeps rare pairs of
+    /// rare symbols from winning.
+    LikelihoodGain,
+}
+
+impl MergeCriterion {
+    /// Returns the stable name recorded in [`TrainConfig::merge_criterion`].
+    pub(crate) fn describe(self) -> &'static str {
+        match self {
+            MergeCriterion::Frequency => "frequency",
+            MergeCriterion::LikelihoodGain => "likelihood-gain",
+        }
+    }
+}
+
+/// When a [`Trainer`] stops learning merges before reaching its `num_merges`.
+///
+/// Late merges are learned from ever rarer pairs and compress less and less, so past
+/// some point more vocabulary only costs embedding rows. A stop rule ends training at
+/// that point, and `num_merges` becomes an upper bound instead of a guess. Both rules
+/// look at the pair chosen to merge next: training stops before merging it.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{StopRule, Trainer};
+///
+/// let corpus = ["the cat sat on the mat", "the dog sat on the log"];
+///
+/// let merges = Trainer::new(1000)
+///     .with_stop_rule(StopRule::PairFrequencyBelow(0.2))
+///     .train(&corpus);
+///
+/// // Only pairs seen in at least 20% of the 12 words are merged.
+/// assert!(!merges.is_empty() && merges.len() < Trainer::new(1000).train(&corpus).len());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopRule {
+    /// Stop when the next pair occurs fewer times than this fraction of the
+    /// pre-tokenized words in the corpus, e.g. `0.001` for 0.1%.
+    PairFrequencyBelow(f64),
+    /// Stop when the next merge would shrink the training words by less than this
+    /// fraction of their current tokens, e.g. `0.0001` for 0.01%.
+    ///
+    /// The gain is the pair's count over the tokens left, the compression a merge
+    /// adds on the training data itself.
+    CompressionGainBelow(f64),
+}
+
+impl StopRule {
+    /// Returns the stable name recorded in [`TrainConfig::stop_rule`].
+    pub(crate) fn describe(self) -> String {
+        match self {
+            StopRule::PairFrequencyBelow(share) => format!("pair-frequency<{}", share),
+            StopRule::CompressionGainBelow(gain) => format!("compression-gain<{}", gain),
+        }
+    }
+
+    /// Returns whether a pair seen `count` times is below the rule's threshold.
+    fn stops(self, count: usize, word_freqs: &HashMap<Vec<String>, usize>) -> bool {
+        let (threshold, total): (f64, usize) = match self {
+            StopRule::PairFrequencyBelow(share) => (share, word_freqs.values().sum()),
+            StopRule::CompressionGainBelow(gain) => (
+                gain,
+                word_freqs
+                    .iter()
+                    .map(|(symbols, count)| symbols.len() * count)
+                    .sum(),
+            ),
+        };
+
+        (count as f64) < threshold * total as f64
+    }
+}
+
+/// Pair counts of one merge step.
+enum PairCounts {
+    /// Every pair with its exact count.
+    Exact(HashMap<(String, String), usize>),
+    /// The pair chosen from a count-min sketch, with its exact count.
+    Sketched {
+        best: Option<((String, String), usize)>,
+        accuracy: SketchAccuracy,
+        bytes: usize,
+    },
+}
+
+impl PairCounts {
+    /// Returns the pair to merge next and its count.
+    fn into_best(
+        self,
+        trainer: &Trainer,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        token_to_id: &HashMap<String, u32>,
+    ) -> Option<((String, String), usize)> {
+        match self {
+            PairCounts::Exact(pair_freqs) => trainer
+                .select_pair(&pair_freqs, word_freqs, token_to_id)
+                .map(|pair| {
+                    let count = pair_freqs[&pair];
+                    (pair, count)
+                }),
+            PairCounts::Sketched { best, .. } => best,
+        }
+    }
+}
+
+struct TrainingOutcome {
+    merges: Vec<(String, String)>,
+    merge_frequencies: Vec<usize>,
+    word_freqs: HashMap<Vec<String>, usize>,
 }
 
 impl Trainer {
@@ -52,7 +282,280 @@ impl Trainer {
         Self {
             num_merges,
             pre_tokenizer: PreTokenizer::default(),
+            normalizer: Normalizer::new(),
+            script_boosts: Vec::new(),
+            tie_break: TieBreak::default(),
+            merge_criterion: MergeCriterion::default(),
+            stop_rule: None,
+            max_label_share: None,
+            progress: None,
+            memory_accounting: false,
+            max_unique_words: None,
+            sketch_counting: None,
+        }
+    }
+
+    /// Sets the normalizer applied to every training text before pre-tokenization.
+    ///
+    /// Use the same normalizer at inference time (see [`BpeTokenizer::from_trainer`],
+    /// which copies it automatically) so that learned merges match encoded text.
+    ///
+    /// [`BpeTokenizer::from_trainer`]: crate::BpeTokenizer::from_trainer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{NewlineMode, Normalizer, Trainer};
+    ///
+    /// let trainer = Trainer::new(1)
+    ///     .with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+    /// let merges = trainer.train(&["\r\n\r\n"]);
+    ///
+    /// assert_eq!(merges, vec![("Ċ".to_string(), "Ċ".to_string())]);
+    /// ```
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Multiplies the frequency of words containing characters from `range` by `factor`.
+    ///
+    /// Low-resource scripts often make up a tiny share of a multilingual corpus and
+    /// receive few merges. Boosting them makes their pairs compete as if they were
+    /// `factor` times more common. A word matching several boosted ranges uses the
+    /// largest factor. Boosting changes which merges are learned, so the result no
+    /// longer matches an unweighted HuggingFace trainer.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Unicode code point range of the script, e.g. `'\u{0E00}'..='\u{0E7F}'` for Thai
+    /// * `factor` - Count multiplier for matching words
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let corpus = ["ab ab ab", "я я"];
+    ///
+    /// let plain = Trainer::new(1).train(&corpus);
+    /// let boosted = Trainer::new(1)
+    ///     .with_script_boost('\u{0400}'..='\u{04FF}', 10)
+    ///     .train(&corpus);
+    ///
+    /// assert_eq!(plain[0], ("a".to_string(), "b".to_string()));
+    /// assert_ne!(boosted[0], plain[0]);
+    /// ```
+    pub fn with_script_boost(mut self, range: RangeInclusive<char>, factor: usize) -> Self {
+        self.script_boosts.push((range, factor));
+        self
+    }
+
+    /// Sets the pre-tokenizer used to split training texts into words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{PreTokenizer, Trainer};
+    ///
+    /// let code = ["\n        x", "\n        y"];
+    /// let trainer = Trainer::new(3).with_pre_tokenizer(PreTokenizer::new().with_whitespace_runs(&[8]));
+    ///
+    /// let merges = trainer.train(&code);
+    /// assert_eq!(merges.last().unwrap(), &("ĠĠĠĠ".to_string(), "ĠĠĠĠ".to_string()));
+    /// ```
+    pub fn with_pre_tokenizer(mut self, pre_tokenizer: PreTokenizer) -> Self {
+        self.pre_tokenizer = pre_tokenizer;
+        self
+    }
+
+    /// Caps the number of distinct words kept while counting the training texts.
+    ///
+    /// Noisy corpora full of hashes, URLs and other one-off strings can hold far more
+    /// distinct words than fit in memory, although such words barely affect which
+    /// merges are learned. With a cap, whenever the table reaches twice `max_words`
+    /// entries it is cut back to the `max_words` most frequent words (ties broken by
+    /// the words' symbols, so runs stay deterministic), and once more after the last
+    /// text.
+    ///
+    /// The cap trades accuracy for bounded memory. A word evicted mid-stream that
+    /// occurs again starts counting from zero, so moderately common words that
+    /// arrive in scattered bursts can be undercounted or dropped, and pairs that
+    /// only occur in dropped words are never learned. Merges match an uncapped run
+    /// exactly when the corpus has at most `max_words` distinct words; otherwise
+    /// the frequent head of the merge list usually agrees and the rare tail
+    /// differs. Choose a cap well above the number of words you expect to matter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let mut corpus = vec!["hello hello hello"; 10];
+    /// let junk: Vec<String> = (0..1000).map(|n| format!("x{:x}q", n * 7919)).collect();
+    /// corpus.extend(junk.iter().map(String::as_str));
+    ///
+    /// let capped = Trainer::new(4).with_max_unique_words(16).train(&corpus);
+    ///
+    /// assert_eq!(capped, Trainer::new(4).train(&corpus[..10]));
+    /// ```
+    pub fn with_max_unique_words(mut self, max_words: usize) -> Self {
+        self.max_unique_words = Some(max_words);
+        self
+    }
+
+    /// Counts pairs approximately with a count-min sketch instead of an exact table.
+    ///
+    /// Meant for corpora whose pair table does not fit in memory: pair counting then
+    /// needs a fixed amount of memory, at the price of hashing every pair three
+    /// times per merge and, rarely, learning a different merge than exact training
+    /// would. [`TrainProgress::sketch`] reports the estimated error of every step.
+    /// See [`SketchCounting`] for the trade-offs. The word table is still exact;
+    /// combine with [`with_max_unique_words`](Trainer::with_max_unique_words) to
+    /// bound it too. [`train_with_report`](Trainer::train_with_report) builds the
+    /// exact pair table once at the end for its leftover pair frequencies.
+    pub fn with_sketch_counting(mut self, counting: SketchCounting) -> Self {
+        self.sketch_counting = Some(counting);
+        self
+    }
+
+    /// Sets how ties between equally frequent pairs are broken.
+    ///
+    /// The default, [`TieBreak::LowestIds`], matches HuggingFace's `BpeTrainer`. See
+    /// [`TieBreak`] for the differences between the rules.
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Sets how the next merge is chosen among the adjacent pairs.
+    ///
+    /// The default, [`MergeCriterion::Frequency`], matches HuggingFace's
+    /// `BpeTrainer`. Ties are broken by [`with_tie_break`](Trainer::with_tie_break)
+    /// under either criterion. With
+    /// [`with_sketch_counting`](Trainer::with_sketch_counting), the criterion ranks
+    /// the most frequent candidates that are recounted exactly, so a pair too rare to
+    /// be a candidate is never chosen.
+    pub fn with_merge_criterion(mut self, criterion: MergeCriterion) -> Self {
+        self.merge_criterion = criterion;
+        self
+    }
+
+    /// Stops training early once the next merge is no longer worth learning.
+    ///
+    /// `num_merges` still caps the run. See [`StopRule`] for the rules;
+    /// [`sweep`](Trainer::sweep) applies the rule too, so sizes beyond the stopping
+    /// point report the size actually reached.
+    pub fn with_stop_rule(mut self, rule: StopRule) -> Self {
+        self.stop_rule = Some(rule);
+        self
+    }
+
+    /// Caps the share of merges any one label may dominate in
+    /// [`train_labeled`](Trainer::train_labeled).
+    ///
+    /// A merge is dominated by the label whose texts contribute most of its pair's
+    /// count. While learning merge `n`, a label that already dominates
+    /// `ceil(max_share * n)` merges cannot take another: its pairs are skipped until
+    /// other labels catch up, so a majority language cannot monopolize the
+    /// vocabulary, and every prefix of the merge list respects the cap. Training
+    /// stops early once every remaining pair belongs to a label at its cap. With `n`
+    /// labels, a share below `1 / n` leaves merges no label may take, so training
+    /// stops early too. Has no effect on unlabeled training.
+    pub fn with_max_label_share(mut self, max_share: f64) -> Self {
+        self.max_label_share = Some(max_share);
+        self
+    }
+
+    /// Sets a callback that receives a [`TrainProgress`] after the training texts are
+    /// counted and after every learned merge.
+    ///
+    /// The callback runs on the training thread, so keep it cheap or hand the
+    /// progress off to another thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let learned = Arc::new(AtomicUsize::new(0));
+    /// let counter = Arc::clone(&learned);
+    /// let trainer = Trainer::new(3).with_progress(move |progress| {
+    ///     counter.store(progress.merges_learned, Ordering::Relaxed);
+    /// });
+    ///
+    /// trainer.train(&["aaab aaab"]);
+    /// assert_eq!(learned.load(Ordering::Relaxed), 3);
+    /// ```
+    pub fn with_progress(
+        mut self,
+        callback: impl Fn(&TrainProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Enables measuring the training tables for [`TrainProgress::memory`].
+    ///
+    /// Measuring walks both tables on every progress report, which slows training
+    /// noticeably, so it is off by default. The first report comes before any merge
+    /// and is close to the peak of the run: check it to see whether a corpus fits in
+    /// memory, then stop the run or sample a smaller corpus if it does not. See
+    /// [`MemoryUsage`] for what is counted.
+    pub fn with_memory_accounting(mut self, enabled: bool) -> Self {
+        self.memory_accounting = enabled;
+        self
+    }
+
+    /// Returns the rule used to break ties between equally frequent pairs.
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Returns the criterion used to choose the next merge.
+    pub fn merge_criterion(&self) -> MergeCriterion {
+        self.merge_criterion
+    }
+
+    /// Returns the pre-tokenizer used to split training texts.
+    pub fn pre_tokenizer(&self) -> &PreTokenizer {
+        &self.pre_tokenizer
+    }
+
+    /// Returns the normalizer applied to training texts.
+    pub fn normalizer(&self) -> &Normalizer {
+        &self.normalizer
+    }
+
+    /// Returns warnings about the trainer configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Trainer, Warning};
+    ///
+    /// assert_eq!(Trainer::new(0).warnings(), vec![Warning::ZeroMerges]);
+    /// assert!(Trainer::new(100).warnings().is_empty());
+    /// ```
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+
+        if self.num_merges == 0 {
+            warnings.push(Warning::ZeroMerges);
+        }
+        for (range, factor) in &self.script_boosts {
+            if *factor == 0 {
+                warnings.push(Warning::ZeroScriptBoost {
+                    range: range.clone(),
+                });
+            }
         }
+        warnings.extend(self.pre_tokenizer.warnings());
+
+        warnings
     }
 
     /// Trains the BPE tokenizer on the given texts.
@@ -82,28 +585,579 @@ impl Trainer {
     /// assert!(merges.len() <= 5);
     /// ```
     pub fn train(&self, training_texts: &[&str]) -> Vec<(String, String)> {
-        let mut merges = Vec::with_capacity(self.num_merges);
-        let mut word_freqs = self.build_word_frequencies(training_texts);
-        let mut token_to_id = self.build_initial_token_to_id();
-        let mut next_id = token_to_id.len() as u32;
+        self.run(training_texts, self.num_merges).merges
+    }
+
+    /// Trains on texts tagged with a language or source label, given as
+    /// `(label, text)`.
+    ///
+    /// Without [`with_max_label_share`](Trainer::with_max_label_share) this learns
+    /// the same merges as [`train`](Trainer::train) on the texts alone. With it, no
+    /// label may dominate more than its share of the merges. Returns the merges and,
+    /// for each merge, the label that dominated it. Pairs are always counted
+    /// exactly, even with
+    /// [`with_sketch_counting`](Trainer::with_sketch_counting), because every
+    /// label's contribution to a pair is needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let corpus = [
+    ///     ("en", "the theme of the thesis"),
+    ///     ("en", "the other three"),
+    ///     ("de", "die Dinge des Diebes"),
+    /// ];
+    ///
+    /// let (merges, labels) = Trainer::new(6).with_max_label_share(0.5).train_labeled(&corpus);
+    ///
+    /// assert_eq!(merges.len(), 6);
+    /// assert_eq!(labels.iter().filter(|&&label| label == "en").count(), 3);
+    /// ```
+    pub fn train_labeled<'a>(
+        &self,
+        training_texts: &[(&'a str, &str)],
+    ) -> (Vec<(String, String)>, Vec<&'a str>) {
+        let mut labels: Vec<&str> = training_texts.iter().map(|(label, _)| *label).collect();
+        labels.sort_unstable();
+        labels.dedup();
 
-        for _ in 0..self.num_merges {
-            let pair_freqs = Self::compute_pair_frequencies(&word_freqs);
+        let mut word_freqs = HashMap::new();
+        let mut label_word_freqs = Vec::with_capacity(labels.len());
+        for label in &labels {
+            let texts: Vec<&str> = training_texts
+                .iter()
+                .filter(|(text_label, _)| text_label == label)
+                .map(|(_, text)| *text)
+                .collect();
+            let label_words = self.build_word_frequencies(&texts);
+            for (symbols, &count) in &label_words {
+                *word_freqs.entry(symbols.clone()).or_insert(0) += count;
+            }
+            label_word_freqs.push(label_words);
+        }
+        if let Some(max_words) = self.max_unique_words {
+            Self::evict_rarest_words(&mut word_freqs, max_words);
+            for label_words in &mut label_word_freqs {
+                label_words.retain(|symbols, _| word_freqs.contains_key(symbols));
+            }
+        }
 
-            if let Some(best_pair) = Self::find_best_pair(&pair_freqs, &token_to_id) {
-                word_freqs = Self::apply_merge(&word_freqs, &best_pair);
+        let budget = LabelBudget::new(self.max_label_share.unwrap_or(1.0), label_word_freqs);
+        let (outcome, dominated_by) = self.learn(word_freqs, Some(budget), None, self.num_merges);
+        let dominated_by = dominated_by
+            .into_iter()
+            .map(|index| labels[index])
+            .collect();
 
-                let merged_token = Self::create_merged_token(&best_pair);
-                token_to_id.insert(merged_token, next_id);
-                next_id += 1;
+        (outcome.merges, dominated_by)
+    }
 
-                merges.push(best_pair);
-            } else {
+    /// Learns merges that extend `base` without changing any of its IDs, for adding
+    /// domain tokens to an imported vocabulary such as GPT-2's.
+    ///
+    /// Words are counted with the normalizer and pre-tokenizer of `base` and first
+    /// segmented with its merges, so the new merges build on its tokens. No new merge
+    /// produces a token `base` already has. Returns only the new merges, at most
+    /// `num_merges`; [`BpeTokenizer::extend`] appends them to `base`. Pairs are always
+    /// counted exactly, even with
+    /// [`with_sketch_counting`](Trainer::with_sketch_counting).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+    ///
+    /// let base = BpeTokenizer::from_trainer(&Trainer::new(3), &["the cat"], vec![]);
+    /// let extension = Trainer::new(4).train_extension(&base, &["kinase kinase kinase"]);
+    ///
+    /// assert_eq!(extension.len(), 4);
+    /// assert!(extension.iter().all(|merge| !base.merges().contains(merge)));
+    /// ```
+    pub fn train_extension(
+        &self,
+        base: &BpeTokenizer,
+        training_texts: &[&str],
+    ) -> Vec<(String, String)> {
+        let table = MergeTable::new(base.merges().to_vec());
+        let word_freqs = self
+            .count_words(base.normalizer(), base.pre_tokenizer(), training_texts)
+            .into_iter()
+            .map(|(symbols, count)| (table.merge_symbols(symbols), count))
+            .collect();
+
+        self.learn(word_freqs, None, Some(base.vocabulary()), self.num_merges)
+            .0
+            .merges
+    }
+
+    /// Trains on a UTF-8 text file cut into texts by `segmentation`.
+    ///
+    /// `.gz` and `.zst` files are decompressed while reading (see [`read_corpus`](crate::read_corpus)).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or decompressed, or is not valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Segmentation, Trainer};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("corpus.rs");
+    /// std::fs::write(&path, "fn a() {}\n\nfn b() {}\n").unwrap();
+    ///
+    /// let trainer = Trainer::new(5);
+    /// let merges = trainer.train_file(&path, Segmentation::Paragraphs).unwrap();
+    ///
+    /// assert_eq!(merges, trainer.train(&["fn a() {}", "fn b() {}"]));
+    /// ```
+    pub fn train_file(
+        &self,
+        path: impl AsRef<Path>,
+        segmentation: Segmentation,
+    ) -> io::Result<Vec<(String, String)>> {
+        self.train_from_files(&[path], segmentation)
+    }
+
+    /// Trains on several UTF-8 text files as one corpus, each cut into texts by
+    /// `segmentation`.
+    ///
+    /// Files are read like [`read_corpus`](crate::read_corpus), so compressed and plain files can be mixed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from reading a file.
+    pub fn train_from_files<P: AsRef<Path>>(
+        &self,
+        paths: &[P],
+        segmentation: Segmentation,
+    ) -> io::Result<Vec<(String, String)>> {
+        let sources: Vec<CorpusSource> = paths
+            .iter()
+            .map(|path| CorpusSource::from(path.as_ref()))
+            .collect();
+
+        self.train_from_sources(&sources, segmentation)
+    }
+
+    /// Trains on several corpus sources as one corpus, each cut into texts by
+    /// `segmentation`.
+    ///
+    /// Sources can be local files or, with the `object-store` feature, `s3://` and
+    /// `gs://` objects read chunk by chunk straight from the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from reading a source.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use bpe_tokenizer_rs::{CorpusSource, Segmentation, Trainer};
+    ///
+    /// let sources = [
+    ///     CorpusSource::parse("s3://corpora/web/part-0000.txt.zst"),
+    ///     CorpusSource::parse("local/extra.txt"),
+    /// ];
+    /// let merges = Trainer::new(1000)
+    ///     .train_from_sources(&sources, Segmentation::Lines)
+    ///     .unwrap();
+    /// ```
+    pub fn train_from_sources(
+        &self,
+        sources: &[CorpusSource],
+        segmentation: Segmentation,
+    ) -> io::Result<Vec<(String, String)>> {
+        let contents = sources
+            .iter()
+            .map(CorpusSource::read)
+            .collect::<io::Result<Vec<String>>>()?;
+        let texts: Vec<&str> = contents
+            .iter()
+            .flat_map(|text| segmentation.segments(text))
+            .collect();
+
+        Ok(self.train(&texts))
+    }
+
+    /// Trains on the given texts and returns a [`TrainReport`] with training statistics.
+    ///
+    /// The learned merges are identical to those returned by [`train`](Trainer::train).
+    ///
+    /// # Arguments
+    ///
+    /// * `training_texts` - Slice of text strings to train on
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let report = Trainer::new(2).train_with_report(&["hello hello"]);
+    ///
+    /// assert_eq!(report.merges.len(), 2);
+    /// assert!(!report.pair_frequencies.is_empty());
+    /// ```
+    pub fn train_with_report(&self, training_texts: &[&str]) -> TrainReport {
+        let outcome = self.run(training_texts, self.num_merges);
+
+        let mut pair_frequencies: Vec<PairFrequency> =
+            Self::compute_pair_frequencies(&outcome.word_freqs)
+                .into_iter()
+                .map(|((first, second), count)| PairFrequency {
+                    first,
+                    second,
+                    count,
+                })
+                .collect();
+        pair_frequencies.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| (&a.first, &a.second).cmp(&(&b.first, &b.second)))
+        });
+
+        TrainReport {
+            merges: outcome.merges,
+            merge_frequencies: outcome.merge_frequencies,
+            pair_frequencies,
+            config: self.config(training_texts),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Returns the configuration a training run on `training_texts` would record.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let config = Trainer::new(10).config(&["hello"]);
+    ///
+    /// assert_eq!(config.num_merges, 10);
+    /// assert_eq!(config.pre_tokenizer, "gpt2");
+    /// assert_eq!(config.corpus_texts, 1);
+    /// ```
+    pub fn config(&self, training_texts: &[&str]) -> TrainConfig {
+        TrainConfig {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            num_merges: self.num_merges,
+            pre_tokenizer: self.pre_tokenizer.describe(),
+            normalizer: self.normalizer.describe(),
+            script_boosts: self
+                .script_boosts
+                .iter()
+                .map(|(range, factor)| {
+                    format!(
+                        "U+{:04X}..=U+{:04X}*{}",
+                        *range.start() as u32,
+                        *range.end() as u32,
+                        factor
+                    )
+                })
+                .collect(),
+            tie_break: self.tie_break.describe().to_string(),
+            merge_criterion: self.merge_criterion.describe().to_string(),
+            stop_rule: self.stop_rule.map(StopRule::describe),
+            max_unique_words: self.max_unique_words,
+            pair_counting: self
+                .sketch_counting
+                .map_or_else(|| "exact".to_string(), |counting| counting.describe()),
+            corpus_texts: training_texts.len(),
+            corpus_hash: CorpusHasher::hash_with(&self.normalizer, training_texts),
+        }
+    }
+
+    /// Re-runs a recorded training run and verifies it learns byte-identical merges.
+    ///
+    /// The trainer configuration and corpus are checked against `expected.config`
+    /// before training, so a changed setting or corpus fails immediately and names
+    /// every differing field. If they match, training runs and the merges are compared
+    /// rule by rule.
+    ///
+    /// The crate version is not part of the check: a report recorded by another
+    /// release is reproduced as usual, and the returned report lists a
+    /// [`Warning::CrateVersionChanged`] in its
+    /// [`warnings`](TrainReport::warnings).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ReproducibilityError::ConfigMismatch`] if the configuration differs and
+    /// [`ReproducibilityError::MergeMismatch`] if the merges differ.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{ReproducibilityError, Trainer};
+    ///
+    /// let corpus = ["hello world", "hello there"];
+    /// let report = Trainer::new(5).train_with_report(&corpus);
+    ///
+    /// assert!(Trainer::new(5).reproduce(&corpus, &report).is_ok());
+    ///
+    /// let err = Trainer::new(6).reproduce(&corpus, &report).unwrap_err();
+    /// assert!(matches!(err, ReproducibilityError::ConfigMismatch { .. }));
+    /// ```
+    pub fn reproduce(
+        &self,
+        training_texts: &[&str],
+        expected: &TrainReport,
+    ) -> Result<TrainReport, ReproducibilityError> {
+        let fields = expected.config.differences(&self.config(training_texts));
+        if !fields.is_empty() {
+            return Err(ReproducibilityError::ConfigMismatch { fields });
+        }
+
+        let mut report = self.train_with_report(training_texts);
+        check_reproduced(expected, &report)?;
+        if expected.config.crate_version != report.config.crate_version {
+            report.warnings.push(Warning::CrateVersionChanged {
+                recorded: expected.config.crate_version.clone(),
+                current: report.config.crate_version.clone(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Evaluates several vocabulary sizes from a single training run.
+    ///
+    /// Trains once up to the largest requested size, then measures compression on
+    /// `training_texts` using only the first merges for each size. Because BPE learns
+    /// merges greedily, a prefix of the merge list is exactly what a smaller training
+    /// run would have produced, so this replaces N separate training runs. The
+    /// trainer's own `num_merges` is ignored.
+    ///
+    /// Sizes count the 256 byte-level base tokens. If training converges before a
+    /// requested size is reached, the reported `vocab_size` is the size actually
+    /// achieved.
+    ///
+    /// # Arguments
+    ///
+    /// * `training_texts` - Texts to train on and evaluate against
+    /// * `sizes` - Vocabulary sizes to evaluate
+    ///
+    /// # Returns
+    ///
+    /// One `(requested_size, stats)` entry per element of `sizes`, in input order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Trainer;
+    ///
+    /// let trainer = Trainer::new(0);
+    /// let results = trainer.sweep(&["hello hello world"], &[256, 260, 264]);
+    ///
+    /// assert_eq!(results.len(), 3);
+    /// assert!(results[2].1.tokens < results[0].1.tokens);
+    /// ```
+    pub fn sweep(&self, training_texts: &[&str], sizes: &[usize]) -> Vec<(usize, TokenizerStats)> {
+        let max_merges = sizes
+            .iter()
+            .map(|size| size.saturating_sub(256))
+            .max()
+            .unwrap_or(0);
+        let merges = self.run(training_texts, max_merges).merges;
+
+        sizes
+            .iter()
+            .map(|&size| {
+                let tokenizer =
+                    BpeTokenizer::with_merge_prefix(&merges, size.saturating_sub(256), vec![])
+                        .with_normalizer(self.normalizer.clone());
+
+                (size, tokenizer.stats(training_texts))
+            })
+            .collect()
+    }
+
+    fn run(&self, training_texts: &[&str], num_merges: usize) -> TrainingOutcome {
+        let word_freqs = self.build_word_frequencies(training_texts);
+        self.learn(word_freqs, None, None, num_merges).0
+    }
+
+    /// Learns merges from counted words. With a label budget, pairs are counted
+    /// exactly and filtered by the budget; the labels that dominated every merge are
+    /// returned alongside. With a frozen vocabulary, pairs are counted exactly, new
+    /// IDs follow the frozen ones and no merge may produce a frozen token again.
+    fn learn(
+        &self,
+        mut word_freqs: HashMap<Vec<String>, usize>,
+        mut budget: Option<LabelBudget>,
+        frozen: Option<&Vocabulary>,
+        num_merges: usize,
+    ) -> (TrainingOutcome, Vec<usize>) {
+        let mut merges = Vec::with_capacity(num_merges);
+        let mut merge_frequencies = Vec::with_capacity(num_merges);
+        let mut dominated_by = Vec::new();
+        let (mut token_to_id, first_id) = match frozen {
+            Some(vocabulary) => (
+                vocabulary
+                    .iter()
+                    .map(|(id, token)| (token.to_string(), id))
+                    .collect(),
+                vocabulary.len() as u32,
+            ),
+            None => {
+                let token_to_id = self.build_initial_token_to_id();
+                let first_id = token_to_id.len() as u32;
+                (token_to_id, first_id)
+            }
+        };
+        let exact = budget.is_some() || frozen.is_some();
+        let mut pair_counts = self.count_pairs(&word_freqs, &token_to_id, exact);
+        self.report_progress(0, num_merges, &word_freqs, &pair_counts);
+
+        for next_id in (first_id..).take(num_merges) {
+            pair_counts = match pair_counts {
+                PairCounts::Exact(mut pair_freqs) => {
+                    if let Some(vocabulary) = frozen {
+                        pair_freqs.retain(|pair, _| {
+                            vocabulary
+                                .token_to_id(&Self::create_merged_token(pair))
+                                .is_none()
+                        });
+                    }
+                    if let Some(budget) = &budget {
+                        pair_freqs = budget.eligible(pair_freqs, merges.len());
+                    }
+                    PairCounts::Exact(pair_freqs)
+                }
+                pair_counts => pair_counts,
+            };
+            let Some((best_pair, count)) = pair_counts.into_best(self, &word_freqs, &token_to_id)
+            else {
                 break;
+            };
+            if let Some(rule) = self.stop_rule
+                && rule.stops(count, &word_freqs)
+            {
+                break;
+            }
+            word_freqs = Self::apply_merge(&word_freqs, &best_pair);
+            if let Some(budget) = &mut budget {
+                budget.merge(&best_pair);
+            }
+
+            let merged_token = Self::create_merged_token(&best_pair);
+            token_to_id.insert(merged_token, next_id);
+
+            merge_frequencies.push(count);
+            merges.push(best_pair);
+
+            pair_counts = self.count_pairs(&word_freqs, &token_to_id, exact);
+            self.report_progress(merges.len(), num_merges, &word_freqs, &pair_counts);
+        }
+        if let Some(budget) = &budget {
+            dominated_by = budget.dominated_by().to_vec();
+        }
+
+        let outcome = TrainingOutcome {
+            merges,
+            merge_frequencies,
+            word_freqs,
+        };
+        (outcome, dominated_by)
+    }
+
+    fn report_progress(
+        &self,
+        merges_learned: usize,
+        num_merges: usize,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        pair_counts: &PairCounts,
+    ) {
+        if let Some(callback) = &self.progress {
+            let sketch = match pair_counts {
+                PairCounts::Exact(_) => None,
+                PairCounts::Sketched { accuracy, .. } => Some(*accuracy),
+            };
+            callback(&TrainProgress {
+                merges_learned,
+                num_merges,
+                unique_words: word_freqs.len(),
+                memory: self.memory_accounting.then(|| MemoryUsage {
+                    word_frequencies: MemoryUsage::word_table_bytes(word_freqs),
+                    pair_frequencies: match pair_counts {
+                        PairCounts::Exact(pair_freqs) => MemoryUsage::pair_table_bytes(pair_freqs),
+                        PairCounts::Sketched { bytes, .. } => *bytes,
+                    },
+                }),
+                sketch,
+            });
+        }
+    }
+
+    fn count_pairs(
+        &self,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        token_to_id: &HashMap<String, u32>,
+        exact: bool,
+    ) -> PairCounts {
+        match &self.sketch_counting {
+            Some(counting) if !exact => self.sketch_pairs(counting, word_freqs, token_to_id),
+            _ => PairCounts::Exact(Self::compute_pair_frequencies(word_freqs)),
+        }
+    }
+
+    /// Picks the next merge from a count-min sketch: estimates every pair, recounts
+    /// the top candidates exactly and chooses among them.
+    fn sketch_pairs(
+        &self,
+        counting: &SketchCounting,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        token_to_id: &HashMap<String, u32>,
+    ) -> PairCounts {
+        let mut sketch = CountMinSketch::new(counting);
+        let mut total = 0;
+        for (symbols, &count) in word_freqs {
+            for pair in symbols.windows(2) {
+                sketch.add(&pair[0], &pair[1], count);
+                total += count;
+            }
+        }
+
+        let mut top = TopCandidates::new(counting.candidates());
+        for symbols in word_freqs.keys() {
+            for pair in symbols.windows(2) {
+                top.offer((&pair[0], &pair[1]), sketch.estimate(&pair[0], &pair[1]));
             }
         }
+        let (candidates, excluded_estimate) = top.finish();
 
-        merges
+        let mut exact: HashMap<(&str, &str), usize> =
+            candidates.into_iter().map(|pair| (pair, 0)).collect();
+        for (symbols, &count) in word_freqs {
+            for pair in symbols.windows(2) {
+                if let Some(exact_count) = exact.get_mut(&(pair[0].as_str(), pair[1].as_str())) {
+                    *exact_count += count;
+                }
+            }
+        }
+        let candidates: HashMap<(String, String), usize> = exact
+            .into_iter()
+            .map(|((first, second), count)| ((first.to_string(), second.to_string()), count))
+            .collect();
+
+        let best = self
+            .select_pair(&candidates, word_freqs, token_to_id)
+            .map(|pair| {
+                let count = candidates[&pair];
+                (pair, count)
+            });
+        PairCounts::Sketched {
+            accuracy: SketchAccuracy {
+                error_bound: counting.error_bound(total),
+                excluded_estimate,
+                chosen_count: best.as_ref().map_or(0, |(_, count)| *count),
+            },
+            bytes: counting.sketch_bytes() + MemoryUsage::pair_table_bytes(&candidates),
+            best,
+        }
     }
 
     fn build_initial_token_to_id(&self) -> HashMap<String, u32> {
@@ -119,25 +1173,68 @@ impl Trainer {
     }
 
     fn build_word_frequencies(&self, training_texts: &[&str]) -> HashMap<Vec<String>, usize> {
+        self.count_words(&self.normalizer, &self.pre_tokenizer, training_texts)
+    }
+
+    /// Counts the pre-tokenized words of `training_texts` as byte-level symbols.
+    fn count_words(
+        &self,
+        normalizer: &Normalizer,
+        pre_tokenizer: &PreTokenizer,
+        training_texts: &[&str],
+    ) -> HashMap<Vec<String>, usize> {
         let byte_encoder = bytes_to_unicode();
 
-        training_texts
+        let mut word_freqs = training_texts
             .iter()
-            .flat_map(|text| self.pre_tokenizer.pre_tokenize(text))
+            .flat_map(|text| pre_tokenizer.pre_tokenize(&normalizer.normalize(text)))
             .map(|chunk| {
-                chunk
+                let weight = self.word_weight(&chunk);
+                let tokens = chunk
                     .as_bytes()
                     .iter()
                     .map(|&byte| byte_encoder[&byte].to_string())
-                    .collect::<Vec<String>>()
+                    .collect::<Vec<String>>();
+                (tokens, weight)
             })
-            .fold(HashMap::new(), |mut word_freqs, tokens| {
-                *word_freqs.entry(tokens).or_insert(0) += 1;
+            .fold(HashMap::new(), |mut word_freqs, (tokens, weight)| {
+                *word_freqs.entry(tokens).or_insert(0) += weight;
+                if let Some(max_words) = self.max_unique_words
+                    && word_freqs.len() >= max_words.saturating_mul(2).max(1)
+                {
+                    Self::evict_rarest_words(&mut word_freqs, max_words);
+                }
                 word_freqs
-            })
+            });
+        if let Some(max_words) = self.max_unique_words {
+            Self::evict_rarest_words(&mut word_freqs, max_words);
+        }
+        word_freqs
+    }
+
+    /// Keeps the `max_words` most frequent words, breaking ties by symbols.
+    fn evict_rarest_words(word_freqs: &mut HashMap<Vec<String>, usize>, max_words: usize) {
+        if word_freqs.len() <= max_words {
+            return;
+        }
+        let mut words: Vec<(Vec<String>, usize)> = word_freqs.drain().collect();
+        words.sort_unstable_by(|(symbols_a, count_a), (symbols_b, count_b)| {
+            count_b.cmp(count_a).then_with(|| symbols_a.cmp(symbols_b))
+        });
+        words.truncate(max_words);
+        word_freqs.extend(words);
+    }
+
+    fn word_weight(&self, word: &str) -> usize {
+        self.script_boosts
+            .iter()
+            .filter(|(range, _)| word.chars().any(|ch| range.contains(&ch)))
+            .map(|(_, factor)| *factor)
+            .max()
+            .unwrap_or(1)
     }
 
-    fn compute_pair_frequencies(
+    pub(crate) fn compute_pair_frequencies(
         word_freqs: &HashMap<Vec<String>, usize>,
     ) -> HashMap<(String, String), usize> {
         let mut pair_freqs = HashMap::new();
@@ -153,22 +1250,90 @@ impl Trainer {
         pair_freqs
     }
 
+    /// Chooses the next merge among `pair_freqs` by the trainer's criterion.
+    fn select_pair(
+        &self,
+        pair_freqs: &HashMap<(String, String), usize>,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        token_to_id: &HashMap<String, u32>,
+    ) -> Option<(String, String)> {
+        match self.merge_criterion {
+            MergeCriterion::Frequency => {
+                Self::find_best_pair(pair_freqs, token_to_id, self.tie_break)
+            }
+            MergeCriterion::LikelihoodGain => Self::find_best_pair_by_likelihood(
+                pair_freqs,
+                word_freqs,
+                token_to_id,
+                self.tie_break,
+            ),
+        }
+    }
+
     fn find_best_pair(
         pair_freqs: &HashMap<(String, String), usize>,
         token_to_id: &HashMap<String, u32>,
+        tie_break: TieBreak,
     ) -> Option<(String, String)> {
         pair_freqs
             .iter()
             .max_by(|(pair_a, count_a), (pair_b, count_b)| {
-                count_a.cmp(count_b).then_with(|| {
-                    let ids_a = Self::get_pair_ids(pair_a, token_to_id);
-                    let ids_b = Self::get_pair_ids(pair_b, token_to_id);
-                    ids_b.cmp(&ids_a)
-                })
+                count_a
+                    .cmp(count_b)
+                    .then_with(|| Self::break_tie(pair_a, pair_b, token_to_id, tie_break))
+            })
+            .map(|(pair, _)| pair.clone())
+    }
+
+    fn find_best_pair_by_likelihood(
+        pair_freqs: &HashMap<(String, String), usize>,
+        word_freqs: &HashMap<Vec<String>, usize>,
+        token_to_id: &HashMap<String, u32>,
+        tie_break: TieBreak,
+    ) -> Option<(String, String)> {
+        let mut symbol_counts: HashMap<&str, usize> = HashMap::new();
+        for (symbols, &count) in word_freqs {
+            for symbol in symbols {
+                *symbol_counts.entry(symbol).or_insert(0) += count;
+            }
+        }
+        let total = symbol_counts.values().sum::<usize>() as f64;
+        let gain = |(first, second): &(String, String), count: usize| {
+            let count = count as f64;
+            let expected = symbol_counts[first.as_str()] as f64
+                * symbol_counts[second.as_str()] as f64
+                / total;
+            count * (count / expected).ln()
+        };
+
+        pair_freqs
+            .iter()
+            .map(|(pair, &count)| (pair, gain(pair, count)))
+            .max_by(|(pair_a, gain_a), (pair_b, gain_b)| {
+                gain_a
+                    .total_cmp(gain_b)
+                    .then_with(|| Self::break_tie(pair_a, pair_b, token_to_id, tie_break))
             })
             .map(|(pair, _)| pair.clone())
     }
 
+    /// Orders two equally ranked pairs so that the one `tie_break` prefers is greater.
+    fn break_tie(
+        pair_a: &(String, String),
+        pair_b: &(String, String),
+        token_to_id: &HashMap<String, u32>,
+        tie_break: TieBreak,
+    ) -> std::cmp::Ordering {
+        match tie_break {
+            TieBreak::LowestIds => {
+                let ids_a = Self::get_pair_ids(pair_a, token_to_id);
+                let ids_b = Self::get_pair_ids(pair_b, token_to_id);
+                ids_b.cmp(&ids_a)
+            }
+            TieBreak::Lexicographic => pair_b.cmp(pair_a),
+        }
+    }
+
     fn get_pair_ids(pair: &(String, String), token_to_id: &HashMap<String, u32>) -> (u32, u32) {
         let id_0 = token_to_id.get(&pair.0).copied().unwrap_or(u32::MAX);
         let id_1 = token_to_id.get(&pair.1).copied().unwrap_or(u32::MAX);
@@ -179,7 +1344,7 @@ impl Trainer {
         format!("{}{}", pair.0, pair.1)
     }
 
-    fn apply_merge(
+    pub(crate) fn apply_merge(
         word_freqs: &HashMap<Vec<String>, usize>,
         pair: &(String, String),
     ) -> HashMap<Vec<String>, usize> {
@@ -331,6 +1496,26 @@ mod tests {
         assert_eq!(result.get(&expected_tokens), Some(&1));
     }
 
+    #[test]
+    fn build_word_frequencies_applies_script_boost() {
+        let trainer = Trainer::new(10).with_script_boost('\u{0400}'..='\u{04FF}', 5);
+        let result = trainer.build_word_frequencies(&["мир hi мир"]);
+
+        assert_eq!(result.get(&chunk_to_tokens("мир")), Some(&5));
+        assert_eq!(result.get(&chunk_to_tokens(" мир")), Some(&5));
+        assert_eq!(result.get(&chunk_to_tokens(" hi")), Some(&1));
+    }
+
+    #[test]
+    fn build_word_frequencies_uses_largest_matching_boost() {
+        let trainer = Trainer::new(10)
+            .with_script_boost('a'..='z', 2)
+            .with_script_boost('\u{3040}'..='\u{309F}', 7);
+        let result = trainer.build_word_frequencies(&["aあ"]);
+
+        assert_eq!(result.get(&chunk_to_tokens("aあ")), Some(&7));
+    }
+
     #[test]
     fn compute_pair_frequencies_empty() {
         let word_freqs = HashMap::new();
@@ -360,7 +1545,7 @@ mod tests {
     fn find_best_pair_returns_none_when_empty() {
         let pair_freqs = HashMap::new();
         let token_to_id = HashMap::new();
-        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id);
+        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id, TieBreak::LowestIds);
 
         assert_eq!(result, None);
     }
@@ -380,7 +1565,7 @@ mod tests {
         token_to_id.insert("e".to_string(), 4);
         token_to_id.insert("f".to_string(), 5);
 
-        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id);
+        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id, TieBreak::LowestIds);
 
         assert_eq!(result, Some(("c".to_string(), "d".to_string())));
     }
@@ -399,11 +1584,44 @@ mod tests {
         token_to_id.insert("d".to_string(), 3);
         token_to_id.insert("z".to_string(), 25);
 
-        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id);
+        let result = Trainer::find_best_pair(&pair_freqs, &token_to_id, TieBreak::LowestIds);
 
         assert_eq!(result, Some(("a".to_string(), "b".to_string())));
     }
 
+    #[test]
+    fn find_best_pair_breaks_tie_by_strings_in_lexicographic_mode() {
+        let mut pair_freqs = HashMap::new();
+        pair_freqs.insert(("b".to_string(), "x".to_string()), 2);
+        pair_freqs.insert(("aa".to_string(), "x".to_string()), 2);
+
+        let mut token_to_id = HashMap::new();
+        token_to_id.insert("b".to_string(), 65);
+        token_to_id.insert("x".to_string(), 87);
+        token_to_id.insert("aa".to_string(), 256);
+
+        let by_ids = Trainer::find_best_pair(&pair_freqs, &token_to_id, TieBreak::LowestIds);
+        let by_strings =
+            Trainer::find_best_pair(&pair_freqs, &token_to_id, TieBreak::Lexicographic);
+
+        assert_eq!(by_ids, Some(("b".to_string(), "x".to_string())));
+        assert_eq!(by_strings, Some(("aa".to_string(), "x".to_string())));
+    }
+
+    #[test]
+    fn tie_break_is_recorded_in_config() {
+        let corpus = ["aax", "bx"];
+        let report = Trainer::new(2).train_with_report(&corpus);
+        let lexicographic = Trainer::new(2).with_tie_break(TieBreak::Lexicographic);
+
+        assert_eq!(report.config.tie_break, "lowest-token-ids");
+        assert_eq!(lexicographic.config(&corpus).tie_break, "lexicographic");
+        assert!(matches!(
+            lexicographic.reproduce(&corpus, &report),
+            Err(ReproducibilityError::ConfigMismatch { fields }) if fields[0].0 == "tie_break"
+        ));
+    }
+
     #[test]
     fn apply_merge_combines_adjacent_pair() {
         let mut word_freqs = HashMap::new();
@@ -445,6 +1663,50 @@ mod tests {
         assert_eq!(result.get(&expected), Some(&1));
     }
 
+    #[test]
+    fn train_with_unix_newlines_learns_no_carriage_returns() {
+        use crate::{NewlineMode, Normalizer};
+
+        let trainer =
+            Trainer::new(10).with_normalizer(Normalizer::new().with_newlines(NewlineMode::Unix));
+        let merges = trainer.train(&["line\r\nline\r\n", "line\r\n"]);
+
+        let carriage_return = bytes_to_unicode()[&b'\r'].to_string();
+        assert!(!merges.is_empty());
+        assert!(
+            merges
+                .iter()
+                .all(|(a, b)| !a.contains(&carriage_return) && !b.contains(&carriage_return))
+        );
+    }
+
+    #[test]
+    fn sweep_matches_separate_training_runs() {
+        let texts = ["the cat sat on the mat", "the dog sat on the log"];
+
+        let results = Trainer::new(0).sweep(&texts, &[258, 262]);
+
+        for (size, stats) in results {
+            let tokenizer = BpeTokenizer::from_trainer(&Trainer::new(size - 256), &texts, vec![]);
+            assert_eq!(stats, tokenizer.stats(&texts));
+        }
+    }
+
+    #[test]
+    fn sweep_caps_at_converged_size() {
+        let results = Trainer::new(0).sweep(&["ab"], &[300]);
+
+        assert_eq!(results[0].0, 300);
+        assert_eq!(results[0].1.vocab_size, 257);
+    }
+
+    #[test]
+    fn sweep_empty_sizes() {
+        let results = Trainer::new(5).sweep(&["hello"], &[]);
+
+        assert!(results.is_empty());
+    }
+
     #[test]
     fn train_produces_same_merges_regardless_of_special_tokens() {
         use crate::Vocabulary;
@@ -466,4 +1728,386 @@ mod tests {
         assert_eq!(vocab_without_special.token_to_id("[PAD]"), None);
         assert_eq!(vocab_with_special.token_to_id("[PAD]"), Some(1));
     }
+
+    #[test]
+    fn warnings_report_zero_factor_boost() {
+        let trainer = Trainer::new(5).with_script_boost('a'..='z', 0);
+
+        assert_eq!(
+            trainer.warnings(),
+            vec![Warning::ZeroScriptBoost { range: 'a'..='z' }]
+        );
+    }
+
+    #[test]
+    fn reproduce_matches_identical_run() {
+        let corpus = ["the cat sat", "the cat ran"];
+        let trainer = Trainer::new(8).with_script_boost('a'..='z', 2);
+        let report = trainer.train_with_report(&corpus);
+
+        let reproduced = trainer.reproduce(&corpus, &report).unwrap();
+
+        assert_eq!(reproduced.merges, report.merges);
+        assert_eq!(reproduced.config, report.config);
+    }
+
+    #[test]
+    fn reproduce_rejects_changed_corpus_and_settings() {
+        let corpus = ["the cat sat", "the cat ran"];
+        let report = Trainer::new(8).train_with_report(&corpus);
+
+        let err = Trainer::new(8)
+            .with_normalizer(Normalizer::new().with_nfc(true))
+            .reproduce(&["the cat sat", "the dog ran"], &report)
+            .unwrap_err();
+
+        let ReproducibilityError::ConfigMismatch { fields } = err else {
+            panic!("expected a configuration mismatch");
+        };
+        let names: Vec<&str> = fields.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, vec!["normalizer", "corpus_hash"]);
+    }
+
+    #[test]
+    fn reproduce_warns_about_another_crate_version() {
+        let corpus = ["the cat sat", "the cat ran"];
+        let mut report = Trainer::new(8).train_with_report(&corpus);
+        report.config.crate_version = "0.0.1".to_string();
+
+        let reproduced = Trainer::new(8).reproduce(&corpus, &report).unwrap();
+
+        assert_eq!(
+            reproduced.warnings,
+            vec![Warning::CrateVersionChanged {
+                recorded: "0.0.1".to_string(),
+                current: env!("CARGO_PKG_VERSION").to_string(),
+            }]
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn reproduce_accepts_a_report_stored_as_json() {
+        let corpus = ["the cat sat", "the cat ran"];
+        let report = Trainer::new(8).train_with_report(&corpus);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let stored: TrainReport = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(stored, report);
+        assert!(Trainer::new(8).reproduce(&corpus, &stored).is_ok());
+    }
+
+    #[test]
+    fn progress_reports_counting_and_every_merge() {
+        use std::sync::{Arc, Mutex};
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&reports);
+        let trainer = Trainer::new(10).with_progress(move |progress| {
+            recorded.lock().unwrap().push(progress.clone());
+        });
+
+        let merges = trainer.train(&["ab ab", "abc"]);
+
+        let reports = reports.lock().unwrap();
+        let learned: Vec<usize> = reports.iter().map(|p| p.merges_learned).collect();
+        assert_eq!(learned, (0..=merges.len()).collect::<Vec<_>>());
+        assert!(
+            reports
+                .iter()
+                .all(|p| p.num_merges == 10 && p.memory.is_none())
+        );
+        assert_eq!(reports[0].unique_words, 3);
+    }
+
+    #[test]
+    fn memory_accounting_shrinks_as_words_merge() {
+        use std::sync::{Arc, Mutex};
+
+        let totals = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&totals);
+        let trainer =
+            Trainer::new(20)
+                .with_memory_accounting(true)
+                .with_progress(move |progress| {
+                    recorded.lock().unwrap().push(progress.memory.unwrap());
+                });
+
+        trainer.train(&["the theme of the thesis", "then there were three"]);
+
+        let totals = totals.lock().unwrap();
+        let first = totals.first().unwrap();
+        assert!(first.word_frequencies > 0 && first.pair_frequencies > 0);
+        assert!(totals.last().unwrap().total() < first.total());
+    }
+
+    #[test]
+    fn unique_word_cap_matches_uncapped_run_when_not_exceeded() {
+        let corpus = ["the theme of the thesis", "then there were three"];
+
+        // Nine distinct words, counting the leading space: "the", " theme", ...
+        let capped = Trainer::new(20).with_max_unique_words(9).train(&corpus);
+
+        assert_eq!(capped, Trainer::new(20).train(&corpus));
+    }
+
+    #[test]
+    fn unique_word_cap_bounds_the_table_and_keeps_frequent_words() {
+        let junk: Vec<String> = (0..500).map(|n| format!("z{}", n)).collect();
+        let mut corpus: Vec<&str> = junk.iter().map(String::as_str).collect();
+        corpus.extend(["abc abc abc"; 4]);
+        let trainer = Trainer::new(0).with_max_unique_words(10);
+
+        let word_freqs = trainer.build_word_frequencies(&corpus);
+
+        assert_eq!(word_freqs.len(), 10);
+        assert_eq!(word_freqs[&chunk_to_tokens(" abc")], 8);
+        assert_eq!(
+            trainer.config(&corpus).max_unique_words,
+            Some(10),
+            "the cap is part of the recorded configuration"
+        );
+    }
+
+    #[test]
+    fn sketch_counting_matches_exact_training_and_reports_accuracy() {
+        use std::sync::{Arc, Mutex};
+
+        let corpus = [
+            "the quick brown fox jumps over the lazy dog",
+            "then the three thinkers thought thoroughly",
+            "a stitch in time saves nine",
+        ];
+        let accuracies = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&accuracies);
+        let trainer = Trainer::new(30)
+            .with_sketch_counting(SketchCounting::new(1 << 14, 4).with_candidates(64))
+            .with_memory_accounting(true)
+            .with_progress(move |progress| {
+                recorded.lock().unwrap().push(progress.sketch.unwrap());
+            });
+
+        let report = trainer.train_with_report(&corpus);
+        let exact = Trainer::new(30).train_with_report(&corpus);
+
+        assert_eq!(report.merges, exact.merges);
+        assert_eq!(report.merge_frequencies, exact.merge_frequencies);
+        assert!(report.config.pair_counting.starts_with("count-min"));
+        let accuracies = accuracies.lock().unwrap();
+        assert_eq!(accuracies.len(), 31);
+        assert_eq!(accuracies[0].chosen_count, exact.merge_frequencies[0]);
+    }
+
+    #[test]
+    fn narrow_sketch_is_deterministic_and_recounts_chosen_merges_exactly() {
+        let corpus = ["aaab aaab aaab", "cdcd cdcd", "efg"];
+        let trainer =
+            || Trainer::new(1).with_sketch_counting(SketchCounting::new(2, 1).with_candidates(2));
+
+        let sketched = trainer().train_with_report(&corpus);
+
+        assert_eq!(trainer().train(&corpus), sketched.merges);
+        let word_freqs = trainer().build_word_frequencies(&corpus);
+        let pair_freqs = Trainer::compute_pair_frequencies(&word_freqs);
+        assert_eq!(
+            sketched.merge_frequencies[0],
+            pair_freqs[&sketched.merges[0]]
+        );
+
+        let exact = Trainer::new(1).config(&corpus);
+        let fields = exact.differences(&sketched.config);
+        assert_eq!(fields[0].0, "pair_counting");
+    }
+
+    #[test]
+    fn likelihood_gain_prefers_associated_pairs_over_frequent_ones() {
+        let corpus = ["a , b , c , d , e , qu qu qu"];
+        let trainer = || Trainer::new(3).with_merge_criterion(MergeCriterion::LikelihoodGain);
+
+        let report = trainer().train_with_report(&corpus);
+        let frequency = Trainer::new(3).train_with_report(&corpus);
+
+        assert_eq!(report.merges[0], ("q".to_string(), "u".to_string()));
+        assert_eq!(frequency.merges[0], ("Ġ".to_string(), ",".to_string()));
+        assert_eq!(
+            report.merge_frequencies[0], 3,
+            "merge frequencies stay counts"
+        );
+        assert_eq!(report.config.merge_criterion, "likelihood-gain");
+        assert!(trainer().reproduce(&corpus, &report).is_ok());
+        let fields = frequency.config.differences(&report.config);
+        assert_eq!(fields[0].0, "merge_criterion");
+    }
+
+    #[test]
+    fn likelihood_gain_breaks_ties_and_works_with_sketch_counting() {
+        let word_freqs = HashMap::from([(chunk_to_tokens("ab"), 2), (chunk_to_tokens("cd"), 2)]);
+        let pair_freqs = Trainer::compute_pair_frequencies(&word_freqs);
+        let token_to_id = Trainer::new(0).build_initial_token_to_id();
+
+        let best = Trainer::find_best_pair_by_likelihood(
+            &pair_freqs,
+            &word_freqs,
+            &token_to_id,
+            TieBreak::LowestIds,
+        );
+        assert_eq!(best, Some(("a".to_string(), "b".to_string())));
+
+        let corpus = [
+            "the quick brown fox jumps over the lazy dog",
+            "a , b , qu qu",
+        ];
+        let trainer = || Trainer::new(10).with_merge_criterion(MergeCriterion::LikelihoodGain);
+        let sketched = trainer()
+            .with_sketch_counting(SketchCounting::new(1 << 14, 4))
+            .train(&corpus);
+        assert_eq!(sketched, trainer().train(&corpus));
+    }
+
+    #[test]
+    fn pair_frequency_stop_rule_keeps_only_common_merges() {
+        let corpus = ["the cat sat on the mat", "the dog sat on the log"];
+        let full = Trainer::new(100).train_with_report(&corpus);
+        let trainer = || Trainer::new(100).with_stop_rule(StopRule::PairFrequencyBelow(0.25));
+
+        let report = trainer().train_with_report(&corpus);
+
+        // 12 words: merges need a pair seen at least 3 times.
+        let kept = full
+            .merge_frequencies
+            .iter()
+            .take_while(|&&count| count >= 3);
+        assert_eq!(report.merges.len(), kept.count());
+        assert_eq!(report.merges[..], full.merges[..report.merges.len()]);
+        assert_eq!(
+            report.config.stop_rule.as_deref(),
+            Some("pair-frequency<0.25")
+        );
+        let fields = full.config.differences(&report.config);
+        assert_eq!(fields[0].0, "stop_rule");
+        assert!(trainer().reproduce(&corpus, &report).is_ok());
+    }
+
+    #[test]
+    fn compression_gain_stop_rule_compares_with_remaining_tokens() {
+        let word_freqs = HashMap::from([(chunk_to_tokens("abcd"), 5)]);
+
+        assert!(!StopRule::CompressionGainBelow(0.25).stops(5, &word_freqs));
+        assert!(StopRule::CompressionGainBelow(0.3).stops(5, &word_freqs));
+        assert!(!StopRule::PairFrequencyBelow(1.0).stops(5, &word_freqs));
+
+        let corpus = ["aaaa bbbb cccc aaaa"];
+        let stopped = Trainer::new(100)
+            .with_stop_rule(StopRule::CompressionGainBelow(0.9))
+            .train(&corpus);
+        assert!(stopped.is_empty());
+        let unlimited = Trainer::new(100)
+            .with_stop_rule(StopRule::CompressionGainBelow(0.0))
+            .train(&corpus);
+        assert_eq!(unlimited, Trainer::new(100).train(&corpus));
+    }
+
+    #[test]
+    fn label_share_keeps_a_majority_label_from_taking_every_merge() {
+        let english = [
+            "the theme of the thesis",
+            "then there were three",
+            "the other",
+        ];
+        let mut corpus: Vec<(&str, &str)> = english.iter().map(|text| ("en", *text)).collect();
+        corpus.push(("ru", "мир"));
+        let texts: Vec<&str> = corpus.iter().map(|(_, text)| *text).collect();
+
+        let (unlimited, _) = Trainer::new(12).train_labeled(&corpus);
+        assert_eq!(unlimited, Trainer::new(12).train(&texts));
+
+        let (merges, labels) = Trainer::new(12)
+            .with_max_label_share(0.5)
+            .train_labeled(&corpus);
+        assert_eq!(merges.len(), labels.len());
+        for learned in 1..=labels.len() {
+            let english = labels[..learned].iter().filter(|&&label| label == "en");
+            assert!(english.count() <= learned.div_ceil(2));
+        }
+        assert!(labels.contains(&"ru"));
+        assert!(merges.len() < 12, "stops once only English pairs are left");
+    }
+
+    #[test]
+    fn extension_keeps_frozen_ids_and_builds_on_frozen_tokens() {
+        let base = BpeTokenizer::from_trainer(
+            &Trainer::new(6),
+            &["the theme of the thesis"],
+            vec!["<|endoftext|>".to_string()],
+        );
+        let (ranked, _) = base.rank_ids_by_frequency(&["thesis"]);
+        let domain = ["the thesis thesis thesis"];
+
+        let extension = Trainer::new(3).train_extension(&ranked, &domain);
+        let extended = ranked.extend(&Trainer::new(3), &domain);
+
+        let frozen = ranked.vocabulary().len();
+        assert_eq!(extended.merges()[..ranked.merges().len()], *ranked.merges());
+        assert_eq!(extended.merges()[ranked.merges().len()..], *extension);
+        assert!(
+            ranked
+                .vocabulary()
+                .iter()
+                .eq(extended.vocabulary().iter().take(frozen))
+        );
+        assert_eq!(extended.vocabulary().token_to_id("<|endoftext|>"), Some(0));
+        assert!(extended.encode(" thesis").len() < ranked.encode(" thesis").len());
+        let frozen_tokens: Vec<&str> = ranked.vocabulary().iter().map(|(_, t)| t).collect();
+        assert!(frozen_tokens.contains(&extension[0].0.as_str()));
+    }
+
+    #[test]
+    fn extends_a_base_with_two_merges_producing_the_same_token() {
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "c".to_string()),
+            ("b".to_string(), "c".to_string()),
+            ("a".to_string(), "bc".to_string()),
+        ];
+        let base = BpeTokenizer::new(merges, vec![]);
+
+        let extended = base.extend(&Trainer::new(2), &["abcd abcd abcd"]);
+
+        let frozen = base.vocabulary().len();
+        assert_eq!(extended.vocabulary().len(), frozen + 2);
+        assert!(
+            base.vocabulary()
+                .iter()
+                .eq(extended.vocabulary().iter().take(frozen))
+        );
+        assert_eq!(extended.encode("abcd").len(), 1);
+    }
+
+    #[test]
+    fn extension_keeps_the_unknown_token_and_word_cache() {
+        let base = BpeTokenizer::new(vec![], vec!["<unk>".to_string()])
+            .with_unknown_token("<unk>")
+            .unwrap()
+            .with_word_cache(16);
+
+        let extended = base.extend(&Trainer::new(2), &["kinase kinase"]);
+
+        assert_eq!(extended.unknown_token(), Some("<unk>"));
+        assert_eq!(extended.word_cache_stats().unwrap().capacity, 16);
+    }
+
+    #[test]
+    fn extension_never_relearns_a_frozen_token() {
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("b".to_string(), "c".to_string()),
+            ("a".to_string(), "bc".to_string()),
+        ];
+        let base = BpeTokenizer::new(merges, vec![]);
+
+        assert_eq!(base.encode("abc").len(), 2, "segmented as ab + c");
+        assert!(Trainer::new(3).train_extension(&base, &["abc"]).is_empty());
+        assert_eq!(Trainer::new(1).train(&["abc"]).len(), 1);
+    }
 }
@@ -1,6 +1,25 @@
-use std::collections::HashMap;
-
-use crate::bytes_to_unicode;
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "mmap")]
+use std::io;
+#[cfg(feature = "mmap")]
+use std::path::Path;
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+
+#[cfg(feature = "mmap")]
+use crate::mapped_vocabulary::MappedTokens;
+use crate::{VocabularyConflict, VocabularyError, bytes_to_unicode, unicode_to_bytes};
+
+/// Where [`Vocabulary::find`] looks for the text in a token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenQuery<'a> {
+    /// The token's text starts with the given text.
+    Prefix(&'a str),
+    /// The token's text ends with the given text.
+    Suffix(&'a str),
+    /// The token's text contains the given text anywhere.
+    Contains(&'a str),
+}
 
 /// Manages bidirectional mapping between tokens and their IDs for BPE tokenization.
 ///
@@ -17,6 +36,15 @@ use crate::bytes_to_unicode;
 /// 2. Byte-level tokens: sorted by Unicode character value
 /// 3. Merged tokens: in the order they were learned during training
 ///
+/// Sorting byte-level tokens by character value is exactly the order of GPT-2's
+/// `vocab.json` and of Hugging Face `tokenizers` when its BPE trainer is given the full
+/// `ByteLevel` alphabet, so IDs line up when only a `merges.txt` from such a tokenizer
+/// is available. Tokenizers trained on a partial alphabet cannot be reproduced from the
+/// merges alone, because their base IDs depend on which bytes occurred in the data.
+///
+/// [`BpeTokenizer::rank_ids_by_frequency`](crate::BpeTokenizer::rank_ids_by_frequency)
+/// renumbers the non-special tokens of an existing vocabulary; merge ranks are unaffected.
+///
 /// # Performance
 ///
 /// Uses two data structures for optimal performance:
@@ -26,6 +54,9 @@ use crate::bytes_to_unicode;
 /// Vec is used instead of HashMap<u32, String> for `id_to_token` because IDs are
 /// sequential (0, 1, 2, ...), making Vec index access more efficient than hash lookup.
 ///
+/// With the `serde` feature enabled, a `Vocabulary` serializes to an array of token
+/// strings indexed by ID.
+///
 /// # Examples
 ///
 /// ```
@@ -40,9 +71,46 @@ use crate::bytes_to_unicode;
 /// assert_eq!(vocab.id_to_token(0), Some("<|endoftext|>"));
 /// ```
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<String>", into = "Vec<String>"))]
 pub struct Vocabulary {
-    token_to_id: HashMap<String, u32>,
-    id_to_token: Vec<String>,
+    storage: Storage,
+}
+
+#[derive(Clone)]
+enum Storage {
+    Owned {
+        token_to_id: HashMap<String, u32>,
+        id_to_token: Vec<String>,
+    },
+    /// Tokens read in place from a file mapping shared by every clone.
+    #[cfg(feature = "mmap")]
+    Mapped(Arc<MappedTokens>),
+}
+
+#[cfg(feature = "serde")]
+impl From<Vec<String>> for Vocabulary {
+    fn from(id_to_token: Vec<String>) -> Self {
+        Vocabulary::from_tokens(id_to_token)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Vocabulary> for Vec<String> {
+    fn from(vocabulary: Vocabulary) -> Self {
+        match vocabulary.storage {
+            Storage::Owned { id_to_token, .. } => id_to_token,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(tokens) => (0..tokens.len() as u32)
+                .map(|id| {
+                    tokens
+                        .token(id)
+                        .expect("IDs below the length exist")
+                        .to_string()
+                })
+                .collect(),
+        }
+    }
 }
 
 impl Vocabulary {
@@ -53,6 +121,9 @@ impl Vocabulary {
     /// 2. All 256 byte-level base tokens (sorted by Unicode value)
     /// 3. Merged tokens from BPE training
     ///
+    /// A token listed twice keeps both IDs, and lookups return the higher one. Use
+    /// [`try_new`](Vocabulary::try_new) to reject such input instead.
+    ///
     /// # Arguments
     ///
     /// * `special_tokens` - Vector of special tokens (e.g., `<|endoftext|>`, `[PAD]`)
@@ -88,16 +159,155 @@ impl Vocabulary {
             id_to_token.push(token);
         }
 
-        for (part1, part2) in merges {
-            let token = format!("{}{}", part1, part2);
+        for (mut token, part2) in merges {
+            token.push_str(&part2);
             let id = id_to_token.len() as u32;
             token_to_id.insert(token.clone(), id);
             id_to_token.push(token);
         }
 
         Vocabulary {
-            token_to_id,
-            id_to_token,
+            storage: Storage::Owned {
+                token_to_id,
+                id_to_token,
+            },
+        }
+    }
+
+    /// Creates a vocabulary like [`new`](Vocabulary::new), but fails instead of
+    /// giving a token more than one ID.
+    ///
+    /// [`new`](Vocabulary::new) accepts repeated tokens and lets the highest ID win
+    /// lookups, which leaves the lower IDs decodable but never produced. That is
+    /// harmless for tokenizers trained by this crate, but in hand-written or
+    /// third-party special token lists and merges it usually means a mistake.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`VocabularyError`] listing every duplicate special token, special
+    /// token that equals a byte-level or merged token, and merge that produces the
+    /// same token as an earlier one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{Vocabulary, VocabularyConflict};
+    ///
+    /// let merges = vec![
+    ///     ("a".to_string(), "b".to_string()),
+    ///     ("ab".to_string(), "c".to_string()),
+    ///     ("a".to_string(), "bc".to_string()),
+    /// ];
+    /// let err = Vocabulary::try_new(vec!["ab".to_string()], merges).err().unwrap();
+    ///
+    /// assert_eq!(
+    ///     err.conflicts,
+    ///     vec![
+    ///         VocabularyConflict::SpecialTokenCollision { token: "ab".to_string() },
+    ///         VocabularyConflict::DuplicateMergeTarget {
+    ///             token: "abc".to_string(),
+    ///             first_rank: 1,
+    ///             rank: 2,
+    ///         },
+    ///     ]
+    /// );
+    /// assert!(Vocabulary::try_new(vec!["<|endoftext|>".to_string()], vec![]).is_ok());
+    /// ```
+    pub fn try_new(
+        special_tokens: Vec<String>,
+        merges: Vec<(String, String)>,
+    ) -> Result<Self, VocabularyError> {
+        let mut merge_ranks: HashMap<String, usize> = HashMap::with_capacity(merges.len());
+        let mut merge_conflicts = Vec::new();
+        for (rank, (first, second)) in merges.iter().enumerate() {
+            let token = format!("{}{}", first, second);
+            if let Some(&first_rank) = merge_ranks.get(&token) {
+                merge_conflicts.push(VocabularyConflict::DuplicateMergeTarget {
+                    token,
+                    first_rank,
+                    rank,
+                });
+            } else {
+                merge_ranks.insert(token, rank);
+            }
+        }
+
+        let mut seen = HashSet::with_capacity(special_tokens.len());
+        let mut unique = Vec::with_capacity(special_tokens.len());
+        let mut conflicts = Vec::new();
+        for token in &special_tokens {
+            if seen.insert(token.as_str()) {
+                unique.push(token);
+                continue;
+            }
+            let conflict = VocabularyConflict::DuplicateSpecialToken {
+                token: token.clone(),
+            };
+            if !conflicts.contains(&conflict) {
+                conflicts.push(conflict);
+            }
+        }
+        let byte_chars: HashSet<String> = bytes_to_unicode()
+            .values()
+            .map(|ch| ch.to_string())
+            .collect();
+        conflicts.extend(
+            unique
+                .into_iter()
+                .filter(|token| byte_chars.contains(*token) || merge_ranks.contains_key(*token))
+                .map(|token| VocabularyConflict::SpecialTokenCollision {
+                    token: token.clone(),
+                }),
+        );
+        conflicts.extend(merge_conflicts);
+
+        if conflicts.is_empty() {
+            Ok(Vocabulary::new(special_tokens, merges))
+        } else {
+            Err(VocabularyError { conflicts })
+        }
+    }
+
+    /// Returns a copy of this vocabulary renumbered so that new ID `i` is old ID
+    /// `new_to_old[i]`. `new_to_old` must be a permutation of all IDs.
+    pub(crate) fn permuted(&self, new_to_old: &[u32]) -> Vocabulary {
+        let id_to_token = new_to_old
+            .iter()
+            .map(|&old_id| {
+                self.id_to_token(old_id)
+                    .expect("a permutation only holds existing IDs")
+                    .to_string()
+            })
+            .collect();
+
+        Vocabulary::from_tokens(id_to_token)
+    }
+
+    /// Returns a copy of this vocabulary with `tokens` appended after its last ID.
+    pub(crate) fn extended(&self, tokens: impl IntoIterator<Item = String>) -> Vocabulary {
+        let id_to_token = self
+            .iter()
+            .map(|(_, token)| token.to_string())
+            .chain(tokens)
+            .collect();
+
+        Vocabulary::from_tokens(id_to_token)
+    }
+
+    /// Creates a vocabulary from tokens in ID order.
+    pub(crate) fn from_tokens(id_to_token: Vec<String>) -> Vocabulary {
+        // Later IDs win for repeated tokens, as in `Vocabulary::new`.
+        let token_to_id = id_to_token
+            .iter()
+            .enumerate()
+            .map(|(id, token)| (token.clone(), id as u32))
+            .collect();
+
+        Vocabulary {
+            storage: Storage::Owned {
+                token_to_id,
+                id_to_token,
+            },
         }
     }
 
@@ -122,7 +332,11 @@ impl Vocabulary {
     /// assert_eq!(vocab.token_to_id("unknown"), None);
     /// ```
     pub fn token_to_id(&self, token: &str) -> Option<u32> {
-        self.token_to_id.get(token).copied()
+        match &self.storage {
+            Storage::Owned { token_to_id, .. } => token_to_id.get(token).copied(),
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(tokens) => tokens.id(token),
+        }
     }
 
     /// Converts a token ID to its corresponding string.
@@ -146,7 +360,196 @@ impl Vocabulary {
     /// assert_eq!(vocab.id_to_token(99999), None);
     /// ```
     pub fn id_to_token(&self, id: u32) -> Option<&str> {
-        self.id_to_token.get(id as usize).map(|s| s.as_str())
+        match &self.storage {
+            Storage::Owned { id_to_token, .. } => id_to_token.get(id as usize).map(|s| s.as_str()),
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(tokens) => tokens.token(id),
+        }
+    }
+
+    /// Iterates over all `(id, token)` entries in ascending ID order.
+    ///
+    /// The order is guaranteed, so the output can be snapshotted or diffed directly.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Vocabulary;
+    ///
+    /// let vocab = Vocabulary::new(vec!["[PAD]".to_string()], vec![]);
+    /// let first: Vec<(u32, &str)> = vocab.iter().take(2).collect();
+    ///
+    /// assert_eq!(first, vec![(0, "[PAD]"), (1, "!")]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &str)> + '_ {
+        (0..self.len() as u32).map(|id| {
+            let token = self.id_to_token(id).expect("IDs below the length exist");
+            (id, token)
+        })
+    }
+
+    /// Returns the `(id, token)` entries whose text matches `query`, in ascending ID
+    /// order.
+    ///
+    /// Tokens are matched by the text they decode to rather than their byte-level
+    /// spelling, so `" cat"` finds `Ġcat`. Matching compares bytes, which lets a
+    /// query match tokens holding part of a multi-byte character. Tokens with
+    /// characters outside the byte-level alphabet, such as some special tokens, are
+    /// matched as written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::{TokenQuery, Vocabulary};
+    ///
+    /// let merges = vec![
+    ///     ("a".to_string(), "t".to_string()),
+    ///     ("Ġ".to_string(), "c".to_string()),
+    ///     ("Ġc".to_string(), "at".to_string()),
+    /// ];
+    /// let vocab = Vocabulary::new(vec![], merges);
+    ///
+    /// assert_eq!(vocab.find(TokenQuery::Prefix(" ca")), vec![(258, "Ġcat")]);
+    /// assert_eq!(
+    ///     vocab.find(TokenQuery::Suffix("at")),
+    ///     vec![(256, "at"), (258, "Ġcat")]
+    /// );
+    /// ```
+    pub fn find(&self, query: TokenQuery<'_>) -> Vec<(u32, &str)> {
+        let unicode_to_byte = unicode_to_bytes();
+        let decoded = |token: &str| -> Option<Vec<u8>> {
+            token
+                .chars()
+                .map(|ch| unicode_to_byte.get(&ch).copied())
+                .collect()
+        };
+
+        self.iter()
+            .filter(|(_, token)| {
+                let bytes = decoded(token).unwrap_or_else(|| token.as_bytes().to_vec());
+                match query {
+                    TokenQuery::Prefix(text) => bytes.starts_with(text.as_bytes()),
+                    TokenQuery::Suffix(text) => bytes.ends_with(text.as_bytes()),
+                    TokenQuery::Contains(text) => {
+                        text.is_empty()
+                            || bytes
+                                .windows(text.len())
+                                .any(|window| window == text.as_bytes())
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the number of characters in each token, in ID order.
+    pub(crate) fn char_lens(&self) -> Box<[usize]> {
+        match &self.storage {
+            Storage::Owned { id_to_token, .. } => id_to_token
+                .iter()
+                .map(|token| token.chars().count())
+                .collect(),
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(tokens) => tokens.char_lens(),
+        }
+    }
+
+    /// Returns the number of tokens in the vocabulary, including special and byte-level tokens.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Vocabulary;
+    ///
+    /// let vocab = Vocabulary::new(vec!["[PAD]".to_string()], vec![]);
+    /// assert_eq!(vocab.len(), 257);
+    /// ```
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Owned { id_to_token, .. } => id_to_token.len(),
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(tokens) => tokens.len(),
+        }
+    }
+
+    /// Returns `true` if the vocabulary contains no tokens.
+    ///
+    /// A vocabulary built with [`Vocabulary::new`] always contains the 256 byte-level tokens,
+    /// so this only returns `true` for hand-built edge cases.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes the vocabulary in a layout that
+    /// [`open_mapped`](Vocabulary::open_mapped) reads in place (feature `mmap`).
+    ///
+    /// The file holds the tokens in ID order and an index of them sorted by their
+    /// bytes; see [`MAPPED_VOCABULARY_MAGIC`](crate::MAPPED_VOCABULARY_MAGIC).
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from creating or writing the file.
+    #[cfg(feature = "mmap")]
+    pub fn save_mapped(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let tokens: Vec<&str> = self.iter().map(|(_, token)| token).collect();
+        MappedTokens::write(path, &tokens)
+    }
+
+    /// Opens a vocabulary written by [`save_mapped`](Vocabulary::save_mapped) as a
+    /// read-only memory mapping (feature `mmap`).
+    ///
+    /// Token strings are read from the mapping instead of being copied into `String`s,
+    /// so worker processes that open the same file share one copy through the page
+    /// cache, and clones of the vocabulary share the mapping. The layout is validated
+    /// once when opening. Lookups by ID cost the same as for an owned vocabulary;
+    /// lookups by token use binary search instead of hashing, which makes
+    /// [`token_to_id`](Vocabulary::token_to_id) somewhat slower.
+    ///
+    /// The file must not be modified or truncated while it is mapped: like every
+    /// memory mapping, that can make reads return changed data or crash the process.
+    /// Replace vocabulary files by writing a new file and renaming it over the old one.
+    ///
+    /// # Errors
+    ///
+    /// Returns any I/O error from opening or mapping the file, and
+    /// [`io::ErrorKind::InvalidData`] if it does not have the expected layout.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use bpe_tokenizer_rs::Vocabulary;
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// let path = dir.path().join("vocab.bin");
+    /// let merges = vec![("h".to_string(), "i".to_string())];
+    /// Vocabulary::new(vec!["<s>".to_string()], merges).save_mapped(&path).unwrap();
+    ///
+    /// let mapped = Vocabulary::open_mapped(&path).unwrap();
+    ///
+    /// assert!(mapped.is_mapped());
+    /// assert_eq!(mapped.token_to_id("hi"), Some(257));
+    /// assert_eq!(mapped.id_to_token(0), Some("<s>"));
+    /// ```
+    #[cfg(feature = "mmap")]
+    pub fn open_mapped(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::from_mapped(MappedTokens::open(path)?))
+    }
+
+    /// Creates a vocabulary that reads its tokens from `tokens`.
+    #[cfg(feature = "mmap")]
+    pub(crate) fn from_mapped(tokens: MappedTokens) -> Self {
+        Vocabulary {
+            storage: Storage::Mapped(Arc::new(tokens)),
+        }
+    }
+
+    /// Returns `true` if the tokens are read from a file mapping opened with
+    /// `open_mapped` (feature `mmap`).
+    pub fn is_mapped(&self) -> bool {
+        match self.storage {
+            Storage::Owned { .. } => false,
+            #[cfg(feature = "mmap")]
+            Storage::Mapped(_) => true,
+        }
     }
 }
 
@@ -154,6 +557,33 @@ impl Vocabulary {
 mod tests {
     use super::*;
 
+    #[test]
+    fn find_matches_decoded_text() {
+        let merges = vec![
+            ("Ã".to_string(), "©".to_string()),
+            ("c".to_string(), "af".to_string()),
+            ("a".to_string(), "f".to_string()),
+        ];
+        let vocab = Vocabulary::new(
+            vec!["<|endoftext|>".to_string(), "<日本>".to_string()],
+            merges,
+        );
+
+        assert_eq!(vocab.find(TokenQuery::Contains("é")), vec![(258, "Ã©")]);
+        assert_eq!(vocab.find(TokenQuery::Prefix("\u{e9}")), vec![(258, "Ã©")]);
+        assert_eq!(
+            vocab.find(TokenQuery::Contains("af")),
+            vec![(259, "caf"), (260, "af")]
+        );
+        assert_eq!(vocab.find(TokenQuery::Suffix("日本>")), vec![(1, "<日本>")]);
+        assert_eq!(
+            vocab.find(TokenQuery::Prefix("<|")),
+            vec![(0, "<|endoftext|>")]
+        );
+        assert_eq!(vocab.find(TokenQuery::Contains("")).len(), vocab.len());
+        assert!(vocab.find(TokenQuery::Contains("Ã")).is_empty());
+    }
+
     #[test]
     fn vocabulary_base_tokens_correct() {
         let vocab = Vocabulary::new(vec![], vec![]);
@@ -293,4 +723,59 @@ mod tests {
         let recovered2 = vocab.id_to_token(id2).unwrap();
         assert_eq!(token2, recovered2);
     }
+
+    #[test]
+    fn iter_yields_entries_in_id_order() {
+        let merges = vec![("a".to_string(), "b".to_string())];
+        let vocab = Vocabulary::new(vec!["<s>".to_string()], merges);
+        let entries: Vec<(u32, &str)> = vocab.iter().collect();
+
+        assert_eq!(entries.len(), vocab.len());
+        assert!(
+            entries
+                .iter()
+                .enumerate()
+                .all(|(i, (id, _))| i as u32 == *id)
+        );
+        assert_eq!(entries[0], (0, "<s>"));
+        assert_eq!(entries[257], (257, "ab"));
+    }
+
+    #[test]
+    fn try_new_reports_each_duplicate_special_token_once() {
+        let specials = ["<s>", "A", "<s>", "</s>", "<s>", "</s>"];
+        let specials: Vec<String> = specials.iter().map(|token| token.to_string()).collect();
+
+        let err = Vocabulary::try_new(specials, vec![]).err().unwrap();
+
+        let duplicate = |token: &str| VocabularyConflict::DuplicateSpecialToken {
+            token: token.to_string(),
+        };
+        assert_eq!(
+            err.conflicts,
+            vec![
+                duplicate("<s>"),
+                duplicate("</s>"),
+                VocabularyConflict::SpecialTokenCollision {
+                    token: "A".to_string()
+                },
+            ]
+        );
+        assert!(err.to_string().starts_with("3 conflicting token(s)"));
+    }
+
+    #[test]
+    fn try_new_matches_new_when_valid() {
+        let merges = vec![
+            ("a".to_string(), "b".to_string()),
+            ("ab".to_string(), "c".to_string()),
+        ];
+        let specials = vec!["<|endoftext|>".to_string()];
+
+        let checked = Vocabulary::try_new(specials.clone(), merges.clone())
+            .ok()
+            .unwrap();
+
+        assert!(checked.iter().eq(Vocabulary::new(specials, merges).iter()));
+    }
 }
@@ -0,0 +1,512 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+#[cfg(feature = "mmap")]
+use std::path::Path;
+
+use xxhash_rust::xxh3::xxh3_64;
+
+#[cfg(feature = "mmap")]
+use crate::Vocabulary;
+#[cfg(feature = "mmap")]
+use crate::low_level::MergeTable;
+#[cfg(feature = "mmap")]
+use crate::mapped_merges::MappedMerges;
+#[cfg(feature = "mmap")]
+use crate::mapped_vocabulary::{MappedTokens, map_file};
+use crate::{ConfigError, Encoding, TokenizerConfig};
+
+/// Magic bytes at the start of every file written by
+/// [`BpeTokenizer::save_binary`](crate::BpeTokenizer::save_binary).
+pub const BINARY_FORMAT_MAGIC: [u8; 8] = *b"BPETOKZR";
+
+/// Version of the tokenizer layout written by
+/// [`BpeTokenizer::save_binary`](crate::BpeTokenizer::save_binary). Bumped whenever
+/// the layout changes; files with a newer version are rejected instead of misread.
+pub const BINARY_FORMAT_VERSION: u32 = 1;
+
+/// Magic bytes at the start of every file written by
+/// `BpeTokenizer::save_mapped` (features `bincode` and `mmap`).
+#[cfg(feature = "mmap")]
+pub const MAPPED_TOKENIZER_MAGIC: [u8; 8] = *b"BPETOKMP";
+
+/// Version of the layout written by `BpeTokenizer::save_mapped` (features
+/// `bincode` and `mmap`). Bumped whenever the layout changes; files with a newer
+/// version are rejected instead of misread.
+#[cfg(feature = "mmap")]
+pub const MAPPED_FORMAT_VERSION: u32 = 1;
+
+/// Bytes before the payload: magic, version, payload length and checksum.
+const HEADER_LEN: usize = 8 + 4 + 8 + 8;
+
+/// Error returned when a batch or a tokenizer cannot be converted to or from its
+/// binary form.
+#[derive(Debug)]
+pub enum WireError {
+    /// A file could not be read or written.
+    Io(io::Error),
+    /// The data does not start with [`BINARY_FORMAT_MAGIC`], so it is not a saved
+    /// tokenizer.
+    NotATokenizer,
+    /// The file was written by a newer release in a layout this one cannot read.
+    UnsupportedVersion { found: u32, supported: u32 },
+    /// The payload length differs from the header, usually because a write or copy
+    /// was interrupted.
+    LengthMismatch { expected: u64, actual: u64 },
+    /// The payload does not match its checksum.
+    ChecksumMismatch { expected: u64, actual: u64 },
+    /// The payload is not a valid bincode encoding.
+    Decode(bincode::Error),
+    /// The saved vocabulary does not match its merges.
+    InvalidConfig(ConfigError),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Io(err) => write!(f, "failed to access binary tokenizer file: {}", err),
+            WireError::NotATokenizer => write!(f, "invalid binary data: missing tokenizer header"),
+            WireError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "binary tokenizer format version {} is newer than the supported version {}",
+                found, supported
+            ),
+            WireError::LengthMismatch { expected, actual } => write!(
+                f,
+                "invalid binary data: expected {} payload bytes but found {}",
+                expected, actual
+            ),
+            WireError::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "invalid binary data: checksum {:016x} does not match stored {:016x}",
+                actual, expected
+            ),
+            WireError::Decode(err) => write!(f, "invalid binary data: {}", err),
+            WireError::InvalidConfig(err) => write!(f, "invalid binary data: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for WireError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WireError::Io(err) => Some(err),
+            WireError::Decode(err) => Some(&**err),
+            WireError::InvalidConfig(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WireError {
+    fn from(err: io::Error) -> Self {
+        WireError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for WireError {
+    fn from(err: bincode::Error) -> Self {
+        WireError::Decode(err)
+    }
+}
+
+impl From<ConfigError> for WireError {
+    fn from(err: ConfigError) -> Self {
+        WireError::InvalidConfig(err)
+    }
+}
+
+/// Writes `config` in the format of
+/// [`BpeTokenizer::save_binary`](crate::BpeTokenizer::save_binary): the header,
+/// then the config as bincode.
+pub(crate) fn write_config(writer: impl Write, config: &TokenizerConfig) -> Result<(), WireError> {
+    let payload = bincode::serialize(config)?;
+    write_with_header(writer, BINARY_FORMAT_MAGIC, BINARY_FORMAT_VERSION, &payload)
+}
+
+/// Writes `payload` after a header of `magic`, `version`, the payload length and
+/// its checksum.
+fn write_with_header(
+    mut writer: impl Write,
+    magic: [u8; 8],
+    version: u32,
+    payload: &[u8],
+) -> Result<(), WireError> {
+    writer.write_all(&magic)?;
+    writer.write_all(&version.to_le_bytes())?;
+    writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+    writer.write_all(&xxh3_64(payload).to_le_bytes())?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Reads a config written by [`write_config`], checking the header first.
+pub(crate) fn read_config(mut reader: impl Read) -> Result<TokenizerConfig, WireError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let payload = checked_payload(&bytes, BINARY_FORMAT_MAGIC, BINARY_FORMAT_VERSION)?;
+
+    Ok(bincode::deserialize(payload)?)
+}
+
+/// Checks the header written by [`write_with_header`] and returns the payload
+/// after it.
+fn checked_payload(bytes: &[u8], magic: [u8; 8], supported: u32) -> Result<&[u8], WireError> {
+    if bytes.len() < HEADER_LEN || bytes[..8] != magic {
+        return Err(WireError::NotATokenizer);
+    }
+
+    let field = |range: std::ops::Range<usize>| -> [u8; 8] {
+        bytes[range].try_into().expect("eight bytes")
+    };
+    let version = u32::from_le_bytes(bytes[8..12].try_into().expect("four bytes"));
+    if version > supported {
+        return Err(WireError::UnsupportedVersion {
+            found: version,
+            supported,
+        });
+    }
+    let length = u64::from_le_bytes(field(12..20));
+    let checksum = u64::from_le_bytes(field(20..28));
+
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() as u64 != length {
+        return Err(WireError::LengthMismatch {
+            expected: length,
+            actual: payload.len() as u64,
+        });
+    }
+    let actual = xxh3_64(payload);
+    if actual != checksum {
+        return Err(WireError::ChecksumMismatch {
+            expected: checksum,
+            actual,
+        });
+    }
+    Ok(payload)
+}
+
+/// Writes a tokenizer in the format of `BpeTokenizer::save_mapped`: the header,
+/// then the length of `settings` as bincode and the bincode itself, the length of
+/// the vocabulary section and the section in the layout of
+/// [`Vocabulary::save_mapped`](crate::Vocabulary::save_mapped), then the merge
+/// rules in the layout of [`MappedMerges`].
+#[cfg(feature = "mmap")]
+pub(crate) fn write_mapped(
+    writer: impl Write,
+    settings: &TokenizerConfig,
+    vocabulary: &Vocabulary,
+    merges: &[(String, String)],
+) -> Result<(), WireError> {
+    let settings = bincode::serialize(settings)?;
+    let tokens: Vec<&str> = vocabulary.iter().map(|(_, token)| token).collect();
+    let mut vocabulary = Vec::new();
+    MappedTokens::write_to(&mut vocabulary, &tokens)?;
+
+    let mut payload = Vec::new();
+    for section in [settings, vocabulary] {
+        payload.extend_from_slice(&(section.len() as u64).to_le_bytes());
+        payload.extend_from_slice(&section);
+    }
+    MappedMerges::write_to(&mut payload, merges)?;
+    write_with_header(
+        writer,
+        MAPPED_TOKENIZER_MAGIC,
+        MAPPED_FORMAT_VERSION,
+        &payload,
+    )
+}
+
+/// Maps a file written by [`write_mapped`] and returns its settings, with the
+/// vocabulary and merge rules read in place.
+#[cfg(feature = "mmap")]
+pub(crate) fn open_mapped(
+    path: impl AsRef<Path>,
+) -> Result<(TokenizerConfig, Vocabulary, MergeTable), WireError> {
+    let map = map_file(path)?;
+    let payload = checked_payload(&map, MAPPED_TOKENIZER_MAGIC, MAPPED_FORMAT_VERSION)?;
+
+    // Each of the first two sections is preceded by its length.
+    let mut sections = Vec::with_capacity(2);
+    let mut start = HEADER_LEN;
+    for _ in 0..2 {
+        let end = payload
+            .get(start - HEADER_LEN..start - HEADER_LEN + 8)
+            .map(|length| u64::from_le_bytes(length.try_into().expect("eight bytes")))
+            .and_then(|length| (start as u64 + 8).checked_add(length))
+            .filter(|&end| end <= map.len() as u64)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "section is cut short"))?
+            as usize;
+        sections.push(start + 8..end);
+        start = end;
+    }
+
+    let settings: TokenizerConfig = bincode::deserialize(&map[sections[0].clone()])?;
+    let vocabulary = MappedTokens::parse(map.clone(), sections[1].clone())?;
+    let merges = MappedMerges::parse(map.clone(), start..map.len())?;
+    Ok((
+        settings,
+        Vocabulary::from_mapped(vocabulary),
+        MergeTable::from_mapped(merges),
+    ))
+}
+
+/// Serializes a batch of encodings into a compact binary form.
+///
+/// The format is bincode 1.x with its default configuration applied to
+/// `Vec<Encoding>`, using the same field layout as the JSON schema documented on
+/// [`Encoding`]. It is cheaper to produce and parse than JSON, which matters when
+/// the tokenizer runs as a sidecar and ships every batch over IPC.
+///
+/// # Errors
+///
+/// Returns a [`WireError`] if serialization fails.
+///
+/// # Examples
+///
+/// ```
+/// use bpe_tokenizer_rs::{BpeTokenizer, encodings_from_bytes, encodings_to_bytes};
+///
+/// let tokenizer = BpeTokenizer::new(vec![], vec![]);
+/// let batch = vec![tokenizer.encode_with_offsets("hi"), tokenizer.encode_with_offsets("yo")];
+///
+/// let bytes = encodings_to_bytes(&batch).unwrap();
+/// assert_eq!(encodings_from_bytes(&bytes).unwrap(), batch);
+/// ```
+pub fn encodings_to_bytes(encodings: &[Encoding]) -> Result<Vec<u8>, WireError> {
+    Ok(bincode::serialize(encodings)?)
+}
+
+/// Deserializes a batch of encodings produced by [`encodings_to_bytes`].
+///
+/// # Errors
+///
+/// Returns a [`WireError`] if the bytes are truncated, corrupted, or describe an
+/// encoding whose arrays differ in length or whose masks hold values other than 0
+/// and 1.
+pub fn encodings_from_bytes(bytes: &[u8]) -> Result<Vec<Encoding>, WireError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BpeTokenizer;
+
+    #[test]
+    fn wire_roundtrip_preserves_batch() {
+        let special_tokens = vec!["<|endoftext|>".to_string()];
+        let tokenizer = BpeTokenizer::new(vec![("h".to_string(), "i".to_string())], special_tokens);
+        let batch: Vec<Encoding> = ["hi there", "<|endoftext|>日本", ""]
+            .iter()
+            .map(|text| tokenizer.encode_with_offsets(text))
+            .collect();
+
+        let bytes = encodings_to_bytes(&batch).unwrap();
+
+        assert_eq!(encodings_from_bytes(&bytes).unwrap(), batch);
+    }
+
+    #[test]
+    fn binary_tokenizer_roundtrip_keeps_ids_and_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.bin");
+        let tokenizer = BpeTokenizer::from_trainer(
+            &crate::Trainer::new(8),
+            &["low lower lowest"],
+            vec!["<|endoftext|>".to_string()],
+        )
+        .with_encode_strategy(crate::EncodeStrategy::LongestMatch);
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["lowest lowest"]);
+
+        ranked.save_binary(&path).unwrap();
+        let loaded = BpeTokenizer::load_binary(&path).unwrap();
+
+        let text = "lower<|endoftext|>slow";
+        assert_eq!(loaded.encode(text), ranked.encode(text));
+        assert_eq!(
+            loaded.encode_strategy(),
+            crate::EncodeStrategy::LongestMatch
+        );
+    }
+
+    #[test]
+    fn binary_tokenizer_rejects_damaged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.bin");
+        let merges = vec![("h".to_string(), "i".to_string())];
+        BpeTokenizer::new(merges, vec![])
+            .save_binary(&path)
+            .unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(matches!(
+            BpeTokenizer::load_binary(&path),
+            Err(WireError::LengthMismatch { .. })
+        ));
+
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        std::fs::write(&path, &flipped).unwrap();
+        assert!(matches!(
+            BpeTokenizer::load_binary(&path),
+            Err(WireError::ChecksumMismatch { .. })
+        ));
+
+        std::fs::write(&path, "#version: 0.2\nh i\n").unwrap();
+        assert!(matches!(
+            BpeTokenizer::load_binary(&path),
+            Err(WireError::NotATokenizer)
+        ));
+
+        let missing = BpeTokenizer::load_binary(dir.path().join("missing.bin"));
+        assert!(missing.err().unwrap().to_string().contains("No such file"));
+    }
+
+    #[test]
+    fn reads_files_of_every_released_version() {
+        // Written by version 1. A layout change must bump BINARY_FORMAT_VERSION and
+        // keep this file loading.
+        let bytes = include_bytes!("../tests/fixtures/tokenizer-v1.bin");
+
+        let config = read_config(&bytes[..]).unwrap();
+        let tokenizer = BpeTokenizer::from_config(config).unwrap();
+
+        assert_eq!(tokenizer.encode("hi hi<|endoftext|>"), vec![257, 258, 0]);
+        assert_eq!(
+            tokenizer.encode_strategy(),
+            crate::EncodeStrategy::LongestMatch
+        );
+        assert_eq!(tokenizer.metadata().name.as_deref(), Some("fixture"));
+    }
+
+    #[test]
+    fn rejects_files_from_a_newer_version() {
+        let mut bytes = Vec::new();
+        write_config(&mut bytes, &BpeTokenizer::new(vec![], vec![]).config()).unwrap();
+        bytes[8..12].copy_from_slice(&(BINARY_FORMAT_VERSION + 1).to_le_bytes());
+        // A newer release may change the payload in any way.
+        bytes.truncate(HEADER_LEN + 3);
+
+        let err = read_config(bytes.as_slice()).err().unwrap();
+
+        assert!(matches!(
+            err,
+            WireError::UnsupportedVersion { found, supported: BINARY_FORMAT_VERSION }
+                if found == BINARY_FORMAT_VERSION + 1
+        ));
+        assert!(err.to_string().contains("newer"), "{}", err);
+    }
+
+    #[test]
+    fn header_records_version_length_and_checksum() {
+        let mut bytes = Vec::new();
+        write_config(&mut bytes, &BpeTokenizer::new(vec![], vec![]).config()).unwrap();
+
+        let payload = &bytes[HEADER_LEN..];
+        assert_eq!(bytes[..8], BINARY_FORMAT_MAGIC);
+        assert_eq!(bytes[8..12], BINARY_FORMAT_VERSION.to_le_bytes());
+        assert_eq!(bytes[12..20], (payload.len() as u64).to_le_bytes());
+        assert_eq!(bytes[20..28], xxh3_64(payload).to_le_bytes());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_tokenizer_matches_the_saved_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.map");
+        let tokenizer = BpeTokenizer::from_trainer(
+            &crate::Trainer::new(20),
+            &["the cat sat on the mat", "naïve café 日本語"],
+            vec!["<|endoftext|>".to_string()],
+        )
+        .with_normalizer(crate::Normalizer::new().with_nfc(true))
+        .with_metadata(crate::Metadata {
+            name: Some("mapped".to_string()),
+            ..Default::default()
+        });
+        let (ranked, _) = tokenizer.rank_ids_by_frequency(&["the mat"]);
+
+        ranked.save_mapped(&path).unwrap();
+        let mapped = BpeTokenizer::open_mapped(&path).unwrap();
+
+        let text = "the café sat<|endoftext|>日本 mat";
+        assert!(mapped.vocabulary().is_mapped());
+        assert_eq!(mapped.encode(text), ranked.encode(text));
+        assert_eq!(mapped.decode(&mapped.encode(text)), text);
+        assert_eq!(mapped.decoded_len(&ranked.encode(text)), text.len());
+        assert_eq!(mapped.merges(), ranked.merges());
+        assert_eq!(
+            mapped.normalizer().describe(),
+            ranked.normalizer().describe()
+        );
+        assert_eq!(mapped.metadata().name.as_deref(), Some("mapped"));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn mapped_tokenizer_rejects_damaged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tokenizer.map");
+        let merges = vec![("h".to_string(), "i".to_string())];
+        let tokenizer = BpeTokenizer::new(merges, vec![]);
+        tokenizer.save_mapped(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        let open = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            BpeTokenizer::open_mapped(&path).err().unwrap()
+        };
+
+        assert!(matches!(
+            open(&bytes[..bytes.len() - 1]),
+            WireError::LengthMismatch { .. }
+        ));
+        let mut flipped = bytes.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(open(&flipped), WireError::ChecksumMismatch { .. }));
+
+        // A rank table slot damaged before the checksum was taken: the table's two
+        // slots sit just before the two bytes of "hi".
+        let table = bytes.len() - 10;
+        let resealed = |mut bytes: Vec<u8>| {
+            let checksum = xxh3_64(&bytes[HEADER_LEN..]);
+            bytes[20..28].copy_from_slice(&checksum.to_le_bytes());
+            open(&bytes).to_string()
+        };
+        let mut out_of_range = bytes.clone();
+        out_of_range[table..table + 8].copy_from_slice(&[7, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert!(resealed(out_of_range).contains("invalid or repeated rank"));
+        let mut swapped = bytes.clone();
+        swapped[table..table + 8].rotate_left(4);
+        assert!(resealed(swapped).contains("does not find rule 0"));
+
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(MAPPED_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            open(&newer),
+            WireError::UnsupportedVersion { found, supported: MAPPED_FORMAT_VERSION }
+                if found == MAPPED_FORMAT_VERSION + 1
+        ));
+
+        tokenizer.save_binary(&path).unwrap();
+        assert!(matches!(
+            BpeTokenizer::open_mapped(&path),
+            Err(WireError::NotATokenizer)
+        ));
+    }
+
+    #[test]
+    fn wire_roundtrip_empty_batch() {
+        let bytes = encodings_to_bytes(&[]).unwrap();
+
+        assert!(encodings_from_bytes(&bytes).unwrap().is_empty());
+    }
+
+    #[test]
+    fn wire_rejects_truncated_input() {
+        let tokenizer = BpeTokenizer::new(vec![], vec![]);
+        let bytes = encodings_to_bytes(&[tokenizer.encode_with_offsets("hello")]).unwrap();
+
+        assert!(encodings_from_bytes(&bytes[..bytes.len() - 3]).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::instrumentation;
+
+/// Marks the end of the recency list.
+const NIL: usize = usize::MAX;
+
+/// Size and effectiveness of an encoder's word cache, as returned by
+/// [`Encoder::word_cache_stats`](crate::Encoder::word_cache_stats).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WordCacheStats {
+    /// Most words the cache holds before evicting the least recently used one.
+    pub capacity: usize,
+    /// Words currently cached.
+    pub len: usize,
+    /// Lookups answered from the cache since it was created or cleared.
+    pub hits: u64,
+    /// Lookups that had to merge the word since it was created or cleared.
+    pub misses: u64,
+}
+
+impl WordCacheStats {
+    /// Returns the share of lookups answered from the cache, or 0.0 before the
+    /// first lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+/// A cached word and its neighbours in the recency list.
+struct Entry {
+    word: String,
+    tokens: Vec<String>,
+    /// Next more recently used entry.
+    prev: usize,
+    /// Next less recently used entry.
+    next: usize,
+}
+
+/// Least-recently-used map from words to their merged tokens, with entries kept
+/// in a slab and linked in recency order so every operation is O(1).
+struct Lru {
+    index: HashMap<String, usize>,
+    entries: Vec<Entry>,
+    /// Most recently used entry.
+    head: usize,
+    /// Least recently used entry, evicted first.
+    tail: usize,
+}
+
+impl Lru {
+    fn new() -> Self {
+        Lru {
+            index: HashMap::new(),
+            entries: Vec::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    fn get(&mut self, word: &str) -> Option<Vec<String>> {
+        let slot = *self.index.get(word)?;
+        self.unlink(slot);
+        self.push_front(slot);
+        Some(self.entries[slot].tokens.clone())
+    }
+
+    fn insert(&mut self, word: &str, tokens: Vec<String>, capacity: usize) {
+        if let Some(&slot) = self.index.get(word) {
+            self.entries[slot].tokens = tokens;
+            self.unlink(slot);
+            self.push_front(slot);
+            return;
+        }
+
+        let slot = if self.entries.len() < capacity {
+            self.entries.push(Entry {
+                word: word.to_string(),
+                tokens,
+                prev: NIL,
+                next: NIL,
+            });
+            self.entries.len() - 1
+        } else {
+            let slot = self.tail;
+            self.unlink(slot);
+            let entry = &mut self.entries[slot];
+            self.index.remove(&entry.word);
+            entry.word = word.to_string();
+            entry.tokens = tokens;
+            slot
+        };
+        self.index.insert(word.to_string(), slot);
+        self.push_front(slot);
+    }
+
+    fn unlink(&mut self, slot: usize) {
+        let (prev, next) = (self.entries[slot].prev, self.entries[slot].next);
+        match prev {
+            NIL => self.head = next,
+            prev => self.entries[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entries[next].prev = prev,
+        }
+    }
+
+    fn push_front(&mut self, slot: usize) {
+        self.entries[slot].prev = NIL;
+        self.entries[slot].next = self.head;
+        match self.head {
+            NIL => self.tail = slot,
+            head => self.entries[head].prev = slot,
+        }
+        self.head = slot;
+    }
+}
+
+/// Cache of merge results per pre-tokenized word, shared by all threads using the
+/// encoder.
+///
+/// The lock is only held to look up or store a word, never while merging, so
+/// threads encoding different words do not wait on each other's merges.
+pub(crate) struct WordCache {
+    capacity: usize,
+    lru: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl WordCache {
+    /// Creates an empty cache holding up to `capacity` words, which must be
+    /// positive.
+    pub(crate) fn new(capacity: usize) -> Self {
+        WordCache {
+            capacity,
+            lru: Mutex::new(Lru::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the cached tokens of `word`, or merges it with `merge` and caches
+    /// the result.
+    pub(crate) fn get_or_merge(
+        &self,
+        word: &str,
+        merge: impl FnOnce() -> Vec<String>,
+    ) -> Vec<String> {
+        if let Some(tokens) = self.lock().get(word) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            instrumentation::record_word_cache(true);
+            return tokens;
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        instrumentation::record_word_cache(false);
+        let tokens = merge();
+        self.lock().insert(word, tokens.clone(), self.capacity);
+        tokens
+    }
+
+    pub(crate) fn stats(&self) -> WordCacheStats {
+        WordCacheStats {
+            capacity: self.capacity,
+            len: self.lock().index.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Removes every word and resets the hit and miss counts.
+    pub(crate) fn clear(&self) {
+        *self.lock() = Lru::new();
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    /// Locks the map. A panic while holding the lock cannot leave it half-updated
+    /// in a way that returns wrong tokens, so a poisoned lock is used as is.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Lru> {
+        self.lru.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(word: &str) -> Vec<String> {
+        word.chars().map(String::from).collect()
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_word() {
+        let cache = WordCache::new(2);
+        let merge = |word: &str| cache.get_or_merge(word, || tokens(word));
+
+        merge("ab");
+        merge("cd");
+        merge("ab");
+        merge("ef");
+
+        assert_eq!(cache.get_or_merge("ab", || panic!("cached")), tokens("ab"));
+        assert_eq!(cache.get_or_merge("cd", || vec!["cd".into()]), ["cd"]);
+        let stats = cache.stats();
+        assert_eq!(stats.len, 2);
+        assert_eq!((stats.hits, stats.misses), (2, 4));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clear_empties_the_cache_and_resets_counts() {
+        let cache = WordCache::new(1);
+        cache.get_or_merge("a", || tokens("a"));
+        cache.get_or_merge("a", || tokens("a"));
+
+        cache.clear();
+
+        assert_eq!(
+            cache.stats(),
+            WordCacheStats {
+                capacity: 1,
+                len: 0,
+                hits: 0,
+                misses: 0,
+            }
+        );
+        assert_eq!(cache.stats().hit_rate(), 0.0);
+    }
+}
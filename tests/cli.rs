@@ -0,0 +1,122 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+use bpe_tokenizer_rs::{BpeTokenizer, ShardWriter, TokenWriter};
+
+fn bpe(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bpe"))
+        .args(args)
+        .output()
+        .expect("failed to run bpe")
+}
+
+fn save_tokenizer(dir: &Path) -> (BpeTokenizer, String) {
+    let tokenizer = BpeTokenizer::new(vec![("h".to_string(), "i".to_string())], vec![]);
+    let path = dir.join("merges.txt");
+    tokenizer.save_merges(&path).unwrap();
+
+    (tokenizer, path.to_string_lossy().into_owned())
+}
+
+#[test]
+fn sample_decodes_indexed_shard_documents() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tokenizer, merges) = save_tokenizer(dir.path());
+    let shard = dir.path().join("shard.bin");
+    let mut writer = ShardWriter::create(&shard).unwrap();
+    for text in ["hi there", "second doc"] {
+        writer.write_document(&tokenizer.encode(text)).unwrap();
+    }
+    writer.finish_with_index(&shard).unwrap();
+
+    let output = bpe(&["sample", &merges, shard.to_str().unwrap(), "--count", "10"]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("hi there"));
+    assert!(stdout.contains("second doc"));
+    assert_eq!(stdout.matches("=== #").count(), 2);
+}
+
+#[test]
+fn sample_reads_packed_sequences() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tokenizer, merges) = save_tokenizer(dir.path());
+    let packed = dir.path().join("packed.bin");
+    let mut writer = TokenWriter::new(Vec::new(), 4, 0);
+    writer
+        .write_document(&tokenizer.encode("abcdefgh"))
+        .unwrap();
+    std::fs::write(&packed, writer.finish().unwrap()).unwrap();
+
+    let output = bpe(&[
+        "sample",
+        &merges,
+        packed.to_str().unwrap(),
+        "--sequence-length",
+        "4",
+        "--count",
+        "1",
+        "--seed",
+        "3",
+    ]);
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("(4 tokens)"));
+}
+
+#[test]
+fn unknown_command_fails_with_usage() {
+    let output = bpe(&["frobnicate"]);
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("usage: bpe"));
+}
+
+#[test]
+fn verify_accepts_roundtripping_corpus() {
+    let dir = tempfile::tempdir().unwrap();
+    let (_, merges) = save_tokenizer(dir.path());
+    let corpus = dir.path().join("corpus.txt");
+    std::fs::write(&corpus, "hi there\nnaïve 🦀\n").unwrap();
+
+    let output = bpe(&["verify", &merges, corpus.to_str().unwrap()]);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("all 2 texts round-trip"));
+}
+
+#[test]
+#[cfg(feature = "json")]
+fn test_vectors_match_the_library_encoding() {
+    let dir = tempfile::tempdir().unwrap();
+    let (tokenizer, merges) = save_tokenizer(dir.path());
+    let corpus = dir.path().join("corpus.txt");
+    std::fs::write(&corpus, "hi there\nnaïve 🦀\n").unwrap();
+    let output_path = dir.path().join("vectors.json");
+
+    let output = bpe(&[
+        "test-vectors",
+        &merges,
+        "--corpus",
+        corpus.to_str().unwrap(),
+        "--output",
+        output_path.to_str().unwrap(),
+    ]);
+
+    assert!(output.status.success());
+    let document: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&output_path).unwrap()).unwrap();
+    let vectors = document["vectors"].as_array().unwrap();
+    assert_eq!(vectors.len(), 2);
+    assert_eq!(vectors[0]["text"], "hi there");
+    assert_eq!(
+        vectors[0]["ids"],
+        serde_json::json!(tokenizer.encode("hi there"))
+    );
+
+    let output = bpe(&["test-vectors", &merges]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\"vectors\""));
+}
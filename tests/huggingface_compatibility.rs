@@ -1,9 +1,9 @@
-use bpe_tokenizer_rs::{BpeTokenizer, Trainer};
+use bpe_tokenizer_rs::{BpeTokenizer, TieBreak, Trainer};
 use std::fs;
 use std::io::Write;
 use tempfile::TempDir;
 use tokenizers::models::bpe::{BPE, BpeTrainerBuilder};
-use tokenizers::{AddedToken, Tokenizer, TokenizerBuilder};
+use tokenizers::{AddedToken, Model, Tokenizer, TokenizerBuilder};
 
 fn train_hf_tokenizer(
     training_texts: &[&str],
@@ -234,3 +234,90 @@ fn complex_text_with_special_tokens_matches_hf() {
         assert_encoding_matches(&our, &hf, text);
     }
 }
+
+#[test]
+fn base_token_ids_match_hf_byte_level_alphabet() {
+    let special_tokens = vec!["<|endoftext|>".to_string()];
+    let our = BpeTokenizer::new(vec![], special_tokens.clone());
+    let hf = train_hf_tokenizer(&["x"], 0, special_tokens);
+    let hf_vocab = hf.get_vocab(false);
+
+    assert_eq!(hf_vocab.len(), our.vocabulary().len());
+    for (id, token) in our.vocabulary().iter() {
+        assert_eq!(hf_vocab.get(token), Some(&id), "token {:?}", token);
+    }
+}
+
+#[test]
+fn merge_ids_match_hf_when_importing_merges_only() {
+    let training_data = &["hello world", "hello there", "the world is wide"];
+    let hf = train_hf_tokenizer(training_data, 20, vec![]);
+    let hf_vocab = hf.get_vocab(false);
+
+    let temp_dir = TempDir::new().unwrap();
+    hf.get_model().save(temp_dir.path(), None).unwrap();
+    let our = BpeTokenizer::from_merges_file(temp_dir.path().join("merges.txt"), vec![]).unwrap();
+
+    assert_eq!(hf_vocab.len(), our.vocabulary().len());
+    for (id, token) in our.vocabulary().iter() {
+        assert_eq!(hf_vocab.get(token), Some(&id), "token {:?}", token);
+    }
+}
+
+fn assert_merges_match_hf(training_data: &[&str], num_merges: usize) {
+    let (our, hf) = create_tokenizers_with_merges(training_data, num_merges);
+
+    let mut hf_vocab: Vec<(String, u32)> = hf.get_vocab(false).into_iter().collect();
+    hf_vocab.sort_by_key(|(_, id)| *id);
+    assert_eq!(our.get_vocab(), hf_vocab);
+}
+
+#[test]
+fn all_pairs_tied_match_hf() {
+    // Every pair occurs once, so every merge is decided by the tie-break.
+    assert_merges_match_hf(&["zy xw vu ts rq po"], 6);
+}
+
+#[test]
+fn merged_token_tie_matches_hf_id_order() {
+    // After "a"+"a", the pairs "aa"+"x" and "b"+"x" tie; HF takes "b"+"x" because
+    // "aa" has ID 256 even though it sorts before "b".
+    assert_merges_match_hf(&["aax", "aax", "bx", "bx"], 3);
+}
+
+#[test]
+fn ties_between_overlapping_runs_match_hf() {
+    assert_merges_match_hf(&["aaaa bbbb aaa bbb ab ba"], 10);
+}
+
+#[test]
+fn ties_across_scripts_match_hf() {
+    // Multi-byte characters produce pairs of high byte-level IDs that tie with ASCII.
+    assert_merges_match_hf(&["éé ab 日日 cd 🦀🦀 ef"], 12);
+}
+
+#[test]
+fn ties_after_many_merges_match_hf() {
+    let corpus = [
+        "the cat sat on the mat",
+        "a dog and a cat met on a log",
+        "tea at ten, then tan hats",
+    ];
+    assert_merges_match_hf(&corpus, 40);
+}
+
+#[test]
+fn lexicographic_tie_break_diverges_from_hf() {
+    let corpus = ["aax", "aax", "bx", "bx"];
+    let hf = train_hf_tokenizer(&corpus, 3, vec![]);
+    let lexicographic = BpeTokenizer::from_trainer(
+        &Trainer::new(3).with_tie_break(TieBreak::Lexicographic),
+        &corpus,
+        vec![],
+    );
+
+    assert_ne!(
+        lexicographic.encode("bx"),
+        hf.encode("bx", false).unwrap().get_ids()
+    );
+}
@@ -0,0 +1,18 @@
+//! Runs in its own test binary because the strictness policy is process-wide.
+
+use bpe_tokenizer_rs::{BpeTokenizer, Strictness, set_strictness};
+
+#[test]
+fn lenient_decode_never_panics() {
+    set_strictness(Strictness::Lenient);
+    let tokenizer = BpeTokenizer::new(vec![], vec!["<my token>".to_string()]);
+    let ids = tokenizer.encode("hi<my token>é");
+
+    assert_eq!(tokenizer.decode(&ids), "hi<my token>é");
+    assert_eq!(
+        tokenizer.decode(&ids[..ids.len() - 1]),
+        "hi<my token>\u{FFFD}"
+    );
+    assert_eq!(tokenizer.decode(&[9999, ids[0]]), "\u{FFFD}h");
+    assert_eq!(tokenizer.decode_batch(&[&ids]), ["hi<my token>é"]);
+}
@@ -0,0 +1,178 @@
+#![cfg(feature = "json")]
+
+use bpe_tokenizer_rs::{
+    BpeTokenizer, ControlChars, NewlineMode, Normalizer, PreTokenizer, Trainer,
+};
+use tempfile::TempDir;
+use tokenizers::models::bpe::{BPE, BpeTrainerBuilder};
+use tokenizers::pre_tokenizers::byte_level::ByteLevel;
+use tokenizers::{AddedToken, Tokenizer, TokenizerBuilder};
+
+const CORPUS: [&str; 3] = [
+    "the cat sat on the mat",
+    "a dog and a cat met on a log",
+    "naïve café 日本語 🦀🦀",
+];
+
+const PROBES: [&str; 8] = [
+    "the cat",
+    "<|endoftext|>a dog<|endoftext|>",
+    "café\r\nnaïve\u{7}",
+    "on the log, a mat",
+    "🦀 mat",
+    "a  dog",
+    "x   y",
+    "  the\n\n  cat  ",
+];
+
+fn hf_encode(tokenizer: &Tokenizer, text: &str) -> Vec<u32> {
+    tokenizer.encode(text, false).unwrap().get_ids().to_vec()
+}
+
+fn assert_same_encodings(ours: &BpeTokenizer, theirs: &Tokenizer) {
+    for text in PROBES {
+        assert_eq!(ours.encode(text), hf_encode(theirs, text), "{:?}", text);
+    }
+}
+
+fn save_and_load_in_hf(tokenizer: &BpeTokenizer) -> Tokenizer {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("tokenizer.json");
+
+    tokenizer.save(&path).unwrap();
+    Tokenizer::from_file(&path).unwrap()
+}
+
+#[test]
+fn hf_loads_saved_tokenizer() {
+    let tokenizer = BpeTokenizer::from_trainer(
+        &Trainer::new(30),
+        &CORPUS,
+        vec!["<|endoftext|>".to_string()],
+    );
+
+    assert_same_encodings(&tokenizer, &save_and_load_in_hf(&tokenizer));
+}
+
+#[test]
+fn hf_loads_saved_normalizer() {
+    let normalizer = Normalizer::new()
+        .with_nfc(true)
+        .with_newlines(NewlineMode::Unix)
+        .with_control_chars(ControlChars::Strip);
+    let tokenizer = BpeTokenizer::from_trainer(
+        &Trainer::new(30).with_normalizer(normalizer),
+        &CORPUS,
+        vec![],
+    );
+
+    let theirs = save_and_load_in_hf(&tokenizer);
+
+    assert_same_encodings(&tokenizer, &theirs);
+    assert_eq!(
+        theirs
+            .decode(&hf_encode(&theirs, "a\r\nb\u{1}\u{85}c"), false)
+            .unwrap(),
+        "a\nbc"
+    );
+}
+
+#[test]
+fn hf_loads_saved_regex_pre_tokenizer() {
+    let pre_tokenizer = PreTokenizer::with_regex(regex::Regex::new(r"\w+|[^\w\s]+").unwrap());
+    let tokenizer = BpeTokenizer::from_trainer(
+        &Trainer::new(30).with_pre_tokenizer(pre_tokenizer),
+        &CORPUS,
+        vec![],
+    );
+
+    assert_same_encodings(&tokenizer, &save_and_load_in_hf(&tokenizer));
+}
+
+#[test]
+fn loads_tokenizer_saved_by_hf() {
+    let mut trainer = BpeTrainerBuilder::new()
+        .vocab_size(256 + 30)
+        .min_frequency(0)
+        .show_progress(false)
+        .initial_alphabet(ByteLevel::alphabet().into_iter().collect())
+        .build();
+    let mut builder = TokenizerBuilder::new()
+        .with_model(BPE::default())
+        .with_pre_tokenizer(Some(ByteLevel::default().add_prefix_space(false)))
+        .with_decoder(Some(tokenizers::decoders::byte_level::ByteLevel::default()))
+        .with_normalizer(None::<tokenizers::normalizers::Sequence>)
+        .with_post_processor(None::<tokenizers::processors::sequence::Sequence>)
+        .build()
+        .unwrap();
+    builder.train(&mut trainer, CORPUS.iter()).unwrap();
+    let mut theirs: Tokenizer = builder.into();
+    // Added after training, so the special token gets the last ID as in GPT-2.
+    theirs.add_special_tokens(&[AddedToken::from("<|endoftext|>", true)]);
+
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("tokenizer.json");
+    theirs.save(&path, true).unwrap();
+    let ours = BpeTokenizer::from_file(&path).unwrap();
+
+    assert_eq!(ours.encode("<|endoftext|>"), vec![256 + 30]);
+    assert_same_encodings(&ours, &theirs);
+}
+
+#[test]
+fn rejects_prefix_space() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("tokenizer.json");
+    BpeTokenizer::new(vec![], vec![])
+        .with_pre_tokenizer(PreTokenizer::new().with_whitespace_lookahead(true))
+        .save(&path)
+        .unwrap();
+    let contents = std::fs::read_to_string(&path).unwrap().replacen(
+        "\"add_prefix_space\": false",
+        "\"add_prefix_space\": true",
+        1,
+    );
+    std::fs::write(&path, contents).unwrap();
+
+    let err = BpeTokenizer::from_file(&path).err().unwrap();
+
+    assert_eq!(
+        err.to_string(),
+        "unsupported tokenizer.json setting: pre_tokenizer ByteLevel"
+    );
+}
+
+#[test]
+fn loads_gpt2_files_like_hf() {
+    let trained = BpeTokenizer::from_trainer(&Trainer::new(30), &CORPUS, vec![]);
+    let mut encoder: std::collections::HashMap<String, u32> =
+        trained.get_vocab().into_iter().collect();
+    encoder.insert("<|endoftext|>".to_string(), encoder.len() as u32);
+
+    let dir = TempDir::new().unwrap();
+    let encoder_path = dir.path().join("encoder.json");
+    let merges_path = dir.path().join("vocab.bpe");
+    std::fs::write(&encoder_path, serde_json::to_string(&encoder).unwrap()).unwrap();
+    trained.save_merges(&merges_path).unwrap();
+
+    let ours = BpeTokenizer::from_gpt2_files(&encoder_path, &merges_path).unwrap();
+    let model = BPE::from_file(
+        encoder_path.to_str().unwrap(),
+        merges_path.to_str().unwrap(),
+    )
+    .build()
+    .unwrap();
+    let mut theirs: Tokenizer = TokenizerBuilder::new()
+        .with_model(model)
+        .with_pre_tokenizer(Some(ByteLevel::default().add_prefix_space(false)))
+        .with_decoder(Some(tokenizers::decoders::byte_level::ByteLevel::default()))
+        .with_normalizer(None::<tokenizers::normalizers::Sequence>)
+        .with_post_processor(None::<tokenizers::processors::sequence::Sequence>)
+        .build()
+        .unwrap()
+        .into();
+    theirs.add_special_tokens(&[AddedToken::from("<|endoftext|>", true)]);
+
+    assert_eq!(ours.encode("<|endoftext|>"), vec![256 + 30]);
+    assert_same_encodings(&ours, &theirs);
+}